@@ -0,0 +1,69 @@
+//! Decoding received packets, as VRChat sends them during a boop storm
+
+use std::hint::black_box;
+
+use criterion::{Criterion, criterion_group, criterion_main};
+use osc_booper::{
+    config::Options,
+    decode::{self, AddressFilter},
+};
+use rosc::{OscBundle, OscMessage, OscPacket, OscTime, OscType};
+
+fn message(addr: &str, value: OscType) -> OscPacket {
+    OscPacket::Message(OscMessage {
+        addr: addr.into(),
+        args: vec![value],
+    })
+}
+
+/// Bundle of parameter updates, with a single one we count
+fn storm_bundle() -> Vec<u8> {
+    let mut content: Vec<_> = (0..30)
+        .map(|i| {
+            let addr = format!("/avatar/parameters/Receiver{i}_Proximity");
+            message(&addr, OscType::Float(i as f32 / 30.0))
+        })
+        .collect();
+    content.push(message("/avatar/parameters/OSCBoop", OscType::Bool(true)));
+
+    let bundle = OscPacket::Bundle(OscBundle {
+        timetag: OscTime {
+            seconds: 0,
+            fractional: 1,
+        },
+        content,
+    });
+    rosc::encoder::encode(&bundle).unwrap()
+}
+
+fn bench_receive(c: &mut Criterion) {
+    let options = Options {
+        boop_address: "/OSCBoop".into(),
+        ..Default::default()
+    };
+    let filter = AddressFilter::new(&options);
+    let bundle = storm_bundle();
+    let proximity = message(
+        "/avatar/parameters/Receiver0_Proximity",
+        OscType::Float(0.5),
+    );
+    let proximity = rosc::encoder::encode(&proximity).unwrap();
+
+    let mut group = c.benchmark_group("receive");
+    group.bench_function("bundle", |b| {
+        b.iter(|| decode::decode_packet(black_box(&bundle)))
+    });
+    group.bench_function("bundle_filtered", |b| {
+        b.iter(|| decode::decode_filtered(black_box(&bundle), &filter))
+    });
+    group.bench_function("message", |b| {
+        b.iter(|| decode::decode_packet(black_box(&proximity)))
+    });
+    group.bench_function("message_filtered", |b| {
+        b.iter(|| decode::decode_filtered(black_box(&proximity), &filter))
+    });
+    group.finish();
+}
+
+criterion_group!(benches, bench_receive);
+criterion_main!(benches);
@@ -0,0 +1,142 @@
+use axum::{
+    Json, Router,
+    extract::{Query, Request, State},
+    http::{StatusCode, header},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    routing::{get, patch, post},
+};
+use serde::Deserialize;
+use serde_valid::Validate;
+use subtle::ConstantTimeEq;
+
+use crate::{
+    command::{self, Command, ConfigPatch, ControlRequest, ControlResponse},
+    http::HttpState,
+};
+
+/// JSON API for stream deck plugins and scripts
+///
+/// Routes:
+/// - `GET /stats`: current counters and state
+/// - `POST /reset/today`: reset today's boops
+/// - `POST /announce`: post the stats to the chatbox now
+/// - `POST /adjust`: add `by` of an [`Adjust`] body to the boops
+/// - `POST /undo`: revert the last boop
+/// - `PATCH /config`: change a [`ConfigPatch`] subset of the options
+///
+/// All of them reply with the resulting stats, and require the configured
+/// bearer token, see [`require_token`].
+pub(crate) fn router() -> Router<HttpState> {
+    Router::new()
+        .route("/stats", get(stats))
+        .route("/reset/today", post(reset_today))
+        .route("/announce", post(announce))
+        .route("/adjust", post(adjust))
+        .route("/undo", post(undo))
+        .route("/config", patch(configure))
+}
+
+/// Body of `POST /adjust`
+#[derive(Deserialize)]
+struct Adjust {
+    /// amount to add, negative to subtract
+    by: i64,
+}
+
+/// Query of requests that can't send headers, like browser WebSockets
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+/// Reject requests without the configured token, passed as bearer token or
+/// as `token` query parameter
+pub(crate) async fn require_token(
+    State(state): State<HttpState>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let Some(token) = &state.api_token else {
+        return next.run(request).await;
+    };
+
+    let bearer = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::to_string);
+    let given = bearer.or_else(|| {
+        Query::<TokenQuery>::try_from_uri(request.uri())
+            .ok()
+            .and_then(|Query(query)| query.token)
+    });
+    if !given.is_some_and(|given| is_token(token, &given)) {
+        return StatusCode::UNAUTHORIZED.into_response();
+    }
+
+    next.run(request).await
+}
+
+/// Compare tokens in constant time, not leaking how much of it was guessed
+fn is_token(token: &str, given: &str) -> bool {
+    token.as_bytes().ct_eq(given.as_bytes()).into()
+}
+
+async fn stats(State(state): State<HttpState>) -> Response {
+    control(&state, ControlRequest::Stats).await
+}
+
+async fn reset_today(State(state): State<HttpState>) -> Response {
+    control(&state, ControlRequest::ResetToday).await
+}
+
+async fn announce(State(state): State<HttpState>) -> Response {
+    control(&state, ControlRequest::PostNow).await
+}
+
+async fn adjust(State(state): State<HttpState>, Json(Adjust { by }): Json<Adjust>) -> Response {
+    control(&state, ControlRequest::Adjust { by }).await
+}
+
+async fn undo(State(state): State<HttpState>) -> Response {
+    control(&state, ControlRequest::Undo).await
+}
+
+async fn configure(State(state): State<HttpState>, Json(patch): Json<ConfigPatch>) -> Response {
+    if let Err(errors) = patch.validate() {
+        let response = ControlResponse::error(errors.to_string());
+        return (StatusCode::UNPROCESSABLE_ENTITY, Json(response)).into_response();
+    }
+
+    let command = Command::Configure(patch);
+    if state.commands.send(command).await.is_err() {
+        let response = ControlResponse::error("booper is shutting down");
+        return (StatusCode::SERVICE_UNAVAILABLE, Json(response)).into_response();
+    }
+    control(&state, ControlRequest::Stats).await
+}
+
+async fn control(state: &HttpState, request: ControlRequest) -> Response {
+    let response = command::execute(request, &state.commands, "api").await;
+    let status = if response.ok {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(response)).into_response()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_token() {
+        assert!(is_token("secret", "secret"));
+        assert!(!is_token("secret", "secreT"));
+        assert!(!is_token("secret", "secret2"));
+        assert!(!is_token("secret", ""));
+    }
+}
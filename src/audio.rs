@@ -0,0 +1,124 @@
+use std::{
+    error::Error,
+    fs::File,
+    io::BufReader,
+    path::{Path, PathBuf},
+    sync::mpsc,
+};
+
+use rodio::{Decoder, OutputStream, OutputStreamHandle, Sink};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, warn};
+use tts::Tts;
+
+use crate::{
+    config::{CountKind, SoundAlert},
+    events::BooperEvent,
+    template,
+};
+
+enum Alert {
+    Sound(PathBuf),
+    Say(String),
+}
+
+/// Play sounds or speak phrases when boops hit the configured counts
+pub(crate) fn spawn(
+    token: CancellationToken,
+    alerts: Vec<SoundAlert>,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    // audio output and speech block and aren't Send, keep them on a
+    // dedicated thread
+    let (tx, rx) = mpsc::channel::<Alert>();
+    std::thread::spawn(move || play_loop(rx));
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            let (today, total) = match event {
+                Ok(BooperEvent::BoopCounted { today, total, .. }) => (today, total),
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "audio alerts lagging behind");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            for alert in &alerts {
+                let number = match alert.count {
+                    CountKind::Today => today as u64,
+                    CountKind::Total => total,
+                };
+                if !alert.matches(number) {
+                    continue;
+                }
+
+                if let Some(sound) = &alert.sound {
+                    tx.send(Alert::Sound(sound.clone())).ok();
+                }
+                if let Some(say) = &alert.say {
+                    let phrase = template::render(say, |key| match key {
+                        "today" => Some(today.to_string()),
+                        "total" => Some(total.to_string()),
+                        _ => None,
+                    });
+                    tx.send(Alert::Say(phrase)).ok();
+                }
+            }
+        }
+    });
+}
+
+/// Play alerts one after another, opening the outputs on first use
+fn play_loop(alerts: mpsc::Receiver<Alert>) {
+    let mut output = None;
+    let mut tts = None;
+
+    while let Ok(alert) = alerts.recv() {
+        match alert {
+            Alert::Sound(path) => {
+                if let Err(e) = play(&mut output, &path) {
+                    warn!(err=%e, path=%path.display(), "failed to play sound");
+                }
+            }
+            Alert::Say(phrase) => {
+                if let Err(e) = say(&mut tts, &phrase) {
+                    warn!(err=%e, "failed to speak {phrase:?}");
+                }
+            }
+        }
+    }
+}
+
+fn play(
+    output: &mut Option<(OutputStream, OutputStreamHandle)>,
+    path: &Path,
+) -> Result<(), Box<dyn Error>> {
+    if output.is_none() {
+        *output = Some(OutputStream::try_default()?);
+    }
+    let (_, handle) = output.as_ref().expect("opened above");
+
+    let sink = Sink::try_new(handle)?;
+    sink.append(Decoder::new(BufReader::new(File::open(path)?))?);
+    sink.sleep_until_end();
+
+    Ok(())
+}
+
+fn say(tts: &mut Option<Tts>, phrase: &str) -> Result<(), Box<dyn Error>> {
+    if tts.is_none() {
+        *tts = Some(Tts::default()?);
+    }
+
+    // queued behind earlier phrases
+    tts.as_mut().expect("opened above").speak(phrase, false)?;
+    Ok(())
+}
@@ -0,0 +1,141 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use jiff::{SignedDuration, Timestamp, civil::DateTime, tz::TimeZone};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{config::BackupOptions, instance, paths, storage};
+
+/// Time of a snapshot in its file name, sorting chronologically
+const STAMP_FORMAT: &str = "%Y%m%dT%H%M%SZ";
+
+/// Copy the boop stats to timestamped snapshots every `options.every`,
+/// deleting the oldest ones beyond `options.keep`
+pub(crate) fn spawn(token: CancellationToken, options: BackupOptions) {
+    if options.every.is_zero() || options.every.is_negative() {
+        warn!(every=%options.every, "ignoring backups with non-positive interval");
+        return;
+    }
+
+    let dir = options
+        .dir
+        .clone()
+        .unwrap_or_else(|| paths::data_dir().join("backups"));
+    let base = instance::file_name(storage::FILE_NAME);
+    info!(every=%options.every, keep = options.keep, "backing up boop stats to {}", dir.display());
+
+    tokio::spawn(async move {
+        let mut last_run = None;
+
+        loop {
+            // continue the cadence across restarts
+            let last = latest_snapshot(&dir, &base).max(last_run);
+            let wait = last.map_or(SignedDuration::ZERO, |last| {
+                (last + options.every).duration_since(Timestamp::now())
+            });
+            debug!(wait=%wait, "waiting for next backup");
+
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = tokio::time::sleep(wait.max(SignedDuration::ZERO).unsigned_abs()) => {}
+            }
+
+            last_run = Some(Timestamp::now());
+            if let Err(e) = snapshot(&dir, &base, options.keep) {
+                error!(err=%e, "failed to back up boop stats to {}", dir.display());
+            }
+        }
+    });
+}
+
+/// Copy the boop stats to `dir` and delete snapshots beyond `keep`
+fn snapshot(dir: &Path, base: &str, keep: usize) -> io::Result<()> {
+    let file = paths::data_file(storage::FILE_NAME);
+    if !file.exists() {
+        debug!("no boop stats to back up yet");
+        return Ok(());
+    }
+
+    fs::create_dir_all(dir)?;
+    let target = dir.join(snapshot_name(base, Timestamp::now()));
+    fs::copy(&file, &target)?;
+    info!("backed up boop stats to {}", target.display());
+
+    for (_, expired) in snapshots(dir, base)?.iter().rev().skip(keep) {
+        match fs::remove_file(expired) {
+            Ok(()) => debug!("deleted old backup {}", expired.display()),
+            Err(e) => warn!(err=%e, "failed to delete old backup {}", expired.display()),
+        }
+    }
+    Ok(())
+}
+
+/// Time of the most recent snapshot in `dir`
+fn latest_snapshot(dir: &Path, base: &str) -> Option<Timestamp> {
+    let snapshots = snapshots(dir, base).ok()?;
+    snapshots.last().map(|(time, _)| *time)
+}
+
+/// Snapshots of `base` in `dir`, oldest first
+fn snapshots(dir: &Path, base: &str) -> io::Result<Vec<(Timestamp, PathBuf)>> {
+    let mut snapshots = vec![];
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let time = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| snapshot_time(base, name));
+        if let Some(time) = time {
+            snapshots.push((time, path));
+        }
+    }
+
+    snapshots.sort();
+    Ok(snapshots)
+}
+
+/// `boops.toml` turns into `boops.20250326T150000Z.toml`
+fn snapshot_name(base: &str, time: Timestamp) -> String {
+    let stamp = time.strftime(STAMP_FORMAT);
+    match base.rsplit_once('.') {
+        Some((stem, extension)) => format!("{stem}.{stamp}.{extension}"),
+        None => format!("{base}.{stamp}"),
+    }
+}
+
+/// Time of the snapshot `name` of `base`, if it is one
+fn snapshot_time(base: &str, name: &str) -> Option<Timestamp> {
+    let stamp = match base.rsplit_once('.') {
+        Some((stem, extension)) => name
+            .strip_prefix(stem)?
+            .strip_prefix('.')?
+            .strip_suffix(extension)?
+            .strip_suffix('.')?,
+        None => name.strip_prefix(base)?.strip_prefix('.')?,
+    };
+
+    let time = DateTime::strptime(STAMP_FORMAT, stamp).ok()?;
+    Some(time.to_zoned(TimeZone::UTC).ok()?.timestamp())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_snapshot_name() {
+        let time: Timestamp = "2025-03-26T15:00:00Z".parse().unwrap();
+
+        let name = snapshot_name("boops.toml", time);
+        assert_eq!(name, "boops.20250326T150000Z.toml");
+        assert_eq!(snapshot_time("boops.toml", &name), Some(time));
+
+        // other instances and unrelated files
+        let other = snapshot_name("boops.quest.toml", time);
+        assert_eq!(snapshot_time("boops.toml", &other), None);
+        assert_eq!(snapshot_time("boops.toml", "boops.toml.bak"), None);
+    }
+}
@@ -0,0 +1,181 @@
+use std::{
+    fs::File,
+    io::{self, BufWriter, Write},
+    net::{IpAddr, SocketAddr},
+    path::Path,
+    sync::{Mutex, OnceLock},
+    time::SystemTime,
+};
+
+use tracing::{error, info};
+
+/// pcapng block types
+const SECTION_HEADER_BLOCK: u32 = 0x0A0D_0D0A;
+const INTERFACE_DESCRIPTION_BLOCK: u32 = 1;
+const ENHANCED_PACKET_BLOCK: u32 = 6;
+
+/// Raw IP packets, version taken from the first nibble
+const LINKTYPE_RAW: u16 = 101;
+
+const IPPROTO_UDP: u8 = 17;
+
+/// Active capture, if enabled
+static CAPTURE: OnceLock<Mutex<BufWriter<File>>> = OnceLock::new();
+
+/// Start writing OSC datagrams to a pcapng file at `path`
+pub(crate) fn start(path: &Path) {
+    let mut file = match File::create(path) {
+        Ok(file) => BufWriter::new(file),
+        Err(e) => {
+            error!(err=%e, "failed to create capture file {}", path.display());
+            return;
+        }
+    };
+
+    if let Err(e) = write_header(&mut file) {
+        error!(err=%e, "failed to write capture header");
+        return;
+    }
+
+    info!("capturing OSC traffic to {}", path.display());
+    CAPTURE.set(Mutex::new(file)).ok();
+}
+
+/// Record datagram from `src` to `dst`, if capturing
+pub(crate) fn record(src: SocketAddr, dst: SocketAddr, payload: &[u8]) {
+    let Some(capture) = CAPTURE.get() else {
+        return;
+    };
+
+    let Some(packet) = udp_packet(src, dst, payload) else {
+        return;
+    };
+
+    let mut file = capture.lock().unwrap();
+    if let Err(e) = write_packet(&mut *file, &packet).and_then(|_| file.flush()) {
+        error!(err=%e, "failed to write captured packet");
+    }
+}
+
+fn write_header(w: &mut impl Write) -> io::Result<()> {
+    // section header: byte order magic, version 1.0, unknown section length
+    let mut body = vec![];
+    body.extend(0x1A2B_3C4Du32.to_le_bytes());
+    body.extend(1u16.to_le_bytes());
+    body.extend(0u16.to_le_bytes());
+    body.extend((-1i64).to_le_bytes());
+    write_block(w, SECTION_HEADER_BLOCK, &body)?;
+
+    // single interface, no snap length, microsecond timestamps
+    let mut body = vec![];
+    body.extend(LINKTYPE_RAW.to_le_bytes());
+    body.extend(0u16.to_le_bytes());
+    body.extend(0u32.to_le_bytes());
+    write_block(w, INTERFACE_DESCRIPTION_BLOCK, &body)
+}
+
+fn write_packet(w: &mut impl Write, packet: &[u8]) -> io::Result<()> {
+    let micros = SystemTime::now()
+        .duration_since(SystemTime::UNIX_EPOCH)
+        .unwrap_or_default()
+        .as_micros() as u64;
+
+    let mut body = vec![];
+    body.extend(0u32.to_le_bytes());
+    body.extend(((micros >> 32) as u32).to_le_bytes());
+    body.extend((micros as u32).to_le_bytes());
+    body.extend((packet.len() as u32).to_le_bytes());
+    body.extend((packet.len() as u32).to_le_bytes());
+    body.extend(packet);
+    write_block(w, ENHANCED_PACKET_BLOCK, &body)
+}
+
+/// Write block with type, padded body and both length fields
+fn write_block(w: &mut impl Write, block_type: u32, body: &[u8]) -> io::Result<()> {
+    let padding = (4 - body.len() % 4) % 4;
+    let total_len = (12 + body.len() + padding) as u32;
+
+    w.write_all(&block_type.to_le_bytes())?;
+    w.write_all(&total_len.to_le_bytes())?;
+    w.write_all(body)?;
+    w.write_all(&[0u8; 3][..padding])?;
+    w.write_all(&total_len.to_le_bytes())
+}
+
+/// Build IP packet with UDP header around `payload`
+///
+/// The UDP checksum is left at zero, which Wireshark accepts
+fn udp_packet(src: SocketAddr, dst: SocketAddr, payload: &[u8]) -> Option<Vec<u8>> {
+    let udp_len = u16::try_from(8 + payload.len()).ok()?;
+
+    let mut packet = match (src.ip(), dst.ip()) {
+        (IpAddr::V4(src_ip), IpAddr::V4(dst_ip)) => {
+            let total_len = 20u16.checked_add(udp_len)?;
+            let mut header = vec![0x45, 0];
+            header.extend(total_len.to_be_bytes());
+            // identification, don't fragment
+            header.extend([0, 0, 0x40, 0]);
+            header.extend([64, IPPROTO_UDP, 0, 0]);
+            header.extend(src_ip.octets());
+            header.extend(dst_ip.octets());
+
+            let checksum = ipv4_checksum(&header);
+            header[10..12].copy_from_slice(&checksum.to_be_bytes());
+            header
+        }
+        (src_ip, dst_ip) => {
+            let to_v6 = |ip: IpAddr| match ip {
+                IpAddr::V4(ip) => ip.to_ipv6_mapped(),
+                IpAddr::V6(ip) => ip,
+            };
+
+            let mut header = vec![0x60, 0, 0, 0];
+            header.extend(udp_len.to_be_bytes());
+            header.extend([IPPROTO_UDP, 64]);
+            header.extend(to_v6(src_ip).octets());
+            header.extend(to_v6(dst_ip).octets());
+            header
+        }
+    };
+
+    packet.extend(src.port().to_be_bytes());
+    packet.extend(dst.port().to_be_bytes());
+    packet.extend(udp_len.to_be_bytes());
+    packet.extend([0, 0]);
+    packet.extend(payload);
+
+    Some(packet)
+}
+
+/// One's complement sum of the header's 16 bit words
+fn ipv4_checksum(header: &[u8]) -> u16 {
+    let mut sum: u32 = header
+        .chunks(2)
+        .map(|word| u16::from_be_bytes([word[0], word[1]]) as u32)
+        .sum();
+    while sum > 0xFFFF {
+        sum = (sum & 0xFFFF) + (sum >> 16);
+    }
+
+    !(sum as u16)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_udp_packet() {
+        let src: SocketAddr = "127.0.0.1:9000".parse().unwrap();
+        let dst: SocketAddr = "127.0.0.1:9001".parse().unwrap();
+        let packet = udp_packet(src, dst, b"/boop\0\0\0,\0\0\0").unwrap();
+
+        assert_eq!(packet.len(), 20 + 8 + 12);
+        // total length
+        assert_eq!(&packet[2..4], &40u16.to_be_bytes());
+        // valid header checksum sums up to zero
+        assert_eq!(ipv4_checksum(&packet[..20]), 0);
+        // ports
+        assert_eq!(&packet[20..24], &[0x23, 0x28, 0x23, 0x29]);
+    }
+}
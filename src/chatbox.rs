@@ -0,0 +1,184 @@
+use std::collections::VecDeque;
+
+use jiff::{SignedDuration, Timestamp};
+
+use crate::config::MilestoneSequence;
+
+/// Message waiting for the chatbox cooldown
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Outgoing {
+    Text(String),
+    /// milestone played as timed sequence
+    Milestone(String, MilestoneSequence),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum Priority {
+    /// latest state, replaced by newer updates while waiting
+    Update,
+    /// milestones, storms and summaries, sent in order and never dropped
+    Important,
+}
+
+/// Whether the chatbox shows one of our messages, which needs clearing
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub(crate) enum ChatboxState {
+    /// nothing to clear
+    #[default]
+    Idle,
+    /// message shown until `clear_at`, unless replaced by the next one
+    Shown { clear_at: Timestamp },
+}
+
+/// Outgoing chatbox messages, sent no faster than VRChat's rate limit allows
+///
+/// Important messages go first, followed by the latest update. An update
+/// queued before an important message is dropped, that one shows the latest
+/// numbers already.
+#[derive(Debug, Default)]
+pub(crate) struct ChatboxQueue {
+    important: VecDeque<Outgoing>,
+    update: Option<Outgoing>,
+    /// earliest time for the next message
+    next_send: Option<Timestamp>,
+    state: ChatboxState,
+}
+
+impl ChatboxQueue {
+    pub(crate) fn push(&mut self, message: Outgoing, priority: Priority) {
+        match priority {
+            Priority::Update => self.update = Some(message),
+            Priority::Important => {
+                self.update = None;
+                self.important.push_back(message);
+            }
+        }
+    }
+
+    /// Next message to send at `now`, if any is queued and the cooldown since
+    /// the previous one has passed
+    pub(crate) fn pop(&mut self, now: Timestamp, cooldown: SignedDuration) -> Option<Outgoing> {
+        if self.next_send.is_some_and(|next_send| now < next_send) {
+            return None;
+        }
+
+        let message = self.important.pop_front().or_else(|| self.update.take())?;
+        self.next_send = Some(now + cooldown);
+        Some(message)
+    }
+
+    /// Hold back further messages until `until`, e.g. while the parts of a
+    /// long message are sent
+    pub(crate) fn delay_until(&mut self, until: Timestamp) {
+        self.next_send = self.next_send.max(Some(until));
+    }
+
+    /// When the next queued message is due, `None` while the queue is empty
+    pub(crate) fn next_due(&self) -> Option<Timestamp> {
+        if self.important.is_empty() && self.update.is_none() {
+            return None;
+        }
+        Some(self.next_send.unwrap_or_else(Timestamp::now))
+    }
+
+    /// A message was sent, which stays until `clear_at`, forever if `None`
+    pub(crate) fn shown(&mut self, clear_at: Option<Timestamp>) {
+        self.state = match clear_at {
+            Some(clear_at) => ChatboxState::Shown { clear_at },
+            None => ChatboxState::Idle,
+        };
+    }
+
+    /// The chatbox was cleared, or is cleared by someone else
+    pub(crate) fn cleared(&mut self) {
+        self.state = ChatboxState::Idle;
+    }
+
+    /// When the shown message should be cleared
+    ///
+    /// Never within `guard` before the next queued message, which replaces
+    /// the shown one anyway. Clearing right before it would only make the
+    /// chatbox flicker.
+    pub(crate) fn next_clear(&self, guard: SignedDuration) -> Option<Timestamp> {
+        let ChatboxState::Shown { clear_at } = self.state else {
+            return None;
+        };
+        match self.next_due() {
+            Some(due) if due <= clear_at + guard => None,
+            _ => Some(clear_at),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn text(message: &str) -> Outgoing {
+        Outgoing::Text(message.into())
+    }
+
+    #[test]
+    fn test_updates_are_coalesced() {
+        let now = Timestamp::now();
+        let cooldown = SignedDuration::from_secs(2);
+        let mut queue = ChatboxQueue::default();
+
+        queue.push(text("1"), Priority::Update);
+        assert_eq!(queue.pop(now, cooldown), Some(text("1")));
+
+        queue.push(text("2"), Priority::Update);
+        queue.push(text("3"), Priority::Update);
+        assert_eq!(queue.pop(now, cooldown), None);
+        assert_eq!(queue.next_due(), Some(now + cooldown));
+        assert_eq!(queue.pop(now + cooldown, cooldown), Some(text("3")));
+        assert_eq!(queue.next_due(), None);
+    }
+
+    #[test]
+    fn test_important_messages_are_kept() {
+        let now = Timestamp::now();
+        let cooldown = SignedDuration::from_secs(2);
+        let mut queue = ChatboxQueue::default();
+        queue.delay_until(now + cooldown);
+
+        queue.push(text("update"), Priority::Update);
+        queue.push(text("milestone"), Priority::Important);
+        queue.push(text("storm"), Priority::Important);
+        queue.push(text("newer update"), Priority::Update);
+
+        let sent: Vec<_> = (1..=4)
+            .filter_map(|i| queue.pop(now + cooldown * i, cooldown))
+            .collect();
+        assert_eq!(
+            sent,
+            [text("milestone"), text("storm"), text("newer update")]
+        );
+    }
+
+    #[test]
+    fn test_no_clear_before_pending_message() {
+        let now = Timestamp::now();
+        let cooldown = SignedDuration::from_secs(2);
+        let guard = SignedDuration::from_secs(1);
+        let mut queue = ChatboxQueue::default();
+        assert_eq!(queue.next_clear(guard), None);
+
+        queue.push(text("1"), Priority::Update);
+        queue.pop(now, cooldown);
+        queue.shown(Some(now + SignedDuration::from_millis(1500)));
+        assert_eq!(
+            queue.next_clear(guard),
+            Some(now + SignedDuration::from_millis(1500))
+        );
+
+        // due at +2s, within the guard after the clear at +1.5s
+        queue.push(text("2"), Priority::Update);
+        assert_eq!(queue.next_clear(guard), None);
+
+        queue.pop(now + cooldown, cooldown);
+        queue.shown(Some(now + cooldown + SignedDuration::from_millis(1500)));
+        queue.cleared();
+        assert_eq!(queue.next_clear(guard), None);
+    }
+}
@@ -0,0 +1,344 @@
+//! Entry point of the `osc-booper` binary
+
+use std::{path::Path, sync::Arc};
+
+use clap::Parser;
+use dotenvy::dotenv;
+use tokio::sync::Notify;
+use tokio_util::sync::CancellationToken;
+
+use crate::{
+    capture,
+    command::ControlRequest,
+    config::{
+        Cli, CliCommand, DataFormat, LoggingOptions, MetricsCommand, Options, OutputFormat,
+        Platform,
+    },
+    events::{self, BooperEvent},
+    instance, ipc, logging, metrics,
+    osc::OscBooper,
+    oscquery, paths, presets, report, service, simulate,
+    storage::BoopStorage,
+    watch,
+};
+
+/// Run the `osc-booper` binary with the process arguments
+pub async fn run() {
+    let mut args = Cli::parse();
+    if args.service {
+        service::init();
+    }
+    dotenv().ok();
+
+    if let Some(name) = &args.instance {
+        instance::set(name.clone());
+    }
+    let migrations = paths::init(args.config.clone(), args.data_dir.clone());
+
+    // logging is configured by the config file, so errors loading it are
+    // logged with the defaults
+    let opt = Options::new(&args);
+    let logging = match &opt {
+        Ok(opt) => opt.logging.clone(),
+        Err(_) => {
+            let mut logging = LoggingOptions::default();
+            logging.apply_args(&args);
+            logging
+        }
+    };
+
+    // keep stdout clean for the JSON event stream
+    let json_output = args.output == OutputFormat::Json;
+    let _log_guard = logging::init(&logging, json_output);
+    for migration in &migrations {
+        migration.log();
+    }
+
+    // works with broken packs, to disable them again
+    if let Some(CliCommand::Presets { command }) = &args.command {
+        presets::run(command);
+        return;
+    }
+
+    let opt = match opt {
+        Ok(opt) => opt,
+        Err(e) => {
+            tracing::error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    if let Some(command) = args.command.take() {
+        run_subcommand(command, &opt).await;
+        return;
+    }
+
+    let token = CancellationToken::new();
+    setup_signal_handlers(token.clone()).await;
+
+    // todo: get sending port from VRC mDNS response
+
+    if let Some(path) = &opt.capture_file {
+        capture::start(path);
+    }
+
+    // set up OSC listener/responder & main loop
+    let osc_transport = opt.osc_transport;
+    let platform = opt.platform;
+    let announce = opt.announce;
+    let osc_ip = opt.osc_listen_addr();
+    let service = args.service;
+    let mut osc = match OscBooper::new(opt).await {
+        Ok(osc) => osc,
+        Err(e) => {
+            tracing::error!("{e}");
+            std::process::exit(1);
+        }
+    };
+
+    watch::spawn(token.clone(), args, osc.commands());
+
+    if json_output {
+        events::spawn_stdout_sink(osc.subscribe());
+    }
+
+    // set up OSCQuery & mDNS announcements, only VRChat discovers services
+    let mdns = if announce && platform == Platform::VRChat {
+        // follow VRChat across restarts, checking its avatar parameters
+        let reannounce = Arc::new(Notify::new());
+        oscquery::client::spawn(
+            token.clone(),
+            osc.oscquery_nodes(),
+            osc.subscribe(),
+            osc.commands(),
+            reannounce.clone(),
+        );
+
+        let mdns = oscquery::announce(
+            token.clone(),
+            reannounce,
+            osc_ip,
+            osc.osc_port,
+            osc_transport,
+            osc.oscquery_nodes(),
+            osc.parameters(),
+        )
+        .await;
+        match mdns {
+            Ok(mdns) => Some(mdns),
+            Err(e) => {
+                tracing::error!("{e}, VRChat won't find us");
+                None
+            }
+        }
+    } else {
+        None
+    };
+
+    if service {
+        service::ready(token.clone());
+    }
+
+    // run main loop
+    let result = osc.run(token.clone()).await;
+    if let Err(e) = &result {
+        tracing::error!("{e}");
+        token.cancel();
+    }
+
+    // wait for the mDNS goodbye
+    if let Some(mdns) = mdns {
+        mdns.await.ok();
+    }
+
+    if service {
+        service::stopped();
+    }
+
+    if result.is_err() {
+        std::process::exit(1);
+    }
+
+    // todo: more prometheus metrics (served on http_port)
+    //      - can I include avatar ID in there as label?
+    //      needs prometheus_enable (pe) and prometheus_port (pp)
+}
+
+/// Run one-off CLI subcommand instead of the booper
+async fn run_subcommand(command: CliCommand, options: &Options) {
+    match command {
+        CliCommand::Adjust { by } => {
+            // a running booper would overwrite the stored counters
+            if let Some(response) = ipc::request(&ControlRequest::Adjust { by }).await {
+                let Some(stats) = response.stats else {
+                    let error = response.error.unwrap_or_default();
+                    tracing::error!(err=%error, "failed to adjust boops of the running booper");
+                    std::process::exit(1);
+                };
+                tracing::info!(
+                    by,
+                    today = stats.today,
+                    total = stats.total,
+                    "adjusted boops of the running booper"
+                );
+                return;
+            }
+
+            let mut storage = BoopStorage::load()
+                .with_reset_policy(options.reset_policy)
+                .with_reset_time(options.reset_time, options.reset_timezone.clone())
+                .with_counter_resets(&options.counters);
+            // the daily summary is only posted by a running booper
+            if let Some(summary) = storage.check_reset() {
+                events::append(BooperEvent::Reset {
+                    previous: summary.boops,
+                    date: summary.start.date(),
+                    record: summary.record,
+                    total: summary.total,
+                });
+                if let Some(reports) = &options.reports {
+                    report::write(reports, &summary);
+                }
+            }
+            storage.adjust(by);
+            storage.save();
+
+            let (today, total) = storage.boop_numbers();
+            tracing::info!(by, today, total, "adjusted boops");
+            events::append(BooperEvent::Adjusted {
+                by,
+                source: "cli".into(),
+                today,
+                total,
+            });
+        }
+        CliCommand::Export { path, format } => {
+            let storage = BoopStorage::load();
+            let contents = match data_format(&path, format) {
+                DataFormat::Csv => storage.to_csv(),
+                DataFormat::Json => storage.to_json().expect("storage is valid json"),
+            };
+
+            if let Err(e) = std::fs::write(&path, contents) {
+                tracing::error!(err=%e, "failed to write {}", path.display());
+                std::process::exit(1);
+            }
+            tracing::info!("exported boops to {}", path.display());
+        }
+        CliCommand::Import { path, format } => {
+            // a running booper would overwrite the imported counters
+            if ipc::request(&ControlRequest::Stats).await.is_some() {
+                tracing::error!("osc-booper is running, stop it before importing boops");
+                std::process::exit(1);
+            }
+
+            let contents = match std::fs::read_to_string(&path) {
+                Ok(contents) => contents,
+                Err(e) => {
+                    tracing::error!(err=%e, "failed to read {}", path.display());
+                    std::process::exit(1);
+                }
+            };
+
+            let result = match data_format(&path, format) {
+                DataFormat::Csv => {
+                    let mut storage = BoopStorage::load();
+                    storage.apply_csv(&contents).map(|_| storage)
+                }
+                DataFormat::Json => BoopStorage::from_json(&contents).map_err(|e| e.to_string()),
+            };
+            let mut storage = match result {
+                Ok(storage) => storage,
+                Err(e) => {
+                    tracing::error!(err=%e, "failed to import {}", path.display());
+                    std::process::exit(1);
+                }
+            };
+
+            storage.save();
+            let (today, total) = storage.boop_numbers();
+            tracing::info!(today, total, "imported boops from {}", path.display());
+        }
+        CliCommand::ListParameters => oscquery::client::list_parameters().await,
+        CliCommand::Metrics {
+            command: MetricsCommand::Dashboard,
+        } => {
+            let dashboard = serde_json::to_string_pretty(&metrics::dashboard())
+                .expect("dashboard is valid json");
+            println!("{dashboard}");
+        }
+        CliCommand::Presets { command } => presets::run(&command),
+        CliCommand::Simulate(args) => simulate::run(&args, options).await,
+    }
+}
+
+/// Format of an export file, exits if it can't be guessed from the path
+fn data_format(path: &Path, format: Option<DataFormat>) -> DataFormat {
+    match format.or_else(|| DataFormat::from_path(path)) {
+        Some(format) => format,
+        None => {
+            tracing::error!("unknown format of {}, pass --format", path.display());
+            std::process::exit(1);
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn setup_signal_handlers(token: CancellationToken) {
+    // https://docs.rs/tokio/latest/tokio/signal/unix/struct.Signal.html
+    use tokio::signal::unix::{SignalKind, signal};
+
+    let mut sigterm = signal(SignalKind::terminate()).unwrap();
+    let mut sigint = signal(SignalKind::interrupt()).unwrap();
+
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = sigterm.recv() => {
+                tracing::debug!("received SIGTERM");
+                token.cancel();
+            },
+            _ = sigint.recv() => {
+                tracing::info!("received SIGINT");
+                token.cancel();
+            }
+        }
+    });
+
+    tracing::debug!("waiting for unix signal");
+}
+
+#[cfg(windows)]
+async fn setup_signal_handlers(token: CancellationToken) {
+    // https://docs.rs/tokio/latest/tokio/signal/windows/index.html
+    // https://learn.microsoft.com/en-us/windows/console/console-control-handlers
+    // https://learn.microsoft.com/en-us/windows/console/handlerroutine
+    use tokio::signal::windows;
+
+    let mut ctrl_break = windows::ctrl_break().unwrap();
+    let mut ctrl_c = windows::ctrl_c().unwrap();
+    let mut ctrl_close = windows::ctrl_close().unwrap();
+    let mut ctrl_shutdown = windows::ctrl_shutdown().unwrap();
+
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = ctrl_break.recv() => {
+                tracing::debug!("received CTRL_BREAK");
+                token.cancel();
+            },
+            _ = ctrl_c.recv() => {
+                tracing::info!("received CTRL_C");
+                token.cancel();
+            },
+            _ = ctrl_close.recv() => {
+                tracing::debug!("received CTRL_CLOSE");
+                token.cancel();
+            },
+            _ = ctrl_shutdown.recv() => {
+                tracing::debug!("received CTRL_SHUTDOWN");
+                token.cancel();
+            },
+        };
+    });
+
+    tracing::debug!("waiting for windows signal");
+}
@@ -0,0 +1,221 @@
+use std::{collections::BTreeMap, net::IpAddr};
+
+use jiff::SignedDuration;
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+use tokio::sync::{mpsc, oneshot};
+
+use crate::{
+    config::Options,
+    storage::{CounterBoops, GroupBoops},
+    world::World,
+};
+
+/// Runtime commands handled by the main [`crate::osc::OscBooper`] loop
+#[derive(Debug)]
+pub(crate) enum Command {
+    /// Post the current stats to the chatbox
+    ///
+    /// Uses the given template instead of the configured message template,
+    /// if set. Manual posts bypass pause, idle detection and cooldown.
+    PostStats {
+        template: Option<String>,
+        manual: bool,
+    },
+
+    /// Reset today's boops, regardless of reset policy
+    ResetToday,
+
+    /// Set pause mode (neither count nor post), toggles if `None`
+    Pause(Option<bool>),
+
+    /// Pause for the given time, resuming automatically
+    #[cfg_attr(not(all(windows, feature = "toast")), allow(dead_code))]
+    PauseFor(SignedDuration),
+
+    /// Set freeze mode (count without posting), toggles if `None`
+    Freeze(Option<bool>),
+
+    /// Revert the most recently counted boop
+    Undo,
+
+    /// Add to (or subtract from) the counters
+    Adjust {
+        by: i64,
+        /// where the adjustment came from, for the event log
+        source: &'static str,
+    },
+
+    /// Reply with current stats
+    Stats(oneshot::Sender<StatsSnapshot>),
+
+    /// Apply options re-read from the changed config file
+    Reload(Box<Options>),
+
+    /// Change options at runtime, until the next reload
+    Configure(ConfigPatch),
+
+    /// User entered another world, according to VRChat's log
+    WorldChanged(World),
+
+    /// VRChat's OSCQuery server was found, with its address and the port it
+    /// receives OSC on
+    VrchatFound { addr: IpAddr, osc_port: u16 },
+
+    /// VRChat is gone, e.g. after quitting or crashing
+    VrchatLost { reason: &'static str },
+
+    /// We send the shared chatbox messages now, or hand ours to the booper
+    /// sending them
+    CoordinationRole { leader: bool },
+
+    /// Line of another booper sharing our chatbox, `None` once it's gone
+    CoordinatedLine { name: String, line: Option<String> },
+}
+
+pub(crate) type CommandSender = mpsc::Sender<Command>;
+pub(crate) type CommandReceiver = mpsc::Receiver<Command>;
+
+/// Create command channel
+pub(crate) fn channel() -> (CommandSender, CommandReceiver) {
+    mpsc::channel(32)
+}
+
+/// Current counters and state
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct StatsSnapshot {
+    pub today: u32,
+    pub total: u64,
+    pub yesterday: u32,
+    /// boops since the booper started or VRChat reconnected
+    pub session: u32,
+    pub today_record: u32,
+    pub week: u64,
+    pub month: u64,
+    pub streak: u32,
+    /// boops within the last minute
+    pub rate: u32,
+    pub avatar_id: Option<String>,
+    pub avatar_total: Option<u64>,
+    pub avatar_today: Option<u32>,
+    pub world_id: Option<String>,
+    pub world_name: Option<String>,
+    pub counters: BTreeMap<String, CounterBoops>,
+    pub groups: BTreeMap<String, GroupBoops>,
+    pub paused: bool,
+    pub afk: bool,
+    pub frozen: bool,
+    /// chatbox messages weren't turned off via the avatar menu
+    pub chatbox_enabled: bool,
+}
+
+/// Options safe to change via the HTTP API, unset ones are kept
+#[derive(Debug, Default, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct ConfigPatch {
+    pub message_template: Option<String>,
+    pub split_long_messages: Option<bool>,
+    #[validate(minimum = 1.5)]
+    pub chatbox_cooldown_secs: Option<f64>,
+    #[validate(minimum = 0.5)]
+    pub chatbox_clear_secs: Option<f64>,
+    pub clear_chatbox: Option<bool>,
+}
+
+impl ConfigPatch {
+    pub(crate) fn apply(self, options: &mut Options) {
+        if let Some(template) = self.message_template {
+            options.message_template = template;
+        }
+        if let Some(split) = self.split_long_messages {
+            options.split_long_messages = split;
+        }
+        if let Some(cooldown) = self.chatbox_cooldown_secs {
+            options.chatbox_cooldown_secs = cooldown;
+        }
+        if let Some(clear) = self.chatbox_clear_secs {
+            options.chatbox_clear_secs = clear;
+        }
+        if let Some(clear) = self.clear_chatbox {
+            options.clear_chatbox = clear;
+        }
+    }
+}
+
+/// Request of external control interfaces
+#[derive(Debug, Serialize, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub(crate) enum ControlRequest {
+    Stats,
+    Pause,
+    Resume,
+    PostNow,
+    Adjust { by: i64 },
+    Undo,
+    ResetToday,
+}
+
+/// Response to [`ControlRequest`], always includes the resulting stats
+#[derive(Debug, Serialize, Deserialize)]
+pub(crate) struct ControlResponse {
+    pub ok: bool,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub stats: Option<StatsSnapshot>,
+
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl ControlResponse {
+    pub(crate) fn error(error: impl Into<String>) -> Self {
+        ControlResponse {
+            ok: false,
+            stats: None,
+            error: Some(error.into()),
+        }
+    }
+}
+
+/// Execute control request via the command channel
+pub(crate) async fn execute(
+    request: ControlRequest,
+    commands: &CommandSender,
+    source: &'static str,
+) -> ControlResponse {
+    let command = match request {
+        ControlRequest::Stats => None,
+        ControlRequest::Pause => Some(Command::Pause(Some(true))),
+        ControlRequest::Resume => Some(Command::Pause(Some(false))),
+        ControlRequest::PostNow => Some(Command::PostStats {
+            template: None,
+            manual: true,
+        }),
+        ControlRequest::Adjust { by } => Some(Command::Adjust { by, source }),
+        ControlRequest::Undo => Some(Command::Undo),
+        ControlRequest::ResetToday => Some(Command::ResetToday),
+    };
+
+    let sent = match command {
+        Some(command) => commands.send(command).await.is_ok(),
+        None => true,
+    };
+    if !sent {
+        return ControlResponse::error("booper is shutting down");
+    }
+
+    // commands are handled in order, so stats include the command's effect
+    let (tx, rx) = oneshot::channel();
+    if commands.send(Command::Stats(tx)).await.is_err() {
+        return ControlResponse::error("booper is shutting down");
+    }
+
+    match rx.await {
+        Ok(stats) => ControlResponse {
+            ok: true,
+            stats: Some(stats),
+            error: None,
+        },
+        Err(_) => ControlResponse::error("booper is shutting down"),
+    }
+}
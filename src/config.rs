@@ -1,42 +1,351 @@
-use std::{fs, path::Path};
+use std::{
+    collections::BTreeMap,
+    fs,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    path::{Path, PathBuf},
+};
 
-use clap::Parser;
-use serde::{Deserialize, Serialize};
-use serde_valid::{
-    Validate,
-    toml::{FromTomlStr, ToTomlString},
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use jiff::{
+    SignedDuration,
+    civil::{DateTime, Time, Weekday},
+    tz::TimeZone,
 };
-use tracing::error;
+use regex::Regex;
+use rosc::OscType;
+use serde::{Deserialize, Serialize};
+use serde_valid::Validate;
+use thiserror::Error;
+use tracing::warn;
+
+use crate::{goals, i18n, instance, paths, presets, template};
 
-const FILE_NAME: &str = "config.toml";
+pub const FILE_NAME: &str = "config.toml";
 
 /// Send one or many messages to a UDP-based OSC-accepting socket
 #[derive(Parser, Debug, Serialize, Deserialize)]
 #[command(version, about, long_about = None)]
-pub(crate) struct Cli {
+pub struct Cli {
     /// Port to send to [default: 9000]
     #[arg(short, long, value_parser=clap::value_parser!(u16).range(1024..))]
     send: Option<u16>,
 
+    /// Port to listen on [default: random, 9001 with --no-announce]
+    #[arg(short, long, value_parser=clap::value_parser!(u16).range(1024..))]
+    listen: Option<u16>,
+
+    /// Skip OSCQuery/mDNS announcements, e.g. when VRChat's OSC ports are set
+    /// via launch options
+    #[arg(long, default_value_t = false)]
+    no_announce: bool,
+
+    /// Write received and sent OSC datagrams to a pcapng file, for Wireshark
+    #[arg(long, value_name = "FILE")]
+    capture: Option<PathBuf>,
+
+    /// Name of this instance, to run one booper per VRChat client
+    ///
+    /// Uses separate config, storage and event files like `config.<name>.toml`
+    /// as well as distinct announcement, socket and D-Bus names
+    #[arg(long, value_parser = instance::parse_name)]
+    pub instance: Option<String>,
+
     /// Create config.toml with specified/default values
     #[arg(long, default_value_t = false)]
     save: bool,
 
-    /// OSC parameter suffix for boops [default: /OSCBoop]
+    /// OSC parameter suffix for boops [default: /OSCBoop on VRChat]
     ///
-    /// Matching is done via str.ends_with({boop_address})
+    /// Full addresses match exactly, `*` and `?` match like globs, see
+    /// `boop_address` in the config
     #[arg(short, long, value_parser=clap::value_parser!(String))]
     boop_address: Option<String>,
+
+    /// Output format of stdout
+    ///
+    /// `json` prints every event as JSON line, logs go to stderr instead
+    #[arg(long, value_enum, default_value_t = OutputFormat::Text)]
+    pub output: OutputFormat,
+
+    /// Format of log lines [default: text]
+    #[arg(long, value_enum)]
+    log_format: Option<LogFormat>,
+
+    /// Also write logs to files in this directory, rotated daily by default
+    #[arg(long, value_name = "DIR")]
+    log_dir: Option<PathBuf>,
+
+    /// Config file to use [default: config.toml in the OS config directory]
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Directory of boops and the event log [default: OS data directory]
+    #[arg(long, value_name = "DIR")]
+    pub data_dir: Option<PathBuf>,
+
+    /// Run under a service manager, notifying systemd or the Windows service
+    /// control manager about startup and shutdown
+    #[arg(long, default_value_t = false)]
+    pub service: bool,
+
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum OutputFormat {
+    /// human-readable logs
+    Text,
+    /// JSON event stream
+    Json,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// human-readable lines
+    #[default]
+    Text,
+    /// one JSON object per line, including span fields
+    Json,
+}
+
+/// One-off commands, run instead of the booper
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+pub enum CliCommand {
+    /// Add or subtract boops from the stored counters
+    ///
+    /// Sent to the running booper via its control socket if there is one,
+    /// stop it first otherwise, it would overwrite the change
+    Adjust {
+        /// Amount to add, negative to subtract
+        #[arg(allow_negative_numbers = true)]
+        by: i64,
+    },
+
+    /// Write stored boops, daily history, avatars and counters to a file
+    Export {
+        path: PathBuf,
+
+        /// File format, guessed from the file extension if unset
+        #[arg(long, value_enum)]
+        format: Option<DataFormat>,
+    },
+
+    /// Replace stored boops with the ones of an exported file
+    ///
+    /// Refused while a booper is running, it would overwrite the imported boops
+    Import {
+        path: PathBuf,
+
+        /// File format, guessed from the file extension if unset
+        #[arg(long, value_enum)]
+        format: Option<DataFormat>,
+    },
+
+    /// Prometheus metrics helpers
+    Metrics {
+        #[command(subcommand)]
+        command: MetricsCommand,
+    },
+
+    /// Print the contact receivers (bool and float parameters) of the
+    /// current avatar, asking VRChat's OSCQuery server
+    ListParameters,
+
+    /// Manage packs of text suffixes and templates in the `presets`
+    /// directory next to the config file
+    Presets {
+        #[command(subcommand)]
+        command: PresetsCommand,
+    },
+
+    /// Send synthetic boops, avatar changes and parameters to a running
+    /// booper, to try it out without VRChat
+    Simulate(SimulateArgs),
+}
+
+#[derive(Args, Debug, Clone, Serialize, Deserialize)]
+pub struct SimulateArgs {
+    /// Port of the booper [default: listen_port, or 9001]
+    #[arg(long)]
+    pub port: Option<u16>,
+
+    /// Boops per minute, released again after a moment
+    #[arg(long, default_value_t = 30)]
+    pub boops: u32,
+
+    /// Avatar changes per minute
+    #[arg(long, default_value_t = 0)]
+    pub avatar_changes: u32,
+
+    /// Updates of an unrelated parameter per second, like a moving avatar
+    #[arg(long, default_value_t = 0)]
+    pub parameters: u32,
+
+    /// Stop after this long, e.g. `5m` [default: until stopped]
+    #[arg(long)]
+    pub duration: Option<SignedDuration>,
+}
+
+#[derive(ValueEnum, Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub enum DataFormat {
+    /// one row per count, for spreadsheets and graphs
+    Csv,
+    /// complete storage, for backups
+    Json,
+}
+
+impl DataFormat {
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(DataFormat::Csv),
+            "json" => Some(DataFormat::Json),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+pub enum MetricsCommand {
+    /// Print a Grafana dashboard JSON for the exported metrics, ready to
+    /// import
+    Dashboard,
+}
+
+#[derive(Subcommand, Debug, Serialize, Deserialize)]
+pub enum PresetsCommand {
+    /// List preset packs, in the order they're applied
+    List,
+
+    /// Enable a disabled preset pack
+    Enable {
+        /// File name without extension
+        name: String,
+    },
+
+    /// Disable a preset pack, without deleting it
+    Disable {
+        /// File name without extension
+        name: String,
+    },
 }
 
 #[derive(Debug, Serialize, Deserialize, Validate)]
-pub(crate) struct Options {
+#[validate(custom = Options::validate_templates)]
+pub struct Options {
     #[validate(minimum = 0)]
     #[serde(default = "default_osc_send_port")]
     pub osc_send_port: u16,
 
-    #[serde(default = "default_boop_address")]
-    pub boop_address: String,
+    /// Send to the OSC port VRChat reports via OSCQuery instead of
+    /// `osc_send_port`, e.g. when it's started with `--osc`
+    #[serde(default = "default_true")]
+    pub follow_vrchat_port: bool,
+
+    /// Port we receive OSC packets on, random if unset
+    #[validate(minimum = 1024)]
+    #[serde(default)]
+    pub listen_port: Option<u16>,
+
+    /// Size of the UDP receive buffer in bytes, larger datagrams are dropped
+    #[validate(minimum = 512)]
+    #[validate(maximum = 65507)]
+    #[serde(default = "default_receive_buffer")]
+    pub receive_buffer: usize,
+
+    /// Bind to `::1` instead of `127.0.0.1`
+    ///
+    /// OSCQuery is then served on both, with A and AAAA records announced
+    #[serde(default)]
+    pub ipv6: bool,
+
+    /// Address our OSC socket and OSCQuery server bind to, loopback if unset
+    ///
+    /// Set to a LAN address when VRChat runs on another machine
+    #[serde(default)]
+    pub osc_listen_addr: Option<IpAddr>,
+
+    /// Address OSC packets are sent to, loopback if unset
+    #[serde(default)]
+    pub osc_send_addr: Option<IpAddr>,
+
+    /// Addresses OSC packets are accepted from, others are dropped
+    ///
+    /// Loopback, `osc_send_addr` and the VRChat found via OSCQuery if unset
+    #[serde(default)]
+    pub osc_allowed_sources: Option<Vec<IpAddr>>,
+
+    /// Relay every received OSC packet to these addresses, e.g.
+    /// `["127.0.0.1:9011"]` for other tools reading avatar parameters
+    #[serde(default)]
+    pub forward_to: Vec<SocketAddr>,
+
+    /// Address the HTTP server binds to, loopback if unset
+    #[serde(default)]
+    pub http_listen_addr: Option<IpAddr>,
+
+    /// Announce ourselves via OSCQuery and mDNS
+    ///
+    /// Without announcements, we listen on `listen_port` or 9001, VRChat's
+    /// default
+    #[serde(default = "default_true")]
+    pub announce: bool,
+
+    /// Social VR platform we're talking to, determines outputs and defaults
+    #[serde(default)]
+    pub platform: Platform,
+
+    /// Address counted as boop, defaults to the platform's suffix
+    ///
+    /// See [`AddressPattern`] for exact, prefix and glob matching
+    #[serde(default)]
+    pub boop_address: AddressPattern,
+
+    /// Ignore boops this soon after the previous one on the same address,
+    /// e.g. `"300ms"`, as a single boop may trigger the contact repeatedly
+    #[serde(default)]
+    pub boop_debounce: Option<SignedDuration>,
+
+    /// Value counted as boop, e.g. `"float > 0.8"` for proximity receivers
+    #[serde(default)]
+    pub boop_trigger: Trigger,
+
+    /// How far a numeric boop value has to fall below the trigger threshold
+    /// before the next boop counts
+    #[validate(minimum = 0.0)]
+    #[serde(default)]
+    pub boop_hysteresis: f32,
+
+    /// Counters besides boops, e.g. headpats
+    ///
+    /// Each is available as `{<name>_today}` and `{<name>_total}` in templates
+    #[serde(default)]
+    #[validate]
+    pub counters: Vec<CounterOptions>,
+
+    /// Counters combined into a named group, e.g. all head receivers
+    ///
+    /// Each is available as `{group:<name>.today}` and `{group:<name>.total}`
+    /// in templates
+    #[serde(default)]
+    #[validate]
+    pub counter_groups: Vec<CounterGroup>,
+
+    /// Contacts telling who booped, e.g. receivers only triggered by a
+    /// friend's collider, counted per booper for the leaderboard
+    #[serde(default)]
+    pub boopers: Vec<BooperOptions>,
+
+    /// Pattern over parameter addresses, attributing their boops to the
+    /// booper captured as `who`, e.g. `"Boop_(?P<who>\\w+)"`
+    #[serde(default)]
+    pub booper_pattern: Option<BooperPattern>,
+
+    /// Format of `{leaderboard}`, today's top boopers
+    #[serde(default)]
+    #[validate]
+    pub leaderboard: LeaderboardOptions,
 
     #[serde(
         default = "default_text_suffixes",
@@ -44,10 +353,322 @@ pub(crate) struct Options {
     )]
     #[validate]
     pub text_suffixes: Vec<TextSuffix>,
+
+    /// Chatbox message template
+    ///
+    /// Available variables: `{today}`, `{total}`, `{session}` (since the
+    /// start or VRChat reconnecting), `{today_suffix}`, `{total_suffix}`,
+    /// `{session_suffix}`, `{week}`, `{month}`, `{streak}`, `{avatar_today}`,
+    /// `{avatar_total}`, `{rate}` (boops within the last minute) and
+    /// `{data:<name>}` for configured data sources
+    ///
+    /// Numbers followed by a word in its plural form for the number, e.g.
+    /// `{today:boop}` for "1 boop" or "2 boops", are available via
+    /// `{<variable>:<word>}`. `{<variable>:sep}` adds thousands separators,
+    /// `{<variable>:short}` abbreviates like `1.2k` and
+    /// `{progress:<variable>/<goal>}` shows a bar like `▰▰▰▱▱`, optionally
+    /// with its width, e.g. `{progress:today/500:5}`
+    #[serde(default = "default_message_template")]
+    pub message_template: String,
+
+    /// Language of the chatbox messages, e.g. `"de"`
+    ///
+    /// Replaces default templates with their translation and picks plural
+    /// forms of words by the language's rules.
+    #[serde(default)]
+    pub language: Option<String>,
+
+    /// Separator of `{<variable>:sep}`, e.g. `"."` for `1.234`
+    #[serde(default = "default_thousands_separator")]
+    pub thousands_separator: String,
+
+    /// Translations by language, extending or replacing the built-in ones
+    #[serde(default)]
+    pub translations: BTreeMap<String, Translation>,
+
+    /// Chatbox message templates used in turn instead of `message_template`,
+    /// if set
+    #[serde(default)]
+    pub message_templates: Vec<String>,
+
+    /// Order `message_templates` are used in
+    #[serde(default)]
+    pub template_rotation: TemplateRotation,
+
+    /// External HTTP data sources, usable as `{data:<name>}` in templates
+    #[serde(default)]
+    #[validate]
+    pub data_sources: Vec<DataSource>,
+
+    /// Periodic stat posts
+    #[serde(default)]
+    pub schedules: Vec<Schedule>,
+
+    /// Suspend periodic posts after this long without any OSC activity,
+    /// e.g. `"15m"`
+    #[serde(default)]
+    pub idle_timeout: Option<SignedDuration>,
+
+    /// Neither count nor post while the AFK parameter is set
+    #[serde(default)]
+    pub pause_while_afk: bool,
+
+    /// Bool parameter telling whether we're AFK, VRChat's built-in one by
+    /// default
+    #[serde(default = "default_afk_address")]
+    pub afk_address: String,
+
+    /// Bool parameter turning chatbox messages on and off, e.g.
+    /// `/avatar/parameters/BooperEnabled` toggled from the action menu
+    #[serde(default)]
+    pub chatbox_toggle_address: Option<String>,
+
+    /// Times without chatbox messages, boops are still counted, e.g.
+    /// `["23:00-08:00", "friday,saturday 20:00-23:30"]`
+    #[serde(default)]
+    pub quiet_hours: Vec<QuietHours>,
+
+    /// When today's boops get reset
+    #[serde(default)]
+    pub reset_policy: ResetPolicy,
+
+    /// Time of day periods start at, e.g. `"04:00"` for nights going on past
+    /// midnight
+    #[serde(default)]
+    pub reset_time: Time,
+
+    /// IANA time zone of `reset_time`, e.g. `"Europe/Berlin"`, the system's
+    /// by default
+    #[serde(default, with = "jiff::fmt::serde::tz::optional")]
+    pub reset_timezone: Option<TimeZone>,
+
+    /// Whether the displayed total is the lifetime or the current avatar's
+    #[serde(default)]
+    pub total_policy: TotalPolicy,
+
+    /// Whether avatars seen for the first time start at zero boops, instead
+    /// of the current lifetime total
+    #[serde(default = "default_true")]
+    pub new_avatar_starts_at_zero: bool,
+
+    /// Avatars whose contacts are counted, all by default
+    #[serde(default)]
+    pub avatars: AvatarFilter,
+
+    /// Transport advertised via OSCQuery
+    ///
+    /// UDP is always received, TCP is received on the same port number when
+    /// selected
+    #[serde(default)]
+    pub osc_transport: OscTransport,
+
+    /// Transport used to send to `osc_send_addr:osc_send_port`
+    #[serde(default)]
+    pub osc_send_transport: OscTransport,
+
+    /// Framing of OSC packets on TCP connections, both received and sent
+    #[serde(default)]
+    pub tcp_framing: TcpFraming,
+
+    /// Port of our HTTP server, disabled if unset
+    ///
+    /// Serves `/osc`, a WebSocket accepting binary OSC packets
+    #[validate(minimum = 1024)]
+    #[serde(default)]
+    pub http_port: Option<u16>,
+
+    /// Token required by the JSON API, the WebSockets, the dashboard data and
+    /// the parameters of the HTTP server, which are open to anyone reaching
+    /// the port if unset
+    #[serde(default)]
+    pub http_token: Option<String>,
+
+    /// Send the chatbox message and related outputs as one OSC bundle, so
+    /// they're applied together
+    #[serde(default = "default_true")]
+    pub bundle_outputs: bool,
+
+    /// Timed sequence played instead of a plain message on milestones
+    #[serde(default)]
+    pub milestone_sequence: Option<MilestoneSequence>,
+
+    /// Special chatbox message when boops come in unusually fast
+    #[serde(default)]
+    #[validate]
+    pub boop_storm: Option<BoopStormOptions>,
+
+    /// Daily and lifetime goals, announced as they're approached
+    #[serde(default)]
+    #[validate]
+    pub goals: Option<GoalOptions>,
+
+    /// Chatbox message posted when today's boops are reset, e.g.
+    /// `"Yesterday: {yesterday} boops"`
+    ///
+    /// `{yesterday}` holds the boops of the finished period, everything from
+    /// `message_template` is available as well
+    #[serde(default)]
+    pub daily_summary_template: Option<String>,
+
+    /// Split messages exceeding the chatbox limit into multiple messages,
+    /// sent one after another
+    #[serde(default)]
+    pub split_long_messages: bool,
+
+    /// Steps shortening messages exceeding the chatbox limit otherwise, in
+    /// order, before cutting them at the last word that fits
+    #[serde(default = "default_truncation")]
+    pub truncation: Vec<TruncationStep>,
+
+    /// Minimum seconds between chatbox messages
+    ///
+    /// VRChat drops messages sent faster than about one every 1.5 seconds
+    #[validate(minimum = 1.5)]
+    #[serde(default = "default_chatbox_cooldown_secs")]
+    pub chatbox_cooldown_secs: f64,
+
+    /// Seconds after the last message until the chatbox gets cleared
+    #[validate(minimum = 0.5)]
+    #[serde(default = "default_chatbox_clear_secs")]
+    pub chatbox_clear_secs: f64,
+
+    /// Seconds before a queued message in which the chatbox is never
+    /// cleared, the message replaces the shown one anyway
+    #[validate(minimum = 0.0)]
+    #[serde(default = "default_chatbox_clear_guard_secs")]
+    pub chatbox_clear_guard_secs: f64,
+
+    /// Clear the chatbox after messages, disable to keep the last message
+    /// shown
+    #[serde(default = "default_true")]
+    pub clear_chatbox: bool,
+
+    /// Listen for JSON control requests on a local socket
+    ///
+    /// `$XDG_RUNTIME_DIR/osc-booper.sock` on Linux/macOS,
+    /// `\\.\pipe\osc-booper` on Windows
+    #[serde(default)]
+    pub control_socket: bool,
+
+    /// Publish stats and controls on the D-Bus session bus
+    ///
+    /// Linux only, requires the `dbus` cargo feature
+    #[serde(default)]
+    pub dbus: bool,
+
+    /// Show actionable toast notifications on milestones
+    ///
+    /// Windows only, requires the `toast` cargo feature
+    #[serde(default)]
+    pub toasts: bool,
+
+    /// Show counters and controls as SteamVR dashboard overlay
+    ///
+    /// Requires the `overlay` cargo feature
+    #[serde(default)]
+    pub overlay: bool,
+
+    /// Show live counts and controls as tray icon
+    ///
+    /// Windows and Linux only, requires the `tray` cargo feature
+    #[serde(default)]
+    pub tray: bool,
+
+    /// Write a report of every finished period into a directory
+    #[serde(default)]
+    pub reports: Option<ReportOptions>,
+
+    /// Post a summary of every finished period to social media
+    #[serde(default)]
+    pub social: Option<SocialOptions>,
+
+    /// Post milestones and summaries to a Discord webhook
+    #[serde(default)]
+    #[validate]
+    pub discord: Option<DiscordOptions>,
+
+    /// Append a row of every finished period to remote spreadsheets
+    #[serde(default)]
+    pub export: Option<ExportOptions>,
+
+    /// Write the counts to a serial port whenever they change
+    ///
+    /// Requires the `serial` cargo feature
+    #[serde(default)]
+    pub serial: Option<SerialOptions>,
+
+    /// Flash WLED or Hue lights on boops and milestones
+    #[serde(default)]
+    pub lights: Option<LightsOptions>,
+
+    /// Publish events and counters to an MQTT broker
+    ///
+    /// Requires the `mqtt` cargo feature
+    #[serde(default)]
+    pub mqtt: Option<MqttOptions>,
+
+    /// Write the counts to a text file whenever they change
+    #[serde(default)]
+    pub text_file: Option<TextFileOptions>,
+
+    /// Downsample old boops in the event log into hourly and daily totals
+    #[serde(default)]
+    pub event_log_compaction: Option<CompactionOptions>,
+
+    /// Keep timestamped copies of the boop stats
+    #[serde(default)]
+    #[validate]
+    pub backups: Option<BackupOptions>,
+
+    /// Write received and sent OSC datagrams to this pcapng file
+    #[serde(default)]
+    pub capture_file: Option<PathBuf>,
+
+    /// Track the current VRChat world by tailing its output log
+    #[serde(default)]
+    pub world_tracking: Option<WorldTrackingOptions>,
+
+    /// Share the chatbox with other boopers on this machine, merging their
+    /// messages into one
+    #[serde(default)]
+    pub coordination: Option<CoordinationOptions>,
+
+    /// Log format, level filters and log files, applied on startup
+    #[serde(default)]
+    pub logging: LoggingOptions,
+
+    /// Outputs for `platform = "resonite"`
+    #[serde(default)]
+    pub resonite: ResoniteOptions,
+
+    /// Outputs for `platform = "cvr"`
+    #[serde(default)]
+    pub cvr: ChilloutVrOptions,
+
+    /// Output for `platform = "generic"`
+    #[serde(default)]
+    pub generic: GenericOptions,
+
+    /// Avatar parameters receiving the counts whenever they change
+    #[serde(default)]
+    #[validate]
+    pub parameter_outputs: Vec<ParameterOutput>,
+
+    /// OSC messages sent when a counted boop matches their conditions
+    #[serde(default)]
+    #[validate]
+    pub output_rules: Vec<OutputRule>,
+
+    /// Sounds or spoken phrases on specific counts
+    ///
+    /// Requires the `audio` cargo feature
+    #[serde(default)]
+    #[validate]
+    pub sounds: Vec<SoundAlert>,
 }
 
 #[derive(Debug, PartialEq, Serialize, Validate)]
-pub(crate) struct TextSuffix {
+pub struct TextSuffix {
     /// arithmetic remainder
     #[validate(minimum = 0)]
     value: u64,
@@ -60,102 +681,1947 @@ pub(crate) struct TextSuffix {
     divisor: u128,
 }
 
-#[derive(Debug, PartialEq)]
-pub enum TextSuffixResult {
-    /// lookup loop should break
-    Break,
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct DataSource {
+    /// name used for the template variable `{data:<name>}`
+    pub name: String,
+
+    /// URL returning JSON
+    pub url: String,
+
+    /// JSONPath to the value, e.g. `$.current.temperature`
+    #[serde(default = "default_data_source_path")]
+    pub path: String,
+
+    /// seconds between refreshes
+    #[validate(minimum = 10)]
+    #[serde(default = "default_data_source_refresh_secs")]
+    pub refresh_secs: u64,
+}
+
+/// Counter of contacts on another address
+///
+/// ```toml
+/// [[counters]]
+/// name = "headpats"
+/// address = "/OSCHeadpat"
+/// display_name = "Headpats"
+/// cooldown = "30s"
+/// reset_policy = { weekly = "monday" }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct CounterOptions {
+    /// identifier used in storage and templates
+    #[validate(pattern = r"^[a-z][a-z0-9_]*$")]
+    pub name: String,
+
+    /// address counted, matched like `boop_address`
+    pub address: AddressPattern,
+
+    /// ignore contacts this soon after the previous one, like `boop_debounce`
+    #[serde(default)]
+    pub debounce: Option<SignedDuration>,
+
+    /// value counted, like `boop_trigger`
+    #[serde(default)]
+    pub trigger: Trigger,
+
+    /// like `boop_hysteresis`
+    #[validate(minimum = 0.0)]
+    #[serde(default)]
+    pub hysteresis: f32,
+
+    /// name shown in messages, defaults to `name`
+    #[serde(default)]
+    pub display_name: Option<String>,
+
+    /// minimum time between messages of this counter
+    #[serde(default = "default_counter_cooldown")]
+    pub cooldown: SignedDuration,
+
+    /// message sent when counted
+    ///
+    /// Available variables: `{name}`, `{today}`, `{total}`, plus everything
+    /// from `message_template`
+    #[serde(default = "default_counter_message_template")]
+    pub message_template: String,
+
+    /// when `today` resets, `reset_policy` by default
+    #[serde(default)]
+    pub reset_policy: Option<ResetPolicy>,
+
+    /// time of day periods start at, `reset_time` by default
+    #[serde(default)]
+    pub reset_time: Option<Time>,
+
+    /// time zone of `reset_time`, `reset_timezone` by default
+    #[serde(default, with = "jiff::fmt::serde::tz::optional")]
+    pub reset_timezone: Option<TimeZone>,
+}
+
+impl CounterOptions {
+    pub fn display_name(&self) -> &str {
+        self.display_name.as_deref().unwrap_or(&self.name)
+    }
+}
+
+/// Avatars counted, by ID as reported via `/avatar/change`
+///
+/// ```toml
+/// [avatars]
+/// except = ["avtr_00000000-0000-0000-0000-000000000000"]
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AvatarFilter {
+    /// only count while one of these is worn, any if empty
+    #[serde(default)]
+    pub only: Vec<String>,
+
+    /// never count while one of these is worn
+    #[serde(default)]
+    pub except: Vec<String>,
+}
+
+impl AvatarFilter {
+    /// Whether contacts on `avatar` are counted
+    ///
+    /// Until the first avatar change the avatar is unknown, which is only
+    /// counted without `only` list.
+    pub fn counts(&self, avatar: Option<&str>) -> bool {
+        match avatar {
+            Some(avatar) => {
+                let listed = |ids: &[String]| ids.iter().any(|id| id == avatar);
+                (self.only.is_empty() || listed(&self.only)) && !listed(&self.except)
+            }
+            None => self.only.is_empty(),
+        }
+    }
+}
+
+/// Counters whose counts are added up, the counters keep their own stats
+///
+/// ```toml
+/// [[counter_groups]]
+/// name = "head"
+/// counters = ["left_ear", "right_ear", "nose"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct CounterGroup {
+    /// identifier used in templates
+    #[validate(pattern = r"^[a-z][a-z0-9_]*$")]
+    pub name: String,
+
+    /// names of the counters in this group
+    pub counters: Vec<String>,
+}
+
+/// Default templates and words of a language, replacing the English ones
+///
+/// ```toml
+/// [translations.de]
+/// message_template = "Heute: {today:boop}\nInsgesamt: {total:boop}"
+/// words.boop = { one = "Stupser", other = "Stupser" }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct Translation {
+    #[serde(default)]
+    pub message_template: Option<String>,
+
+    /// default of every counter's `message_template`
+    #[serde(default)]
+    pub counter_message_template: Option<String>,
+
+    /// default of `leaderboard.message_template`
+    #[serde(default)]
+    pub leaderboard_template: Option<String>,
+
+    /// words used with `{<variable>:<word>}`
+    #[serde(default)]
+    pub words: BTreeMap<String, PluralForms>,
+}
+
+/// Forms of a word by plural category, see
+/// https://www.unicode.org/cldr/charts/latest/supplemental/language_plural_rules.html
+///
+/// Languages only use some of them, missing ones fall back to `other`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct PluralForms {
+    #[serde(default)]
+    pub one: Option<String>,
+
+    #[serde(default)]
+    pub few: Option<String>,
+
+    #[serde(default)]
+    pub many: Option<String>,
+
+    pub other: String,
+}
+
+/// Contact identifying a booper
+///
+/// ```toml
+/// [[boopers]]
+/// name = "Alice"
+/// address = "/OSCBoopAlice"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BooperOptions {
+    /// shown on the leaderboard
+    pub name: String,
+
+    /// address counted, matched like `boop_address`
+    pub address: AddressPattern,
+}
+
+/// Pattern over OSC addresses, compiled once when the config is loaded
+///
+/// - `"/OSCBoop"` matches addresses ending with it
+/// - `"/avatar/parameters/OSCBoop"`, full addresses match only themselves
+/// - `"/avatar/parameters/Boop*"`, `*` matches any characters but `/`, `?`
+///   matches a single one
+/// - `"exact:…"`, `"prefix:…"`, `"suffix:…"` and `"glob:…"` pick the kind
+///   explicitly
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[serde(from = "String", into = "String")]
+pub struct AddressPattern {
+    source: String,
+    matcher: AddressMatcher,
+}
+
+#[derive(Debug, Clone)]
+enum AddressMatcher {
+    Exact(String),
+    Prefix(String),
+    Suffix(String),
+    Glob(Regex),
+}
+
+impl Default for AddressMatcher {
+    fn default() -> Self {
+        AddressMatcher::Suffix(String::new())
+    }
+}
+
+impl AddressPattern {
+    pub fn matches(&self, address: &str) -> bool {
+        match &self.matcher {
+            AddressMatcher::Exact(exact) => address == exact,
+            AddressMatcher::Prefix(prefix) => address.starts_with(prefix.as_str()),
+            AddressMatcher::Suffix(suffix) => address.ends_with(suffix.as_str()),
+            AddressMatcher::Glob(regex) => regex.is_match(address),
+        }
+    }
+
+    /// Single parameter this pattern stands for, `None` for prefixes and
+    /// globs
+    pub fn path(&self) -> Option<&str> {
+        match &self.matcher {
+            AddressMatcher::Exact(path) | AddressMatcher::Suffix(path) => Some(path.as_str()),
+            AddressMatcher::Prefix(_) | AddressMatcher::Glob(_) => None,
+        }
+    }
+
+    /// Some address matching this pattern, e.g. for simulated boops
+    pub fn example(&self) -> String {
+        match &self.matcher {
+            AddressMatcher::Exact(address)
+            | AddressMatcher::Prefix(address)
+            | AddressMatcher::Suffix(address) => address.clone(),
+            AddressMatcher::Glob(_) => {
+                let glob = self.source.strip_prefix("glob:").unwrap_or(&self.source);
+                glob.replace('*', "").replace('?', "_")
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.source.is_empty()
+    }
+}
+
+/// Anchored regex of a glob, wildcards stay within one address part
+fn glob_regex(glob: &str) -> Regex {
+    let mut regex = String::from("^");
+    for c in glob.chars() {
+        match c {
+            '*' => regex.push_str("[^/]*"),
+            '?' => regex.push_str("[^/]"),
+            c => regex.push_str(&regex::escape(c.encode_utf8(&mut [0; 4]))),
+        }
+    }
+    regex.push('$');
+    Regex::new(&regex).expect("escaped glob is a valid regex")
+}
+
+impl From<String> for AddressPattern {
+    fn from(source: String) -> Self {
+        let matcher = if let Some(exact) = source.strip_prefix("exact:") {
+            AddressMatcher::Exact(exact.into())
+        } else if let Some(prefix) = source.strip_prefix("prefix:") {
+            AddressMatcher::Prefix(prefix.into())
+        } else if let Some(suffix) = source.strip_prefix("suffix:") {
+            AddressMatcher::Suffix(suffix.into())
+        } else if let Some(glob) = source.strip_prefix("glob:") {
+            AddressMatcher::Glob(glob_regex(glob))
+        } else if source.contains(['*', '?']) {
+            AddressMatcher::Glob(glob_regex(&source))
+        } else if source.starts_with("/avatar/") {
+            AddressMatcher::Exact(source.clone())
+        } else {
+            AddressMatcher::Suffix(source.clone())
+        };
+
+        AddressPattern { source, matcher }
+    }
+}
+
+impl From<&str> for AddressPattern {
+    fn from(source: &str) -> Self {
+        source.to_string().into()
+    }
+}
+
+impl From<AddressPattern> for String {
+    fn from(value: AddressPattern) -> Self {
+        value.source
+    }
+}
+
+impl PartialEq for AddressPattern {
+    fn eq(&self, other: &Self) -> bool {
+        self.source == other.source
+    }
+}
+
+/// Regex over parameter addresses, capturing the booper as `who`
+///
+/// Any part of the address may match, anchor it with `$` to match the end.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct BooperPattern(Regex);
+
+impl BooperPattern {
+    /// Booper the boop on `address` is attributed to, if it matches
+    pub fn booper<'a>(&self, address: &'a str) -> Option<&'a str> {
+        let who = self.0.captures(address)?.name("who")?.as_str();
+        (!who.is_empty()).then_some(who)
+    }
+}
+
+impl TryFrom<String> for BooperPattern {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let regex = Regex::new(&value).map_err(|e| e.to_string())?;
+        if !regex.capture_names().flatten().any(|name| name == "who") {
+            return Err(format!(
+                "{value:?} captures no booper, add a `who` group like `(?P<who>\\w+)`"
+            ));
+        }
+        Ok(BooperPattern(regex))
+    }
+}
+
+impl From<BooperPattern> for String {
+    fn from(value: BooperPattern) -> Self {
+        value.0.as_str().into()
+    }
+}
+
+/// Leaderboard of today's top boopers
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct LeaderboardOptions {
+    /// amount of boopers listed
+    #[validate(minimum = 1)]
+    #[serde(default = "default_leaderboard_size")]
+    pub size: usize,
+
+    /// line per booper, variables: `{rank}`, `{name}`, `{today}` and
+    /// `{total}`
+    #[serde(default = "default_leaderboard_entry")]
+    pub entry_template: String,
+
+    /// posted on `/booper/leaderboard`
+    #[serde(default = "default_leaderboard_template")]
+    pub message_template: String,
+}
+
+impl Default for LeaderboardOptions {
+    fn default() -> Self {
+        LeaderboardOptions {
+            size: default_leaderboard_size(),
+            entry_template: default_leaderboard_entry(),
+            message_template: default_leaderboard_template(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Schedule {
+    /// interval between posts, e.g. `"30m"` or `"1h 30m"`
+    pub every: SignedDuration,
+
+    /// align posts to multiples of `every` since midnight, like cron
+    #[serde(default)]
+    pub align: bool,
+
+    /// template to post instead of `message_template`
+    pub template: Option<String>,
+}
+
+/// Time span without chatbox messages, on the given days or every day
+///
+/// Spans past midnight belong to the day they start on, `friday 23:00-02:00`
+/// ends on Saturday.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct QuietHours {
+    pub start: Time,
+    pub end: Time,
+    /// every day if empty
+    pub days: Vec<Weekday>,
+}
+
+/// Weekday names, as used in the config
+const WEEKDAYS: [(&str, Weekday); 7] = [
+    ("monday", Weekday::Monday),
+    ("tuesday", Weekday::Tuesday),
+    ("wednesday", Weekday::Wednesday),
+    ("thursday", Weekday::Thursday),
+    ("friday", Weekday::Friday),
+    ("saturday", Weekday::Saturday),
+    ("sunday", Weekday::Sunday),
+];
+
+impl QuietHours {
+    /// Whether local `time` falls into these quiet hours
+    pub fn contains(&self, time: DateTime) -> bool {
+        let (weekday, time) = (time.weekday(), time.time());
+        if self.start <= self.end {
+            return self.on(weekday) && self.start <= time && time < self.end;
+        }
+
+        // past midnight, the span may have started yesterday
+        (self.on(weekday) && self.start <= time) || (self.on(weekday.previous()) && time < self.end)
+    }
+
+    fn on(&self, weekday: Weekday) -> bool {
+        self.days.is_empty() || self.days.contains(&weekday)
+    }
+}
+
+impl TryFrom<String> for QuietHours {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (days, span) = value.trim().rsplit_once(' ').unwrap_or(("", value.trim()));
+        let Some((start, end)) = span.split_once('-') else {
+            return Err(format!("expected e.g. \"23:00-08:00\", got {value:?}"));
+        };
+        let time = |time: &str| {
+            time.trim()
+                .parse::<Time>()
+                .map_err(|e| format!("invalid time {time:?}: {e}"))
+        };
+
+        let days = days
+            .split(',')
+            .map(str::trim)
+            .filter(|day| !day.is_empty())
+            .map(|day| {
+                WEEKDAYS
+                    .iter()
+                    .find(|(name, _)| day.eq_ignore_ascii_case(name))
+                    .map(|&(_, weekday)| weekday)
+                    .ok_or_else(|| format!("unknown weekday {day:?}"))
+            })
+            .collect::<Result<_, _>>()?;
+
+        Ok(QuietHours {
+            start: time(start)?,
+            end: time(end)?,
+            days,
+        })
+    }
+}
+
+impl From<QuietHours> for String {
+    fn from(value: QuietHours) -> Self {
+        let span = format!(
+            "{}-{}",
+            value.start.strftime("%H:%M"),
+            value.end.strftime("%H:%M")
+        );
+        let days: Vec<_> = WEEKDAYS
+            .iter()
+            .filter(|(_, weekday)| value.days.contains(weekday))
+            .map(|(name, _)| *name)
+            .collect();
+
+        if days.is_empty() {
+            span
+        } else {
+            format!("{} {span}", days.join(","))
+        }
+    }
+}
+
+/// Reset policy for the current period's counter
+///
+/// ```toml
+/// reset_policy = "daily"              # or "monthly", "never", "manual"
+/// reset_policy = { weekly = "monday" }
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResetPolicy {
+    /// reset on local midnight
+    #[default]
+    Daily,
+
+    /// reset on local midnight of the given weekday
+    Weekly(ResetWeekday),
+
+    /// reset on the first of the month
+    Monthly,
+
+    /// never reset automatically
+    Never,
+
+    /// only reset via `/booper/reset_today`
+    Manual,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ResetWeekday {
+    Monday,
+    Tuesday,
+    Wednesday,
+    Thursday,
+    Friday,
+    Saturday,
+    Sunday,
+}
+
+impl From<ResetWeekday> for Weekday {
+    fn from(value: ResetWeekday) -> Self {
+        match value {
+            ResetWeekday::Monday => Weekday::Monday,
+            ResetWeekday::Tuesday => Weekday::Tuesday,
+            ResetWeekday::Wednesday => Weekday::Wednesday,
+            ResetWeekday::Thursday => Weekday::Thursday,
+            ResetWeekday::Friday => Weekday::Friday,
+            ResetWeekday::Saturday => Weekday::Saturday,
+            ResetWeekday::Sunday => Weekday::Sunday,
+        }
+    }
+}
+
+/// Way of shortening chatbox messages exceeding the limit
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TruncationStep {
+    /// render `{today_suffix}`, `{total_suffix}` and `{session_suffix}` empty
+    DropSuffixes,
+
+    /// collapse repeated spaces and empty lines
+    CompressWhitespace,
+}
+
+/// Order of rotating message templates
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TemplateRotation {
+    /// one after another
+    #[default]
+    RoundRobin,
+
+    /// randomly picked for every message
+    Random,
+}
+
+/// Which total is displayed in the chatbox
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TotalPolicy {
+    /// lifetime boops across all avatars
+    #[default]
+    Shared,
+
+    /// boops of the current avatar, falls back to lifetime boops while the
+    /// avatar is unknown
+    PerAvatar,
+}
+
+/// Avatar parameter displaying one of the counts
+///
+/// ```toml
+/// [[parameter_outputs]]
+/// parameter = "BoopCountToday"
+/// count = "today"
+///
+/// # radial showing the last two digits of the total
+/// [[parameter_outputs]]
+/// parameter = "BoopTotalRadial"
+/// count = "total"
+/// type = "float"
+/// modulo = 100
+/// scale = 100.0
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct ParameterOutput {
+    /// parameter name, or full address if starting with `/`
+    pub parameter: String,
+
+    pub count: CountKind,
+
+    #[serde(default, rename = "type")]
+    pub kind: ParameterKind,
+
+    /// wrap the count before scaling, e.g. 100 to show the last two digits
+    #[validate(minimum = 1)]
+    #[serde(default)]
+    pub modulo: Option<u64>,
+
+    /// float parameters are set to `count / scale`
+    #[serde(default = "default_parameter_scale")]
+    pub scale: f64,
+
+    /// values received on the parameter this long after setting it are
+    /// ignored, VRChat echoes them back to us
+    #[serde(default = "default_echo_window")]
+    pub echo_window: SignedDuration,
+}
+
+/// OSC message sent when a counted boop matches all set conditions
+///
+/// ```toml
+/// [[output_rules]]
+/// every = 100
+/// address = "/avatar/parameters/Confetti"
+/// value = "bool:true"
+/// reset = "bool:false"
+/// reset_after = "2s"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct OutputRule {
+    /// count the `equals` and `every` conditions look at
+    #[serde(default)]
+    pub count: CountKind,
+
+    /// matches when the count reaches exactly this
+    #[serde(default)]
+    pub equals: Option<u64>,
+
+    /// matches on every multiple of this
+    #[validate(minimum = 1)]
+    #[serde(default)]
+    pub every: Option<u64>,
+
+    /// matches when the boops within the last minute reach this, again only
+    /// after dropping below it
+    #[serde(default)]
+    pub rate: Option<u32>,
+
+    pub address: String,
+
+    /// argument sent, variables: `{today}`, `{total}` and `{rate}`
+    pub value: ArgTemplate,
+
+    /// argument sent `reset_after` the value, e.g. to release a toggle
+    #[serde(default)]
+    pub reset: Option<ArgTemplate>,
+
+    #[serde(default = "default_pulse_duration")]
+    pub reset_after: SignedDuration,
+}
+
+impl OutputRule {
+    /// Whether a boop bringing the count to `count` and the rate from
+    /// `previous_rate` to `rate` matches, rules without conditions never do
+    pub fn matches(&self, count: u64, previous_rate: u32, rate: u32) -> bool {
+        let conditions = [
+            self.equals.map(|equals| count == equals),
+            self.every
+                .map(|every| every > 0 && count.is_multiple_of(every)),
+            self.rate
+                .map(|threshold| previous_rate < threshold && rate >= threshold),
+        ];
+        conditions.iter().any(Option::is_some) && conditions.iter().all(|c| c.unwrap_or(true))
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CountKind {
+    Today,
+    #[default]
+    Total,
+}
+
+/// Sound played or phrase spoken when a count is hit
+///
+/// Counts are matched like text suffixes, `value = 1000` matches 1000, 11000,
+/// 21000 and so on.
+///
+/// ```toml
+/// [[sounds]]
+/// value = 1000
+/// sound = "fanfare.ogg"
+/// say = "{total} boops!"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct SoundAlert {
+    #[validate(minimum = 1)]
+    pub value: u64,
+
+    /// count to match, the total by default
+    #[serde(default)]
+    pub count: CountKind,
+
+    /// sound file to play, wav, mp3, ogg or flac
+    #[serde(default)]
+    pub sound: Option<PathBuf>,
+
+    /// phrase to speak, variables: `{today}` and `{total}`
+    #[serde(default)]
+    pub say: Option<String>,
+}
+
+impl SoundAlert {
+    pub fn matches(&self, number: u64) -> bool {
+        number >= self.value
+            && number as u128 % TextSuffix::calculate_divisor(self.value) == self.value as u128
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ParameterKind {
+    #[default]
+    Int,
+    Float,
+}
+
+/// Period reports, written on reset
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ReportOptions {
+    /// directory receiving one file per period, named after its start date
+    #[serde(default = "default_reports_dir")]
+    pub dir: PathBuf,
+
+    #[serde(default)]
+    pub format: ReportFormat,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ReportFormat {
+    #[default]
+    Markdown,
+    Html,
+}
+
+/// Summary posts on reset, credentials are read from the environment
+///
+/// Mastodon: `OSC_BOOPER_MASTODON_TOKEN`, Bluesky: `OSC_BOOPER_BLUESKY_HANDLE`
+/// and `OSC_BOOPER_BLUESKY_APP_PASSWORD`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SocialOptions {
+    /// text of the post, variables: `{boops}`, `{date}`, `{record}` and
+    /// `{total}`
+    #[serde(default = "default_social_template")]
+    pub template: String,
+
+    /// Mastodon instance to post to, e.g. `https://mastodon.social`
+    pub mastodon_instance: Option<String>,
+
+    /// post to Bluesky
+    #[serde(default)]
+    pub bluesky: bool,
+
+    /// don't post periods without any boops
+    #[serde(default = "default_true")]
+    pub skip_empty: bool,
+}
+
+/// Discord webhook posts, the webhook URL is read from
+/// `OSC_BOOPER_DISCORD_WEBHOOK`
+///
+/// ```toml
+/// [discord]
+/// every = 1000
+/// every_template = "{total} boops reached!"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct DiscordOptions {
+    /// post whenever the lifetime boops reach a multiple of this
+    #[validate(minimum = 1)]
+    #[serde(default)]
+    pub every: Option<u64>,
+
+    /// variables: `{today}` and `{total}`
+    #[serde(default = "default_discord_every_template")]
+    pub every_template: String,
+
+    /// post when today's boops break the daily record, once per period
+    #[serde(default = "default_true")]
+    pub record: bool,
+
+    /// variables: `{today}`, `{previous}` (the old record) and `{total}`
+    #[serde(default = "default_discord_record_template")]
+    pub record_template: String,
+
+    /// post a summary of every finished period
+    #[serde(default = "default_true")]
+    pub summary: bool,
+
+    /// variables: `{boops}`, `{date}`, `{record}` and `{total}`
+    #[serde(default = "default_social_template")]
+    pub summary_template: String,
+
+    /// minimum time between milestone posts, summaries are always posted
+    #[serde(default = "default_discord_min_interval")]
+    pub min_interval: SignedDuration,
+}
+
+/// Spreadsheet exports on reset, one row per period: date, boops, record and
+/// total
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ExportOptions {
+    /// CSV file on a WebDAV server, credentials via `OSC_BOOPER_WEBDAV_USER`
+    /// and `OSC_BOOPER_WEBDAV_PASSWORD`
+    pub webdav_url: Option<String>,
+
+    /// Google Sheet, service account key via `GOOGLE_APPLICATION_CREDENTIALS`
+    pub google_sheet: Option<GoogleSheetOptions>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GoogleSheetOptions {
+    /// spreadsheet ID, as found in its URL
+    pub id: String,
+
+    /// range the row is appended to
+    #[serde(default = "default_sheet_range")]
+    pub range: String,
+}
+
+/// Serial output, e.g. for an Arduino driving a seven-segment display
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SerialOptions {
+    /// port name, e.g. `/dev/ttyUSB0` or `COM3`
+    pub port: String,
+
+    #[serde(default = "default_baud")]
+    pub baud: u32,
+
+    /// written on every change, variables: `{today}` and `{total}`
+    #[serde(default = "default_serial_template")]
+    pub template: String,
+}
+
+/// Text file output, e.g. for OBS "Text from file" sources
+///
+/// ```toml
+/// [text_file]
+/// path = "boops.txt"
+/// template = "Boops today: {today}"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TextFileOptions {
+    pub path: PathBuf,
+
+    /// variables: `{today}` and `{total}`
+    #[serde(default = "default_text_file_template")]
+    pub template: String,
+
+    /// wait this long after a change before writing, to skip intermediate
+    /// counts
+    #[serde(default = "default_text_file_debounce")]
+    pub debounce: SignedDuration,
+}
+
+/// World tracking, VRChat only reports worlds in its output log
+///
+/// ```toml
+/// [world_tracking]
+/// log_dir = "C:\\Users\\me\\AppData\\LocalLow\\VRChat\\VRChat"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct WorldTrackingOptions {
+    /// directory containing VRChat's `output_log_*.txt` files
+    #[serde(default = "default_vrchat_log_dir")]
+    pub log_dir: PathBuf,
+
+    /// how often the newest log file is checked for new lines
+    #[serde(default = "default_log_poll_interval")]
+    pub poll_interval: SignedDuration,
+}
+
+/// Chatbox shared by boopers on this machine
+///
+/// The first booper listening on `port` sends the chatbox messages, one line
+/// per booper, the others hand their messages to it.
+///
+/// ```toml
+/// [coordination]
+/// name = "headpats"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CoordinationOptions {
+    /// loopback port the boopers meet on
+    #[serde(default = "default_coordination_port")]
+    pub port: u16,
+
+    /// lines are ordered by name, the instance name by default
+    #[serde(default)]
+    pub name: Option<String>,
+}
+
+/// Logging, e.g. for running headless as a service
+///
+/// ```toml
+/// [logging]
+/// format = "json"
+/// filter = "info,osc_booper::oscquery=debug"
+/// dir = "logs"
+/// rotation = "daily"
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct LoggingOptions {
+    #[serde(default)]
+    pub format: LogFormat,
+
+    /// level per target, in the syntax of `RUST_LOG`, which takes precedence
+    #[serde(default)]
+    pub filter: Option<String>,
+
+    /// also write logs to files in this directory
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+
+    /// when to start a new log file
+    #[serde(default)]
+    pub rotation: LogRotation,
+}
+
+impl LoggingOptions {
+    /// Apply the CLI overrides
+    pub fn apply_args(&mut self, args: &Cli) {
+        if let Some(format) = args.log_format {
+            self.format = format;
+        }
+        if let Some(dir) = &args.log_dir {
+            self.dir = Some(dir.clone());
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogRotation {
+    Hourly,
+    #[default]
+    Daily,
+    Never,
+}
+
+/// Light triggers
+///
+/// ```toml
+/// [lights]
+/// wled = "192.168.1.50"
+/// on_boop = { color = "#ff69b4" }
+/// on_milestone = { color = "#ffd700", duration = "3s", wled_effect = 1 }
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightsOptions {
+    /// minimum time between two flashes, boops in between are ignored
+    #[serde(default = "default_min_flash_interval")]
+    pub min_interval: SignedDuration,
+
+    /// host of a WLED controller
+    pub wled: Option<String>,
+
+    /// Hue bridge, username via `OSC_BOOPER_HUE_USERNAME`
+    pub hue: Option<HueOptions>,
+
+    pub on_boop: Option<LightEffect>,
+
+    pub on_milestone: Option<LightEffect>,
+}
+
+/// MQTT publishing, e.g. for home automation
+///
+/// ```toml
+/// [mqtt]
+/// host = "homeassistant.local"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MqttOptions {
+    /// host of the broker, credentials via `OSC_BOOPER_MQTT_USER` and
+    /// `OSC_BOOPER_MQTT_PASSWORD`
+    pub host: String,
+
+    #[serde(default = "default_mqtt_port")]
+    pub port: u16,
+
+    #[serde(default = "default_mqtt_client_id")]
+    pub client_id: String,
+
+    /// every event is published to `{events_topic}/{type}`, e.g.
+    /// `osc-booper/events/boop_counted`
+    #[serde(default = "default_mqtt_events_topic")]
+    pub events_topic: String,
+
+    /// retained stats, updated whenever the counters change
+    #[serde(default = "default_mqtt_stats_topic")]
+    pub stats_topic: String,
+
+    /// retained `online`, or `offline` via last will once we're gone
+    #[serde(default = "default_mqtt_status_topic")]
+    pub status_topic: String,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HueOptions {
+    /// host of the bridge
+    pub bridge: String,
+
+    /// IDs of the lights to flash
+    pub lights: Vec<u32>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LightEffect {
+    pub color: Rgb,
+
+    /// how long the color is shown before restoring the previous state
+    #[serde(default = "default_flash_duration")]
+    pub duration: SignedDuration,
+
+    /// WLED effect ID, solid color by default
+    #[serde(default)]
+    pub wled_effect: u8,
+}
+
+/// Event log compaction, raw boops older than `hourly_after_days` are
+/// merged into hourly totals, older than `daily_after_days` into daily totals
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct CompactionOptions {
+    #[serde(default = "default_hourly_after_days")]
+    pub hourly_after_days: u32,
+
+    #[serde(default = "default_daily_after_days")]
+    pub daily_after_days: u32,
+}
+
+/// Scheduled snapshots of the boop stats
+///
+/// ```toml
+/// [backups]
+/// every = "24h"
+/// keep = 14
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct BackupOptions {
+    /// time between two snapshots
+    #[serde(default = "default_backup_interval")]
+    pub every: SignedDuration,
+
+    /// number of snapshots kept, older ones are deleted
+    #[validate(minimum = 1)]
+    #[serde(default = "default_backup_keep")]
+    pub keep: usize,
+
+    /// `backups` in the data directory by default
+    #[serde(default)]
+    pub dir: Option<PathBuf>,
+}
+
+/// Color in hex notation, e.g. `#ff69b4`
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct Rgb(pub [u8; 3]);
+
+impl TryFrom<String> for Rgb {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let hex = value.strip_prefix('#').unwrap_or(&value);
+        if hex.len() != 6 || !hex.is_ascii() {
+            return Err(format!("expected color like #ff69b4, got {value:?}"));
+        }
+
+        let mut rgb = [0; 3];
+        for (i, channel) in rgb.iter_mut().enumerate() {
+            *channel = u8::from_str_radix(&hex[i * 2..i * 2 + 2], 16)
+                .map_err(|e| format!("invalid color {value:?}: {e}"))?;
+        }
+
+        Ok(Rgb(rgb))
+    }
+}
+
+impl From<Rgb> for String {
+    fn from(Rgb([r, g, b]): Rgb) -> Self {
+        format!("#{r:02x}{g:02x}{b:02x}")
+    }
+}
+
+/// Contact receiver value counted, e.g. `bool`, `int >= 1` or `float > 0.8`
+///
+/// Values of other types than the configured one never count.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Trigger {
+    /// `true`, sent by regular contact receivers
+    #[default]
+    Bool,
+    Int(Comparison, i32),
+    Float(Comparison, f32),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Comparison {
+    Greater,
+    GreaterOrEqual,
+    Equal,
+}
+
+impl Comparison {
+    fn holds<T: PartialOrd>(self, value: T, threshold: T) -> bool {
+        match self {
+            Comparison::Greater => value > threshold,
+            Comparison::GreaterOrEqual => value >= threshold,
+            Comparison::Equal => value == threshold,
+        }
+    }
+
+    fn as_str(self) -> &'static str {
+        match self {
+            Comparison::Greater => ">",
+            Comparison::GreaterOrEqual => ">=",
+            Comparison::Equal => "==",
+        }
+    }
+}
+
+impl Trigger {
+    /// Whether the received `value` counts
+    pub fn matches(&self, value: &OscType) -> bool {
+        match (self, value) {
+            (Trigger::Bool, OscType::Bool(value)) => *value,
+            (Trigger::Int(comparison, threshold), OscType::Int(value)) => {
+                comparison.holds(value, threshold)
+            }
+            (Trigger::Float(comparison, threshold), OscType::Float(value)) => {
+                comparison.holds(value, threshold)
+            }
+            _ => false,
+        }
+    }
+
+    /// Whether the received `value` ends a contact, so the next match counts
+    /// again
+    ///
+    /// Numeric values have to fall `hysteresis` below the threshold, values
+    /// of other types are ignored.
+    pub fn releases(&self, value: &OscType, hysteresis: f32) -> bool {
+        let (comparison, value, threshold) = match (self, value) {
+            (Trigger::Bool, OscType::Bool(value)) => return !value,
+            (Trigger::Int(comparison, threshold), OscType::Int(value)) => {
+                (comparison, *value as f32, *threshold as f32)
+            }
+            (Trigger::Float(comparison, threshold), OscType::Float(value)) => {
+                (comparison, *value, *threshold)
+            }
+            _ => return false,
+        };
+
+        match comparison {
+            Comparison::Equal => value != threshold,
+            comparison => !comparison.holds(value, threshold - hysteresis),
+        }
+    }
+}
+
+impl TryFrom<String> for Trigger {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let parts: Vec<_> = value.split_whitespace().collect();
+        let (kind, condition) = match parts[..] {
+            [kind] => (kind, None),
+            [kind, comparison, threshold] => (kind, Some((comparison, threshold))),
+            _ => return Err(format!("expected e.g. \"float > 0.8\", got {value:?}")),
+        };
+
+        let comparison = match condition.map(|(comparison, _)| comparison) {
+            None | Some(">") => Comparison::Greater,
+            Some(">=") => Comparison::GreaterOrEqual,
+            Some("==") => Comparison::Equal,
+            Some(other) => return Err(format!("unknown comparison {other:?}")),
+        };
+        let threshold = condition.map_or("0", |(_, threshold)| threshold);
+
+        match (kind, condition) {
+            ("bool", None) => Ok(Trigger::Bool),
+            ("bool", Some(_)) => Err("bool triggers take no condition".into()),
+            ("int", _) => threshold
+                .parse()
+                .map(|threshold| Trigger::Int(comparison, threshold))
+                .map_err(|e| format!("invalid int threshold {threshold:?}: {e}")),
+            ("float", _) => threshold
+                .parse()
+                .map(|threshold| Trigger::Float(comparison, threshold))
+                .map_err(|e| format!("invalid float threshold {threshold:?}: {e}")),
+            (other, _) => Err(format!("unknown trigger type {other:?}")),
+        }
+    }
+}
+
+impl From<Trigger> for String {
+    fn from(value: Trigger) -> Self {
+        match value {
+            Trigger::Bool => "bool".into(),
+            Trigger::Int(comparison, threshold) => {
+                format!("int {} {threshold}", comparison.as_str())
+            }
+            Trigger::Float(comparison, threshold) => {
+                format!("float {} {threshold}", comparison.as_str())
+            }
+        }
+    }
+}
+
+/// Boop storm, detected when a minute has at least `threshold` boops
+///
+/// ```toml
+/// [boop_storm]
+/// threshold = 20
+/// message_template = "Boop storm! {rate} boops in the last minute"
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct BoopStormOptions {
+    /// boops within a minute starting a storm
+    #[validate(minimum = 2)]
+    #[serde(default = "default_storm_threshold")]
+    pub threshold: u32,
+
+    /// sent once per storm, bypassing the chatbox cooldown, supports the
+    /// same variables as `message_template`
+    #[serde(default = "default_storm_template")]
+    pub message_template: String,
+}
+
+/// Goals of boops per period and in total
+///
+/// Templates show what's left as `{remaining}` (of the daily goal, if set),
+/// `{remaining_today}` and `{remaining_total}`, and the goals as
+/// `{goal_today}` and `{goal_total}`.
+///
+/// ```toml
+/// [goals]
+/// daily = 500
+/// lifetime = 100000
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Validate)]
+pub struct GoalOptions {
+    /// boops to reach every period, announced again after each reset
+    #[validate(minimum = 1)]
+    #[serde(default)]
+    pub daily: Option<u64>,
+
+    #[validate(minimum = 1)]
+    #[serde(default)]
+    pub lifetime: Option<u64>,
+
+    /// percentages of a goal announced once reached
+    #[serde(default = "default_goal_announce_at")]
+    pub announce_at: Vec<u32>,
+
+    /// announcement below 100%, variables: `{scope}` (`daily` or
+    /// `lifetime`), `{goal}`, `{percent}` and `{remaining}`, plus everything
+    /// from `message_template`
+    #[serde(default = "default_goal_template")]
+    pub message_template: String,
+
+    /// announcement once a goal is reached, same variables
+    #[serde(default = "default_goal_reached_template")]
+    pub reached_template: String,
+}
+
+/// Timed outputs for milestone (suffixed) boops, sent as timetagged bundles
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MilestoneSequence {
+    /// bool avatar parameter set immediately, e.g. `/avatar/parameters/Confetti`
+    pub pulse_address: Option<String>,
+
+    /// how long the pulse parameter stays true
+    #[serde(default = "default_pulse_duration")]
+    pub pulse_duration: SignedDuration,
+
+    /// delay before the chatbox message is shown
+    #[serde(default = "default_chatbox_delay")]
+    pub chatbox_delay: SignedDuration,
+
+    /// delay before the chatbox is cleared
+    #[serde(default = "default_clear_delay")]
+    pub clear_delay: SignedDuration,
+
+    /// hold bundles locally until due, as VRChat applies bundles immediately
+    /// regardless of their timetag
+    #[serde(default = "default_true")]
+    pub hold_until_due: bool,
+}
+
+/// Social VR platform profile
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Platform {
+    /// chatbox output, announced via OSCQuery
+    #[default]
+    VRChat,
+
+    /// no chatbox, counters and message are sent to dynamic variables
+    Resonite,
+
+    /// ChilloutVR, no chatbox, counters are sent as avatar parameters and the
+    /// message is shown as desktop notification
+    #[serde(rename = "cvr")]
+    ChilloutVR,
+
+    /// any OSC application, single message with configurable arguments
+    Generic,
+}
+
+impl Platform {
+    /// Boop address suffix used unless configured otherwise
+    pub fn default_boop_address(self) -> &'static str {
+        match self {
+            Platform::VRChat | Platform::ChilloutVR => "/OSCBoop",
+            Platform::Resonite => "/Boop",
+            Platform::Generic => "/boop",
+        }
+    }
+
+    /// Whether the platform displays text in a chatbox
+    pub fn has_chatbox(self) -> bool {
+        self == Platform::VRChat
+    }
+}
+
+/// Resonite dynamic variables, driven via `OSC_Field` components
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ResoniteOptions {
+    /// address prefix of the variables, `<prefix>/Message`, `<prefix>/Today`
+    /// and `<prefix>/Total`
+    #[serde(default = "default_resonite_prefix")]
+    pub prefix: String,
+}
+
+impl Default for ResoniteOptions {
+    fn default() -> Self {
+        ResoniteOptions {
+            prefix: default_resonite_prefix(),
+        }
+    }
+}
+
+/// ChilloutVR avatar parameters, driven via the OSC mod
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ChilloutVrOptions {
+    /// int parameter receiving today's boops
+    #[serde(default = "default_today_parameter")]
+    pub today_parameter: String,
+
+    /// int parameter receiving total boops
+    #[serde(default = "default_total_parameter")]
+    pub total_parameter: String,
+
+    /// show the message as desktop notification, requires the `notify` cargo
+    /// feature
+    #[serde(default = "default_true")]
+    pub notifications: bool,
+}
+
+impl Default for ChilloutVrOptions {
+    fn default() -> Self {
+        ChilloutVrOptions {
+            today_parameter: default_today_parameter(),
+            total_parameter: default_total_parameter(),
+            notifications: true,
+        }
+    }
+}
+
+/// Output of `platform = "generic"`
+///
+/// ```toml
+/// [generic]
+/// address = "/boops"
+/// args = ["int:{today}", "int:{total}", "string:{message}"]
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct GenericOptions {
+    /// address of the output message
+    #[serde(default = "default_generic_address")]
+    pub address: String,
+
+    /// arguments in order, each `<type>:<template>`
+    ///
+    /// Types: `int`, `long`, `float`, `double`, `string` and `bool`, templates
+    /// may use `{today}`, `{total}` and `{message}`
+    #[serde(default = "default_generic_args")]
+    pub args: Vec<ArgTemplate>,
+}
+
+impl Default for GenericOptions {
+    fn default() -> Self {
+        GenericOptions {
+            address: default_generic_address(),
+            args: default_generic_args(),
+        }
+    }
+}
+
+/// Typed OSC argument rendered from a template, e.g. `int:{today}`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct ArgTemplate {
+    pub kind: ArgKind,
+    pub template: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ArgKind {
+    Int,
+    Long,
+    Float,
+    Double,
+    String,
+    Bool,
+}
+
+impl TryFrom<String> for ArgTemplate {
+    type Error = String;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let (kind, template) = value
+            .split_once(':')
+            .ok_or_else(|| format!("expected <type>:<template>, got {value:?}"))?;
+
+        let kind = match kind {
+            "int" => ArgKind::Int,
+            "long" => ArgKind::Long,
+            "float" => ArgKind::Float,
+            "double" => ArgKind::Double,
+            "string" => ArgKind::String,
+            "bool" => ArgKind::Bool,
+            other => return Err(format!("unknown argument type {other:?}")),
+        };
+
+        Ok(ArgTemplate {
+            kind,
+            template: template.into(),
+        })
+    }
+}
+
+impl From<ArgTemplate> for String {
+    fn from(value: ArgTemplate) -> Self {
+        let kind = match value.kind {
+            ArgKind::Int => "int",
+            ArgKind::Long => "long",
+            ArgKind::Float => "float",
+            ArgKind::Double => "double",
+            ArgKind::String => "string",
+            ArgKind::Bool => "bool",
+        };
+        format!("{kind}:{}", value.template)
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OscTransport {
+    #[default]
+    Udp,
+    Tcp,
+}
+
+/// How OSC packets are delimited on a TCP stream
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TcpFraming {
+    /// SLIP encoded, as per OSC 1.1
+    #[default]
+    Slip,
+    /// prefixed with their 32 bit big endian size, as per OSC 1.0
+    Length,
+}
+
+#[derive(Debug, PartialEq)]
+pub enum TextSuffixResult {
+    /// lookup loop should break
+    Break,
+
+    /// message matches, send
+    Message(String),
+
+    /// does not match
+    Skip,
+}
+
+/// Reasons the config file can't be used
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to read {file}: {source}")]
+    Read {
+        file: String,
+        source: std::io::Error,
+    },
+
+    /// not valid TOML or not matching the expected structure
+    // includes line, column and the offending snippet
+    #[error("failed to parse {file}: {source}")]
+    Parse {
+        file: String,
+        source: toml::de::Error,
+    },
+
+    /// values out of their allowed range
+    #[error("invalid values in {file}: {errors}")]
+    Invalid {
+        file: String,
+        // boxed, it would bloat every config result otherwise
+        errors: Box<serde_valid::validation::Errors>,
+    },
+
+    #[error("failed to serialize config: {0}")]
+    Serialize(#[source] toml::ser::Error),
+
+    #[error("failed to write {file}: {source}")]
+    Write {
+        file: String,
+        source: std::io::Error,
+    },
+}
+
+impl Options {
+    /// Load config and apply CLI arguments, saving it if requested
+    ///
+    /// Fails on config files or preset packs that can't be read or parsed,
+    /// which are never overwritten
+    pub fn new(args: &Cli) -> Result<Self, ConfigError> {
+        // try to load config/init with args/defaults
+        let mut options = Options::load()?;
+        let packs = presets::load(&paths::presets_dir())?;
+        options.apply_args(args);
+
+        // save new config, without the contents of preset packs
+        if args.save {
+            options.save()?;
+        }
 
-    /// message matches, send
-    Message(String),
+        presets::apply(&mut options, packs);
+        options.check_template_lengths();
+        Ok(options)
+    }
 
-    /// does not match
-    Skip,
-}
+    /// Re-read config file, returns `None` if it's invalid
+    pub fn reload(args: &Cli) -> Option<Self> {
+        let mut options = Options::load()
+            .map_err(|e| warn!("{e}, keeping current config"))
+            .ok()?;
+        let packs = presets::load(&paths::presets_dir())
+            .map_err(|e| warn!("{e}, keeping current config"))
+            .ok()?;
 
-impl Options {
-    pub(crate) fn new() -> Self {
-        let args = Cli::parse();
+        options.apply_args(args);
+        presets::apply(&mut options, packs);
+        options.check_template_lengths();
+        Some(options)
+    }
 
-        // try to load config/init with args/defaults
-        let mut options = Options::load();
+    /// Resolve defaults and apply CLI overrides
+    fn apply_args(&mut self, args: &Cli) {
+        if self.boop_address.is_empty() {
+            self.boop_address = self.platform.default_boop_address().into();
+        }
 
         // override values again, if specified
         if let Some(send) = args.send {
-            options.osc_send_port = send;
+            self.osc_send_port = send;
+        }
+        if let Some(boop_address) = &args.boop_address {
+            self.boop_address = boop_address.as_str().into();
         }
-        if let Some(boop_address) = args.boop_address {
-            options.boop_address = boop_address;
+        if let Some(listen) = args.listen {
+            self.listen_port = Some(listen);
         }
+        if args.no_announce {
+            self.announce = false;
+        }
+        if let Some(capture) = &args.capture {
+            self.capture_file = Some(capture.clone());
+        }
+        self.logging.apply_args(args);
+        i18n::apply_defaults(self);
 
-        // save new config
-        if args.save {
-            options.save();
+        // nobody would find a random port
+        if !self.announce && self.listen_port.is_none() {
+            self.listen_port = Some(default_listen_port());
+        }
+    }
+
+    /// Warn about chatbox templates exceeding the limit by their text alone,
+    /// which are always shortened or split
+    fn check_template_lengths(&self) {
+        if !self.platform.has_chatbox() {
+            return;
+        }
+
+        let action = if self.split_long_messages {
+            "split"
+        } else {
+            "shortened"
+        };
+        let counter_templates = self.counters.iter().map(|c| &c.message_template);
+        let templates = [&self.message_template]
+            .into_iter()
+            .chain(&self.message_templates)
+            .chain(counter_templates);
+        for template in templates {
+            let length = template::render(template, |_| Some("".into()))
+                .chars()
+                .count();
+            if length > template::CHATBOX_MAX_CHARS {
+                warn!(
+                    length,
+                    "template {template:?} exceeds the chatbox limit without any variables, it will be {action}"
+                );
+            }
+        }
+    }
+
+    /// Reject templates with unknown variables, like typos or data sources
+    /// that aren't configured, which would show up verbatim
+    fn validate_templates(&self) -> Result<(), Vec<serde_valid::validation::Error>> {
+        const COUNTS: &[&str] = &["today", "total"];
+        const PERIOD: &[&str] = &["boops", "date", "record", "total"];
+
+        // rendered like chatbox messages, with their own variables on top
+        let mut chatbox: Vec<(&str, &[&str])> = vec![(&self.message_template, &[])];
+        chatbox.extend(self.message_templates.iter().map(|t| (t.as_str(), &[][..])));
+        chatbox.push((&self.leaderboard.message_template, &[]));
+        chatbox.extend(
+            self.schedules
+                .iter()
+                .filter_map(|s| Some((s.template.as_deref()?, &[][..]))),
+        );
+        if let Some(template) = &self.daily_summary_template {
+            chatbox.push((template, &["yesterday"]));
+        }
+        let counter_vars: &[&str] = &["name", "today", "total"];
+        chatbox.extend(
+            self.counters
+                .iter()
+                .map(|c| (c.message_template.as_str(), counter_vars)),
+        );
+        if let Some(storm) = &self.boop_storm {
+            chatbox.push((&storm.message_template, &[]));
+        }
+        if let Some(goals) = &self.goals {
+            let goal_vars: &[&str] = &["scope", "goal", "percent", "remaining"];
+            chatbox.push((&goals.message_template, goal_vars));
+            chatbox.push((&goals.reached_template, goal_vars));
+        }
+        for translation in self.translations.values() {
+            let templates = [
+                (&translation.message_template, &[][..]),
+                (&translation.counter_message_template, counter_vars),
+                (&translation.leaderboard_template, &[]),
+            ];
+            chatbox.extend(
+                templates
+                    .into_iter()
+                    .filter_map(|(template, vars)| Some((template.as_deref()?, vars))),
+            );
+        }
+
+        // rendered with their own variables only
+        let mut plain: Vec<(&str, &[&str])> = vec![(
+            &self.leaderboard.entry_template,
+            &["rank", "name", "today", "total"],
+        )];
+        if let Some(social) = &self.social {
+            plain.push((&social.template, PERIOD));
+        }
+        if let Some(discord) = &self.discord {
+            plain.push((&discord.every_template, COUNTS));
+            plain.push((&discord.record_template, &["today", "previous", "total"]));
+            plain.push((&discord.summary_template, PERIOD));
+        }
+        if let Some(serial) = &self.serial {
+            plain.push((&serial.template, COUNTS));
+        }
+        if let Some(text_file) = &self.text_file {
+            plain.push((&text_file.template, COUNTS));
         }
+        plain.extend(
+            self.sounds
+                .iter()
+                .filter_map(|s| Some((s.say.as_deref()?, COUNTS))),
+        );
+        plain.extend(
+            self.output_rules
+                .iter()
+                .map(|r| (r.value.template.as_str(), &["today", "total", "rate"][..])),
+        );
+        plain.extend(
+            self.generic
+                .args
+                .iter()
+                .map(|a| (a.template.as_str(), &["today", "total", "message"][..])),
+        );
+
+        let chatbox = chatbox.into_iter().flat_map(|(template, vars)| {
+            unknown_variables(template, |key| self.is_template_variable(key, vars))
+        });
+        let plain = plain
+            .into_iter()
+            .flat_map(|(template, vars)| unknown_variables(template, |key| vars.contains(&key)));
+        let errors: Vec<_> = chatbox.chain(plain).collect();
 
-        options
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors)
+        }
     }
 
-    /// Load config if it exists
-    fn load() -> Self {
-        let file = Path::new(FILE_NAME);
+    /// Whether `key` is rendered by chatbox templates, with `vars` on top
+    fn is_template_variable(&self, key: &str, vars: &[&str]) -> bool {
+        let known = |names: &[&str]| names.contains(&key);
+        let is_counter = || {
+            key.rsplit_once('_').is_some_and(|(name, field)| {
+                matches!(field, "today" | "total") && self.counters.iter().any(|c| c.name == name)
+            })
+        };
+        let is_group = || {
+            key.strip_prefix("group:")
+                .and_then(|key| key.rsplit_once('.'))
+                .is_some_and(|(name, field)| {
+                    matches!(field, "today" | "total")
+                        && self.counter_groups.iter().any(|g| g.name == name)
+                })
+        };
+        let is_data = || {
+            key.strip_prefix("data:")
+                .is_some_and(|name| self.data_sources.iter().any(|s| s.name == name))
+        };
+        let is_progress = || {
+            let Some(spec) = key.strip_prefix("progress:") else {
+                return false;
+            };
+            let spec = spec.split_once(':').map_or(spec, |(spec, _)| spec);
+            spec.split_once('/').is_some_and(|(variable, goal)| {
+                self.is_template_variable(variable, vars)
+                    && (goal.parse::<u64>().is_ok() || self.is_template_variable(goal, vars))
+            })
+        };
+        // numbers with a format or word, e.g. `{today:sep}`
+        let is_formatted = || {
+            key.rsplit_once(':')
+                .is_some_and(|(variable, _)| self.is_template_variable(variable, vars))
+        };
+
+        known(template::VARIABLES)
+            || known(vars)
+            || (self.goals.is_some() && known(goals::VARIABLES))
+            || is_counter()
+            || is_group()
+            || is_data()
+            || is_progress()
+            || is_formatted()
+    }
 
-        if !file.exists() {
-            return Options::default();
+    /// Load config if it exists, defaults otherwise
+    fn load() -> Result<Self, ConfigError> {
+        let path = paths::config_file();
+        if !path.exists() {
+            return Ok(Options::default());
         }
 
-        // try to read existing config
-        let contents = match fs::read_to_string(file) {
+        let file = path.display().to_string();
+        let contents = match fs::read_to_string(&path) {
             Ok(contents) => contents,
-            Err(e) => {
-                error!(err=%e, "failed to read {FILE_NAME}");
-                return Options::default();
-            }
+            Err(source) => return Err(ConfigError::Read { file, source }),
         };
 
-        let options = Options::from_toml_str(&contents)
-            .map_err(|e| {
-                error!(err=%e, "failed to parse {FILE_NAME}");
-            })
-            .unwrap_or_default();
+        let options: Options = match toml::from_str(&contents) {
+            Ok(options) => options,
+            Err(source) => return Err(ConfigError::Parse { file, source }),
+        };
 
-        options
-            .validate()
-            .map_err(|errors| {
-                error!("failed to validate config file {FILE_NAME}: {errors}");
-            })
-            .unwrap(); // crash intentionally
+        if let Err(errors) = options.validate() {
+            return Err(ConfigError::Invalid {
+                file,
+                errors: Box::new(errors),
+            });
+        }
 
-        options
+        Ok(options)
     }
 
     /// Save config
-    fn save(&self) {
-        let toml = match self.to_toml_string() {
-            Ok(toml) => toml,
-            Err(e) => {
-                error!(err=%e, "failed to serialize config to string");
-                panic!();
-            }
-        };
+    fn save(&self) -> Result<(), ConfigError> {
+        let toml = toml::to_string(self).map_err(ConfigError::Serialize)?;
+
+        let path = paths::config_file();
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir).ok();
+        }
+        fs::write(&path, toml).map_err(|source| ConfigError::Write {
+            file: path.display().to_string(),
+            source,
+        })
+    }
 
-        if let Err(e) = fs::write(FILE_NAME, toml) {
-            error!(err=%e, "failed to write config to {FILE_NAME}");
+    /// Loopback address our sockets bind and send to by default
+    pub fn loopback(&self) -> IpAddr {
+        if self.ipv6 {
+            Ipv6Addr::LOCALHOST.into()
+        } else {
+            Ipv4Addr::LOCALHOST.into()
         }
     }
+
+    pub fn osc_listen_addr(&self) -> IpAddr {
+        self.osc_listen_addr.unwrap_or(self.loopback())
+    }
+
+    pub fn osc_send_addr(&self) -> IpAddr {
+        self.osc_send_addr.unwrap_or(self.loopback())
+    }
+
+    pub fn http_listen_addr(&self) -> IpAddr {
+        self.http_listen_addr.unwrap_or(self.loopback())
+    }
+}
+
+/// Errors for the variables of `template` that `is_known` rejects
+fn unknown_variables(
+    template: &str,
+    is_known: impl Fn(&str) -> bool,
+) -> Vec<serde_valid::validation::Error> {
+    template::placeholders(template)
+        .into_iter()
+        .filter(|key| !is_known(key))
+        .map(|key| {
+            serde_valid::validation::Error::Custom(format!(
+                "unknown variable {{{key}}} in template {template:?}"
+            ))
+        })
+        .collect()
 }
 
 impl Default for Options {
     fn default() -> Self {
         Options {
             osc_send_port: 9000,
-            boop_address: default_boop_address(),
+            follow_vrchat_port: true,
+            listen_port: None,
+            receive_buffer: default_receive_buffer(),
+            ipv6: false,
+            osc_listen_addr: None,
+            osc_allowed_sources: None,
+            osc_send_addr: None,
+            forward_to: vec![],
+            http_listen_addr: None,
+            announce: true,
+            platform: Platform::default(),
+            boop_address: AddressPattern::default(),
+            boop_debounce: None,
+            boop_trigger: Trigger::default(),
+            boop_hysteresis: 0.0,
+            counters: vec![],
+            counter_groups: vec![],
+            boopers: vec![],
+            booper_pattern: None,
+            leaderboard: LeaderboardOptions::default(),
             text_suffixes: default_text_suffixes(),
+            message_template: default_message_template(),
+            message_templates: vec![],
+            language: None,
+            thousands_separator: default_thousands_separator(),
+            translations: BTreeMap::new(),
+            template_rotation: TemplateRotation::default(),
+            data_sources: vec![],
+            schedules: vec![],
+            idle_timeout: None,
+            pause_while_afk: false,
+            afk_address: default_afk_address(),
+            chatbox_toggle_address: None,
+            quiet_hours: vec![],
+            reset_policy: ResetPolicy::default(),
+            reset_time: Time::midnight(),
+            reset_timezone: None,
+            total_policy: TotalPolicy::default(),
+            new_avatar_starts_at_zero: true,
+            avatars: AvatarFilter::default(),
+            osc_transport: OscTransport::default(),
+            osc_send_transport: OscTransport::default(),
+            tcp_framing: TcpFraming::default(),
+            http_port: None,
+            http_token: None,
+            bundle_outputs: true,
+            milestone_sequence: None,
+            boop_storm: None,
+            goals: None,
+            daily_summary_template: None,
+            split_long_messages: false,
+            truncation: default_truncation(),
+            chatbox_cooldown_secs: default_chatbox_cooldown_secs(),
+            chatbox_clear_secs: default_chatbox_clear_secs(),
+            chatbox_clear_guard_secs: default_chatbox_clear_guard_secs(),
+            clear_chatbox: true,
+            control_socket: false,
+            dbus: false,
+            toasts: false,
+            overlay: false,
+            tray: false,
+            reports: None,
+            social: None,
+            discord: None,
+            export: None,
+            serial: None,
+            lights: None,
+            mqtt: None,
+            text_file: None,
+            event_log_compaction: None,
+            backups: None,
+            capture_file: None,
+            world_tracking: None,
+            coordination: None,
+            logging: LoggingOptions::default(),
+            resonite: ResoniteOptions::default(),
+            cvr: ChilloutVrOptions::default(),
+            generic: GenericOptions::default(),
+            parameter_outputs: vec![],
+            output_rules: vec![],
+            sounds: vec![],
         }
     }
 }
 
 impl TextSuffix {
-    pub(crate) fn new(value: u64, message: String) -> Self {
+    pub fn new(value: u64, message: String) -> Self {
         TextSuffix {
             value,
             message,
@@ -163,8 +2629,12 @@ impl TextSuffix {
         }
     }
 
+    pub fn value(&self) -> u64 {
+        self.value
+    }
+
     /// check for number match, or break
-    pub(crate) fn check_value(&self, value: u64) -> TextSuffixResult {
+    pub fn check_value(&self, value: u64) -> TextSuffixResult {
         if value < self.value {
             return TextSuffixResult::Break;
         }
@@ -177,7 +2647,7 @@ impl TextSuffix {
     }
 
     /// calculate appropriate divisor via log10
-    pub(crate) fn calculate_divisor(value: u64) -> u128 {
+    pub fn calculate_divisor(value: u64) -> u128 {
         let logged_value = value.ilog10();
 
         // 10^(n+1) results in appropriate value
@@ -218,12 +2688,139 @@ impl<'de> Deserialize<'de> for TextSuffix {
     }
 }
 
+fn default_truncation() -> Vec<TruncationStep> {
+    vec![
+        TruncationStep::DropSuffixes,
+        TruncationStep::CompressWhitespace,
+    ]
+}
+
+fn default_thousands_separator() -> String {
+    ",".into()
+}
+
 fn default_osc_send_port() -> u16 {
     9000
 }
 
-fn default_boop_address() -> String {
-    "/OSCBoop".into()
+fn default_social_template() -> String {
+    "Got booped {boops} times on {date}, {total} boops in total!".into()
+}
+
+fn default_discord_every_template() -> String {
+    "{total} boops reached!".into()
+}
+
+fn default_discord_record_template() -> String {
+    "New daily record: {today} boops, beating the old record of {previous}!".into()
+}
+
+fn default_discord_min_interval() -> SignedDuration {
+    SignedDuration::from_mins(1)
+}
+
+fn default_min_flash_interval() -> SignedDuration {
+    SignedDuration::from_secs(2)
+}
+
+fn default_mqtt_port() -> u16 {
+    1883
+}
+
+fn default_mqtt_client_id() -> String {
+    instance::scoped("osc-booper", "-")
+}
+
+fn default_mqtt_events_topic() -> String {
+    "osc-booper/events".into()
+}
+
+fn default_mqtt_stats_topic() -> String {
+    "osc-booper/stats".into()
+}
+
+fn default_mqtt_status_topic() -> String {
+    "osc-booper/status".into()
+}
+
+fn default_text_file_template() -> String {
+    "Today: {today}\nTotal: {total}".into()
+}
+
+fn default_backup_interval() -> SignedDuration {
+    SignedDuration::from_hours(24)
+}
+
+fn default_backup_keep() -> usize {
+    14
+}
+
+fn default_text_file_debounce() -> SignedDuration {
+    SignedDuration::from_millis(250)
+}
+
+fn default_flash_duration() -> SignedDuration {
+    SignedDuration::from_millis(500)
+}
+
+fn default_hourly_after_days() -> u32 {
+    30
+}
+
+fn default_daily_after_days() -> u32 {
+    365
+}
+
+fn default_baud() -> u32 {
+    9600
+}
+
+fn default_serial_template() -> String {
+    "{today},{total}\n".into()
+}
+
+fn default_sheet_range() -> String {
+    "Sheet1!A:D".into()
+}
+
+fn default_leaderboard_size() -> usize {
+    3
+}
+
+fn default_leaderboard_entry() -> String {
+    "{rank}. {name} ({today})".into()
+}
+
+pub fn default_leaderboard_template() -> String {
+    "Top boopers today:\n{leaderboard}".into()
+}
+
+fn default_vrchat_log_dir() -> PathBuf {
+    let home = PathBuf::from(std::env::var_os("USERPROFILE").unwrap_or_default());
+    home.join("AppData")
+        .join("LocalLow")
+        .join("VRChat")
+        .join("VRChat")
+}
+
+fn default_coordination_port() -> u16 {
+    9050
+}
+
+fn default_log_poll_interval() -> SignedDuration {
+    SignedDuration::from_secs(1)
+}
+
+fn default_reports_dir() -> PathBuf {
+    "reports".into()
+}
+
+fn default_listen_port() -> u16 {
+    9001
+}
+
+fn default_receive_buffer() -> usize {
+    rosc::decoder::MTU
 }
 
 fn default_text_suffixes() -> Vec<TextSuffix> {
@@ -236,8 +2833,114 @@ fn default_text_suffixes() -> Vec<TextSuffix> {
     ]
 }
 
+fn default_true() -> bool {
+    true
+}
+
+fn default_pulse_duration() -> SignedDuration {
+    SignedDuration::from_secs(1)
+}
+
+fn default_chatbox_delay() -> SignedDuration {
+    SignedDuration::from_millis(500)
+}
+
+fn default_clear_delay() -> SignedDuration {
+    SignedDuration::from_secs(6)
+}
+
+fn default_storm_threshold() -> u32 {
+    20
+}
+
+fn default_goal_announce_at() -> Vec<u32> {
+    vec![50, 90, 100]
+}
+
+fn default_goal_template() -> String {
+    "{percent}% of the {scope} goal of {goal} boops, {remaining} to go!".into()
+}
+
+fn default_goal_reached_template() -> String {
+    "Reached the {scope} goal of {goal} boops!".into()
+}
+
+fn default_storm_template() -> String {
+    "Boop storm! {rate} boops in the last minute".into()
+}
+
+pub fn default_message_template() -> String {
+    "Today: {today}{today_suffix}\nTotal: {total}{total_suffix}".into()
+}
+
+fn default_chatbox_cooldown_secs() -> f64 {
+    2.0
+}
+
+fn default_chatbox_clear_secs() -> f64 {
+    4.0
+}
+
+fn default_chatbox_clear_guard_secs() -> f64 {
+    1.0
+}
+
+fn default_afk_address() -> String {
+    "/avatar/parameters/AFK".into()
+}
+
+fn default_parameter_scale() -> f64 {
+    1.0
+}
+
+/// Echo window of avatar parameters set without a configured one
+pub fn default_echo_window() -> SignedDuration {
+    SignedDuration::from_secs(1)
+}
+
+fn default_counter_cooldown() -> SignedDuration {
+    SignedDuration::from_secs(10)
+}
+
+pub fn default_counter_message_template() -> String {
+    "{name} today: {today}\nTotal: {total}".into()
+}
+
+fn default_resonite_prefix() -> String {
+    "/OSCBooper".into()
+}
+
+fn default_today_parameter() -> String {
+    "BoopsToday".into()
+}
+
+fn default_total_parameter() -> String {
+    "BoopsTotal".into()
+}
+
+fn default_generic_address() -> String {
+    "/boops".into()
+}
+
+fn default_generic_args() -> Vec<ArgTemplate> {
+    ["int:{today}", "int:{total}", "string:{message}"]
+        .into_iter()
+        .map(|arg| ArgTemplate::try_from(arg.to_string()).expect("valid default argument"))
+        .collect()
+}
+
+fn default_data_source_path() -> String {
+    "$".into()
+}
+
+fn default_data_source_refresh_secs() -> u64 {
+    300
+}
+
 #[cfg(test)]
 mod tests {
+    use serde_valid::toml::FromTomlStr;
+
     use super::*;
 
     #[test]
@@ -268,4 +2971,242 @@ mod tests {
             TextSuffixResult::Message("Nice".into())
         );
     }
+
+    #[test]
+    fn test_arg_template_parsing() {
+        let arg = ArgTemplate::try_from("float:{today}.5".to_string()).unwrap();
+        assert_eq!(arg.kind, ArgKind::Float);
+        assert_eq!(arg.template, "{today}.5");
+        assert_eq!(String::from(arg), "float:{today}.5");
+
+        assert!(ArgTemplate::try_from("{today}".to_string()).is_err());
+        assert!(ArgTemplate::try_from("char:x".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_trigger() {
+        let trigger = Trigger::try_from("float > 0.8".to_string()).unwrap();
+        assert!(trigger.matches(&OscType::Float(0.9)));
+        assert!(!trigger.matches(&OscType::Float(0.8)));
+        assert!(!trigger.matches(&OscType::Bool(true)));
+        assert_eq!(String::from(trigger), "float > 0.8");
+
+        let trigger = Trigger::try_from("int".to_string()).unwrap();
+        assert_eq!(trigger, Trigger::Int(Comparison::Greater, 0));
+        assert!(trigger.matches(&OscType::Int(2)));
+
+        assert!(Trigger::default().matches(&OscType::Bool(true)));
+        assert!(!Trigger::default().matches(&OscType::Bool(false)));
+        assert!(Trigger::try_from("bool > 1".to_string()).is_err());
+        assert!(Trigger::try_from("float < 1".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_trigger_releases() {
+        let trigger = Trigger::Float(Comparison::Greater, 0.8);
+        assert!(!trigger.releases(&OscType::Float(0.9), 0.0));
+        assert!(trigger.releases(&OscType::Float(0.8), 0.0));
+        assert!(!trigger.releases(&OscType::Float(0.7), 0.2));
+        assert!(trigger.releases(&OscType::Float(0.5), 0.2));
+        assert!(!trigger.releases(&OscType::Int(0), 0.2));
+
+        assert!(Trigger::Bool.releases(&OscType::Bool(false), 0.0));
+        assert!(!Trigger::Bool.releases(&OscType::Bool(true), 0.0));
+    }
+
+    #[test]
+    fn test_quiet_hours() {
+        let at = |time: &str| time.parse::<DateTime>().unwrap();
+
+        let nights = QuietHours::try_from("23:00-08:00".to_string()).unwrap();
+        assert!(nights.contains(at("2025-03-28T23:30")));
+        assert!(nights.contains(at("2025-03-29T07:59")));
+        assert!(!nights.contains(at("2025-03-29T08:00")));
+        assert_eq!(String::from(nights), "23:00-08:00");
+
+        // 2025-03-28 is a Friday
+        let weekend = QuietHours::try_from("friday,saturday 22:00-02:00".to_string()).unwrap();
+        assert!(weekend.contains(at("2025-03-28T22:00")));
+        assert!(weekend.contains(at("2025-03-30T01:00")));
+        assert!(!weekend.contains(at("2025-03-28T01:00")));
+        assert!(!weekend.contains(at("2025-03-31T01:00")));
+        assert_eq!(String::from(weekend), "friday,saturday 22:00-02:00");
+
+        assert!(QuietHours::try_from("23:00".to_string()).is_err());
+        assert!(QuietHours::try_from("someday 20:00-22:00".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_address_pattern() {
+        let suffix = AddressPattern::from("/OSCBoop");
+        assert!(suffix.matches("/avatar/parameters/OSCBoop"));
+        assert!(!suffix.matches("/avatar/parameters/OSCBoop_IsGrabbed"));
+        assert_eq!(suffix.path(), Some("/OSCBoop"));
+
+        let exact = AddressPattern::from("/avatar/parameters/Boop");
+        assert!(exact.matches("/avatar/parameters/Boop"));
+        assert!(!exact.matches("/avatar/parameters/NoseBoop"));
+
+        let glob = AddressPattern::from("/avatar/parameters/Boop*");
+        assert!(glob.matches("/avatar/parameters/Boop"));
+        assert!(glob.matches("/avatar/parameters/Boop_Nose"));
+        assert!(!glob.matches("/avatar/parameters/Boop/Nose"));
+        assert!(!glob.matches("/avatar/parameters/NoseBoop"));
+        assert_eq!(glob.path(), None);
+        assert_eq!(glob.example(), "/avatar/parameters/Boop");
+
+        let single = AddressPattern::from("glob:/avatar/parameters/Boop_?.x");
+        assert!(single.matches("/avatar/parameters/Boop_1.x"));
+        assert!(!single.matches("/avatar/parameters/Boop_12.x"));
+        assert!(!single.matches("/avatar/parameters/Boop_1_x"));
+
+        let prefix = AddressPattern::from("prefix:/avatar/parameters/Boop");
+        assert!(prefix.matches("/avatar/parameters/Boop/Nose"));
+        assert!(!prefix.matches("/avatar/parameters/NoseBoop"));
+        assert_eq!(String::from(prefix), "prefix:/avatar/parameters/Boop");
+    }
+
+    #[test]
+    fn test_booper_pattern() {
+        let pattern = BooperPattern::try_from(r"Boop_(?P<who>\w+)$".to_string()).unwrap();
+        assert_eq!(
+            pattern.booper("/avatar/parameters/Boop_Alice"),
+            Some("Alice")
+        );
+        assert_eq!(pattern.booper("/avatar/parameters/OSCBoop"), None);
+
+        assert!(BooperPattern::try_from(r"Boop_(\w+)".to_string()).is_err());
+        assert!(BooperPattern::try_from("Boop_(".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_template_validation() {
+        assert!(Options::default().validate().is_ok());
+
+        let options: Options = toml::from_str(
+            r#"
+            message_template = "{today:sep} boops, {progress:today/goal_today}, {data:weather}"
+
+            [goals]
+            daily = 100
+
+            [[data_sources]]
+            name = "weather"
+            url = "https://example.com/weather.json"
+            "#,
+        )
+        .unwrap();
+        assert!(options.validate().is_ok());
+
+        for template in [
+            "{todya} boops",
+            "{data:unknown}",
+            "{progress:today/remaining}",
+        ] {
+            let options = Options {
+                message_template: template.into(),
+                ..Default::default()
+            };
+            assert!(options.validate().is_err(), "{template} accepted");
+        }
+
+        // every other template, with its own variables
+        let options: Options = toml::from_str(
+            r#"
+            daily_summary_template = "Yesterday: {yesterday:boop}"
+
+            [goals]
+            daily = 100
+            reached_template = "{scope} goal of {goal:sep} reached, {today} today"
+
+            [boop_storm]
+
+            [[counters]]
+            name = "headpats"
+            address = "/OSCHeadpat"
+            "#,
+        )
+        .unwrap();
+        assert!(options.validate().is_ok());
+
+        for toml in [
+            "daily_summary_template = \"{yesterdy}\"",
+            "[goals]\ndaily = 100\nmessage_template = \"{percnt}%\"",
+            "[boop_storm]\nmessage_template = \"{rate} {scope}\"",
+            "[leaderboard]\nentry_template = \"{rank}. {name} {streak}\"",
+        ] {
+            let options: Options = toml::from_str(toml).unwrap();
+            assert!(options.validate().is_err(), "{toml} accepted");
+        }
+    }
+
+    #[test]
+    fn test_avatar_filter() {
+        let filter = AvatarFilter {
+            only: vec![],
+            except: vec!["avtr_joke".into()],
+        };
+        assert!(filter.counts(Some("avtr_main")));
+        assert!(!filter.counts(Some("avtr_joke")));
+        assert!(filter.counts(None));
+
+        let filter = AvatarFilter {
+            only: vec!["avtr_main".into()],
+            except: vec![],
+        };
+        assert!(filter.counts(Some("avtr_main")));
+        assert!(!filter.counts(Some("avtr_other")));
+        assert!(!filter.counts(None));
+    }
+
+    #[test]
+    fn test_output_rule() {
+        let rule: OutputRule = toml::from_str(
+            r#"
+            every = 100
+            address = "/avatar/parameters/Confetti"
+            value = "bool:true"
+        "#,
+        )
+        .unwrap();
+        assert!(rule.matches(200, 0, 1));
+        assert!(!rule.matches(201, 0, 1));
+
+        let rule = OutputRule {
+            rate: Some(10),
+            ..rule
+        };
+        assert!(rule.matches(300, 9, 10));
+        assert!(!rule.matches(300, 10, 11), "rate was reached before");
+        assert!(!rule.matches(301, 9, 10));
+
+        let rule = OutputRule {
+            every: None,
+            rate: None,
+            ..rule
+        };
+        assert!(!rule.matches(300, 9, 10));
+    }
+
+    #[test]
+    fn test_rgb_parsing() {
+        assert_eq!(
+            Rgb::try_from("#ff69b4".to_string()),
+            Ok(Rgb([255, 105, 180]))
+        );
+        assert_eq!(String::from(Rgb([255, 105, 180])), "#ff69b4");
+        assert!(Rgb::try_from("#fff".to_string()).is_err());
+        assert!(Rgb::try_from("#gg0000".to_string()).is_err());
+    }
+
+    #[test]
+    fn test_sound_alert_matches() {
+        let alert = SoundAlert::from_toml_str("value = 1000").unwrap();
+        assert_eq!(alert.count, CountKind::Total);
+
+        assert!(alert.matches(1000));
+        assert!(alert.matches(11000));
+        assert!(!alert.matches(2000));
+        assert!(!alert.matches(999));
+    }
 }
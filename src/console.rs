@@ -0,0 +1,56 @@
+use std::io::BufRead;
+
+use tracing::{debug, info, warn};
+
+use crate::command::{Command, CommandSender};
+
+/// Read hotkey commands from stdin, each confirmed with enter
+///
+/// - `u`: undo last boop
+/// - `f`: toggle freeze mode
+/// - `p`: toggle pause
+/// - `s`: post stats now
+///
+/// Runs on a dedicated thread as reading stdin can't be cancelled, the thread
+/// simply dies with the process.
+pub(crate) fn spawn(commands: CommandSender) {
+    let spawned = std::thread::Builder::new()
+        .name("console".into())
+        .spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => {
+                        warn!(err=%e, "failed to read from stdin");
+                        break;
+                    }
+                };
+
+                let command = match line.trim() {
+                    "u" | "undo" => Command::Undo,
+                    "f" | "freeze" => Command::Freeze(None),
+                    "p" | "pause" => Command::Pause(None),
+                    "s" | "post" => Command::PostStats {
+                        template: None,
+                        manual: true,
+                    },
+                    "" => continue,
+                    other => {
+                        info!("unknown console command {other:?}, available: u (undo), f (freeze), p (pause), s (post)");
+                        continue;
+                    }
+                };
+
+                if commands.blocking_send(command).is_err() {
+                    break;
+                }
+            }
+
+            // stdin closed, e.g. when running as a service
+            debug!("console commands stopped");
+        });
+
+    if let Err(e) = spawned {
+        warn!(err=%e, "failed to spawn console thread");
+    }
+}
@@ -0,0 +1,298 @@
+use std::{
+    collections::BTreeMap,
+    io,
+    net::{Ipv4Addr, SocketAddr},
+    time::Duration,
+};
+
+use serde::{Deserialize, Serialize};
+use tokio::{
+    io::{AsyncBufReadExt, AsyncReadExt, AsyncWriteExt, BufReader},
+    net::{TcpListener, TcpStream, tcp::OwnedWriteHalf},
+    sync::mpsc,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    command::{Command, CommandSender},
+    config::CoordinationOptions,
+    instance,
+};
+
+/// Name of our line without a configured or instance name
+const DEFAULT_NAME: &str = "osc-booper";
+
+/// Wait before taking over or reconnecting after losing the leader
+const RETRY_DELAY: Duration = Duration::from_secs(2);
+
+/// Chatbox line handed to the leader, one JSON object per line
+#[derive(Debug, Serialize, Deserialize)]
+struct LineMessage {
+    name: String,
+    line: String,
+}
+
+/// Our part of the shared chatbox, kept by the main loop
+#[derive(Debug)]
+pub(crate) struct Coordination {
+    /// name of our line
+    name: String,
+
+    /// whether we send the merged messages
+    leader: bool,
+
+    /// lines of all boopers, only used by the leader
+    lines: MergedLines,
+
+    /// our lines for the leader, while we aren't it
+    outgoing: mpsc::Sender<String>,
+}
+
+impl Coordination {
+    pub(crate) fn set_leader(&mut self, leader: bool) {
+        self.leader = leader;
+        if !leader {
+            self.lines = MergedLines::default();
+        }
+    }
+
+    pub(crate) fn is_follower(&self) -> bool {
+        !self.leader
+    }
+
+    /// Update the line of another booper, removing it on `None`
+    pub(crate) fn set_line(&mut self, name: String, line: Option<String>) {
+        self.lines.set(name, line);
+    }
+
+    /// Our latest line, to send again along with changed lines of the others
+    pub(crate) fn own_line(&self) -> String {
+        self.lines.get(&self.name).unwrap_or_default().into()
+    }
+
+    /// Merged message to send instead of our `message`, `None` if it was
+    /// handed to the leader
+    pub(crate) fn outgoing(&mut self, message: String) -> Option<String> {
+        if self.leader {
+            self.lines.set(self.name.clone(), Some(message));
+            return Some(self.lines.message());
+        }
+
+        if let Err(e) = self.outgoing.try_send(message) {
+            debug!(err=%e, "failed to hand chatbox message to the leader");
+        }
+        None
+    }
+}
+
+/// Latest line per booper, merged in the order of their names
+#[derive(Debug, Default)]
+struct MergedLines(BTreeMap<String, String>);
+
+impl MergedLines {
+    fn set(&mut self, name: String, line: Option<String>) {
+        match line {
+            Some(line) => self.0.insert(name, line),
+            None => self.0.remove(&name),
+        };
+    }
+
+    fn get(&self, name: &str) -> Option<&str> {
+        self.0.get(name).map(String::as_str)
+    }
+
+    fn message(&self) -> String {
+        let lines: Vec<_> = self
+            .0
+            .values()
+            .map(|line| line.trim())
+            .filter(|line| !line.is_empty())
+            .collect();
+        lines.join("\n")
+    }
+}
+
+/// Share the chatbox with other boopers on this machine
+///
+/// The first booper listening on the loopback port leads and sends the
+/// merged messages, the others connect to it and hand over their lines. A
+/// follower takes over once the leader is gone. Roles and lines of others
+/// reach the main loop as [`Command`]s.
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: &CoordinationOptions,
+    commands: CommandSender,
+) -> Coordination {
+    let name = options
+        .name
+        .clone()
+        .or_else(|| instance::name().map(String::from))
+        .unwrap_or_else(|| DEFAULT_NAME.into());
+    let addr = SocketAddr::from((Ipv4Addr::LOCALHOST, options.port));
+    let (outgoing, lines) = mpsc::channel(8);
+
+    let task_name = name.clone();
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = token.cancelled() => {}
+            _ = coordinate(addr, task_name, commands, lines) => {}
+        }
+    });
+
+    Coordination {
+        name,
+        leader: false,
+        lines: MergedLines::default(),
+        outgoing,
+    }
+}
+
+async fn coordinate(
+    addr: SocketAddr,
+    name: String,
+    commands: CommandSender,
+    mut lines: mpsc::Receiver<String>,
+) {
+    let mut last_line = None;
+
+    loop {
+        match TcpListener::bind(addr).await {
+            Ok(listener) => {
+                info!("sending the shared chatbox, other boopers connect to {addr}");
+                let role = Command::CoordinationRole { leader: true };
+                if commands.send(role).await.is_ok() {
+                    lead(listener, commands).await;
+                }
+                return;
+            }
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {}
+            Err(e) => {
+                error!(err=%e, "failed to listen for other boopers on {addr}");
+                return;
+            }
+        }
+
+        match TcpStream::connect(addr).await {
+            Ok(stream) => {
+                info!("sharing the chatbox of the booper on {addr}");
+                let role = Command::CoordinationRole { leader: false };
+                if commands.send(role).await.is_err() {
+                    return;
+                }
+                match follow(stream, &name, &mut lines, &mut last_line).await {
+                    Ok(()) => return,
+                    Err(e) => warn!(err=%e, "lost the booper sending the shared chatbox"),
+                }
+            }
+            Err(e) => debug!(err=%e, "failed to connect to the booper on {addr}"),
+        }
+
+        tokio::time::sleep(RETRY_DELAY).await;
+    }
+}
+
+async fn lead(listener: TcpListener, commands: CommandSender) {
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_follower(stream, commands.clone()));
+            }
+            Err(e) => error!(err=%e, "failed to accept booper connection"),
+        }
+    }
+}
+
+/// Pass the lines of a follower to the main loop, removing its line once it
+/// disconnects
+async fn handle_follower(stream: TcpStream, commands: CommandSender) {
+    let mut lines = BufReader::new(stream).lines();
+    let mut name = None;
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        let message: LineMessage = match serde_json::from_str(&line) {
+            Ok(message) => message,
+            Err(e) => {
+                warn!(err=%e, "invalid line from other booper");
+                continue;
+            }
+        };
+
+        name = Some(message.name.clone());
+        let line = Command::CoordinatedLine {
+            name: message.name,
+            line: Some(message.line),
+        };
+        if commands.send(line).await.is_err() {
+            return;
+        }
+    }
+
+    if let Some(name) = name {
+        debug!(name, "booper left the shared chatbox");
+        commands
+            .send(Command::CoordinatedLine { name, line: None })
+            .await
+            .ok();
+    }
+}
+
+/// Hand our lines to the leader until it's gone, `Ok` once we're stopping
+async fn follow(
+    stream: TcpStream,
+    name: &str,
+    lines: &mut mpsc::Receiver<String>,
+    last_line: &mut Option<String>,
+) -> io::Result<()> {
+    let (mut reader, mut writer) = stream.into_split();
+    if let Some(line) = last_line {
+        send_line(&mut writer, name, line).await?;
+    }
+
+    // the leader never writes, reading only notices it's gone
+    let mut buf = [0; 64];
+    loop {
+        tokio::select! {
+            line = lines.recv() => {
+                let Some(line) = line else {
+                    return Ok(());
+                };
+                let line = last_line.insert(line);
+                send_line(&mut writer, name, line).await?;
+            }
+            read = reader.read(&mut buf) => {
+                if read? == 0 {
+                    return Err(io::ErrorKind::UnexpectedEof.into());
+                }
+            }
+        }
+    }
+}
+
+async fn send_line(writer: &mut OwnedWriteHalf, name: &str, line: &str) -> io::Result<()> {
+    let message = LineMessage {
+        name: name.into(),
+        line: line.into(),
+    };
+    let mut json = serde_json::to_string(&message)?;
+    json.push('\n');
+    writer.write_all(json.as_bytes()).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merged_lines() {
+        let mut lines = MergedLines::default();
+        lines.set("headpats".into(), Some("Headpats: 3".into()));
+        lines.set("boops".into(), Some("Boops: 12\n".into()));
+        lines.set("hugs".into(), Some("".into()));
+        assert_eq!(lines.message(), "Boops: 12\nHeadpats: 3");
+
+        lines.set("boops".into(), None);
+        assert_eq!(lines.message(), "Headpats: 3");
+        assert_eq!(lines.get("boops"), None);
+    }
+}
@@ -0,0 +1,169 @@
+use std::{
+    collections::VecDeque,
+    sync::{Arc, Mutex},
+};
+
+use axum::{
+    Json,
+    extract::{
+        State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+};
+use jiff::Timestamp;
+use serde::Serialize;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error};
+
+use crate::{
+    command::{self, ControlRequest, StatsSnapshot},
+    events::{BooperEvent, EventLogEntry},
+    http::HttpState,
+};
+
+/// Amount of recent boops shown on the dashboard
+const RECENT_EVENTS: usize = 20;
+
+const PAGE: &str = include_str!("dashboard.html");
+
+/// Most recent boops and milestones, newest last
+pub(crate) type RecentEvents = Arc<Mutex<VecDeque<EventLogEntry>>>;
+
+/// Everything shown on the dashboard
+#[derive(Serialize)]
+struct DashboardSnapshot {
+    stats: StatsSnapshot,
+    recent: Vec<EventLogEntry>,
+    started: Timestamp,
+    uptime_secs: i64,
+}
+
+/// Message of the live feed
+#[derive(Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LiveMessage {
+    /// sent once after connecting
+    Snapshot(DashboardSnapshot),
+
+    /// sent on every event, with the resulting stats
+    Event {
+        entry: EventLogEntry,
+        stats: StatsSnapshot,
+    },
+}
+
+/// Keep track of recent boops for the dashboard
+pub(crate) fn spawn_recorder(
+    token: CancellationToken,
+    recent: RecentEvents,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            match event {
+                Ok(
+                    event @ (BooperEvent::BoopCounted { .. }
+                    | BooperEvent::Counted { .. }
+                    | BooperEvent::Milestone { .. }),
+                ) => {
+                    let mut recent = recent.lock().unwrap();
+                    if recent.len() == RECENT_EVENTS {
+                        recent.pop_front();
+                    }
+                    recent.push_back(EventLogEntry::now(event));
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "dashboard recorder lagging behind");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+pub(crate) async fn page() -> Html<&'static str> {
+    Html(PAGE)
+}
+
+pub(crate) async fn snapshot_handler(State(state): State<HttpState>) -> Response {
+    match snapshot(&state).await {
+        Some(snapshot) => Json(snapshot).into_response(),
+        None => StatusCode::SERVICE_UNAVAILABLE.into_response(),
+    }
+}
+
+pub(crate) async fn live(ws: WebSocketUpgrade, State(state): State<HttpState>) -> Response {
+    ws.on_upgrade(move |socket| live_feed(socket, state))
+}
+
+async fn snapshot(state: &HttpState) -> Option<DashboardSnapshot> {
+    let stats = stats(state).await?;
+    let recent = state.recent.lock().unwrap().iter().cloned().collect();
+
+    Some(DashboardSnapshot {
+        stats,
+        recent,
+        started: state.started,
+        uptime_secs: Timestamp::now().as_second() - state.started.as_second(),
+    })
+}
+
+async fn stats(state: &HttpState) -> Option<StatsSnapshot> {
+    command::execute(ControlRequest::Stats, &state.commands, "http")
+        .await
+        .stats
+}
+
+/// Send a snapshot, followed by every event as it happens
+async fn live_feed(mut socket: WebSocket, state: HttpState) {
+    debug!("dashboard websocket connected");
+
+    // subscribe first, so nothing happening in between gets lost
+    let mut events = state.events.subscribe();
+    let mut message = snapshot(&state).await.map(LiveMessage::Snapshot);
+
+    while let Some(current) = message.take() {
+        let text = match serde_json::to_string(&current) {
+            Ok(text) => text,
+            Err(e) => {
+                error!(err=%e, "failed to serialize dashboard update");
+                break;
+            }
+        };
+        if socket.send(Message::Text(text)).await.is_err() {
+            break;
+        }
+
+        message = loop {
+            tokio::select! {
+                event = events.recv() => match event {
+                    Ok(event) => {
+                        break stats(&state).await.map(|stats| LiveMessage::Event {
+                            entry: EventLogEntry::now(event),
+                            stats,
+                        });
+                    }
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!(skipped, "dashboard websocket lagging behind");
+                    }
+                    Err(RecvError::Closed) => break None,
+                },
+                received = socket.recv() => match received {
+                    Some(Ok(Message::Close(_)) | Err(_)) | None => break None,
+                    Some(Ok(_)) => {}
+                },
+            }
+        };
+    }
+
+    debug!("dashboard websocket disconnected");
+}
@@ -0,0 +1,130 @@
+use std::{
+    collections::HashMap,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
+
+use serde_json::Value;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::config::DataSource;
+
+/// Latest fetched value per data source name
+pub(crate) type DataCache = Arc<RwLock<HashMap<String, String>>>;
+
+/// Spawn one background fetcher per configured data source
+pub(crate) fn spawn_fetchers(token: CancellationToken, sources: &[DataSource], cache: DataCache) {
+    if sources.is_empty() {
+        return;
+    }
+
+    let client = match reqwest::Client::builder()
+        .user_agent(concat!("osc-booper/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(10))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(err=%e, "failed to create http client, data sources disabled");
+            return;
+        }
+    };
+
+    for source in sources {
+        let token = token.clone();
+        let client = client.clone();
+        let cache = cache.clone();
+        let source = source.clone();
+
+        info!(name=%source.name, url=%source.url, "starting data source");
+
+        tokio::spawn(async move {
+            loop {
+                match fetch(&client, &source).await {
+                    Ok(value) => {
+                        debug!(name=%source.name, value=%value, "data source refreshed");
+                        cache.write().unwrap().insert(source.name.clone(), value);
+                    }
+                    Err(e) => {
+                        // keep the last known value around
+                        warn!(name=%source.name, err=%e, "failed to refresh data source");
+                    }
+                }
+
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = tokio::time::sleep(Duration::from_secs(source.refresh_secs)) => {}
+                }
+            }
+        });
+    }
+}
+
+/// Fetch JSON from source and extract the configured value
+async fn fetch(client: &reqwest::Client, source: &DataSource) -> Result<String, String> {
+    let json = client
+        .get(&source.url)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json::<Value>()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    select_path(&json, &source.path)
+        .map(value_to_string)
+        .ok_or_else(|| format!("path {} not found in response", source.path))
+}
+
+/// Resolve a small JSONPath subset: `$`, `.key`, `['key']` and `[index]`
+pub(crate) fn select_path<'v>(value: &'v Value, path: &str) -> Option<&'v Value> {
+    let mut rest = path.trim().strip_prefix('$').unwrap_or(path.trim());
+    let mut current = value;
+
+    while !rest.is_empty() {
+        if let Some(tail) = rest.strip_prefix('.') {
+            let end = tail.find(['.', '[']).unwrap_or(tail.len());
+            current = current.get(&tail[..end])?;
+            rest = &tail[end..];
+        } else if let Some(tail) = rest.strip_prefix('[') {
+            let end = tail.find(']')?;
+            let key = &tail[..end];
+            current = match key.trim_matches(['\'', '"']) {
+                quoted if quoted.len() != key.len() => current.get(quoted)?,
+                index => current.get(index.parse::<usize>().ok()?)?,
+            };
+            rest = &tail[end + 1..];
+        } else {
+            return None;
+        }
+    }
+
+    Some(current)
+}
+
+fn value_to_string(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Null => "".into(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_select_path() {
+        let json = json!({"current": {"temp": 21.5, "tags": ["a", "b"]}, "song name": "x"});
+
+        assert_eq!(select_path(&json, "$.current.temp"), Some(&json!(21.5)));
+        assert_eq!(select_path(&json, "$.current.tags[1]"), Some(&json!("b")));
+        assert_eq!(select_path(&json, "$['song name']"), Some(&json!("x")));
+        assert_eq!(select_path(&json, "$.missing"), None);
+    }
+}
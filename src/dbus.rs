@@ -0,0 +1,173 @@
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+use zbus::{fdo, interface, object_server::SignalEmitter};
+
+use crate::{
+    command::{self, CommandSender, ControlRequest, StatsSnapshot},
+    events::BooperEvent,
+    instance,
+};
+
+const BUS_NAME: &str = "io.github.Invisi.OscBooper";
+const OBJECT_PATH: &str = "/io/github/Invisi/OscBooper";
+
+/// Session bus service exposing stats and controls
+struct BooperInterface {
+    commands: CommandSender,
+}
+
+impl BooperInterface {
+    async fn request(&self, request: ControlRequest) -> fdo::Result<StatsSnapshot> {
+        let response = command::execute(request, &self.commands, "dbus").await;
+        response
+            .stats
+            .ok_or_else(|| fdo::Error::Failed(response.error.unwrap_or_default()))
+    }
+}
+
+#[interface(name = "io.github.Invisi.OscBooper1")]
+impl BooperInterface {
+    #[zbus(property)]
+    async fn today(&self) -> fdo::Result<u32> {
+        Ok(self.request(ControlRequest::Stats).await?.today)
+    }
+
+    #[zbus(property)]
+    async fn total(&self) -> fdo::Result<u64> {
+        Ok(self.request(ControlRequest::Stats).await?.total)
+    }
+
+    #[zbus(property)]
+    async fn paused(&self) -> fdo::Result<bool> {
+        Ok(self.request(ControlRequest::Stats).await?.paused)
+    }
+
+    #[zbus(property)]
+    async fn avatar_id(&self) -> fdo::Result<String> {
+        Ok(self
+            .request(ControlRequest::Stats)
+            .await?
+            .avatar_id
+            .unwrap_or_default())
+    }
+
+    async fn pause(&self) -> fdo::Result<()> {
+        self.request(ControlRequest::Pause).await.map(|_| ())
+    }
+
+    async fn resume(&self) -> fdo::Result<()> {
+        self.request(ControlRequest::Resume).await.map(|_| ())
+    }
+
+    async fn post_now(&self) -> fdo::Result<()> {
+        self.request(ControlRequest::PostNow).await.map(|_| ())
+    }
+
+    async fn adjust(&self, by: i64) -> fdo::Result<()> {
+        self.request(ControlRequest::Adjust { by })
+            .await
+            .map(|_| ())
+    }
+
+    async fn undo(&self) -> fdo::Result<()> {
+        self.request(ControlRequest::Undo).await.map(|_| ())
+    }
+
+    async fn reset_today(&self) -> fdo::Result<()> {
+        self.request(ControlRequest::ResetToday).await.map(|_| ())
+    }
+
+    /// A boop was counted
+    #[zbus(signal)]
+    async fn booped(emitter: &SignalEmitter<'_>, today: u32, total: u64) -> zbus::Result<()>;
+
+    /// Avatar was changed
+    #[zbus(signal)]
+    async fn avatar_changed(emitter: &SignalEmitter<'_>, avatar_id: &str) -> zbus::Result<()>;
+}
+
+/// Publish service on the session bus and forward events as signals
+pub(crate) fn spawn(
+    token: CancellationToken,
+    commands: CommandSender,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    tokio::spawn(async move {
+        let bus_name = instance::scoped(BUS_NAME, ".");
+        let connection = async {
+            zbus::connection::Builder::session()?
+                .name(bus_name.as_str())?
+                .serve_at(OBJECT_PATH, BooperInterface { commands })?
+                .build()
+                .await
+        }
+        .await;
+
+        let connection = match connection {
+            Ok(connection) => connection,
+            Err(e) => {
+                error!(err=%e, "failed to publish d-bus service");
+                return;
+            }
+        };
+
+        let iface = match connection
+            .object_server()
+            .interface::<_, BooperInterface>(OBJECT_PATH)
+            .await
+        {
+            Ok(iface) => iface,
+            Err(e) => {
+                error!(err=%e, "failed to look up d-bus interface");
+                return;
+            }
+        };
+
+        info!("published d-bus service {bus_name}");
+
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            let emitter = iface.signal_emitter();
+            let result = match event {
+                Ok(BooperEvent::BoopCounted { today, total, .. }) => {
+                    let iface = iface.get().await;
+                    BooperInterface::booped(emitter, today, total)
+                        .await
+                        .and(iface.today_changed(emitter).await)
+                        .and(iface.total_changed(emitter).await)
+                }
+                Ok(BooperEvent::AvatarChanged { avatar_id }) => {
+                    BooperInterface::avatar_changed(emitter, &avatar_id).await
+                }
+                Ok(
+                    BooperEvent::Reset { .. }
+                    | BooperEvent::Adjusted { .. }
+                    | BooperEvent::Undone { .. },
+                ) => {
+                    let iface = iface.get().await;
+                    iface
+                        .today_changed(emitter)
+                        .await
+                        .and(iface.total_changed(emitter).await)
+                }
+                Ok(_) => Ok(()),
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "d-bus event forwarding lagging behind");
+                    Ok(())
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            if let Err(e) = result {
+                warn!(err=%e, "failed to emit d-bus signal");
+            }
+        }
+
+        info!("stopping d-bus service");
+    });
+}
@@ -0,0 +1,277 @@
+use std::collections::HashSet;
+
+use rosc::{OscBundle, OscError, OscMessage, OscPacket, OscTime, OscType};
+use tracing::debug;
+
+use crate::{
+    config::{AddressPattern, BooperPattern, Options},
+    metrics,
+    osc::CONTROL_PREFIX,
+};
+
+const BUNDLE_TAG: &[u8] = b"#bundle\0";
+
+/// Addresses the receive loop acts on, other messages aren't decoded
+#[derive(Debug, Clone)]
+pub struct AddressFilter {
+    exact: HashSet<String>,
+    patterns: Vec<AddressPattern>,
+    booper_pattern: Option<BooperPattern>,
+}
+
+impl AddressFilter {
+    /// Addresses handled with `options`, matched like the receive loop does
+    pub fn new(options: &Options) -> Self {
+        let mut exact = HashSet::from(["/avatar/change".into(), options.afk_address.clone()]);
+        exact.extend(options.chatbox_toggle_address.clone());
+
+        let patterns = [&options.boop_address]
+            .into_iter()
+            .chain(options.boopers.iter().map(|booper| &booper.address))
+            .chain(options.counters.iter().map(|counter| &counter.address))
+            .cloned()
+            .collect();
+
+        AddressFilter {
+            exact,
+            patterns,
+            booper_pattern: options.booper_pattern.clone(),
+        }
+    }
+
+    pub fn matches(&self, address: &str) -> bool {
+        self.exact.contains(address)
+            || address.starts_with(CONTROL_PREFIX)
+            || self.patterns.iter().any(|pattern| pattern.matches(address))
+            || self
+                .booper_pattern
+                .as_ref()
+                .is_some_and(|pattern| pattern.booper(address).is_some())
+    }
+}
+
+/// Decode OSC packet like [`decode_packet`], skipping messages not matching
+/// `filter` before decoding their arguments
+///
+/// A skipped message is returned as empty bundle, it still counts as
+/// activity.
+pub fn decode_filtered(buf: &[u8], filter: &AddressFilter) -> Result<OscPacket, OscError> {
+    match message_address(buf) {
+        Some(address) if !filter.matches(address) => Ok(OscPacket::Bundle(OscBundle {
+            // immediately
+            timetag: OscTime {
+                seconds: 0,
+                fractional: 1,
+            },
+            content: vec![],
+        })),
+        Some(_) => decode_packet(buf),
+        None => match filter_bundle(buf, filter) {
+            Some(bundle) => Ok(OscPacket::Bundle(bundle)),
+            // malformed, let the full decoder report it
+            None => decode_packet(buf),
+        },
+    }
+}
+
+/// Address of an OSC message, `None` for bundles
+fn message_address(buf: &[u8]) -> Option<&str> {
+    if buf.first() != Some(&b'/') {
+        return None;
+    }
+
+    let end = buf.iter().position(|b| *b == 0)?;
+    std::str::from_utf8(&buf[..end]).ok()
+}
+
+/// Decode bundle with only the messages matching `filter`
+fn filter_bundle(buf: &[u8], filter: &AddressFilter) -> Option<OscBundle> {
+    let (timetag, mut elements) = buf.strip_prefix(BUNDLE_TAG)?.split_first_chunk::<8>()?;
+    let (seconds, fractional) = timetag.split_at(4);
+    let timetag = OscTime {
+        seconds: u32::from_be_bytes(seconds.try_into().ok()?),
+        fractional: u32::from_be_bytes(fractional.try_into().ok()?),
+    };
+
+    let mut content = vec![];
+    while !elements.is_empty() {
+        let (size, rest) = elements.split_first_chunk::<4>()?;
+        let size = usize::try_from(i32::from_be_bytes(*size)).ok()?;
+        let element = rest.get(..size)?;
+        elements = &rest[size..];
+
+        let packet = match message_address(element) {
+            Some(address) if !filter.matches(address) => continue,
+            Some(_) => rosc::decoder::decode_udp(element).ok()?.1,
+            None => OscPacket::Bundle(filter_bundle(element, filter)?),
+        };
+        content.push(packet);
+    }
+
+    Some(OscBundle { timetag, content })
+}
+
+/// Decode OSC packet, falling back to [`decode_lenient`] for malformed
+/// messages
+pub fn decode_packet(buf: &[u8]) -> Result<OscPacket, OscError> {
+    match rosc::decoder::decode_udp(buf) {
+        Ok((_, packet)) => Ok(packet),
+        Err(e) => match decode_lenient(buf) {
+            Some(message) => {
+                metrics::LENIENT_DECODES.inc();
+                debug!(err=%e, addr=%message.addr, "recovered malformed OSC message");
+                Ok(OscPacket::Message(message))
+            }
+            None => {
+                metrics::DECODE_ERRORS.inc();
+                Err(e)
+            }
+        },
+    }
+}
+
+/// Recover address and first argument of a malformed OSC message, e.g. with
+/// bad padding or unusual type tags
+///
+/// Unknown or truncated arguments are dropped, bundles aren't recovered.
+pub(crate) fn decode_lenient(buf: &[u8]) -> Option<OscMessage> {
+    if buf.first() != Some(&b'/') {
+        return None;
+    }
+
+    let addr_end = buf.iter().position(|b| *b == 0).unwrap_or(buf.len());
+    let addr = std::str::from_utf8(&buf[..addr_end]).ok()?.to_string();
+    let message = |args| {
+        Some(OscMessage {
+            addr: addr.clone(),
+            args,
+        })
+    };
+
+    // type tags start at the next comma, whatever the padding
+    let Some(tags_start) = buf[addr_end..]
+        .iter()
+        .position(|b| *b == b',')
+        .map(|pos| addr_end + pos)
+    else {
+        return message(vec![]);
+    };
+    let tags = &buf[tags_start + 1..];
+    let tags_len = tags.iter().position(|b| *b == 0).unwrap_or(tags.len());
+    let Some(tag) = tags[..tags_len].first() else {
+        return message(vec![]);
+    };
+
+    // arguments follow the tag string, padded relative to its own start,
+    // unpadded if that's out of bounds
+    let tags_end = tags_start + 1 + tags_len + 1;
+    let padded = tags_start + (tags_end - tags_start).next_multiple_of(4);
+    let data = buf
+        .get(padded..)
+        .or_else(|| buf.get(tags_end..))
+        .unwrap_or(&[]);
+
+    let be4 = |data: &[u8]| data.get(..4).map(|b| [b[0], b[1], b[2], b[3]]);
+    let be8 = |data: &[u8]| {
+        data.get(..8)
+            .map(|b| [b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]])
+    };
+
+    let arg = match tag {
+        b'T' => Some(OscType::Bool(true)),
+        b'F' => Some(OscType::Bool(false)),
+        b'N' => Some(OscType::Nil),
+        b'i' => be4(data).map(|b| OscType::Int(i32::from_be_bytes(b))),
+        b'f' => be4(data).map(|b| OscType::Float(f32::from_be_bytes(b))),
+        b'h' => be8(data).map(|b| OscType::Long(i64::from_be_bytes(b))),
+        b'd' => be8(data).map(|b| OscType::Double(f64::from_be_bytes(b))),
+        b's' => {
+            let end = data.iter().position(|b| *b == 0).unwrap_or(data.len());
+            std::str::from_utf8(&data[..end])
+                .ok()
+                .map(|s| OscType::String(s.into()))
+        }
+        _ => None,
+    };
+
+    message(arg.into_iter().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_decode_lenient() {
+        // address without padding, bool argument
+        let message = decode_lenient(b"/avatar/parameters/OSCBoop\0,T\0\0").unwrap();
+        assert_eq!(message.addr, "/avatar/parameters/OSCBoop");
+        assert_eq!(message.args, vec![OscType::Bool(true)]);
+
+        // unknown second tag, int argument
+        let message = decode_lenient(b"/boop\0\0\0,iX\0\0\0\0\x2a").unwrap();
+        assert_eq!(message.args, vec![OscType::Int(42)]);
+
+        assert!(decode_lenient(b"#bundle\0").is_none());
+    }
+
+    #[test]
+    fn test_decode_filtered() {
+        let options = Options {
+            boop_address: "/OSCBoop".into(),
+            ..Default::default()
+        };
+        let filter = AddressFilter::new(&options);
+        let message = |addr: &str| {
+            OscPacket::Message(OscMessage {
+                addr: addr.into(),
+                args: vec![OscType::Bool(true)],
+            })
+        };
+
+        let bundle = OscPacket::Bundle(OscBundle {
+            timetag: OscTime {
+                seconds: 1,
+                fractional: 2,
+            },
+            content: vec![
+                message("/avatar/parameters/VelocityX"),
+                message("/avatar/parameters/OSCBoop"),
+                OscPacket::Bundle(OscBundle {
+                    timetag: OscTime {
+                        seconds: 0,
+                        fractional: 1,
+                    },
+                    content: vec![
+                        message("/booper/pause"),
+                        message("/avatar/parameters/Grounded"),
+                    ],
+                }),
+            ],
+        });
+        let expected = OscPacket::Bundle(OscBundle {
+            timetag: OscTime {
+                seconds: 1,
+                fractional: 2,
+            },
+            content: vec![
+                message("/avatar/parameters/OSCBoop"),
+                OscPacket::Bundle(OscBundle {
+                    timetag: OscTime {
+                        seconds: 0,
+                        fractional: 1,
+                    },
+                    content: vec![message("/booper/pause")],
+                }),
+            ],
+        });
+        let encoded = rosc::encoder::encode(&bundle).unwrap();
+        assert_eq!(decode_filtered(&encoded, &filter).unwrap(), expected);
+
+        let encoded = rosc::encoder::encode(&message("/avatar/parameters/VelocityX")).unwrap();
+        let OscPacket::Bundle(skipped) = decode_filtered(&encoded, &filter).unwrap() else {
+            panic!("skipped message should be an empty bundle");
+        };
+        assert!(skipped.content.is_empty());
+    }
+}
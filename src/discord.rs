@@ -0,0 +1,137 @@
+use std::time::{Duration, Instant};
+
+use serde_json::json;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{config::DiscordOptions, events::BooperEvent, template};
+
+/// Webhook URL, contains the webhook's token
+const WEBHOOK_ENV: &str = "OSC_BOOPER_DISCORD_WEBHOOK";
+
+/// Post milestones, broken records and period summaries to a Discord webhook
+///
+/// `record` is the daily record at startup
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: DiscordOptions,
+    mut record: u32,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    let Ok(webhook) = std::env::var(WEBHOOK_ENV) else {
+        warn!("{WEBHOOK_ENV} is not set, discord posts disabled");
+        return;
+    };
+
+    let client = match reqwest::Client::builder()
+        .user_agent(concat!("osc-booper/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(30))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(err=%e, "failed to create http client, discord posts disabled");
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        let mut last_post: Option<Instant> = None;
+        let mut record_announced = false;
+
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            let text = match event {
+                Ok(BooperEvent::BoopCounted { today, total, .. }) => {
+                    let previous = record;
+                    record = record.max(today);
+
+                    // the very first day always breaks the record
+                    let broke_record = today > previous && previous > 0;
+                    let reached_every = options.every.is_some_and(|every| total % every == 0);
+
+                    let announce_record = options.record && broke_record && !record_announced;
+                    if !reached_every && !announce_record {
+                        continue;
+                    }
+
+                    // keep boop storms from spamming the channel
+                    let min_interval = options.min_interval.unsigned_abs();
+                    if last_post.is_some_and(|last| last.elapsed() < min_interval) {
+                        debug!("discord post rate limited");
+                        continue;
+                    }
+                    last_post = Some(Instant::now());
+
+                    if reached_every {
+                        render(&options.every_template, today, total, previous)
+                    } else {
+                        record_announced = true;
+                        render(&options.record_template, today, total, previous)
+                    }
+                }
+                Ok(BooperEvent::Reset {
+                    previous,
+                    date,
+                    record: period_record,
+                    total,
+                }) => {
+                    record = period_record;
+                    record_announced = false;
+
+                    if !options.summary {
+                        continue;
+                    }
+                    template::render(&options.summary_template, |key| match key {
+                        "boops" => Some(previous.to_string()),
+                        "date" => Some(date.to_string()),
+                        "record" => Some(period_record.to_string()),
+                        "total" => Some(total.to_string()),
+                        _ => None,
+                    })
+                }
+                Ok(_) => continue,
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "discord poster lagging behind");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            match post(&client, &webhook, &text).await {
+                Ok(()) => info!("posted to discord"),
+                Err(e) => warn!(err=%e, "failed to post to discord"),
+            }
+        }
+    });
+}
+
+fn render(template: &str, today: u32, total: u64, previous: u32) -> String {
+    template::render(template, |key| match key {
+        "today" => Some(today.to_string()),
+        "total" => Some(total.to_string()),
+        "previous" => Some(previous.to_string()),
+        _ => None,
+    })
+}
+
+async fn post(client: &reqwest::Client, webhook: &str, text: &str) -> Result<(), String> {
+    client
+        .post(webhook)
+        .json(&json!({
+            "content": text,
+            // never ping anyone, whatever the template contains
+            "allowed_mentions": { "parse": [] },
+        }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
@@ -0,0 +1,28 @@
+use std::{io, net::SocketAddr};
+
+use thiserror::Error;
+
+use crate::config::ConfigError;
+
+/// Reasons the booper can't start or keep running
+#[derive(Debug, Error)]
+pub enum BooperError {
+    /// config file can't be used
+    #[error(transparent)]
+    Config(#[from] ConfigError),
+
+    /// socket or listener can't be bound, e.g. because the port is taken
+    #[error("failed to bind {what} on {addr}: {source}")]
+    Bind {
+        what: &'static str,
+        addr: SocketAddr,
+        source: io::Error,
+    },
+
+    /// socket can't be set up after binding
+    #[error("failed to set up {what}: {source}")]
+    Socket {
+        what: &'static str,
+        source: io::Error,
+    },
+}
@@ -0,0 +1,400 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, OpenOptions},
+    io::Write,
+};
+
+use jiff::{SignedDuration, Timestamp, ToSpan, civil::Date, tz::TimeZone};
+use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast;
+use tracing::{error, info, warn};
+
+use crate::{config::CompactionOptions, paths};
+
+pub(crate) const FILE_NAME: &str = "events.jsonl";
+
+/// Notable things happening inside the booper
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum BooperEvent {
+    /// A boop was counted
+    BoopCounted {
+        /// address the boop was received on
+        address: String,
+        today: u32,
+        total: u64,
+    },
+
+    /// One of the additional counters was incremented
+    Counted {
+        /// name of the counter
+        counter: String,
+        /// address the contact was received on
+        address: String,
+        today: u32,
+        total: u64,
+    },
+
+    /// A counted boop matched a text suffix
+    Milestone {
+        today: u32,
+        total: u64,
+        /// rendered chatbox message
+        message: String,
+    },
+
+    /// Today's boops were reset
+    Reset {
+        /// boops before the reset
+        previous: u32,
+        /// start date of the finished period
+        date: Date,
+        /// highest daily boops, including the finished period
+        record: u32,
+        total: u64,
+    },
+
+    /// Boops came in faster than the storm threshold
+    BoopStorm {
+        /// boops within the last minute
+        rate: u32,
+        today: u32,
+        total: u64,
+    },
+
+    /// Avatar was changed
+    AvatarChanged { avatar_id: String },
+
+    /// User entered another world
+    WorldChanged {
+        world_id: String,
+        world_name: String,
+    },
+
+    /// AFK parameter changed, pausing while AFK if enabled
+    AfkChanged { afk: bool },
+
+    /// Message was posted to the chatbox
+    ChatboxSent { message: String },
+
+    /// Counters were adjusted manually
+    Adjusted {
+        /// amount added (or subtracted, if negative)
+        by: i64,
+        /// where the adjustment came from, e.g. `osc` or `cli`
+        source: String,
+        today: u32,
+        total: u64,
+    },
+
+    /// Most recent boop was reverted
+    Undone { today: u32, total: u64 },
+
+    /// Boops counted between the entry's time and `until`, replacing
+    /// [`BooperEvent::BoopCounted`] entries during event log compaction
+    Aggregated { until: Timestamp, boops: u64 },
+}
+
+impl BooperEvent {
+    /// Today's and total boops after this event, if it changed them
+    pub(crate) fn counts(&self) -> Option<(u32, u64)> {
+        match self {
+            BooperEvent::BoopCounted { today, total, .. }
+            | BooperEvent::Adjusted { today, total, .. }
+            | BooperEvent::Undone { today, total } => Some((*today, *total)),
+            BooperEvent::Reset { total, .. } => Some((0, *total)),
+            _ => None,
+        }
+    }
+
+    /// Whether this event is kept in the event log
+    pub(crate) fn is_logged(&self) -> bool {
+        matches!(
+            self,
+            BooperEvent::BoopCounted { .. }
+                | BooperEvent::Adjusted { .. }
+                | BooperEvent::Undone { .. }
+        )
+    }
+}
+
+pub(crate) type EventSender = broadcast::Sender<BooperEvent>;
+
+/// Create event channel, lagging receivers lose the oldest events
+pub(crate) fn channel() -> EventSender {
+    broadcast::channel(256).0
+}
+
+/// Single line of the event log
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub(crate) struct EventLogEntry {
+    pub time: Timestamp,
+
+    #[serde(flatten)]
+    pub event: BooperEvent,
+}
+
+impl EventLogEntry {
+    pub(crate) fn now(event: BooperEvent) -> Self {
+        EventLogEntry {
+            time: Timestamp::now(),
+            event,
+        }
+    }
+}
+
+/// Print every event as JSON line to stdout
+pub(crate) fn spawn_stdout_sink(mut events: broadcast::Receiver<BooperEvent>) {
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(event) => match serde_json::to_string(&EventLogEntry::now(event)) {
+                    Ok(line) => println!("{line}"),
+                    Err(e) => error!(err=%e, "failed to serialize event"),
+                },
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "stdout sink lagging behind, events skipped");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Append counting events to the event log, compacting it after each reset
+///
+/// While the booper runs, this is the only writer of the log, so compaction
+/// never races with appends.
+pub(crate) fn spawn_log_sink(
+    compaction: Option<CompactionOptions>,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(BooperEvent::Reset { .. }) => {
+                    if let Some(compaction) = &compaction {
+                        compact(compaction);
+                    }
+                }
+                Ok(event) if event.is_logged() => append(event),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    error!(skipped, "event log lagging behind, events lost");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Append event to the JSON lines event log
+pub(crate) fn append(event: BooperEvent) {
+    let line = match serde_json::to_string(&EventLogEntry::now(event)) {
+        Ok(line) => line,
+        Err(e) => {
+            error!(err=%e, "failed to serialize event");
+            return;
+        }
+    };
+
+    let path = paths::data_file(FILE_NAME);
+    let result = OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&path)
+        .and_then(|mut file| writeln!(file, "{line}"));
+
+    if let Err(e) = result {
+        error!(err=%e, "failed to append event to {}", path.display());
+    }
+}
+
+/// Downsample old boops in the event log into hourly and daily aggregates
+///
+/// Other events are kept as-is, the log is left untouched if any line can't
+/// be parsed.
+pub(crate) fn compact(options: &CompactionOptions) {
+    let path = paths::data_file(FILE_NAME);
+    let file_name = path.display();
+    if !path.exists() {
+        return;
+    }
+
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) => {
+            error!(err=%e, "failed to read {file_name}");
+            return;
+        }
+    };
+
+    let entries = match contents
+        .lines()
+        .filter(|line| !line.trim().is_empty())
+        .map(serde_json::from_str::<EventLogEntry>)
+        .collect::<Result<Vec<_>, _>>()
+    {
+        Ok(entries) => entries,
+        Err(e) => {
+            warn!(err=%e, "failed to parse {file_name}, skipping compaction");
+            return;
+        }
+    };
+
+    let now = Timestamp::now();
+    let hourly_before = now - SignedDuration::from_hours(24 * options.hourly_after_days as i64);
+    let daily_before = now - SignedDuration::from_hours(24 * options.daily_after_days as i64);
+
+    let before = entries.len();
+    let compacted = compact_entries(entries, hourly_before, daily_before, &TimeZone::system());
+    if compacted.len() == before {
+        return;
+    }
+
+    let mut out = String::new();
+    for entry in &compacted {
+        match serde_json::to_string(entry) {
+            Ok(line) => {
+                out.push_str(&line);
+                out.push('\n');
+            }
+            Err(e) => {
+                error!(err=%e, "failed to serialize event, skipping compaction");
+                return;
+            }
+        }
+    }
+
+    // replace atomically, a crash must not lose the log
+    let tmp = format!("{file_name}.tmp");
+    if let Err(e) = fs::write(&tmp, out).and_then(|_| fs::rename(&tmp, &path)) {
+        error!(err=%e, "failed to write compacted {file_name}");
+        return;
+    }
+
+    info!(before, after = compacted.len(), "compacted {file_name}");
+}
+
+/// Replace boops before `hourly_before` with hourly aggregates and boops or
+/// aggregates before `daily_before` with daily aggregates in `tz`
+fn compact_entries(
+    entries: Vec<EventLogEntry>,
+    hourly_before: Timestamp,
+    daily_before: Timestamp,
+    tz: &TimeZone,
+) -> Vec<EventLogEntry> {
+    // bucket start -> (bucket end, boops)
+    let mut buckets: BTreeMap<Timestamp, (Timestamp, u64)> = BTreeMap::new();
+    let mut kept = vec![];
+
+    for entry in entries {
+        let boops = match &entry.event {
+            BooperEvent::BoopCounted { .. } if entry.time < hourly_before => 1,
+            // also merges buckets split by the cutoff of an earlier run
+            BooperEvent::Aggregated { boops, .. } if entry.time < hourly_before => *boops,
+            _ => {
+                kept.push(entry);
+                continue;
+            }
+        };
+
+        let Some((start, end)) = bucket(entry.time, entry.time < daily_before, tz) else {
+            kept.push(entry);
+            continue;
+        };
+        buckets.entry(start).or_insert((end, 0)).1 += boops;
+    }
+
+    kept.extend(
+        buckets
+            .into_iter()
+            .map(|(start, (until, boops))| EventLogEntry {
+                time: start,
+                event: BooperEvent::Aggregated { until, boops },
+            }),
+    );
+    // stable, keeps order of events sharing a timestamp
+    kept.sort_by_key(|entry| entry.time);
+
+    kept
+}
+
+/// Start and end of the hour or local day containing `time`
+fn bucket(time: Timestamp, daily: bool, tz: &TimeZone) -> Option<(Timestamp, Timestamp)> {
+    if daily {
+        let start = time.to_zoned(tz.clone()).start_of_day().ok()?;
+        let end = start.checked_add(1.day()).ok()?;
+        return Some((start.timestamp(), end.timestamp()));
+    }
+
+    let start = Timestamp::from_second(time.as_second().div_euclid(3600) * 3600).ok()?;
+    Some((start, start + SignedDuration::from_hours(1)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn boop(time: &str) -> EventLogEntry {
+        EventLogEntry {
+            time: time.parse().unwrap(),
+            event: BooperEvent::BoopCounted {
+                address: "/avatar/parameters/OSCBoop".into(),
+                today: 1,
+                total: 1,
+            },
+        }
+    }
+
+    #[test]
+    fn test_compact_entries() {
+        let entries = vec![
+            boop("2024-01-01T10:05:00Z"),
+            boop("2024-01-01T13:59:00Z"),
+            boop("2025-03-01T10:05:00Z"),
+            boop("2025-03-01T10:55:00Z"),
+            boop("2025-03-01T11:00:00Z"),
+            boop("2025-03-30T12:00:00Z"),
+        ];
+
+        let compacted = compact_entries(
+            entries,
+            "2025-03-15T00:00:00Z".parse().unwrap(),
+            "2025-01-01T00:00:00Z".parse().unwrap(),
+            &TimeZone::UTC,
+        );
+
+        let aggregates: Vec<_> = compacted
+            .iter()
+            .map(|entry| match &entry.event {
+                BooperEvent::Aggregated { until, boops } => {
+                    (entry.time.to_string(), until.to_string(), *boops)
+                }
+                _ => (entry.time.to_string(), "raw".into(), 1),
+            })
+            .collect();
+
+        assert_eq!(
+            aggregates,
+            vec![
+                (
+                    "2024-01-01T00:00:00Z".into(),
+                    "2024-01-02T00:00:00Z".into(),
+                    2
+                ),
+                (
+                    "2025-03-01T10:00:00Z".into(),
+                    "2025-03-01T11:00:00Z".into(),
+                    2
+                ),
+                (
+                    "2025-03-01T11:00:00Z".into(),
+                    "2025-03-01T12:00:00Z".into(),
+                    1
+                ),
+                ("2025-03-30T12:00:00Z".into(), "raw".into(), 1),
+            ]
+        );
+    }
+}
@@ -0,0 +1,238 @@
+use std::{fs, path::Path, time::Duration};
+
+use jiff::{Timestamp, civil::Date};
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::{ExportOptions, GoogleSheetOptions},
+    events::BooperEvent,
+};
+
+/// Credentials of the WebDAV server, optional
+const WEBDAV_USER_ENV: &str = "OSC_BOOPER_WEBDAV_USER";
+const WEBDAV_PASSWORD_ENV: &str = "OSC_BOOPER_WEBDAV_PASSWORD";
+
+/// Path to the Google service account key
+const GOOGLE_CREDENTIALS_ENV: &str = "GOOGLE_APPLICATION_CREDENTIALS";
+
+const SHEETS_SCOPE: &str = "https://www.googleapis.com/auth/spreadsheets";
+
+const CSV_HEADER: &str = "date,boops,record,total";
+
+/// Stats of a finished period, exported as one row
+#[derive(Debug, Clone)]
+struct Row {
+    date: Date,
+    boops: u32,
+    record: u32,
+    total: u64,
+}
+
+impl Row {
+    fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.date, self.boops, self.record, self.total
+        )
+    }
+}
+
+/// Append a row to the configured spreadsheets on every reset
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: ExportOptions,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    let client = match reqwest::Client::builder()
+        .user_agent(concat!("osc-booper/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(30))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(err=%e, "failed to create http client, exports disabled");
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            match event {
+                Ok(BooperEvent::Reset {
+                    previous,
+                    date,
+                    record,
+                    total,
+                }) => {
+                    let row = Row {
+                        date,
+                        boops: previous,
+                        record,
+                        total,
+                    };
+                    export(&client, &options, &row).await;
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "exporter lagging behind");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+async fn export(client: &reqwest::Client, options: &ExportOptions, row: &Row) {
+    if let Some(url) = &options.webdav_url {
+        match append_webdav(client, url, row).await {
+            Ok(()) => info!("appended stats to {url}"),
+            Err(e) => warn!(err=%e, "failed to append stats to {url}"),
+        }
+    }
+
+    if let Some(sheet) = &options.google_sheet {
+        match append_google_sheet(client, sheet, row).await {
+            Ok(()) => info!("appended stats to google sheet"),
+            Err(e) => warn!(err=%e, "failed to append stats to google sheet"),
+        }
+    }
+}
+
+/// Download remote CSV, append row and upload it again
+async fn append_webdav(client: &reqwest::Client, url: &str, row: &Row) -> Result<(), String> {
+    let auth = |request: reqwest::RequestBuilder| match std::env::var(WEBDAV_USER_ENV) {
+        Ok(user) => request.basic_auth(user, std::env::var(WEBDAV_PASSWORD_ENV).ok()),
+        Err(_) => request,
+    };
+
+    let response = auth(client.get(url))
+        .send()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut csv = if response.status() == reqwest::StatusCode::NOT_FOUND {
+        format!("{CSV_HEADER}\n")
+    } else {
+        response
+            .error_for_status()
+            .map_err(|e| e.to_string())?
+            .text()
+            .await
+            .map_err(|e| e.to_string())?
+    };
+
+    if !csv.is_empty() && !csv.ends_with('\n') {
+        csv.push('\n');
+    }
+    csv.push_str(&row.to_csv());
+    csv.push('\n');
+
+    auth(client.put(url))
+        .header(reqwest::header::CONTENT_TYPE, "text/csv")
+        .body(csv)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Service account key, as downloaded from the Google Cloud console
+#[derive(Deserialize)]
+struct ServiceAccountKey {
+    client_email: String,
+    private_key: String,
+    token_uri: String,
+}
+
+#[derive(Serialize)]
+struct Claims<'a> {
+    iss: &'a str,
+    scope: &'a str,
+    aud: &'a str,
+    iat: i64,
+    exp: i64,
+}
+
+async fn append_google_sheet(
+    client: &reqwest::Client,
+    sheet: &GoogleSheetOptions,
+    row: &Row,
+) -> Result<(), String> {
+    let access_token = google_access_token(client).await?;
+
+    let url = format!(
+        "https://sheets.googleapis.com/v4/spreadsheets/{}/values/{}:append",
+        sheet.id, sheet.range
+    );
+    client
+        .post(url)
+        .query(&[("valueInputOption", "USER_ENTERED")])
+        .bearer_auth(access_token)
+        .json(&json!({
+            "values": [[row.date.to_string(), row.boops, row.record, row.total]],
+        }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Exchange a self-signed JWT of the service account for an access token
+async fn google_access_token(client: &reqwest::Client) -> Result<String, String> {
+    #[derive(Deserialize)]
+    struct TokenResponse {
+        access_token: String,
+    }
+
+    let key_path = std::env::var(GOOGLE_CREDENTIALS_ENV)
+        .map_err(|_| format!("{GOOGLE_CREDENTIALS_ENV} is not set"))?;
+    let key: ServiceAccountKey = fs::read_to_string(Path::new(&key_path))
+        .map_err(|e| e.to_string())
+        .and_then(|contents| serde_json::from_str(&contents).map_err(|e| e.to_string()))?;
+
+    let now = Timestamp::now().as_second();
+    let claims = Claims {
+        iss: &key.client_email,
+        scope: SHEETS_SCOPE,
+        aud: &key.token_uri,
+        iat: now,
+        exp: now + 3600,
+    };
+    let signing_key = jsonwebtoken::EncodingKey::from_rsa_pem(key.private_key.as_bytes())
+        .map_err(|e| e.to_string())?;
+    let assertion = jsonwebtoken::encode(
+        &jsonwebtoken::Header::new(jsonwebtoken::Algorithm::RS256),
+        &claims,
+        &signing_key,
+    )
+    .map_err(|e| e.to_string())?;
+
+    let response: TokenResponse = client
+        .post(&key.token_uri)
+        .form(&[
+            ("grant_type", "urn:ietf:params:oauth:grant-type:jwt-bearer"),
+            ("assertion", assertion.as_str()),
+        ])
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    Ok(response.access_token)
+}
@@ -0,0 +1,61 @@
+use std::net::{Ipv4Addr, Ipv6Addr, SocketAddr};
+
+use rosc::{OscPacket, encoder};
+use tokio::net::UdpSocket;
+use tracing::{debug, error, info};
+
+use crate::error::BooperError;
+
+/// Relays received OSC packets to other applications, like face tracking
+/// tools, which would otherwise fight with us over VRChat's port
+pub(crate) struct Forwarder {
+    /// socket per target, so errors of targets that aren't running never
+    /// reach our listening socket
+    targets: Vec<(SocketAddr, UdpSocket)>,
+}
+
+impl Forwarder {
+    pub(crate) async fn new(targets: &[SocketAddr]) -> Result<Self, BooperError> {
+        let mut sockets = vec![];
+        for &target in targets {
+            let addr = match target {
+                SocketAddr::V4(_) => SocketAddr::from((Ipv4Addr::UNSPECIFIED, 0)),
+                SocketAddr::V6(_) => SocketAddr::from((Ipv6Addr::UNSPECIFIED, 0)),
+            };
+            let socket = UdpSocket::bind(addr)
+                .await
+                .map_err(|source| BooperError::Bind {
+                    what: "forwarding socket",
+                    addr,
+                    source,
+                })?;
+
+            info!("forwarding osc packets to {target}");
+            sockets.push((target, socket));
+        }
+
+        Ok(Forwarder { targets: sockets })
+    }
+
+    /// Relay `data` as received
+    pub(crate) async fn forward(&self, data: &[u8]) {
+        for (target, socket) in &self.targets {
+            // targets come and go, not worth more than a debug log
+            if let Err(e) = socket.send_to(data, target).await {
+                debug!(err=%e, %target, "failed to forward packet");
+            }
+        }
+    }
+
+    /// Relay packet received via another transport than UDP
+    pub(crate) async fn forward_packet(&self, packet: &OscPacket) {
+        if self.targets.is_empty() {
+            return;
+        }
+
+        match encoder::encode(packet) {
+            Ok(data) => self.forward(&data).await,
+            Err(e) => error!(err=%e, "failed to encode forwarded packet"),
+        }
+    }
+}
@@ -0,0 +1,62 @@
+use crate::config::GoalOptions;
+
+/// Highest of the `thresholds`, in percent of `target`, passed by counting
+/// from `previous` up to `current`
+///
+/// Counting starts over at zero after a reset, so daily goals are announced
+/// again every period.
+pub(crate) fn crossed(previous: u64, current: u64, target: u64, thresholds: &[u32]) -> Option<u32> {
+    if target == 0 {
+        return None;
+    }
+
+    // compare in percent of the target, without rounding
+    let reached =
+        |count: u64, threshold: u32| count as u128 * 100 >= threshold as u128 * target as u128;
+    thresholds
+        .iter()
+        .copied()
+        .filter(|&threshold| !reached(previous, threshold) && reached(current, threshold))
+        .max()
+}
+
+/// Template variables of goals
+pub(crate) const VARIABLES: &[&str] = &[
+    "remaining",
+    "remaining_today",
+    "remaining_total",
+    "goal_today",
+    "goal_total",
+];
+
+/// Value of a goal variable like `{remaining_today}` for `today`'s and
+/// `total` boops
+pub(crate) fn value(options: &GoalOptions, key: &str, today: u64, total: u64) -> Option<String> {
+    let remaining = |goal: Option<u64>, count: u64| goal.map(|goal| goal.saturating_sub(count));
+    let value = match key {
+        "remaining" => remaining(options.daily, today).or(remaining(options.lifetime, total)),
+        "remaining_today" => remaining(options.daily, today),
+        "remaining_total" => remaining(options.lifetime, total),
+        "goal_today" => options.daily,
+        "goal_total" => options.lifetime,
+        _ => None,
+    };
+    value.map(|value| value.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_crossed() {
+        let thresholds = [50, 90, 100];
+        assert_eq!(crossed(248, 249, 500, &thresholds), None);
+        assert_eq!(crossed(249, 250, 500, &thresholds), Some(50));
+        assert_eq!(crossed(250, 251, 500, &thresholds), None);
+        assert_eq!(crossed(499, 500, 500, &thresholds), Some(100));
+        // adjustments may skip thresholds, only the highest is announced
+        assert_eq!(crossed(0, 460, 500, &thresholds), Some(90));
+        assert_eq!(crossed(0, 1, 0, &thresholds), None);
+    }
+}
@@ -0,0 +1,336 @@
+use std::{net::SocketAddr, sync::Arc};
+
+use axum::{
+    Json, Router,
+    extract::{
+        ConnectInfo, Path, Query, State,
+        ws::{Message, WebSocket, WebSocketUpgrade},
+    },
+    http::StatusCode,
+    middleware,
+    response::{IntoResponse, Response},
+    routing::get,
+};
+use jiff::Timestamp;
+use rosc::OscPacket;
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use tokio::{
+    net::TcpListener,
+    sync::{broadcast::error::RecvError, mpsc, watch},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    api,
+    command::{self, CommandSender, ControlRequest, StatsSnapshot},
+    dashboard::{self, RecentEvents},
+    decode,
+    error::BooperError,
+    events::{EventLogEntry, EventSender},
+    metrics,
+    parameters::{self, ParameterValues},
+};
+
+/// Shared state of all HTTP handlers
+#[derive(Clone)]
+pub(crate) struct HttpState {
+    /// OSC packets received via WebSocket with their peer, handled by the
+    /// main loop
+    pub packets: mpsc::Sender<(SocketAddr, OscPacket)>,
+
+    /// Runtime commands, used to query stats
+    pub commands: CommandSender,
+
+    /// Events, streamed to dashboards
+    pub events: EventSender,
+
+    /// Recent boops shown on the dashboard
+    pub recent: RecentEvents,
+
+    /// Latest avatar parameter values
+    pub parameters: watch::Receiver<ParameterValues>,
+
+    /// When the booper was started
+    pub started: Timestamp,
+
+    /// Bearer token required by the JSON API
+    pub api_token: Option<Arc<str>>,
+}
+
+/// shields.io endpoint badge, https://shields.io/badges/endpoint-badge
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Badge {
+    schema_version: u8,
+    label: &'static str,
+    message: String,
+    color: &'static str,
+}
+
+/// Query of the `/events` WebSocket
+#[derive(Deserialize)]
+struct EventFilter {
+    /// comma separated event types to stream, e.g. `boop_counted,reset`,
+    /// all if unset
+    types: Option<String>,
+}
+
+impl EventFilter {
+    fn matches(&self, event: &Value) -> bool {
+        let Some(types) = &self.types else {
+            return true;
+        };
+        let Some(event_type) = event["type"].as_str() else {
+            return false;
+        };
+        types.split(',').any(|t| t.trim() == event_type)
+    }
+}
+
+/// Start HTTP server
+///
+/// Routes:
+/// - `/`: dashboard page, `/?overlay` shows only the counts
+/// - `/dashboard.json`: counters, recent boops, current avatar and uptime
+/// - `/live`: WebSocket streaming the dashboard data as JSON
+/// - `/events`: WebSocket streaming events as JSON, as they happen
+/// - `/osc`: WebSocket accepting OSC packets as binary frames
+/// - `/metrics`: counters in the Prometheus text format
+/// - `/badge/total.json`, `/badge/today.json`: shields.io endpoint badges
+/// - `/parameters.json`: latest value of every avatar parameter
+/// - `/parameters/{name}`: latest value of a single avatar parameter
+/// - `/stats`, `/reset/today`, `/announce`, `/adjust`, `/undo`, `/config`:
+///   JSON API, see [`api`]
+///
+/// All but the dashboard page, metrics and badges require the configured
+/// token.
+pub(crate) async fn serve(
+    token: CancellationToken,
+    addr: SocketAddr,
+    state: HttpState,
+) -> Result<(), BooperError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|source| BooperError::Bind {
+            what: "http listener",
+            addr,
+            source,
+        })?;
+
+    if !addr.ip().is_loopback() && state.api_token.is_none() {
+        warn!(%addr, "http server is reachable from other machines without http_token");
+    }
+
+    // anything revealing more than the counts, or changing state
+    let protected = Router::new()
+        .route("/dashboard.json", get(dashboard::snapshot_handler))
+        .route("/live", get(dashboard::live))
+        .route("/events", get(event_websocket))
+        .route("/osc", get(osc_websocket))
+        .route("/parameters.json", get(parameters_handler))
+        .route("/parameters/*name", get(parameter_handler))
+        .merge(api::router())
+        .route_layer(middleware::from_fn_with_state(
+            state.clone(),
+            api::require_token,
+        ));
+
+    let app = Router::new()
+        .route("/", get(dashboard::page))
+        .route("/metrics", get(metrics_handler))
+        .route("/badge/total.json", get(total_badge))
+        .route("/badge/today.json", get(today_badge))
+        .merge(protected)
+        .with_state(state);
+
+    info!("http server listening on {}", addr);
+
+    tokio::spawn(async move {
+        let shutdown = async move { token.cancelled().await };
+        // peers of the `/osc` WebSocket are checked like other OSC sources
+        let app = app.into_make_service_with_connect_info::<SocketAddr>();
+        if let Err(e) = axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown)
+            .await
+        {
+            warn!(err=%e, "http server stopped unexpectedly");
+        }
+        info!("stopping http server");
+    });
+    Ok(())
+}
+
+async fn metrics_handler() -> String {
+    metrics::render()
+}
+
+async fn parameters_handler(State(state): State<HttpState>) -> Json<Value> {
+    let values = state
+        .parameters
+        .borrow()
+        .iter()
+        .map(|(name, value)| (name.clone(), parameters::to_json(value)))
+        .collect();
+    Json(Value::Object(values))
+}
+
+async fn parameter_handler(Path(name): Path<String>, State(state): State<HttpState>) -> Response {
+    match state.parameters.borrow().get(&name) {
+        Some(value) => Json(parameters::to_json(value)).into_response(),
+        None => StatusCode::NOT_FOUND.into_response(),
+    }
+}
+
+async fn total_badge(State(state): State<HttpState>) -> Response {
+    badge(&state, "Total boops", |stats| stats.total).await
+}
+
+async fn today_badge(State(state): State<HttpState>) -> Response {
+    badge(&state, "Boops today", |stats| stats.today as u64).await
+}
+
+async fn badge<F>(state: &HttpState, label: &'static str, value: F) -> Response
+where
+    F: Fn(&StatsSnapshot) -> u64,
+{
+    let response = command::execute(ControlRequest::Stats, &state.commands, "http").await;
+    let Some(stats) = response.stats else {
+        return StatusCode::SERVICE_UNAVAILABLE.into_response();
+    };
+
+    Json(Badge {
+        schema_version: 1,
+        label,
+        message: group_thousands(value(&stats)),
+        color: "ff69b4",
+    })
+    .into_response()
+}
+
+/// Format number with `,` as thousands separator, e.g. `12,345`
+fn group_thousands(number: u64) -> String {
+    let digits = number.to_string();
+    let mut out = String::with_capacity(digits.len() + digits.len() / 3);
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            out.push(',');
+        }
+        out.push(digit);
+    }
+
+    out
+}
+
+async fn event_websocket(
+    ws: WebSocketUpgrade,
+    Query(filter): Query<EventFilter>,
+    State(state): State<HttpState>,
+) -> Response {
+    ws.on_upgrade(move |socket| stream_events(socket, state, filter))
+}
+
+/// Send every matching event as JSON text frame, with the time it happened
+async fn stream_events(mut socket: WebSocket, state: HttpState, filter: EventFilter) {
+    debug!("event websocket connected");
+    let mut events = state.events.subscribe();
+
+    loop {
+        let event = tokio::select! {
+            event = events.recv() => match event {
+                Ok(event) => event,
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "event websocket lagging behind");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            },
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_)) | Err(_)) | None => break,
+                Some(Ok(_)) => continue,
+            },
+        };
+
+        let entry = match serde_json::to_value(EventLogEntry::now(event)) {
+            Ok(entry) => entry,
+            Err(e) => {
+                error!(err=%e, "failed to serialize event");
+                continue;
+            }
+        };
+        if !filter.matches(&entry) {
+            continue;
+        }
+        if socket.send(Message::Text(entry.to_string())).await.is_err() {
+            break;
+        }
+    }
+
+    debug!("event websocket disconnected");
+}
+
+async fn osc_websocket(
+    ws: WebSocketUpgrade,
+    ConnectInfo(peer): ConnectInfo<SocketAddr>,
+    State(state): State<HttpState>,
+) -> Response {
+    ws.on_upgrade(move |socket| handle_osc_websocket(socket, peer, state))
+}
+
+/// Decode binary frames as OSC packets and forward them to the main loop
+async fn handle_osc_websocket(mut socket: WebSocket, peer: SocketAddr, state: HttpState) {
+    debug!(peer=%peer, "osc websocket connected");
+
+    while let Some(message) = socket.recv().await {
+        match message {
+            Ok(Message::Binary(data)) => match decode::decode_packet(&data) {
+                Ok(packet) => {
+                    if state.packets.send((peer, packet)).await.is_err() {
+                        break;
+                    }
+                }
+                Err(e) => {
+                    error!(err=%e, "failed to parse websocket packet");
+                }
+            },
+            Ok(Message::Close(_)) => break,
+            Ok(_) => {}
+            Err(e) => {
+                warn!(err=%e, "osc websocket error");
+                break;
+            }
+        }
+    }
+
+    debug!("osc websocket disconnected");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_event_filter() {
+        let event = serde_json::json!({ "type": "reset", "previous": 12 });
+
+        let filter = EventFilter { types: None };
+        assert!(filter.matches(&event));
+        let filter = EventFilter {
+            types: Some("boop_counted, reset".into()),
+        };
+        assert!(filter.matches(&event));
+        let filter = EventFilter {
+            types: Some("boop_counted".into()),
+        };
+        assert!(!filter.matches(&event));
+    }
+
+    #[test]
+    fn test_group_thousands() {
+        assert_eq!(group_thousands(0), "0");
+        assert_eq!(group_thousands(999), "999");
+        assert_eq!(group_thousands(12345), "12,345");
+        assert_eq!(group_thousands(1234567), "1,234,567");
+    }
+}
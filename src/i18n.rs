@@ -0,0 +1,242 @@
+use std::collections::BTreeMap;
+
+use tracing::warn;
+
+use crate::config::{self, Options, PluralForms, Translation};
+
+/// Language of the built-in templates
+const DEFAULT_LANGUAGE: &str = "en";
+
+/// Plural category of a number, following the CLDR rules
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Plural {
+    One,
+    Few,
+    Many,
+    Other,
+}
+
+impl PluralForms {
+    fn select(&self, plural: Plural) -> &str {
+        let form = match plural {
+            Plural::One => self.one.as_deref(),
+            Plural::Few => self.few.as_deref(),
+            Plural::Many => self.many.as_deref(),
+            Plural::Other => None,
+        };
+        form.unwrap_or(&self.other)
+    }
+}
+
+/// Replace default templates with their translation to the configured
+/// language, templates changed by the user are kept
+pub(crate) fn apply_defaults(options: &mut Options) {
+    let Some(language) = options.language.clone() else {
+        return;
+    };
+    let translation = translation(options, &language);
+    if translation == Translation::default() {
+        warn!("no translation for language {language:?}, keeping the default templates");
+        return;
+    }
+
+    let is_default = options.message_template == config::default_message_template();
+    if let (true, Some(template)) = (is_default, translation.message_template) {
+        options.message_template = template;
+    }
+    if let Some(template) = translation.counter_message_template {
+        for counter in &mut options.counters {
+            if counter.message_template == config::default_counter_message_template() {
+                counter.message_template = template.clone();
+            }
+        }
+    }
+    let is_default = options.leaderboard.message_template == config::default_leaderboard_template();
+    if let (true, Some(template)) = (is_default, translation.leaderboard_template) {
+        options.leaderboard.message_template = template;
+    }
+}
+
+/// `count` followed by `word` in the matching plural form, e.g. `2 boops`
+///
+/// Words missing in the configured language are looked up in English.
+pub(crate) fn count_word(options: &Options, word: &str, count: u64) -> Option<String> {
+    let language = options.language.as_deref().unwrap_or(DEFAULT_LANGUAGE);
+    let (language, forms) = [language, DEFAULT_LANGUAGE]
+        .into_iter()
+        .find_map(|language| {
+            let forms = translation(options, language).words.remove(word)?;
+            Some((language, forms))
+        })?;
+
+    let word = forms.select(plural(base_language(language), count));
+    Some(format!("{count} {word}"))
+}
+
+/// Built-in translation of `language`, overridden by configured ones, which
+/// may be specific to a region like `pt-BR`
+fn translation(options: &Options, language: &str) -> Translation {
+    let base = base_language(language);
+    let mut translation = builtin(base).unwrap_or_default();
+    for key in [base, language] {
+        if let Some(configured) = options.translations.get(key) {
+            merge(&mut translation, configured.clone());
+        }
+    }
+    translation
+}
+
+fn merge(translation: &mut Translation, other: Translation) {
+    if other.message_template.is_some() {
+        translation.message_template = other.message_template;
+    }
+    if other.counter_message_template.is_some() {
+        translation.counter_message_template = other.counter_message_template;
+    }
+    if other.leaderboard_template.is_some() {
+        translation.leaderboard_template = other.leaderboard_template;
+    }
+    translation.words.extend(other.words);
+}
+
+/// `de` of `de-AT`
+fn base_language(language: &str) -> &str {
+    language.split(['-', '_']).next().unwrap_or(language)
+}
+
+/// Plural category of `n` in `language`
+fn plural(language: &str, n: u64) -> Plural {
+    let (n10, n100) = (n % 10, n % 100);
+    let few = (2..=4).contains(&n10) && !(12..=14).contains(&n100);
+
+    match language {
+        "ja" | "ko" | "zh" | "th" | "vi" | "id" => Plural::Other,
+        "fr" | "pt" if n <= 1 => Plural::One,
+        "ru" | "uk" if n10 == 1 && n100 != 11 => Plural::One,
+        "ru" | "uk" if few => Plural::Few,
+        "ru" | "uk" => Plural::Many,
+        "pl" if n == 1 => Plural::One,
+        "pl" if few => Plural::Few,
+        "pl" => Plural::Many,
+        "cs" | "sk" if n == 1 => Plural::One,
+        "cs" | "sk" if (2..=4).contains(&n) => Plural::Few,
+        "fr" | "pt" | "cs" | "sk" => Plural::Other,
+        _ if n == 1 => Plural::One,
+        _ => Plural::Other,
+    }
+}
+
+fn builtin(language: &str) -> Option<Translation> {
+    let (message, counter, leaderboard, boop) = match language {
+        DEFAULT_LANGUAGE => return Some(english()),
+        "de" => (
+            "Heute: {today}{today_suffix}\nInsgesamt: {total}{total_suffix}",
+            "{name} heute: {today}\nInsgesamt: {total}",
+            "Fleißigste Stupser heute:\n{leaderboard}",
+            forms("Stupser", "Stupser"),
+        ),
+        "es" => (
+            "Hoy: {today}{today_suffix}\nTotal: {total}{total_suffix}",
+            "{name} hoy: {today}\nTotal: {total}",
+            "Top boopers de hoy:\n{leaderboard}",
+            forms("boop", "boops"),
+        ),
+        "fr" => (
+            "Aujourd'hui : {today}{today_suffix}\nTotal : {total}{total_suffix}",
+            "{name} aujourd'hui : {today}\nTotal : {total}",
+            "Meilleurs boopeurs du jour :\n{leaderboard}",
+            forms("boop", "boops"),
+        ),
+        "ja" => (
+            "今日: {today}{today_suffix}\n合計: {total}{total_suffix}",
+            "{name} 今日: {today}\n合計: {total}",
+            "今日のトップ:\n{leaderboard}",
+            forms("ブープ", "ブープ"),
+        ),
+        _ => return None,
+    };
+
+    Some(Translation {
+        message_template: Some(message.into()),
+        counter_message_template: Some(counter.into()),
+        leaderboard_template: Some(leaderboard.into()),
+        words: BTreeMap::from([("boop".into(), boop)]),
+    })
+}
+
+/// Only words, the English templates are the defaults
+fn english() -> Translation {
+    Translation {
+        words: BTreeMap::from([
+            ("boop".into(), forms("boop", "boops")),
+            ("day".into(), forms("day", "days")),
+            ("headpat".into(), forms("headpat", "headpats")),
+        ]),
+        ..Default::default()
+    }
+}
+
+fn forms(one: &str, other: &str) -> PluralForms {
+    PluralForms {
+        one: Some(one.into()),
+        few: None,
+        many: None,
+        other: other.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_plural() {
+        assert_eq!(plural("en", 1), Plural::One);
+        assert_eq!(plural("en", 0), Plural::Other);
+        assert_eq!(plural("fr", 0), Plural::One);
+        assert_eq!(plural("ja", 1), Plural::Other);
+        assert_eq!(plural("pl", 22), Plural::Few);
+        assert_eq!(plural("pl", 12), Plural::Many);
+        assert_eq!(plural("ru", 21), Plural::One);
+        assert_eq!(plural("ru", 11), Plural::Many);
+    }
+
+    #[test]
+    fn test_apply_defaults() {
+        let mut options = Options {
+            language: Some("de-AT".into()),
+            ..Default::default()
+        };
+        apply_defaults(&mut options);
+        assert!(options.message_template.starts_with("Heute: "));
+
+        let mut options = Options {
+            language: Some("de".into()),
+            message_template: "Boops: {today}".into(),
+            ..Default::default()
+        };
+        apply_defaults(&mut options);
+        assert_eq!(options.message_template, "Boops: {today}");
+    }
+
+    #[test]
+    fn test_count_word() {
+        let mut options = Options::default();
+        assert_eq!(count_word(&options, "boop", 1).unwrap(), "1 boop");
+        assert_eq!(count_word(&options, "boop", 2).unwrap(), "2 boops");
+        assert_eq!(count_word(&options, "hug", 2), None);
+
+        options.language = Some("pl".into());
+        let words = r#"
+            [pl.words.boop]
+            one = "pstryczek"
+            few = "pstryczki"
+            other = "pstryczków"
+        "#;
+        options.translations = toml::from_str(words).unwrap();
+        assert_eq!(count_word(&options, "boop", 3).unwrap(), "3 pstryczki");
+        assert_eq!(count_word(&options, "boop", 5).unwrap(), "5 pstryczków");
+        // English fallback
+        assert_eq!(count_word(&options, "headpat", 5).unwrap(), "5 headpats");
+    }
+}
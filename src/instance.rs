@@ -0,0 +1,65 @@
+use std::sync::OnceLock;
+
+/// Name of this instance, to run multiple boopers side by side
+static INSTANCE: OnceLock<String> = OnceLock::new();
+
+/// Set instance name, must happen before any files are accessed
+pub(crate) fn set(name: String) {
+    INSTANCE.set(name).ok();
+}
+
+pub(crate) fn name() -> Option<&'static str> {
+    INSTANCE.get().map(String::as_str)
+}
+
+/// Validate instance name for use in file, socket and D-Bus names
+pub(crate) fn parse_name(name: &str) -> Result<String, String> {
+    let valid = name.starts_with(|c: char| c.is_ascii_alphabetic())
+        && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_');
+
+    if !valid {
+        return Err("must start with a letter and contain only letters, digits and _".into());
+    }
+
+    Ok(name.into())
+}
+
+/// File name scoped to this instance, `boops.toml` turns into
+/// `boops.<instance>.toml`
+pub(crate) fn file_name(base: &str) -> String {
+    scoped_file_name(base, name())
+}
+
+/// Name scoped to this instance, `osc-booper` turns into
+/// `osc-booper<separator><instance>`
+pub(crate) fn scoped(base: &str, separator: &str) -> String {
+    match name() {
+        Some(instance) => format!("{base}{separator}{instance}"),
+        None => base.into(),
+    }
+}
+
+fn scoped_file_name(base: &str, instance: Option<&str>) -> String {
+    match (instance, base.rsplit_once('.')) {
+        (Some(instance), Some((stem, extension))) => format!("{stem}.{instance}.{extension}"),
+        (Some(instance), None) => format!("{base}.{instance}"),
+        (None, _) => base.into(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scoped_file_name() {
+        assert_eq!(scoped_file_name("boops.toml", None), "boops.toml");
+        assert_eq!(
+            scoped_file_name("boops.toml", Some("alt")),
+            "boops.alt.toml"
+        );
+        assert!(parse_name("alt_2").is_ok());
+        assert!(parse_name("2nd").is_err());
+        assert!(parse_name("a.b").is_err());
+    }
+}
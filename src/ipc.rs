@@ -0,0 +1,189 @@
+use std::io;
+
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufReader};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    command::{self, CommandSender, ControlRequest, ControlResponse},
+    instance,
+};
+
+/// Serve line-based JSON control interface on a local socket
+///
+/// Unix domain socket on Linux/macOS, named pipe on Windows. Each line is a
+/// [`ControlRequest`] like `{"command": "adjust", "by": -1}`, answered with a
+/// single [`ControlResponse`] line.
+pub(crate) fn spawn(token: CancellationToken, commands: CommandSender) {
+    tokio::spawn(async move {
+        tokio::select! {
+            _ = token.cancelled() => {}
+            _ = listen(commands) => {
+                warn!("control socket stopped unexpectedly");
+            }
+        }
+        cleanup();
+        info!("stopping control socket");
+    });
+}
+
+/// Send `request` to the running booper, `None` if none is listening
+pub(crate) async fn request(request: &ControlRequest) -> Option<ControlResponse> {
+    let stream = match connect().await {
+        Ok(stream) => stream,
+        Err(e) => {
+            debug!(err=%e, "no booper listening on the control socket");
+            return None;
+        }
+    };
+
+    let response = exchange(stream, request)
+        .await
+        .unwrap_or_else(|e| ControlResponse::error(format!("control socket failed: {e}")));
+    Some(response)
+}
+
+/// Write `request` as a line and read the response line
+async fn exchange<S>(stream: S, request: &ControlRequest) -> io::Result<ControlResponse>
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    let (reader, mut writer) = tokio::io::split(stream);
+
+    let mut line = serde_json::to_string(request)?;
+    line.push('\n');
+    writer.write_all(line.as_bytes()).await?;
+
+    let Some(response) = BufReader::new(reader).lines().next_line().await? else {
+        return Err(io::ErrorKind::UnexpectedEof.into());
+    };
+    Ok(serde_json::from_str(&response)?)
+}
+
+#[cfg(unix)]
+fn socket_path() -> std::path::PathBuf {
+    std::env::var_os("XDG_RUNTIME_DIR")
+        .map(std::path::PathBuf::from)
+        .unwrap_or_else(std::env::temp_dir)
+        .join(format!("{}.sock", instance::scoped("osc-booper", "-")))
+}
+
+#[cfg(unix)]
+async fn listen(commands: CommandSender) {
+    let path = socket_path();
+
+    // remove stale socket of a previous run
+    if path.exists() {
+        std::fs::remove_file(&path).ok();
+    }
+
+    let listener = match tokio::net::UnixListener::bind(&path) {
+        Ok(listener) => listener,
+        Err(e) => {
+            error!(err=%e, path=%path.display(), "failed to bind control socket");
+            return;
+        }
+    };
+    info!("control socket listening on {}", path.display());
+
+    loop {
+        match listener.accept().await {
+            Ok((stream, _)) => {
+                tokio::spawn(handle_client(stream, commands.clone()));
+            }
+            Err(e) => {
+                error!(err=%e, "failed to accept control connection");
+            }
+        }
+    }
+}
+
+#[cfg(unix)]
+async fn connect() -> io::Result<tokio::net::UnixStream> {
+    tokio::net::UnixStream::connect(socket_path()).await
+}
+
+#[cfg(unix)]
+fn cleanup() {
+    std::fs::remove_file(socket_path()).ok();
+}
+
+#[cfg(windows)]
+const PIPE_NAME: &str = r"\\.\pipe\osc-booper";
+
+#[cfg(windows)]
+async fn listen(commands: CommandSender) {
+    use tokio::net::windows::named_pipe::ServerOptions;
+
+    let pipe_name = instance::scoped(PIPE_NAME, "-");
+
+    let mut server = match ServerOptions::new()
+        .first_pipe_instance(true)
+        .create(&pipe_name)
+    {
+        Ok(server) => server,
+        Err(e) => {
+            error!(err=%e, "failed to create control pipe {pipe_name}");
+            return;
+        }
+    };
+    info!("control pipe listening on {pipe_name}");
+
+    loop {
+        if let Err(e) = server.connect().await {
+            error!(err=%e, "failed to accept control connection");
+            continue;
+        }
+
+        // hand connected instance off, create a new one for the next client
+        let connected = server;
+        server = match ServerOptions::new().create(&pipe_name) {
+            Ok(server) => server,
+            Err(e) => {
+                error!(err=%e, "failed to create control pipe {pipe_name}");
+                return;
+            }
+        };
+
+        tokio::spawn(handle_client(connected, commands.clone()));
+    }
+}
+
+#[cfg(windows)]
+async fn connect() -> io::Result<tokio::net::windows::named_pipe::NamedPipeClient> {
+    use tokio::net::windows::named_pipe::ClientOptions;
+
+    ClientOptions::new().open(instance::scoped(PIPE_NAME, "-"))
+}
+
+#[cfg(windows)]
+fn cleanup() {}
+
+async fn handle_client<S>(stream: S, commands: CommandSender)
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    debug!("control client connected");
+
+    let (reader, mut writer) = tokio::io::split(stream);
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Ok(Some(line)) = lines.next_line().await {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match serde_json::from_str::<ControlRequest>(&line) {
+            Ok(request) => command::execute(request, &commands, "ipc").await,
+            Err(e) => ControlResponse::error(format!("invalid request: {e}")),
+        };
+
+        let mut out = serde_json::to_string(&response).unwrap_or_default();
+        out.push('\n');
+        if writer.write_all(out.as_bytes()).await.is_err() {
+            break;
+        }
+    }
+
+    debug!("control client disconnected");
+}
@@ -0,0 +1,69 @@
+//! Count boops and other avatar contacts received via OSC, and post them to
+//! the VRChat chatbox
+//!
+//! [`osc::OscBooper`] runs the whole thing, configured via
+//! [`config::Options`]. The `osc-booper` binary is a thin wrapper around
+//! [`cli::run`].
+
+mod api;
+#[cfg(feature = "audio")]
+mod audio;
+mod backup;
+mod capture;
+mod chatbox;
+pub mod cli;
+mod command;
+pub mod config;
+mod console;
+mod coordination;
+mod dashboard;
+mod datasource;
+#[cfg(all(target_os = "linux", feature = "dbus"))]
+mod dbus;
+// public for the benchmarks only
+#[doc(hidden)]
+pub mod decode;
+mod discord;
+mod error;
+mod events;
+mod export;
+mod forward;
+mod goals;
+mod http;
+mod i18n;
+mod instance;
+mod ipc;
+mod lights;
+mod logging;
+mod metrics;
+#[cfg(feature = "mqtt")]
+mod mqtt;
+pub mod osc;
+pub mod oscquery;
+mod output;
+#[cfg(feature = "overlay")]
+mod overlay;
+mod parameters;
+mod paths;
+mod presets;
+mod rate;
+mod report;
+mod scheduler;
+#[cfg(feature = "serial")]
+mod serial;
+mod service;
+mod simulate;
+mod social;
+mod sources;
+pub mod storage;
+mod tcp;
+mod template;
+mod text_file;
+#[cfg(all(windows, feature = "toast"))]
+mod toast;
+#[cfg(all(feature = "tray", any(windows, target_os = "linux")))]
+mod tray;
+mod watch;
+mod world;
+
+pub use crate::{error::BooperError, events::BooperEvent};
@@ -0,0 +1,234 @@
+use std::time::{Duration, Instant};
+
+use serde_json::{Value, json};
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    config::{HueOptions, LightEffect, LightsOptions, Rgb},
+    events::BooperEvent,
+};
+
+/// Username registered on the Hue bridge
+const HUE_USERNAME_ENV: &str = "OSC_BOOPER_HUE_USERNAME";
+
+/// Flash lights on boops and milestones
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: LightsOptions,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    let client = match reqwest::Client::builder()
+        .timeout(Duration::from_secs(5))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(err=%e, "failed to create http client, lights disabled");
+            return;
+        }
+    };
+
+    info!("light triggers enabled");
+
+    tokio::spawn(async move {
+        let mut last_flash: Option<Instant> = None;
+
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            let effect = match event {
+                Ok(BooperEvent::BoopCounted { .. }) => options.on_boop.as_ref(),
+                Ok(BooperEvent::Milestone { .. }) => options.on_milestone.as_ref(),
+                Ok(_) => None,
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "lights lagging behind");
+                    None
+                }
+                Err(RecvError::Closed) => break,
+            };
+            let Some(effect) = effect else {
+                continue;
+            };
+
+            // don't strobe the room during boop storms
+            let min_interval = options.min_interval.unsigned_abs();
+            if last_flash.is_some_and(|last| last.elapsed() < min_interval) {
+                debug!("light flash rate limited");
+                continue;
+            }
+            last_flash = Some(Instant::now());
+
+            flash(&client, &options, effect).await;
+        }
+    });
+}
+
+/// Show effect on all configured lights, restoring their state afterwards
+async fn flash(client: &reqwest::Client, options: &LightsOptions, effect: &LightEffect) {
+    let wled_state = match &options.wled {
+        Some(host) => wled_flash(client, host, effect)
+            .await
+            .map_err(|e| warn!(err=%e, "failed to flash wled"))
+            .ok(),
+        None => None,
+    };
+
+    let hue_states = match &options.hue {
+        Some(hue) => hue_flash(client, hue, effect)
+            .await
+            .map_err(|e| warn!(err=%e, "failed to flash hue lights"))
+            .unwrap_or_default(),
+        None => vec![],
+    };
+
+    tokio::time::sleep(effect.duration.unsigned_abs()).await;
+
+    let restored = match (&options.wled, wled_state) {
+        (Some(host), Some(state)) => wled_set(client, host, &state).await,
+        _ => Ok(()),
+    };
+    if let Err(e) = restored {
+        warn!(err=%e, "failed to restore wled state");
+    }
+
+    if let Some(hue) = &options.hue {
+        for (id, state) in hue_states {
+            if let Err(e) = hue_set(client, hue, id, &state).await {
+                warn!(err=%e, light = id, "failed to restore hue light state");
+            }
+        }
+    }
+}
+
+/// Set WLED color/effect, returns previous state
+async fn wled_flash(
+    client: &reqwest::Client,
+    host: &str,
+    effect: &LightEffect,
+) -> Result<Value, String> {
+    let previous: Value = client
+        .get(format!("http://{host}/json/state"))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let Rgb([r, g, b]) = effect.color;
+    let state = json!({
+        "on": true,
+        "bri": 255,
+        "seg": [{ "col": [[r, g, b]], "fx": effect.wled_effect }],
+    });
+    wled_set(client, host, &state).await?;
+
+    // only restore what we changed
+    Ok(json!({
+        "on": previous["on"],
+        "bri": previous["bri"],
+        "seg": previous["seg"],
+    }))
+}
+
+async fn wled_set(client: &reqwest::Client, host: &str, state: &Value) -> Result<(), String> {
+    client
+        .post(format!("http://{host}/json/state"))
+        .json(state)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+/// Set Hue lights to the effect's color, returns previous states
+async fn hue_flash(
+    client: &reqwest::Client,
+    hue: &HueOptions,
+    effect: &LightEffect,
+) -> Result<Vec<(u32, Value)>, String> {
+    let mut previous = vec![];
+
+    for id in &hue.lights {
+        let light: Value = client
+            .get(hue_url(hue, &format!("lights/{id}"))?)
+            .send()
+            .await
+            .and_then(|r| r.error_for_status())
+            .map_err(|e| e.to_string())?
+            .json()
+            .await
+            .map_err(|e| e.to_string())?;
+
+        let state = &light["state"];
+        previous.push((
+            *id,
+            json!({ "on": state["on"], "bri": state["bri"], "xy": state["xy"] }),
+        ));
+
+        let new_state = json!({
+            "on": true,
+            "bri": 254,
+            "xy": rgb_to_xy(effect.color),
+            "transitiontime": 0,
+        });
+        hue_set(client, hue, *id, &new_state).await?;
+    }
+
+    Ok(previous)
+}
+
+async fn hue_set(
+    client: &reqwest::Client,
+    hue: &HueOptions,
+    id: u32,
+    state: &Value,
+) -> Result<(), String> {
+    client
+        .put(hue_url(hue, &format!("lights/{id}/state"))?)
+        .json(state)
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn hue_url(hue: &HueOptions, path: &str) -> Result<String, String> {
+    let username =
+        std::env::var(HUE_USERNAME_ENV).map_err(|_| format!("{HUE_USERNAME_ENV} is not set"))?;
+    Ok(format!("http://{}/api/{username}/{path}", hue.bridge))
+}
+
+/// Convert sRGB color to CIE xy, as expected by Hue
+fn rgb_to_xy(Rgb(rgb): Rgb) -> [f64; 2] {
+    // undo sRGB gamma
+    let [r, g, b] = rgb.map(|c| {
+        let c = c as f64 / 255.0;
+        if c > 0.04045 {
+            ((c + 0.055) / 1.055).powf(2.4)
+        } else {
+            c / 12.92
+        }
+    });
+
+    let x = r * 0.4124 + g * 0.3576 + b * 0.1805;
+    let y = r * 0.2126 + g * 0.7152 + b * 0.0722;
+    let z = r * 0.0193 + g * 0.1192 + b * 0.9505;
+
+    let sum = x + y + z;
+    if sum == 0.0 {
+        return [0.0, 0.0];
+    }
+
+    [x / sum, y / sum]
+}
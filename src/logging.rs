@@ -0,0 +1,79 @@
+use tracing_appender::{
+    non_blocking::WorkerGuard,
+    rolling::{RollingFileAppender, Rotation},
+};
+use tracing_subscriber::{
+    EnvFilter, Layer, Registry, fmt::MakeWriter, layer::SubscriberExt, util::SubscriberInitExt,
+};
+
+use crate::{
+    config::{LogFormat, LogRotation, LoggingOptions},
+    instance,
+};
+
+type BoxedLayer = Box<dyn Layer<Registry> + Send + Sync>;
+
+/// Set up logging to the terminal and optionally to files
+///
+/// Logs go to stderr with `stderr`, keeping stdout clean. The returned guard
+/// flushes the log file when dropped, so it has to live until the end of
+/// `main`.
+pub(crate) fn init(options: &LoggingOptions, stderr: bool) -> Option<WorkerGuard> {
+    let mut layers = vec![if stderr {
+        layer(options.format, std::io::stderr, true)
+    } else {
+        layer(options.format, std::io::stdout, true)
+    }];
+
+    // the subscriber isn't set up yet, so errors are printed directly
+    let mut guard = None;
+    if let Some(dir) = &options.dir {
+        let appender = RollingFileAppender::builder()
+            .rotation(match options.rotation {
+                LogRotation::Hourly => Rotation::HOURLY,
+                LogRotation::Daily => Rotation::DAILY,
+                LogRotation::Never => Rotation::NEVER,
+            })
+            .filename_prefix(instance::scoped("osc-booper", "."))
+            .filename_suffix("log")
+            .build(dir);
+
+        match appender {
+            Ok(appender) => {
+                let (writer, file_guard) = tracing_appender::non_blocking(appender);
+                layers.push(layer(options.format, writer, false));
+                guard = Some(file_guard);
+            }
+            Err(e) => eprintln!("failed to open log file in {}: {e}", dir.display()),
+        }
+    }
+
+    tracing_subscriber::registry()
+        .with(layers)
+        .with(filter(options))
+        .init();
+
+    guard
+}
+
+fn layer<W>(format: LogFormat, writer: W, ansi: bool) -> BoxedLayer
+where
+    W: for<'w> MakeWriter<'w> + Send + Sync + 'static,
+{
+    let layer = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi);
+
+    match format {
+        LogFormat::Text => layer.boxed(),
+        LogFormat::Json => layer.json().boxed(),
+    }
+}
+
+/// `RUST_LOG` if set, the configured filter otherwise
+fn filter(options: &LoggingOptions) -> EnvFilter {
+    match (&options.filter, std::env::var_os(EnvFilter::DEFAULT_ENV)) {
+        (Some(filter), None) => EnvFilter::new(filter),
+        _ => EnvFilter::from_default_env(),
+    }
+}
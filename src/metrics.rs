@@ -0,0 +1,340 @@
+use std::{
+    collections::BTreeMap,
+    fmt::Write,
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+
+use serde_json::{Value, json};
+use tokio::sync::broadcast;
+use tracing::warn;
+
+use crate::{events::BooperEvent, world::World};
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(crate) enum MetricKind {
+    /// monotonically increasing
+    Counter,
+    /// current value, may go up and down
+    Gauge,
+}
+
+/// Metric exported in the Prometheus text format
+pub(crate) struct Metric {
+    name: &'static str,
+    help: &'static str,
+    kind: MetricKind,
+    value: AtomicU64,
+}
+
+impl Metric {
+    const fn counter(name: &'static str, help: &'static str) -> Self {
+        Metric {
+            name,
+            help,
+            kind: MetricKind::Counter,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    const fn gauge(name: &'static str, help: &'static str) -> Self {
+        Metric {
+            name,
+            help,
+            kind: MetricKind::Gauge,
+            value: AtomicU64::new(0),
+        }
+    }
+
+    pub(crate) fn inc(&self) {
+        self.value.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub(crate) fn set(&self, value: u64) {
+        debug_assert_eq!(self.kind, MetricKind::Gauge, "only gauges can be set");
+        self.value.store(value, Ordering::Relaxed);
+    }
+
+    pub(crate) fn get(&self) -> u64 {
+        self.value.load(Ordering::Relaxed)
+    }
+}
+
+pub(crate) static BOOPS_COUNTED: Metric = Metric::counter(
+    "osc_booper_boops_counted_total",
+    "Boops counted since start",
+);
+
+pub(crate) static BOOPS_TODAY: Metric =
+    Metric::gauge("osc_booper_boops_today", "Boops of the current period");
+
+pub(crate) static BOOPS_LIFETIME: Metric =
+    Metric::gauge("osc_booper_boops_lifetime", "Lifetime boops");
+
+pub(crate) static BOOPS_SESSION: Metric = Metric::gauge(
+    "osc_booper_boops_session",
+    "Boops since the start or VRChat reconnecting",
+);
+
+pub(crate) static BOOPS_WEEK: Metric =
+    Metric::gauge("osc_booper_boops_week", "Boops of the current week");
+
+pub(crate) static BOOPS_MONTH: Metric =
+    Metric::gauge("osc_booper_boops_month", "Boops of the current month");
+
+pub(crate) static STREAK_DAYS: Metric = Metric::gauge(
+    "osc_booper_streak_days",
+    "Consecutive days with at least one boop",
+);
+
+pub(crate) static BOOP_RATE: Metric =
+    Metric::gauge("osc_booper_boop_rate", "Boops within the last minute");
+
+pub(crate) static PAUSED: Metric = Metric::gauge(
+    "osc_booper_paused",
+    "1 while counting is paused, manually or while AFK",
+);
+
+pub(crate) static MESSAGES_SENT: Metric =
+    Metric::counter("osc_booper_messages_sent_total", "Chatbox messages sent");
+
+pub(crate) static DECODE_ERRORS: Metric = Metric::counter(
+    "osc_booper_decode_errors_total",
+    "OSC packets that could not be decoded",
+);
+
+pub(crate) static LENIENT_DECODES: Metric = Metric::counter(
+    "osc_booper_lenient_decodes_total",
+    "Malformed OSC packets recovered by the lenient decoder",
+);
+
+pub(crate) static TRUNCATED_PACKETS: Metric = Metric::counter(
+    "osc_booper_truncated_packets_total",
+    "OSC packets dropped for exceeding the receive buffer",
+);
+
+pub(crate) static REJECTED_PACKETS: Metric = Metric::counter(
+    "osc_booper_rejected_packets_total",
+    "OSC packets dropped for coming from an unexpected source",
+);
+
+pub(crate) static STALE_BUNDLES: Metric = Metric::counter(
+    "osc_booper_stale_bundles_total",
+    "OSC bundles dropped for a timetag too far in the past",
+);
+
+pub(crate) static DEBOUNCED_CONTACTS: Metric = Metric::counter(
+    "osc_booper_debounced_contacts_total",
+    "Repeated contact triggers ignored by the debounce",
+);
+
+/// All exported metrics, also used to generate the Grafana dashboard
+static METRICS: &[&Metric] = &[
+    &BOOPS_COUNTED,
+    &BOOPS_TODAY,
+    &BOOPS_LIFETIME,
+    &BOOPS_SESSION,
+    &BOOPS_WEEK,
+    &BOOPS_MONTH,
+    &STREAK_DAYS,
+    &BOOP_RATE,
+    &PAUSED,
+    &MESSAGES_SENT,
+    &DECODE_ERRORS,
+    &LENIENT_DECODES,
+    &TRUNCATED_PACKETS,
+    &REJECTED_PACKETS,
+    &STALE_BUNDLES,
+    &DEBOUNCED_CONTACTS,
+];
+
+/// Count boops and chatbox messages as they're announced
+///
+/// Gauges depend on the stored stats and are set by the main loop instead.
+pub(crate) fn spawn_sink(mut events: broadcast::Receiver<BooperEvent>) {
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(BooperEvent::BoopCounted { .. }) => BOOPS_COUNTED.inc(),
+                Ok(BooperEvent::ChatboxSent { .. }) => MESSAGES_SENT.inc(),
+                Ok(_) => {}
+                Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                    warn!(skipped, "metrics sink lagging behind, events skipped");
+                }
+                Err(broadcast::error::RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Current world, exported as labels of `osc_booper_world_info`
+static WORLD: Mutex<Option<World>> = Mutex::new(None);
+
+pub(crate) fn set_world(world: &World) {
+    *WORLD.lock().unwrap() = Some(world.clone());
+}
+
+/// Today's and total boops per booper, exported with a `booper` label
+static BOOPERS: Mutex<BTreeMap<String, (u32, u64)>> = Mutex::new(BTreeMap::new());
+
+pub(crate) fn set_boopers<'a>(boopers: impl Iterator<Item = (&'a str, u32, u64)>) {
+    *BOOPERS.lock().unwrap() = boopers
+        .map(|(name, today, total)| (name.into(), (today, total)))
+        .collect();
+}
+
+/// Render all metrics in the Prometheus text format
+pub(crate) fn render() -> String {
+    let mut out = String::new();
+    for metric in METRICS {
+        let kind = match metric.kind {
+            MetricKind::Counter => "counter",
+            MetricKind::Gauge => "gauge",
+        };
+
+        writeln!(out, "# HELP {} {}", metric.name, metric.help).ok();
+        writeln!(out, "# TYPE {} {kind}", metric.name).ok();
+        writeln!(out, "{} {}", metric.name, metric.get()).ok();
+    }
+
+    if let Some(world) = WORLD.lock().unwrap().as_ref() {
+        writeln!(out, "# HELP osc_booper_world_info Current VRChat world").ok();
+        writeln!(out, "# TYPE osc_booper_world_info gauge").ok();
+        writeln!(
+            out,
+            "osc_booper_world_info{{world_id=\"{}\",world_name=\"{}\"}} 1",
+            escape_label(&world.id),
+            escape_label(&world.name)
+        )
+        .ok();
+    }
+
+    let boopers = BOOPERS.lock().unwrap();
+    if !boopers.is_empty() {
+        // name, help and value of today's and lifetime boops
+        type Gauge = (&'static str, &'static str, fn(&(u32, u64)) -> u64);
+        let per_booper: [Gauge; 2] = [
+            (
+                "osc_booper_booper_boops_today",
+                "Boops of the current period per booper",
+                |&(today, _)| today as u64,
+            ),
+            (
+                "osc_booper_booper_boops_lifetime",
+                "Lifetime boops per booper",
+                |&(_, total)| total,
+            ),
+        ];
+        for (name, help, value) in per_booper {
+            writeln!(out, "# HELP {name} {help}").ok();
+            writeln!(out, "# TYPE {name} gauge").ok();
+            for (booper, boops) in boopers.iter() {
+                let booper = escape_label(booper);
+                writeln!(out, "{name}{{booper=\"{booper}\"}} {}", value(boops)).ok();
+            }
+        }
+    }
+
+    out
+}
+
+/// Escape label value for the text format
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Grafana dashboard with one panel per metric, ready to import
+///
+/// Gauges are shown as current value, counters as rate per minute. The
+/// Prometheus data source is picked on import.
+pub(crate) fn dashboard() -> Value {
+    const PANEL_WIDTH: usize = 8;
+    const PANEL_HEIGHT: usize = 8;
+
+    let panels: Vec<Value> = METRICS
+        .iter()
+        .enumerate()
+        .map(|(i, metric)| {
+            let (panel_type, expr) = match metric.kind {
+                MetricKind::Gauge => (
+                    "stat",
+                    format!("{}{{instance=~\"$instance\"}}", metric.name),
+                ),
+                MetricKind::Counter => (
+                    "timeseries",
+                    format!("rate({}{{instance=~\"$instance\"}}[5m]) * 60", metric.name),
+                ),
+            };
+
+            json!({
+                "id": i + 1,
+                "type": panel_type,
+                "title": metric.help,
+                "datasource": { "type": "prometheus", "uid": "${DS_PROMETHEUS}" },
+                "gridPos": {
+                    "x": (i % 3) * PANEL_WIDTH,
+                    "y": (i / 3) * PANEL_HEIGHT,
+                    "w": PANEL_WIDTH,
+                    "h": PANEL_HEIGHT,
+                },
+                "targets": [{
+                    "refId": "A",
+                    "expr": expr,
+                    "legendFormat": "{{instance}}",
+                }],
+            })
+        })
+        .collect();
+
+    json!({
+        "__inputs": [{
+            "name": "DS_PROMETHEUS",
+            "label": "Prometheus",
+            "type": "datasource",
+            "pluginId": "prometheus",
+        }],
+        "title": "osc-booper",
+        "uid": "osc-booper",
+        "schemaVersion": 39,
+        "time": { "from": "now-24h", "to": "now" },
+        "refresh": "1m",
+        "templating": {
+            "list": [{
+                "name": "instance",
+                "type": "query",
+                "datasource": { "type": "prometheus", "uid": "${DS_PROMETHEUS}" },
+                "query": format!("label_values({}, instance)", BOOPS_TODAY.name),
+                "includeAll": true,
+                "multi": true,
+                "current": { "text": "All", "value": "$__all" },
+            }],
+        },
+        "panels": panels,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_dashboard_matches_metrics() {
+        let dashboard = dashboard().to_string();
+        let rendered = render();
+
+        for metric in METRICS {
+            assert!(dashboard.contains(metric.name), "{} missing", metric.name);
+            assert!(rendered.contains(&format!("# TYPE {} ", metric.name)));
+        }
+    }
+
+    #[test]
+    fn test_escape_label() {
+        assert_eq!(escape_label("Cat \"Café\"\\\n"), "Cat \\\"Café\\\"\\\\\\n");
+    }
+}
@@ -0,0 +1,141 @@
+use std::time::Duration;
+
+use rumqttc::{AsyncClient, Event, LastWill, MqttOptions as ClientOptions, Outgoing, Packet, QoS};
+use serde_json::Value;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    command::{self, CommandSender, ControlRequest},
+    config::MqttOptions,
+    events::{BooperEvent, EventLogEntry},
+};
+
+const USER_ENV: &str = "OSC_BOOPER_MQTT_USER";
+const PASSWORD_ENV: &str = "OSC_BOOPER_MQTT_PASSWORD";
+
+/// Wait before reconnecting to an unreachable broker
+const RECONNECT_DELAY: Duration = Duration::from_secs(5);
+
+/// Publish events and stats to an MQTT broker
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: MqttOptions,
+    commands: CommandSender,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    let mut client_options = ClientOptions::new(&options.client_id, &options.host, options.port);
+    client_options.set_keep_alive(Duration::from_secs(30));
+    client_options.set_last_will(LastWill::new(
+        &options.status_topic,
+        "offline",
+        QoS::AtLeastOnce,
+        true,
+    ));
+    if let Ok(user) = std::env::var(USER_ENV) {
+        client_options.set_credentials(user, std::env::var(PASSWORD_ENV).unwrap_or_default());
+    }
+
+    let (client, mut event_loop) = AsyncClient::new(client_options, 32);
+    info!(
+        "publishing to mqtt broker {}:{}",
+        options.host, options.port
+    );
+
+    // drives the connection, the client only queues requests
+    let status_client = client.clone();
+    let status_topic = options.status_topic.clone();
+    tokio::spawn(async move {
+        loop {
+            match event_loop.poll().await {
+                Ok(Event::Incoming(Packet::ConnAck(_))) => {
+                    debug!("connected to mqtt broker");
+                    // the event loop can't wait for its own queue
+                    let online =
+                        status_client.try_publish(&status_topic, QoS::AtLeastOnce, true, "online");
+                    if let Err(e) = online {
+                        warn!(err=%e, "failed to publish mqtt status");
+                    }
+                }
+                Ok(Event::Outgoing(Outgoing::Disconnect)) => break,
+                Ok(_) => {}
+                Err(e) => {
+                    warn!(err=%e, "mqtt connection failed, reconnecting");
+                    tokio::time::sleep(RECONNECT_DELAY).await;
+                }
+            }
+        }
+    });
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => match event {
+                    Ok(event) => event,
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!(skipped, "mqtt lagging behind");
+                        continue;
+                    }
+                    Err(RecvError::Closed) => break,
+                },
+            };
+
+            let changes_counters =
+                event.counts().is_some() || matches!(event, BooperEvent::Counted { .. });
+            publish_event(&client, &options, event).await;
+            if changes_counters {
+                publish_stats(&client, &options, &commands).await;
+            }
+        }
+
+        // a clean disconnect doesn't trigger the last will
+        let offline = client
+            .publish(&options.status_topic, QoS::AtLeastOnce, true, "offline")
+            .await;
+        if let Err(e) = offline.and(client.disconnect().await) {
+            warn!(err=%e, "failed to disconnect from mqtt broker");
+        }
+    });
+}
+
+async fn publish_event(client: &AsyncClient, options: &MqttOptions, event: BooperEvent) {
+    let entry = match serde_json::to_value(EventLogEntry::now(event)) {
+        Ok(entry) => entry,
+        Err(e) => {
+            error!(err=%e, "failed to serialize event");
+            return;
+        }
+    };
+    let Some(Value::String(event_type)) = entry.get("type") else {
+        return;
+    };
+
+    let topic = format!("{}/{event_type}", options.events_topic);
+    let payload = entry.to_string();
+    if let Err(e) = client.publish(topic, QoS::AtMostOnce, false, payload).await {
+        warn!(err=%e, "failed to publish mqtt event");
+    }
+}
+
+async fn publish_stats(client: &AsyncClient, options: &MqttOptions, commands: &CommandSender) {
+    let response = command::execute(ControlRequest::Stats, commands, "mqtt").await;
+    let Some(stats) = response.stats else {
+        return;
+    };
+
+    let payload = match serde_json::to_string(&stats) {
+        Ok(payload) => payload,
+        Err(e) => {
+            error!(err=%e, "failed to serialize stats");
+            return;
+        }
+    };
+    if let Err(e) = client
+        .publish(&options.stats_topic, QoS::AtLeastOnce, true, payload)
+        .await
+    {
+        warn!(err=%e, "failed to publish mqtt stats");
+    }
+}
@@ -1,102 +1,453 @@
-use std::{net::SocketAddr, ops::Add, sync::Arc, time::Duration};
+use std::{
+    collections::{HashMap, HashSet},
+    net::{IpAddr, SocketAddr},
+    ops::Add,
+    sync::Arc,
+    time::{Duration, SystemTime},
+};
 
 use jiff::{SignedDuration, Timestamp};
-use rosc::{OscMessage, OscPacket, OscType};
-use tokio::{net::UdpSocket, sync::Mutex};
+use rosc::{OscBundle, OscMessage, OscPacket, OscTime, OscType};
+use tokio::{
+    net::UdpSocket,
+    sync::{mpsc, watch},
+};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, trace, warn};
 
 use crate::{
-    config::{Options, TextSuffixResult},
-    storage::BoopStorage,
+    backup, capture,
+    chatbox::{ChatboxQueue, Outgoing, Priority},
+    command::{self, Command, CommandReceiver, CommandSender, StatsSnapshot},
+    config::{
+        self, CountKind, CounterOptions, MilestoneSequence, Options, OscTransport,
+        TemplateRotation, TextSuffixResult, TotalPolicy, Trigger, TruncationStep,
+    },
+    console,
+    coordination::{self, Coordination},
+    dashboard::{self, RecentEvents},
+    datasource::{self, DataCache},
+    decode::{self, AddressFilter},
+    discord,
+    error::BooperError,
+    events::{self, BooperEvent, EventSender},
+    export,
+    forward::Forwarder,
+    goals,
+    http::{self, HttpState},
+    i18n, ipc, lights, metrics, oscquery,
+    output::{self, bool_message},
+    parameters::{self, Echoes, PARAMETER_PREFIX, ParameterValues},
+    rate::BoopRate,
+    report, scheduler, social, sources,
+    storage::{self, BoopStorage, PeriodSummary},
+    tcp, template, text_file, world,
+};
+
+/// Special timetag meaning "apply immediately"
+const OSC_TIME_IMMEDIATELY: OscTime = OscTime {
+    seconds: 0,
+    fractional: 1,
 };
 
-pub(crate) struct OscBooper<'a> {
+/// Received bundles scheduled further in the future are dropped
+const MAX_BUNDLE_DELAY: Duration = Duration::from_secs(60);
+
+/// Received bundles with older timetags are dropped as replays
+const MAX_BUNDLE_AGE: Duration = Duration::from_secs(10);
+
+/// Address prefix of our own control endpoints, e.g. `/booper/reset_today`
+pub(crate) const CONTROL_PREFIX: &str = "/booper/";
+
+pub struct OscBooper {
     /// Our receiving socket
     socket: Arc<UdpSocket>,
 
     /// Our OSC port
     /// Stored separated for ease of access
-    pub(crate) osc_port: u16,
+    pub osc_port: u16,
 
-    /// VRChat/OSC receiver address
-    osc_receiver: SocketAddr,
+    /// Where our packets go, VRChat/OSC receiver
+    outbound: Outbound,
 
     /// Boop counter storage
     storage: BoopStorage,
 
-    /// Last sent timestamp, used for cooldown
-    last_message: Timestamp,
+    /// Outgoing chatbox messages, waiting for the cooldown
+    chatbox: ChatboxQueue,
+
+    /// Last message per additional counter, used for their cooldowns
+    last_counter_message: HashMap<String, Timestamp>,
+
+    /// Index of the current template in `message_templates`
+    template_index: usize,
+
+    /// Recent boops, for the boop rate and storm detection
+    rate: BoopRate,
+
+    /// Last contact trigger per address, used for debouncing
+    last_trigger: HashMap<String, Timestamp>,
+
+    /// Addresses of contacts currently triggered, which count again once
+    /// released
+    active_contacts: HashSet<String>,
+
+    /// Last received OSC message, used for idle detection
+    last_activity: Timestamp,
+
+    /// Freeze mode, boops are counted but nothing is posted
+    frozen: bool,
+
+    /// Pause mode, boops are neither counted nor posted
+    paused: bool,
 
-    /// Suffix for boops
-    boop_address: &'a str,
+    /// Temporary pause, ends automatically
+    paused_until: Option<Timestamp>,
+
+    /// Whether the AFK parameter is set
+    afk: bool,
+
+    /// VRChat is known to be gone, chatbox messages are held until it's back
+    vrchat_lost: bool,
+
+    /// Address VRChat was found at via OSCQuery, trusted as packet source
+    vrchat_addr: Option<IpAddr>,
+
+    /// Untrusted sources we've already warned about
+    rejected_sources: HashSet<IpAddr>,
+
+    /// Counts changed without updating the avatar parameters yet, sent along
+    /// with the next chatbox message
+    parameters_pending: bool,
+
+    /// Addresses worth decoding, everything if unset
+    address_filter: Option<AddressFilter>,
+
+    /// Writes the boop storage while the main loop runs
+    saver: Option<storage::Saver>,
 
     /// Our settings/options
     options: Options,
 
-    /// channel to notify chatbox clearing thread
-    clear_tx: Option<tokio::sync::mpsc::Sender<()>>,
+    /// Latest values of external data sources
+    data_cache: DataCache,
+
+    /// Sender for runtime commands, handed out to other tasks
+    command_tx: CommandSender,
+
+    /// Receiver for runtime commands, taken by the main loop
+    command_rx: Option<CommandReceiver>,
+
+    /// Sender for OSC packets received outside our UDP socket, with the peer
+    /// they came from
+    packet_tx: mpsc::Sender<(SocketAddr, OscPacket)>,
+
+    /// Receiver for OSC packets received outside our UDP socket, taken by the
+    /// main loop
+    packet_rx: Option<mpsc::Receiver<(SocketAddr, OscPacket)>>,
+
+    /// Broadcast of everything happening, for sinks like the stdout JSON stream
+    events: EventSender,
+
+    /// Nodes of our OSCQuery tree, following the reloaded options
+    oscquery_nodes: watch::Sender<Vec<oscquery::tree::Node>>,
+
+    /// Latest avatar parameter values, for inspecting avatars
+    parameters: watch::Sender<ParameterValues>,
+
+    /// Avatar parameters we've set, which VRChat echoes back
+    echoes: Echoes,
+
+    /// Chatbox shared with other boopers, if configured
+    coordination: Option<Coordination>,
 }
 
-impl<'a> OscBooper<'a> {
-    pub async fn new(options: Options) -> Self {
-        let socket = UdpSocket::bind("127.0.0.1:0")
+impl OscBooper {
+    /// Bind our OSC socket and load the boop stats
+    pub async fn new(options: Options) -> Result<Self, BooperError> {
+        let addr = SocketAddr::from((options.osc_listen_addr(), options.listen_port.unwrap_or(0)));
+        let socket = UdpSocket::bind(addr)
             .await
-            .map_err(|e| {
-                error!(err=%e, "failed to bind osc socket");
-            })
-            .unwrap();
-        let listen_addr = socket.local_addr().unwrap();
+            .map_err(|source| BooperError::Bind {
+                what: "osc socket",
+                addr,
+                source,
+            })?;
+        let listen_addr = local_addr(&socket)?;
 
-        let osc_receiver: SocketAddr = ([127u8, 0, 0, 1], options.osc_send_port).into();
+        let osc_receiver: SocketAddr = (options.osc_send_addr(), options.osc_send_port).into();
 
         info!("receiving osc packets on {}", listen_addr);
         info!("sending osc packets to {}", osc_receiver);
 
-        // we'll need the string for the entire runtime, just leak it
-        let boop_address = options.boop_address.clone().leak();
+        let socket = Arc::new(socket);
+        let outbound = match options.osc_send_transport {
+            OscTransport::Udp => Outbound::Udp(socket.clone(), osc_receiver),
+            OscTransport::Tcp => {
+                Outbound::Tcp(tcp::TcpSender::spawn(osc_receiver, options.tcp_framing))
+            }
+        };
+
+        let (command_tx, command_rx) = command::channel();
+        let (packet_tx, packet_rx) = mpsc::channel(128);
+        let storage = BoopStorage::load()
+            .with_reset_policy(options.reset_policy)
+            .with_reset_time(options.reset_time, options.reset_timezone.clone())
+            .with_counter_resets(&options.counters);
+        let address_filter = address_filter(&options);
+        let oscquery_nodes = watch::Sender::new(oscquery::tree::nodes(&options));
 
-        OscBooper {
-            socket: Arc::new(socket),
-            boop_address,
+        Ok(OscBooper {
+            socket,
             options,
             osc_port: listen_addr.port(),
-            osc_receiver,
-            storage: BoopStorage::load(),
-            last_message: Timestamp::now(),
-            clear_tx: None,
-        }
+            outbound,
+            storage,
+            chatbox: ChatboxQueue::default(),
+            last_counter_message: HashMap::new(),
+            last_trigger: HashMap::new(),
+            active_contacts: HashSet::new(),
+            template_index: 0,
+            rate: BoopRate::default(),
+            last_activity: Timestamp::now(),
+            frozen: false,
+            paused: false,
+            paused_until: None,
+            afk: false,
+            vrchat_lost: false,
+            vrchat_addr: None,
+            rejected_sources: HashSet::new(),
+            parameters_pending: false,
+            address_filter,
+            saver: None,
+            data_cache: DataCache::default(),
+            command_tx,
+            command_rx: Some(command_rx),
+            packet_tx,
+            packet_rx: Some(packet_rx),
+            events: events::channel(),
+            oscquery_nodes,
+            parameters: watch::Sender::default(),
+            echoes: Echoes::default(),
+            coordination: None,
+        })
+    }
+
+    /// Sender for runtime commands
+    pub(crate) fn commands(&self) -> CommandSender {
+        self.command_tx.clone()
+    }
+
+    /// Receiver of the current OSCQuery tree
+    pub fn oscquery_nodes(&self) -> watch::Receiver<Vec<oscquery::tree::Node>> {
+        self.oscquery_nodes.subscribe()
+    }
+
+    /// Receiver of the latest avatar parameter values
+    pub fn parameters(&self) -> watch::Receiver<ParameterValues> {
+        self.parameters.subscribe()
+    }
+
+    /// Subscribe to [`BooperEvent`]s
+    pub fn subscribe(&self) -> tokio::sync::broadcast::Receiver<BooperEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publish event to all subscribers
+    fn emit(&self, event: BooperEvent) {
+        // fails only without subscribers, which is fine
+        self.events.send(event).ok();
     }
 
-    /// Main program loop
-    pub(crate) async fn run(&mut self, token: CancellationToken) {
-        let mut buf = [0u8; rosc::decoder::MTU];
+    /// Main program loop, until `token` is cancelled
+    pub async fn run(&mut self, token: CancellationToken) -> Result<(), BooperError> {
+        // one extra byte to notice datagrams exceeding the buffer
+        let receive_buffer = self.options.receive_buffer;
+        let mut buf = vec![0u8; receive_buffer + 1];
+        let listen_addr = local_addr(&self.socket)?;
 
         let main_socket = self.socket.clone();
-        let clearing_socket = self.socket.clone();
-        let osc_clone = self.osc_receiver;
+        self.saver = Some(storage::spawn_saver());
+
+        self.update_gauges();
+
+        if let Some(compaction) = &self.options.event_log_compaction {
+            events::compact(compaction);
+        }
 
-        let (clear_tx, clear_rx) = tokio::sync::mpsc::channel(32);
-        self.clear_tx = Some(clear_tx);
+        if let Some(backups) = &self.options.backups {
+            backup::spawn(token.clone(), backups.clone());
+        }
+
+        datasource::spawn_fetchers(
+            token.clone(),
+            &self.options.data_sources,
+            self.data_cache.clone(),
+        );
+        scheduler::spawn(
+            token.clone(),
+            &self.options.schedules,
+            self.command_tx.clone(),
+        );
+        console::spawn(self.command_tx.clone());
+
+        self.spawn_sinks(&token);
+
+        if let Some(world_tracking) = &self.options.world_tracking {
+            world::spawn(
+                token.clone(),
+                world_tracking.clone(),
+                self.command_tx.clone(),
+            );
+        }
+
+        if let Some(coordination) = &self.options.coordination {
+            let commands = self.command_tx.clone();
+            self.coordination = Some(coordination::spawn(token.clone(), coordination, commands));
+        }
+
+        if self.options.control_socket {
+            ipc::spawn(token.clone(), self.command_tx.clone());
+        }
+
+        if self.options.dbus {
+            #[cfg(all(target_os = "linux", feature = "dbus"))]
+            crate::dbus::spawn(token.clone(), self.command_tx.clone(), self.subscribe());
+
+            #[cfg(not(all(target_os = "linux", feature = "dbus")))]
+            warn!("d-bus support is not available in this build");
+        }
+
+        if self.options.toasts {
+            #[cfg(all(windows, feature = "toast"))]
+            crate::toast::spawn(self.command_tx.clone(), self.subscribe());
+
+            #[cfg(not(all(windows, feature = "toast")))]
+            warn!("toast notifications are not available in this build");
+        }
+
+        if self.options.overlay {
+            #[cfg(feature = "overlay")]
+            crate::overlay::spawn(token.clone(), self.command_tx.clone());
+
+            #[cfg(not(feature = "overlay"))]
+            warn!("SteamVR overlay is not available in this build");
+        }
+
+        if self.options.tray {
+            #[cfg(all(feature = "tray", any(windows, target_os = "linux")))]
+            crate::tray::spawn(
+                token.clone(),
+                self.command_tx.clone(),
+                self.options
+                    .http_port
+                    .map(|port| format!("http://localhost:{port}/")),
+            );
+
+            #[cfg(not(all(feature = "tray", any(windows, target_os = "linux"))))]
+            warn!("tray icon is not available in this build");
+        }
+
+        #[cfg(not(feature = "notify"))]
+        if self.options.platform == crate::config::Platform::ChilloutVR
+            && self.options.cvr.notifications
+        {
+            warn!("desktop notifications are not available in this build");
+        }
+
+        if self.options.osc_transport == OscTransport::Tcp {
+            let tcp_addr = SocketAddr::from((self.options.osc_listen_addr(), self.osc_port));
+            let framing = self.options.tcp_framing;
+            tcp::spawn_listener(token.clone(), tcp_addr, framing, self.packet_tx.clone()).await?;
+        }
+
+        if let Some(http_port) = self.options.http_port {
+            let http_addr = SocketAddr::from((self.options.http_listen_addr(), http_port));
+            let recent = RecentEvents::default();
+            dashboard::spawn_recorder(token.clone(), recent.clone(), self.subscribe());
+
+            let state = HttpState {
+                packets: self.packet_tx.clone(),
+                commands: self.command_tx.clone(),
+                events: self.events.clone(),
+                recent,
+                parameters: self.parameters.subscribe(),
+                started: Timestamp::now(),
+                api_token: self.options.http_token.as_deref().map(Arc::from),
+            };
+            http::serve(token.clone(), http_addr, state).await?;
+        }
+
+        let mut command_rx = self.command_rx.take().expect("main loop can only run once");
+        let mut packet_rx = self.packet_rx.take().expect("main loop can only run once");
+
+        let forwarder = Forwarder::new(&self.options.forward_to).await?;
 
         let mut listener_loop = async || {
             loop {
-                match main_socket.recv_from(&mut buf).await {
-                    Ok((size, addr)) => {
-                        let packet = match rosc::decoder::decode_udp(&buf[..size]) {
-                            Ok((_, packet)) => Some(packet),
-                            Err(e) => {
-                                error!(err=%e, addr=%addr, "failed to parse packet");
-                                None
-                            }
-                        };
+                tokio::select! {
+                    received = main_socket.recv_from(&mut buf) => match received {
+                        Ok((_, addr)) if !self.is_allowed_source(addr.ip()) => {
+                            self.reject_source(addr);
+                        }
+                        Ok((size, addr)) if size > receive_buffer => {
+                            metrics::TRUNCATED_PACKETS.inc();
+                            warn!(
+                                addr=%addr,
+                                receive_buffer,
+                                "dropping truncated packet, increase receive_buffer"
+                            );
+                        }
+                        Err(e) if is_truncated(&e) => {
+                            metrics::TRUNCATED_PACKETS.inc();
+                            warn!(receive_buffer, "dropping truncated packet, increase receive_buffer");
+                        }
+                        Err(e) if is_unreachable(&e) => {
+                            self.lost_vrchat("osc port unreachable");
+                        }
+                        Ok((size, addr)) => {
+                            capture::record(addr, listen_addr, &buf[..size]);
+                            forwarder.forward(&buf[..size]).await;
+                            let decoded = match &self.address_filter {
+                                Some(filter) => decode::decode_filtered(&buf[..size], filter),
+                                None => decode::decode_packet(&buf[..size]),
+                            };
+                            let packet = match decoded {
+                                Ok(packet) => Some(packet),
+                                Err(e) => {
+                                    error!(err=%e, addr=%addr, "failed to parse packet");
+                                    None
+                                }
+                            };
 
-                        if let Some(packet) = packet {
-                            self.handle_packet(packet).await;
+                            if let Some(packet) = packet {
+                                self.handle_packet(addr, packet).await;
+                            }
+                        }
+                        Err(e) => {
+                            error!(err=%e, "error receiving from socket");
+                        }
+                    },
+                    Some((addr, packet)) = packet_rx.recv() => {
+                        // TCP and WebSocket peers are held to the same rules
+                        if self.is_allowed_source(addr.ip()) {
+                            forwarder.forward_packet(&packet).await;
+                            self.handle_packet(addr, packet).await;
+                        } else {
+                            self.reject_source(addr);
                         }
                     }
-                    Err(e) => {
-                        error!(err=%e, "error receiving from socket");
+                    Some(command) = command_rx.recv() => {
+                        self.handle_command(command).await;
+                    }
+                    _ = sleep_until(self.storage.next_reset().map(|reset| reset.timestamp())) => {
+                        self.rollover().await;
+                    }
+                    _ = sleep_until(self.chatbox_due()) => {
+                        self.flush_chatbox().await;
+                    }
+                    _ = sleep_until(self.chatbox.next_clear(self.clear_guard())) => {
+                        self.clear_chatbox().await;
                     }
                 }
             }
@@ -109,52 +460,261 @@ impl<'a> OscBooper<'a> {
             _ = listener_loop() => {
                 warn!("osc listener stopped unexpectedly");
             }
-            _ = clear_chatbox_loop(clear_rx, clearing_socket, osc_clone) => {
-                warn!("chatbox clearing loop stopped unexpectedly");
-            }
         }
 
+        if let Some(saver) = self.saver.take() {
+            saver.finish().await;
+        }
         info!("saving boop storage one last time");
         self.storage.save();
         info!("see ya!");
+        Ok(())
     }
 
-    /// Handle received OSC packet
-    async fn handle_packet(&mut self, packet: OscPacket) {
+    /// Start the sinks following our events, like sounds and integrations
+    ///
+    /// They only subscribe to [`BooperEvent`]s, new ones don't need any
+    /// changes to the receive loop.
+    fn spawn_sinks(&self, token: &CancellationToken) {
+        events::spawn_log_sink(self.options.event_log_compaction.clone(), self.subscribe());
+        metrics::spawn_sink(self.subscribe());
+
+        #[cfg(feature = "audio")]
+        if !self.options.sounds.is_empty() {
+            crate::audio::spawn(token.clone(), self.options.sounds.clone(), self.subscribe());
+        }
+
+        #[cfg(not(feature = "audio"))]
+        if !self.options.sounds.is_empty() {
+            warn!("sounds are not available in this build");
+        }
+
+        #[cfg(feature = "serial")]
+        if let Some(serial) = &self.options.serial {
+            crate::serial::spawn(token.clone(), serial.clone(), self.subscribe());
+        }
+
+        #[cfg(not(feature = "serial"))]
+        if self.options.serial.is_some() {
+            warn!("serial output is not available in this build");
+        }
+
+        if let Some(text_file) = &self.options.text_file {
+            text_file::spawn(
+                token.clone(),
+                text_file.clone(),
+                self.storage.boop_numbers(),
+                self.subscribe(),
+            );
+        }
+
+        if let Some(lights) = &self.options.lights {
+            lights::spawn(token.clone(), lights.clone(), self.subscribe());
+        }
+
+        #[cfg(feature = "mqtt")]
+        if let Some(mqtt) = &self.options.mqtt {
+            crate::mqtt::spawn(
+                token.clone(),
+                mqtt.clone(),
+                self.command_tx.clone(),
+                self.subscribe(),
+            );
+        }
+
+        #[cfg(not(feature = "mqtt"))]
+        if self.options.mqtt.is_some() {
+            warn!("mqtt is not available in this build");
+        }
+
+        if let Some(export) = &self.options.export {
+            export::spawn(token.clone(), export.clone(), self.subscribe());
+        }
+
+        if let Some(social) = &self.options.social {
+            social::spawn(token.clone(), social.clone(), self.subscribe());
+        }
+
+        if let Some(discord) = &self.options.discord {
+            discord::spawn(
+                token.clone(),
+                discord.clone(),
+                self.storage.today_record(),
+                self.subscribe(),
+            );
+        }
+    }
+
+    /// Handle OSC packet received from `addr`
+    async fn handle_packet(&mut self, addr: SocketAddr, packet: OscPacket) {
+        if self.is_idle() {
+            info!("OSC activity resumed, periodic posts enabled again");
+        }
+        if self.vrchat_lost {
+            info!("receiving OSC packets again, sending chatbox messages again");
+            self.vrchat_lost = false;
+            self.storage.reset_session();
+            self.update_gauges();
+        }
+        self.last_activity = Timestamp::now();
+
+        let mut messages = vec![];
         match packet {
-            OscPacket::Message(msg) => {
-                if !msg.addr.ends_with("FluffSquishUpper") {
-                    debug!(
-                        "OSC message address: {}, arguments: {:?}",
-                        msg.addr, msg.args
-                    );
+            OscPacket::Message(msg) => messages.push(msg),
+            OscPacket::Bundle(bundle) => {
+                let mut delayed = vec![];
+                unpack_bundle(bundle, &mut messages, &mut delayed);
+
+                for (delay, bundle) in delayed {
+                    self.schedule_bundle(addr, delay, bundle);
                 }
-                self.handle_message(&msg).await;
             }
-            OscPacket::Bundle(bundle) => {
-                info!("OSC Bundle: {:?}", bundle);
+        }
+
+        for msg in messages {
+            if !msg.addr.ends_with("FluffSquishUpper") {
+                debug!(
+                    "OSC message address: {}, arguments: {:?}",
+                    msg.addr, msg.args
+                );
             }
+            self.handle_message(&msg).await;
         }
+
+        self.update_gauges();
+    }
+
+    /// Handle bundle with a future timetag from `addr` once it's due
+    fn schedule_bundle(&self, addr: SocketAddr, delay: Duration, bundle: OscBundle) {
+        if delay > MAX_BUNDLE_DELAY {
+            warn!(?delay, "dropping bundle scheduled too far in the future");
+            return;
+        }
+
+        debug!(?delay, "scheduling bundle");
+        let packets = self.packet_tx.clone();
+        tokio::spawn(async move {
+            tokio::time::sleep(delay).await;
+
+            // fed back as immediate bundle, so nested bundles are unpacked
+            let packet = OscPacket::Bundle(OscBundle {
+                timetag: OSC_TIME_IMMEDIATELY,
+                content: bundle.content,
+            });
+            packets.send((addr, packet)).await.ok();
+        });
     }
 
     /// Handle received OSC message
     async fn handle_message(&mut self, message: &OscMessage) {
-        if message.addr.ends_with(self.boop_address) && !message.args.is_empty() {
-            // skip when contact sender leaves receiver bubble
-            // let's assume that only bools will be sent
-            if let OscType::Bool(false) = message.args[0] {
+        if let (Some(name), Some(value)) = (
+            message.addr.strip_prefix(PARAMETER_PREFIX),
+            message.args.first(),
+        ) {
+            self.parameters
+                .send_if_modified(|values| parameters::record(values, name, value));
+        }
+
+        if self.echoes.is_echo(&message.addr, Timestamp::now()) {
+            trace!(address = %message.addr, "ignoring echo of our own parameter");
+            return;
+        }
+
+        if let Some(control) = message.addr.strip_prefix(CONTROL_PREFIX) {
+            self.handle_control(control, &message.args).await;
+        } else if message.addr == self.options.afk_address {
+            let afk = message.args.first() == Some(&OscType::Bool(true));
+            if afk != self.afk {
+                self.afk = afk;
+                info!("{}", if afk { "went AFK" } else { "back from AFK" });
+                self.emit(BooperEvent::AfkChanged { afk });
+            }
+        } else if self.options.chatbox_toggle_address.as_ref() == Some(&message.addr) {
+            let enabled = message.args.first() == Some(&OscType::Bool(true));
+            if enabled != self.storage.chatbox_enabled() {
+                self.storage.set_chatbox_enabled(enabled);
+                self.save_storage();
+                let state = if enabled { "enabled" } else { "disabled" };
+                info!("chatbox {state} via avatar menu");
+            }
+        } else if self.options.boop_address.matches(&message.addr) && !message.args.is_empty() {
+            // skip when contact sender leaves receiver bubble, or stays within
+            // it without being released in between
+            let (trigger, hysteresis) = (self.options.boop_trigger, self.options.boop_hysteresis);
+            if !self.is_rising_edge(&message.addr, trigger, hysteresis, &message.args[0]) {
+                trace!(value = ?message.args[0], "boop not triggered");
+                return;
+            }
+
+            if self.is_bounce(&message.addr, self.options.boop_debounce) {
+                debug!(address = %message.addr, "ignoring repeated boop");
+                return;
+            }
+
+            if self.is_paused() {
+                debug!("paused, not counting boop");
                 return;
             }
+
+            if !self.is_counted_avatar() {
+                debug!("avatar excluded, not counting boop");
+                return;
+            }
+
+            if let Some(summary) = self.storage.check_reset() {
+                self.finish_period(summary);
+            }
             self.storage.inc_boops();
+            let now = Timestamp::now();
+            let previous_rate = self.rate.per_minute(now);
+            self.rate.record(now);
 
-            let (message, has_suffix) = self.generate_message();
+            let (today, total) = self.storage.boop_numbers();
+            self.emit(BooperEvent::BoopCounted {
+                address: message.addr.clone(),
+                today,
+                total,
+            });
+            self.parameters_pending = true;
+            self.apply_output_rules(previous_rate, now);
+            self.post_boop(today, total, now).await;
+            // not sent along with a chatbox message, e.g. on cooldown
+            self.send_pending_parameters().await;
+        } else if let Some(booper) = self
+            .options
+            .boopers
+            .iter()
+            .find(|booper| booper.address.matches(&message.addr))
+            .map(|booper| booper.name.clone())
+        {
+            self.handle_booper(&booper, message);
+        } else if let Some(booper) = self
+            .options
+            .booper_pattern
+            .as_ref()
+            .and_then(|pattern| pattern.booper(&message.addr))
+        {
+            self.handle_booper(booper, message);
+        } else if let Some(counter) = self
+            .options
+            .counters
+            .iter()
+            .find(|counter| counter.address.matches(&message.addr))
+            .cloned()
+        {
+            let Some(value) = message.args.first() else {
+                return;
+            };
+            if !self.is_rising_edge(&message.addr, counter.trigger, counter.hysteresis, value) {
+                return;
+            }
 
-            // skip if on cooldown or message is without suffix
-            if !has_suffix && !self.should_send_message() {
+            if self.is_bounce(&message.addr, counter.debounce) {
+                debug!(counter = %counter.name, "ignoring repeated contact");
                 return;
             }
 
-            self.send_message(message).await;
+            self.count(&counter, &message.addr).await;
         } else if message.addr == "/avatar/change" {
             // this event fires on map changes (usually) and on avatar change
 
@@ -163,38 +723,932 @@ impl<'a> OscBooper<'a> {
                 return;
             }
 
-            let avatar_id = message.args[0].to_string();
+            let avatar_id = match &message.args[0] {
+                OscType::String(id) => id.clone(),
+                other => other.to_string(),
+            };
             info!("avatar switched to {avatar_id}");
 
-            self.storage.save();
+            self.emit(BooperEvent::AvatarChanged {
+                avatar_id: avatar_id.clone(),
+            });
+            self.storage
+                .set_avatar(avatar_id, self.options.new_avatar_starts_at_zero);
+            self.save_storage();
+
+            // parameters of the previous avatar are meaningless now
+            self.parameters.send_modify(ParameterValues::clear);
+            self.active_contacts.clear();
+
+            // freshly loaded avatars start with default parameter values
+            self.send_parameters().await;
         }
 
         // save storage if it's been a while
         // due to the amount of messages spammed every second, this should be fine™
         // (/avatar/parameters/* gets spammed multiple times per second)
-        if self.storage.time_to_save() {
-            self.storage.save();
+        if self.storage.needs_save() {
+            self.save_storage();
+        }
+    }
+
+    /// Count a boop by `name`, received as contact of `message`
+    fn handle_booper(&mut self, name: &str, message: &OscMessage) {
+        if message.args.is_empty() || message.args[0] == OscType::Bool(false) {
+            return;
+        }
+
+        if self.is_bounce(&message.addr, self.options.boop_debounce)
+            || self.is_paused()
+            || !self.is_counted_avatar()
+        {
+            return;
+        }
+
+        if let Some(summary) = self.storage.check_reset() {
+            self.finish_period(summary);
+        }
+        debug!(booper = name, "booped by");
+        self.storage.inc_booper(name);
+        self.update_gauges();
+    }
+
+    /// Whether `value` starts a contact on `address`, instead of continuing
+    /// one that wasn't released yet
+    ///
+    /// Proximity receivers send a value every frame, only crossing the
+    /// threshold counts.
+    fn is_rising_edge(
+        &mut self,
+        address: &str,
+        trigger: Trigger,
+        hysteresis: f32,
+        value: &OscType,
+    ) -> bool {
+        if trigger.matches(value) {
+            return self.active_contacts.insert(address.into());
+        }
+
+        if trigger.releases(value, hysteresis) {
+            self.active_contacts.remove(address);
+        }
+        false
+    }
+
+    /// Whether a contact on `address` repeats the previous one within
+    /// `debounce`
+    ///
+    /// Every trigger restarts the window, so a contact firing continuously
+    /// only counts once.
+    fn is_bounce(&mut self, address: &str, debounce: Option<SignedDuration>) -> bool {
+        let Some(debounce) = debounce else {
+            return false;
+        };
+
+        let now = Timestamp::now();
+        let previous = self.last_trigger.insert(address.into(), now);
+        let bounce = previous.is_some_and(|previous| now < previous + debounce);
+        if bounce {
+            metrics::DEBOUNCED_CONTACTS.inc();
+        }
+
+        bounce
+    }
+
+    /// Increment additional counter and post its message, unless on cooldown
+    async fn count(&mut self, counter: &CounterOptions, address: &str) {
+        if self.is_paused() {
+            debug!(counter = %counter.name, "paused, not counting");
+            return;
+        }
+
+        if !self.is_counted_avatar() {
+            debug!(counter = %counter.name, "avatar excluded, not counting");
+            return;
+        }
+
+        if let Some(summary) = self.storage.check_reset() {
+            self.finish_period(summary);
+        }
+        let counts = self.storage.inc_counter(&counter.name);
+        debug!(counter = %counter.name, counts.today, counts.total, "counted");
+
+        self.emit(BooperEvent::Counted {
+            counter: counter.name.clone(),
+            address: address.into(),
+            today: counts.today,
+            total: counts.total,
+        });
+
+        let now = Timestamp::now();
+        let on_cooldown = self
+            .last_counter_message
+            .get(&counter.name)
+            .is_some_and(|last| now < last.add(counter.cooldown));
+        if on_cooldown {
+            return;
+        }
+        self.last_counter_message.insert(counter.name.clone(), now);
+
+        let (message, _) = self.render_message(&counter.message_template, |key| match key {
+            "name" => Some(counter.display_name().into()),
+            "today" => Some(counts.today.to_string()),
+            "total" => Some(counts.total.to_string()),
+            _ => None,
+        });
+        self.send_message(message, Priority::Update).await;
+    }
+
+    /// Handle OSC message on one of our own control addresses
+    async fn handle_control(&mut self, control: &str, args: &[OscType]) {
+        let command = match (control, args.first()) {
+            // toggles, bool sets state explicitly
+            ("freeze", Some(OscType::Bool(frozen))) => Command::Freeze(Some(*frozen)),
+            ("freeze", _) => Command::Freeze(None),
+            ("pause", Some(OscType::Bool(paused))) => Command::Pause(Some(*paused)),
+
+            // buttons send true on press and false on release
+            (_, Some(OscType::Bool(false))) => return,
+
+            ("pause", _) => Command::Pause(Some(true)),
+            ("resume", _) => Command::Pause(Some(false)),
+            ("announce", _) => Command::PostStats {
+                template: None,
+                manual: true,
+            },
+            ("leaderboard", _) => Command::PostStats {
+                template: Some(self.options.leaderboard.message_template.clone()),
+                manual: true,
+            },
+            ("reset_today", _) => Command::ResetToday,
+            ("undo", _) => Command::Undo,
+            ("adjust", Some(OscType::Int(by))) => Command::Adjust {
+                by: *by as i64,
+                source: "osc",
+            },
+            ("adjust", Some(OscType::Long(by))) => Command::Adjust {
+                by: *by,
+                source: "osc",
+            },
+            ("adjust", _) => {
+                warn!("expected int argument for {CONTROL_PREFIX}adjust, got {args:?}");
+                return;
+            }
+            (_, _) => {
+                warn!("unknown control address {CONTROL_PREFIX}{control}");
+                return;
+            }
+        };
+
+        self.handle_command(command).await;
+    }
+
+    /// Handle runtime command
+    async fn handle_command(&mut self, command: Command) {
+        debug!("handling command {:?}", command);
+        self.process_command(command).await;
+        self.update_gauges();
+
+        if self.storage.needs_save() {
+            self.save_storage();
+        }
+    }
+
+    async fn process_command(&mut self, command: Command) {
+        match command {
+            Command::PostStats { template, manual } => {
+                let (message, _) =
+                    self.generate_message(template.as_deref().unwrap_or(self.message_template()));
+
+                if !manual {
+                    if self.is_paused() {
+                        debug!("skipping stats post, paused");
+                        return;
+                    }
+
+                    if self.is_idle() {
+                        debug!("skipping stats post, no recent OSC activity");
+                        return;
+                    }
+                }
+
+                if template.is_none() {
+                    self.rotate_template();
+                }
+                // scheduled posts are coalesced with boop updates
+                let priority = if manual {
+                    Priority::Important
+                } else {
+                    Priority::Update
+                };
+                self.send_message(message, priority).await;
+            }
+            Command::Pause(paused) => {
+                self.paused = paused.unwrap_or(!self.is_paused());
+                self.paused_until = None;
+                info!("{}", if self.paused { "paused" } else { "resumed" });
+            }
+            Command::PauseFor(duration) => {
+                self.paused_until = Some(Timestamp::now() + duration);
+                info!("paused for {duration:#}");
+            }
+            Command::Stats(reply) => {
+                reply.send(self.stats()).ok();
+            }
+            Command::Reload(options) => {
+                self.apply_options(*options);
+            }
+            Command::Configure(patch) => {
+                patch.apply(&mut self.options);
+                info!("applied config changes");
+            }
+            Command::WorldChanged(world) => {
+                info!(world_id = %world.id, "entered world {}", world.name);
+
+                metrics::set_world(&world);
+                self.emit(BooperEvent::WorldChanged {
+                    world_id: world.id.clone(),
+                    world_name: world.name.clone(),
+                });
+                self.storage.set_world(world.id, world.name);
+                self.save_storage();
+            }
+            Command::VrchatFound { addr, osc_port } => {
+                self.vrchat_addr = Some(addr);
+                if self.vrchat_lost {
+                    info!("VRChat is back, sending chatbox messages again");
+                    self.vrchat_lost = false;
+                    self.storage.reset_session();
+                    self.update_gauges();
+                }
+                self.follow_vrchat_port(osc_port);
+            }
+            Command::VrchatLost { reason } => self.lost_vrchat(reason),
+            Command::CoordinationRole { leader } => {
+                if let Some(coordination) = &mut self.coordination {
+                    coordination.set_leader(leader);
+                }
+            }
+            Command::CoordinatedLine { name, line } => {
+                if let Some(coordination) = &mut self.coordination {
+                    coordination.set_line(name, line);
+                    // send the merged message again with the changed line
+                    let own_line = Outgoing::Text(coordination.own_line());
+                    self.chatbox.push(own_line, Priority::Update);
+                }
+            }
+            Command::ResetToday => {
+                let summary = self.storage.reset_today(jiff::Zoned::now());
+                self.finish_period(summary);
+                self.send_parameters().await;
+            }
+            Command::Freeze(frozen) => {
+                self.frozen = frozen.unwrap_or(!self.frozen);
+                info!(
+                    "freeze mode {}",
+                    if self.frozen {
+                        "enabled, counting silently"
+                    } else {
+                        "disabled"
+                    }
+                );
+            }
+            Command::Undo => {
+                if !self.storage.undo_boop() {
+                    info!("nothing to undo");
+                    return;
+                }
+
+                let (today, total) = self.storage.boop_numbers();
+                info!(today, total, "reverted last boop");
+
+                self.emit(BooperEvent::Undone { today, total });
+                self.send_parameters().await;
+            }
+            Command::Adjust { by, source } => {
+                // adjusts the new period if the previous one is over
+                if let Some(summary) = self.storage.check_reset() {
+                    self.finish_period(summary);
+                }
+                self.storage.adjust(by);
+
+                let (today, total) = self.storage.boop_numbers();
+                info!(by, source, today, total, "adjusted boops");
+
+                self.emit(BooperEvent::Adjusted {
+                    by,
+                    source: source.into(),
+                    today,
+                    total,
+                });
+                self.send_parameters().await;
+            }
+        }
+    }
+
+    /// Queue chatbox message, sent right away unless on cooldown
+    async fn send_message(&mut self, message: String, priority: Priority) {
+        self.queue_message(Outgoing::Text(message), priority).await;
+    }
+
+    async fn queue_message(&mut self, message: Outgoing, priority: Priority) {
+        self.chatbox.push(message, priority);
+        self.flush_chatbox().await;
+    }
+
+    /// Send the next queued chatbox message, once the cooldown has passed
+    async fn flush_chatbox(&mut self) {
+        let Some(message) = self.chatbox.pop(Timestamp::now(), self.chatbox_cooldown()) else {
+            return;
+        };
+
+        match message {
+            Outgoing::Text(message) => self.deliver_message(message).await,
+            // sequences can't be shared, only their message
+            Outgoing::Milestone(message, _) if self.is_follower() => {
+                self.deliver_message(message).await
+            }
+            Outgoing::Milestone(message, sequence) => {
+                self.play_milestone_sequence(message, sequence)
+            }
+        }
+    }
+
+    /// When the next chatbox message is due, never while VRChat is gone
+    fn chatbox_due(&self) -> Option<Timestamp> {
+        if self.vrchat_lost {
+            return None;
+        }
+        self.chatbox.next_due()
+    }
+
+    /// Save boop stats, on the saver task while the main loop runs
+    fn save_storage(&mut self) {
+        match &self.saver {
+            Some(saver) => self.storage.save_with(saver),
+            None => self.storage.save(),
+        }
+    }
+
+    /// Whether OSC packets from `addr` are accepted
+    fn is_allowed_source(&self, addr: IpAddr) -> bool {
+        sources::is_allowed(&self.options, self.vrchat_addr, addr)
+    }
+
+    /// Drop a packet from an untrusted source, warning once per address
+    fn reject_source(&mut self, addr: SocketAddr) {
+        metrics::REJECTED_PACKETS.inc();
+        if self.rejected_sources.insert(addr.ip()) {
+            warn!(addr=%addr, "dropping packets from untrusted source, see osc_allowed_sources");
+        } else {
+            trace!(addr=%addr, "dropping packet from untrusted source");
+        }
+    }
+
+    /// Hold chatbox messages until VRChat is back, instead of sending them
+    /// into the void
+    fn lost_vrchat(&mut self, reason: &str) {
+        if self.vrchat_lost {
+            return;
+        }
+        warn!(reason, "VRChat is gone, holding chatbox messages");
+        self.vrchat_lost = true;
+    }
+
+    /// Send to the OSC port VRChat reported, which changes when it's
+    /// restarted with other `--osc` options
+    fn follow_vrchat_port(&mut self, osc_port: u16) {
+        if !self.options.follow_vrchat_port {
+            return;
+        }
+
+        match &mut self.outbound {
+            Outbound::Udp(_, addr) if addr.port() != osc_port => {
+                addr.set_port(osc_port);
+                info!("sending osc packets to {addr}, as reported by VRChat");
+            }
+            Outbound::Udp(..) => {}
+            Outbound::Tcp(_) => debug!(osc_port, "not following VRChat's port over TCP"),
+        }
+    }
+
+    /// Post the chatbox message for the boop counted at `now`
+    async fn post_boop(&mut self, today: u32, total: u64, now: Timestamp) {
+        let (message, has_suffix) = self.generate_message(self.message_template());
+
+        if has_suffix {
+            self.emit(BooperEvent::Milestone {
+                today,
+                total,
+                message: message.clone(),
+            });
+        }
+        self.announce_goals(today, total).await;
+
+        let storm = self.options.boop_storm.clone();
+        let storm_started = storm
+            .as_ref()
+            .is_some_and(|storm| self.rate.storm_started(now, storm.threshold));
+        if let (true, Some(storm)) = (storm_started, storm) {
+            let rate = self.rate.per_minute(now);
+            info!(rate, "boop storm");
+            self.emit(BooperEvent::BoopStorm { rate, today, total });
+
+            // takes precedence over the regular message, like milestones
+            let (message, _) = self.generate_message(&storm.message_template);
+            self.send_message(message, Priority::Important).await;
+            return;
         }
+
+        self.rotate_template();
+
+        // milestones are never coalesced with regular updates
+        let priority = if has_suffix {
+            Priority::Important
+        } else {
+            Priority::Update
+        };
+        if let (true, Some(sequence)) = (has_suffix, self.options.milestone_sequence.clone()) {
+            self.queue_message(Outgoing::Milestone(message, sequence), priority)
+                .await;
+            return;
+        }
+
+        self.send_message(message, priority).await;
+    }
+
+    /// Send chatbox message right away
+    async fn deliver_message(&mut self, message: String) {
+        if self.frozen {
+            debug!("freeze mode, not sending chatbox message");
+            return;
+        }
+        if !self.storage.chatbox_enabled() {
+            debug!("chatbox disabled via avatar menu, not sending message");
+            return;
+        }
+        if self.is_quiet() {
+            debug!("quiet hours, not sending chatbox message");
+            return;
+        }
+
+        let message = match &mut self.coordination {
+            Some(coordination) => match coordination.outgoing(message) {
+                Some(merged) => merged,
+                None => {
+                    debug!("handed chatbox message to the booper sharing it");
+                    return;
+                }
+            },
+            None => message,
+        };
+
+        self.emit(BooperEvent::ChatboxSent {
+            message: message.clone(),
+        });
+
+        if self.options.split_long_messages
+            && self.options.platform.has_chatbox()
+            && message.chars().count() > template::CHATBOX_MAX_CHARS
+        {
+            let parts = template::split_chunks(&message, template::CHATBOX_MAX_CHARS);
+            self.send_message_parts(parts);
+            return;
+        }
+
+        // outputs triggered alongside the chatbox message
+        let (today, total) = self.displayed_numbers();
+        let mut messages = output::text_messages(&self.options, message, today, total);
+        let echo_until = Timestamp::now() + config::default_echo_window();
+        for message in &messages {
+            self.echoes.expect(&message.addr, echo_until);
+        }
+        // bundled with the message, so both change at once
+        if std::mem::take(&mut self.parameters_pending) {
+            messages.extend(self.parameter_messages());
+        }
+        if !publish(&self.outbound, messages, self.options.bundle_outputs).await {
+            self.lost_vrchat("failed to send chatbox message");
+        }
+
+        let clear_at = self.clear_delay().map(|delay| Timestamp::now() + delay);
+        self.chatbox.shown(clear_at);
+    }
+
+    /// Clear the chatbox once the last message has been shown long enough
+    async fn clear_chatbox(&mut self) {
+        trace!("clearing chatbox");
+        self.chatbox.cleared();
+        publish(&self.outbound, output::clear_messages(&self.options), false).await;
     }
 
-    async fn send_message(&mut self, message: String) {
-        publish_chatbox(&self.socket, self.osc_receiver, message).await;
-        self.last_message = Timestamp::now();
+    /// Update the configured avatar parameters with the current counts
+    async fn send_parameters(&mut self) {
+        self.parameters_pending = false;
+        let messages = self.parameter_messages();
+        if messages.is_empty() {
+            return;
+        }
+        publish(&self.outbound, messages, self.options.bundle_outputs).await;
+    }
+
+    /// Update the avatar parameters if the last boop's chatbox message didn't
+    async fn send_pending_parameters(&mut self) {
+        if self.parameters_pending {
+            self.send_parameters().await;
+        }
+    }
+
+    /// Messages setting the avatar parameters to the current counts, expecting
+    /// their echoes
+    fn parameter_messages(&mut self) -> Vec<OscMessage> {
+        if self.options.parameter_outputs.is_empty() || self.frozen {
+            return vec![];
+        }
 
-        // notify clear thread
-        if let Some(tx) = &self.clear_tx {
-            tx.send(()).await.ok();
+        let (today, total) = self.displayed_numbers();
+        let messages = output::parameter_messages(&self.options.parameter_outputs, today, total);
+        let now = Timestamp::now();
+        for (output, message) in self.options.parameter_outputs.iter().zip(&messages) {
+            self.echoes.expect(&message.addr, now + output.echo_window);
         }
+        messages
+    }
+
+    /// Send the outputs of all rules matched by the boop counted at `now`
+    fn apply_output_rules(&mut self, previous_rate: u32, now: Timestamp) {
+        if self.options.output_rules.is_empty() || self.frozen {
+            return;
+        }
+
+        let (today, total) = self.displayed_numbers();
+        let rate = self.rate.per_minute(now);
+        let vars = |key: &str| match key {
+            "today" => Some(today.to_string()),
+            "total" => Some(total.to_string()),
+            "rate" => Some(rate.to_string()),
+            _ => None,
+        };
+
+        let mut steps = vec![];
+        for rule in &self.options.output_rules {
+            let count = match rule.count {
+                CountKind::Today => today as u64,
+                CountKind::Total => total,
+            };
+            if !rule.matches(count, previous_rate, rate) {
+                continue;
+            }
+            debug!(address = %rule.address, "output rule matched");
+
+            let Some(message) = output::rendered_message(&rule.address, &rule.value, vars) else {
+                continue;
+            };
+            steps.push((SignedDuration::ZERO, message));
+
+            let reset = rule.reset.as_ref().and_then(|reset| {
+                output::rendered_message(&rule.address, reset, vars)
+                    .map(|message| (rule.reset_after, message))
+            });
+            steps.extend(reset);
+        }
+
+        for (delay, message) in steps {
+            let echo_until = now + delay + config::default_echo_window();
+            self.echoes.expect(&message.addr, echo_until);
+            tokio::spawn(publish_scheduled(
+                self.outbound.clone(),
+                vec![message],
+                delay,
+                true,
+            ));
+        }
+    }
+
+    /// Send parts of a long message one after another, spaced by the chatbox
+    /// cooldown
+    fn send_message_parts(&mut self, parts: Vec<String>) {
+        debug!(parts = parts.len(), "sending long chatbox message in parts");
+
+        // reserve cooldown until the last part has been sent
+        let cooldown = self.chatbox_cooldown();
+        let now = Timestamp::now();
+        self.chatbox
+            .delay_until(now + cooldown * parts.len() as i32);
+
+        let last_part = now + cooldown * (parts.len() as i32 - 1);
+        let clear_at = self.clear_delay().map(|delay| last_part + delay);
+        self.chatbox.shown(clear_at);
+
+        let (today, total) = self.displayed_numbers();
+        let parts: Vec<_> = parts
+            .into_iter()
+            .map(|part| output::text_messages(&self.options, part, today, total))
+            .collect();
+
+        let outbound = self.outbound.clone();
+        let bundle = self.options.bundle_outputs;
+
+        tokio::spawn(async move {
+            for (i, part) in parts.into_iter().enumerate() {
+                if i > 0 {
+                    tokio::time::sleep(cooldown.unsigned_abs()).await;
+                }
+
+                publish(&outbound, part, bundle).await;
+            }
+        });
+    }
+
+    /// Play timed milestone sequence: parameter pulse, chatbox message and
+    /// clear, each sent as a bundle with a matching timetag
+    fn play_milestone_sequence(&mut self, message: String, sequence: MilestoneSequence) {
+        if self.frozen {
+            debug!("freeze mode, not playing milestone sequence");
+            return;
+        }
+        if self.is_quiet() {
+            debug!("quiet hours, not playing milestone sequence");
+            return;
+        }
+
+        self.emit(BooperEvent::ChatboxSent {
+            message: message.clone(),
+        });
+
+        let (today, total) = self.displayed_numbers();
+
+        let mut steps = vec![];
+        if let Some(address) = &sequence.pulse_address {
+            let echo_until =
+                Timestamp::now() + sequence.pulse_duration + config::default_echo_window();
+            self.echoes.expect(address, echo_until);
+            steps.push((SignedDuration::ZERO, vec![bool_message(address, true)]));
+            steps.push((sequence.pulse_duration, vec![bool_message(address, false)]));
+        }
+        steps.push((
+            sequence.chatbox_delay,
+            output::text_messages(&self.options, message, today, total),
+        ));
+        // the sequence clears the chatbox itself
+        self.chatbox.cleared();
+        if self.options.clear_chatbox {
+            steps.push((sequence.clear_delay, output::clear_messages(&self.options)));
+        }
+
+        for (delay, messages) in steps {
+            tokio::spawn(publish_scheduled(
+                self.outbound.clone(),
+                messages,
+                delay,
+                sequence.hold_until_due,
+            ));
+        }
+    }
+
+    /// Apply hot reloaded options
+    ///
+    /// Only settings read on every message are taken over, everything else
+    /// (ports, integrations, schedules, …) requires a restart.
+    fn apply_options(&mut self, options: Options) {
+        self.options.boop_address = options.boop_address;
+        self.options.boop_debounce = options.boop_debounce;
+        self.options.boop_trigger = options.boop_trigger;
+        self.options.boop_hysteresis = options.boop_hysteresis;
+        self.options.text_suffixes = options.text_suffixes;
+        self.options.message_template = options.message_template;
+        self.options.message_templates = options.message_templates;
+        self.options.template_rotation = options.template_rotation;
+        self.options.counters = options.counters;
+        self.options.counter_groups = options.counter_groups;
+        self.options.boopers = options.boopers;
+        self.options.booper_pattern = options.booper_pattern;
+        self.options.leaderboard = options.leaderboard;
+        self.options.milestone_sequence = options.milestone_sequence;
+        self.options.boop_storm = options.boop_storm;
+        self.options.daily_summary_template = options.daily_summary_template;
+        self.options.split_long_messages = options.split_long_messages;
+        self.options.idle_timeout = options.idle_timeout;
+        self.options.pause_while_afk = options.pause_while_afk;
+        self.options.afk_address = options.afk_address;
+        self.options.chatbox_toggle_address = options.chatbox_toggle_address;
+        self.options.osc_allowed_sources = options.osc_allowed_sources;
+        self.options.quiet_hours = options.quiet_hours;
+        self.options.total_policy = options.total_policy;
+        self.options.new_avatar_starts_at_zero = options.new_avatar_starts_at_zero;
+        self.options.avatars = options.avatars;
+        self.options.bundle_outputs = options.bundle_outputs;
+        self.options.parameter_outputs = options.parameter_outputs;
+        self.options.output_rules = options.output_rules;
+        self.options.chatbox_cooldown_secs = options.chatbox_cooldown_secs;
+        self.options.chatbox_clear_secs = options.chatbox_clear_secs;
+        self.options.chatbox_clear_guard_secs = options.chatbox_clear_guard_secs;
+        self.options.clear_chatbox = options.clear_chatbox;
+
+        self.oscquery_nodes
+            .send_replace(oscquery::tree::nodes(&self.options));
+        self.address_filter = address_filter(&self.options);
+
+        info!("applied reloaded config");
+    }
+
+    /// Snapshot of current counters and state
+    fn stats(&self) -> StatsSnapshot {
+        let (today, total) = self.storage.boop_numbers();
+
+        StatsSnapshot {
+            today,
+            total,
+            yesterday: self.storage.yesterday_boops(),
+            session: self.storage.session_boops(),
+            today_record: self.storage.today_record(),
+            week: self.storage.week_boops(),
+            month: self.storage.month_boops(),
+            streak: self.storage.streak(),
+            rate: self.rate.per_minute(Timestamp::now()),
+            avatar_id: self.storage.current_avatar().map(Into::into),
+            avatar_total: self.storage.avatar_total(),
+            avatar_today: self.storage.avatar_today(),
+            world_id: self.storage.current_world().map(Into::into),
+            world_name: self.storage.world_name().map(Into::into),
+            counters: self.storage.counters().clone(),
+            groups: self
+                .options
+                .counter_groups
+                .iter()
+                .map(|group| (group.name.clone(), self.storage.group(&group.counters)))
+                .collect(),
+            paused: self.is_paused(),
+            afk: self.afk,
+            frozen: self.frozen,
+            chatbox_enabled: self.storage.chatbox_enabled(),
+        }
+    }
+
+    /// Announce finished period and write its report, if enabled
+    fn finish_period(&self, summary: PeriodSummary) {
+        self.emit(BooperEvent::Reset {
+            previous: summary.boops,
+            date: summary.start.date(),
+            record: summary.record,
+            total: summary.total,
+        });
+
+        if let Some(reports) = &self.options.reports {
+            report::write(reports, &summary);
+        }
+    }
+
+    /// Reset today's boops once the period is over, even without any boops
+    /// coming in
+    async fn rollover(&mut self) {
+        let Some(summary) = self.storage.check_reset() else {
+            // only counters on their own schedule got reset
+            if self.storage.needs_save() {
+                self.save_storage();
+            }
+            return;
+        };
+        let yesterday = summary.boops;
+        self.finish_period(summary);
+        self.save_storage();
+        self.update_gauges();
+        self.send_parameters().await;
+
+        let Some(template) = self.options.daily_summary_template.clone() else {
+            return;
+        };
+        if self.is_paused() {
+            return;
+        }
+        let (message, _) = self.render_message(&template, |key| match key {
+            "yesterday" => Some(yesterday.to_string()),
+            _ => None,
+        });
+        self.send_message(message, Priority::Important).await;
+    }
+
+    /// Publish current counters as metrics
+    fn update_gauges(&self) {
+        let (today, total) = self.storage.boop_numbers();
+        metrics::BOOPS_TODAY.set(today as u64);
+        metrics::BOOPS_LIFETIME.set(total);
+        metrics::BOOPS_SESSION.set(self.storage.session_boops() as u64);
+        metrics::BOOPS_WEEK.set(self.storage.week_boops());
+        metrics::BOOPS_MONTH.set(self.storage.month_boops());
+        metrics::STREAK_DAYS.set(self.storage.streak() as u64);
+        metrics::BOOP_RATE.set(self.rate.per_minute(Timestamp::now()) as u64);
+        metrics::PAUSED.set(self.is_paused().into());
+        metrics::set_boopers(
+            self.storage
+                .boopers()
+                .map(|(name, boops)| (name, boops.today, boops.total)),
+        );
+    }
+
+    /// Whether we're paused, permanently, temporarily or while AFK
+    fn is_paused(&self) -> bool {
+        self.paused
+            || (self.options.pause_while_afk && self.afk)
+            || self
+                .paused_until
+                .is_some_and(|until| Timestamp::now() < until)
+    }
+
+    /// Whether contacts on the current avatar are counted
+    fn is_counted_avatar(&self) -> bool {
+        self.options.avatars.counts(self.storage.current_avatar())
+    }
+
+    /// Minimum time between chatbox messages
+    fn chatbox_cooldown(&self) -> SignedDuration {
+        SignedDuration::from_secs_f64(self.options.chatbox_cooldown_secs)
+    }
+
+    /// Time until the chatbox gets cleared after a message, if enabled
+    fn clear_delay(&self) -> Option<SignedDuration> {
+        self.options
+            .clear_chatbox
+            .then(|| SignedDuration::from_secs_f64(self.options.chatbox_clear_secs))
+    }
+
+    /// Time before a pending message in which the chatbox is never cleared
+    fn clear_guard(&self) -> SignedDuration {
+        SignedDuration::from_secs_f64(self.options.chatbox_clear_guard_secs)
+    }
+
+    /// Whether we haven't seen any OSC activity for the configured idle
+    /// timeout
+    fn is_idle(&self) -> bool {
+        self.options
+            .idle_timeout
+            .is_some_and(|timeout| Timestamp::now() > self.last_activity.add(timeout))
     }
 
-    /// Whether we should send a chat message again
-    fn should_send_message(&self) -> bool {
-        Timestamp::now() > self.last_message.add(SignedDuration::from_secs(2))
+    /// Whether another booper sends our chatbox messages
+    fn is_follower(&self) -> bool {
+        self.coordination
+            .as_ref()
+            .is_some_and(Coordination::is_follower)
     }
 
-    /// Generate chatbox message
-    fn generate_message(&mut self) -> (String, bool) {
+    /// Whether it's quiet hours, without chatbox messages
+    fn is_quiet(&self) -> bool {
+        let now = jiff::Zoned::now().datetime();
+        self.options
+            .quiet_hours
+            .iter()
+            .any(|quiet_hours| quiet_hours.contains(now))
+    }
+
+    /// Today's and total boops as displayed, respecting the total policy
+    fn displayed_numbers(&self) -> (u32, u64) {
         let (today_boops, total_boops) = self.storage.boop_numbers();
+        match self.options.total_policy {
+            TotalPolicy::Shared => (today_boops, total_boops),
+            TotalPolicy::PerAvatar => (
+                today_boops,
+                self.storage.avatar_total().unwrap_or(total_boops),
+            ),
+        }
+    }
+
+    /// Current chatbox template, one of `message_templates` if set
+    fn message_template(&self) -> &str {
+        let templates = &self.options.message_templates;
+        if templates.is_empty() {
+            return &self.options.message_template;
+        }
+
+        &templates[self.template_index % templates.len()]
+    }
+
+    /// Move on to the next of `message_templates`
+    fn rotate_template(&mut self) {
+        let count = self.options.message_templates.len();
+        if count < 2 {
+            return;
+        }
+
+        self.template_index = match self.options.template_rotation {
+            TemplateRotation::RoundRobin => (self.template_index + 1) % count,
+            TemplateRotation::Random => rand::random_range(0..count),
+        };
+    }
+
+    /// Generate chatbox message from template
+    fn generate_message(&self, message_template: &str) -> (String, bool) {
+        self.render_message(message_template, |_| None)
+    }
+
+    /// Generate chatbox message from template, `vars` take precedence over
+    /// the common variables
+    fn render_message(
+        &self,
+        message_template: &str,
+        vars: impl Fn(&str) -> Option<String>,
+    ) -> (String, bool) {
+        let (today_boops, total_boops) = self.displayed_numbers();
 
         let today_suffix = self
             .generate_text_suffix(today_boops as u64)
@@ -202,17 +1656,195 @@ impl<'a> OscBooper<'a> {
         let total_suffix = self
             .generate_text_suffix(total_boops)
             .map_or("".into(), |suffix| format!(" {suffix}"));
+        let session_suffix = self
+            .generate_text_suffix(self.storage.session_boops() as u64)
+            .map_or("".into(), |suffix| format!(" {suffix}"));
         let is_suffixed = !today_suffix.is_empty() || !total_suffix.is_empty();
 
-        // todo: configurable message template, with some boring validation
-        (
-            format!(
-                "Today: {}{}\nTotal: {}{}",
-                today_boops, today_suffix, total_boops, total_suffix
-            )
-            .to_string(),
-            is_suffixed,
-        )
+        let data = self.data_cache.read().unwrap();
+        let value = |key: &str, suffixes: bool| {
+            vars(key).or_else(|| match key {
+                "today" => Some(today_boops.to_string()),
+                "total" => Some(total_boops.to_string()),
+                "session" => Some(self.storage.session_boops().to_string()),
+                "today_suffix" | "total_suffix" | "session_suffix" if !suffixes => Some("".into()),
+                "today_suffix" => Some(today_suffix.clone()),
+                "total_suffix" => Some(total_suffix.clone()),
+                "session_suffix" => Some(session_suffix.clone()),
+                "week" => Some(self.storage.week_boops().to_string()),
+                "month" => Some(self.storage.month_boops().to_string()),
+                "streak" => Some(self.storage.streak().to_string()),
+                "avatar_today" => Some(self.storage.avatar_today().unwrap_or(0).to_string()),
+                "avatar_total" => Some(self.storage.avatar_total().unwrap_or(0).to_string()),
+                "world" => Some(self.storage.world_name().unwrap_or_default().into()),
+                "world_id" => Some(self.storage.current_world().unwrap_or_default().into()),
+                "world_today" => Some(self.storage.world_today().unwrap_or(0).to_string()),
+                "world_total" => Some(self.storage.world_total().unwrap_or(0).to_string()),
+                "rate" => Some(self.rate.per_minute(Timestamp::now()).to_string()),
+                "leaderboard" => Some(self.leaderboard()),
+                "top_booper" => Some(self.top_booper().unwrap_or_default().into()),
+                _ => self
+                    .counter_value(key)
+                    .or_else(|| self.goal_value(key))
+                    .or_else(|| self.group_value(key.strip_prefix("group:")?))
+                    .or_else(|| data.get(key.strip_prefix("data:")?).cloned()),
+            })
+        };
+        let number = |key: &str| value(key, true)?.parse().ok();
+        let render = |suffixes: bool| {
+            template::render(message_template, |key| {
+                value(key, suffixes).or_else(|| {
+                    if let Some(spec) = key.strip_prefix("progress:") {
+                        return template::progress(spec, number);
+                    }
+
+                    // e.g. `{today:sep}` or `{today:boop}`
+                    let (variable, format) = key.rsplit_once(':')?;
+                    let count = number(variable)?;
+                    template::format_number(format, count, &self.options.thousands_separator)
+                        .or_else(|| i18n::count_word(&self.options, format, count))
+                })
+            })
+        };
+
+        let message = render(true);
+        if self.options.split_long_messages
+            || !self.options.platform.has_chatbox()
+            || message.chars().count() <= template::CHATBOX_MAX_CHARS
+        {
+            return (message, is_suffixed);
+        }
+
+        (self.shorten_message(message, || render(false)), is_suffixed)
+    }
+
+    /// Shorten a message exceeding the chatbox limit by the configured
+    /// `truncation` steps, cutting it at a word boundary if still too long
+    fn shorten_message(&self, message: String, without_suffixes: impl Fn() -> String) -> String {
+        let (length, limit) = (message.chars().count(), template::CHATBOX_MAX_CHARS);
+        let (mut suffixes, mut compress) = (true, false);
+        let mut shortened = message;
+
+        for step in &self.options.truncation {
+            match step {
+                TruncationStep::DropSuffixes => suffixes = false,
+                TruncationStep::CompressWhitespace => compress = true,
+            }
+            if !suffixes {
+                shortened = without_suffixes();
+            }
+            if compress {
+                shortened = template::compress_whitespace(&shortened);
+            }
+            if shortened.chars().count() <= limit {
+                break;
+            }
+        }
+
+        warn!(length, limit, "shortening too long chatbox message");
+        template::truncate_words(&shortened, limit)
+    }
+
+    /// Announce the goals passed by the boop that raised the counts to
+    /// `today` and `total`
+    async fn announce_goals(&mut self, today: u32, total: u64) {
+        let Some(goals) = self.options.goals.clone() else {
+            return;
+        };
+
+        let scopes = [
+            ("daily", goals.daily, today as u64),
+            ("lifetime", goals.lifetime, total),
+        ];
+        for (scope, goal, count) in scopes {
+            let Some(goal) = goal else {
+                continue;
+            };
+            let previous = count.saturating_sub(1);
+            let Some(percent) = goals::crossed(previous, count, goal, &goals.announce_at) else {
+                continue;
+            };
+
+            info!(scope, goal, percent, "goal progress");
+            let template = if percent >= 100 {
+                &goals.reached_template
+            } else {
+                &goals.message_template
+            };
+            let (message, _) = self.render_message(template, |key| match key {
+                "scope" => Some(scope.into()),
+                "goal" => Some(goal.to_string()),
+                "percent" => Some(percent.to_string()),
+                "remaining" => Some(goal.saturating_sub(count).to_string()),
+                _ => None,
+            });
+            self.send_message(message, Priority::Important).await;
+        }
+    }
+
+    /// Today's top boopers, one line each
+    fn leaderboard(&self) -> String {
+        let options = &self.options.leaderboard;
+        let lines: Vec<_> = self
+            .storage
+            .leaderboard(options.size)
+            .into_iter()
+            .enumerate()
+            .map(|(index, (name, booper))| {
+                template::render(&options.entry_template, |key| match key {
+                    "rank" => Some((index + 1).to_string()),
+                    "name" => Some(name.into()),
+                    "today" => Some(booper.today.to_string()),
+                    "total" => Some(booper.total.to_string()),
+                    _ => None,
+                })
+            })
+            .collect();
+
+        lines.join("\n")
+    }
+
+    /// Today's top booper, if anyone booped yet
+    fn top_booper(&self) -> Option<&str> {
+        let (name, _) = self.storage.leaderboard(1).into_iter().next()?;
+        Some(name)
+    }
+
+    /// Value of `{<counter>_today}` or `{<counter>_total}`
+    fn counter_value(&self, key: &str) -> Option<String> {
+        let (name, field) = key.rsplit_once('_')?;
+        self.options.counters.iter().find(|c| c.name == name)?;
+
+        let counts = self.storage.counter(name);
+        match field {
+            "today" => Some(counts.today.to_string()),
+            "total" => Some(counts.total.to_string()),
+            _ => None,
+        }
+    }
+
+    /// Value of goal variables like `{remaining}`
+    fn goal_value(&self, key: &str) -> Option<String> {
+        let goals = self.options.goals.as_ref()?;
+        let (today, total) = self.storage.boop_numbers();
+        goals::value(goals, key, today as u64, total)
+    }
+
+    /// Value of `{group:<name>.today}` or `{group:<name>.total}`
+    fn group_value(&self, key: &str) -> Option<String> {
+        let (name, field) = key.rsplit_once('.')?;
+        let group = self
+            .options
+            .counter_groups
+            .iter()
+            .find(|g| g.name == name)?;
+
+        let counts = self.storage.group(&group.counters);
+        match field {
+            "today" => Some(counts.today.to_string()),
+            "total" => Some(counts.total.to_string()),
+            _ => None,
+        }
     }
 
     /// Loop over registered [`crate::config::TextSuffix`]es and return first
@@ -230,64 +1862,245 @@ impl<'a> OscBooper<'a> {
     }
 }
 
-/// send empty message to chatbox after main message has been sent
-async fn clear_chatbox_loop(
-    mut rx: tokio::sync::mpsc::Receiver<()>,
-    socket: Arc<UdpSocket>,
-    addr: SocketAddr,
+/// Collect messages of bundles that are due, in order, and bundles with a
+/// future timetag together with their delay
+fn unpack_bundle(
+    bundle: OscBundle,
+    due: &mut Vec<OscMessage>,
+    delayed: &mut Vec<(Duration, OscBundle)>,
 ) {
-    let debounce_mutex: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>> =
-        Arc::new(Mutex::new(None));
+    if is_stale(bundle.timetag) {
+        metrics::STALE_BUNDLES.inc();
+        debug!("dropping bundle with an old timetag, possibly replayed");
+        return;
+    }
 
-    while rx.recv().await.is_some() {
-        let mut task = debounce_mutex.lock().await;
+    if let Some(delay) = timetag_delay(bundle.timetag) {
+        delayed.push((delay, bundle));
+        return;
+    }
 
-        // cancel running thread
-        if let Some(handle) = task.take() {
-            trace!("cancelling existing clear task");
-            handle.abort();
+    for packet in bundle.content {
+        match packet {
+            OscPacket::Message(msg) => due.push(msg),
+            OscPacket::Bundle(nested) => unpack_bundle(nested, due, delayed),
         }
+    }
+}
 
-        // wait a bit and then send clear
-        let socket_clone = socket.clone();
-        *task = Some(tokio::spawn(async move {
-            trace!("waiting for clear timeout");
-            tokio::time::sleep(Duration::from_secs(4)).await;
-            publish_chatbox(&socket_clone, addr, "".into()).await;
-            trace!("sent chatbox clear");
-        }));
+/// Time until a timetag is due, None if it is immediate or in the past
+fn timetag_delay(timetag: OscTime) -> Option<Duration> {
+    if timetag == OSC_TIME_IMMEDIATELY {
+        return None;
     }
+
+    SystemTime::from(timetag)
+        .duration_since(SystemTime::now())
+        .ok()
+        .filter(|delay| !delay.is_zero())
 }
 
-/// create buffer for OSC chatbox message
-/// https://docs.vrchat.com/docs/osc-as-input-controller
-fn make_msg_buffer(message: String) -> Option<Vec<u8>> {
-    let packet = OscPacket::Message(OscMessage {
-        addr: "/chatbox/input".into(),
-        args: vec![
-            // message
-            OscType::String(message),
-            // send immediately, bypass keyboard input
-            OscType::Bool(true),
-            // don't trigger SFX
-            OscType::Bool(false),
-        ],
-    });
+/// Filter for received packets, unless the HTTP API inspects all parameters
+fn address_filter(options: &Options) -> Option<AddressFilter> {
+    options
+        .http_port
+        .is_none()
+        .then(|| AddressFilter::new(options))
+}
 
-    match rosc::encoder::encode(&packet) {
-        Ok(buf) => Some(buf),
+/// Whether a timetag is too far in the past, so its bundle may be replayed
+fn is_stale(timetag: OscTime) -> bool {
+    if timetag == OSC_TIME_IMMEDIATELY {
+        return false;
+    }
+
+    SystemTime::now()
+        .duration_since(SystemTime::from(timetag))
+        .is_ok_and(|age| age > MAX_BUNDLE_AGE)
+}
+
+/// Whether receiving failed as the datagram exceeded the buffer
+///
+/// Windows reports this as error, other platforms silently truncate
+fn is_truncated(e: &std::io::Error) -> bool {
+    // WSAEMSGSIZE
+    cfg!(windows) && e.raw_os_error() == Some(10040)
+}
+
+/// Whether VRChat's port is closed, reported on the next receive after
+/// sending to it on Windows
+fn is_unreachable(e: &std::io::Error) -> bool {
+    matches!(
+        e.kind(),
+        std::io::ErrorKind::ConnectionReset | std::io::ErrorKind::ConnectionRefused
+    )
+}
+
+/// Address our OSC socket is bound to
+fn local_addr(socket: &UdpSocket) -> Result<SocketAddr, BooperError> {
+    socket.local_addr().map_err(|source| BooperError::Socket {
+        what: "osc socket",
+        source,
+    })
+}
+
+/// Sleep until `time`, forever without one
+async fn sleep_until(time: Option<Timestamp>) {
+    let Some(time) = time else {
+        return std::future::pending().await;
+    };
+
+    let delay = time.duration_since(Timestamp::now());
+    tokio::time::sleep(delay.max(SignedDuration::ZERO).unsigned_abs()).await;
+}
+
+/// Destination of sent packets
+#[derive(Debug, Clone)]
+enum Outbound {
+    Udp(Arc<UdpSocket>, SocketAddr),
+    Tcp(tcp::TcpSender),
+}
+
+/// encode messages into packet buffers
+///
+/// if `bundle` is set, multiple messages are combined into a single bundle
+/// with an immediate timetag, so they are applied together by the receiver
+fn encode_messages(messages: Vec<OscMessage>, bundle: bool) -> Vec<Vec<u8>> {
+    let packets = if bundle && messages.len() > 1 {
+        vec![OscPacket::Bundle(OscBundle {
+            timetag: OSC_TIME_IMMEDIATELY,
+            content: messages.into_iter().map(OscPacket::Message).collect(),
+        })]
+    } else {
+        messages.into_iter().map(OscPacket::Message).collect()
+    };
+
+    packets
+        .iter()
+        .filter_map(|packet| match rosc::encoder::encode(packet) {
+            Ok(buf) => Some(buf),
+            Err(e) => {
+                error!(err=%e, "failed to encode packet");
+                None
+            }
+        })
+        .collect()
+}
+
+/// send messages to the OSC receiver
+async fn publish(outbound: &Outbound, messages: Vec<OscMessage>, bundle: bool) -> bool {
+    let mut sent = true;
+    for buf in encode_messages(messages, bundle) {
+        sent &= send_packet(outbound, &buf).await;
+    }
+    sent
+}
+
+/// send encoded packet, recording it if capturing, false if it failed
+async fn send_packet(outbound: &Outbound, buf: &[u8]) -> bool {
+    let (socket, addr) = match outbound {
+        Outbound::Udp(socket, addr) => (socket, *addr),
+        Outbound::Tcp(sender) => {
+            sender.send(buf);
+            return true;
+        }
+    };
+
+    match socket.send_to(buf, addr).await {
+        Ok(_) => {
+            if let Ok(local_addr) = socket.local_addr() {
+                capture::record(local_addr, addr, buf);
+            }
+            true
+        }
         Err(e) => {
-            error!(err=%e, "failed to encode chatbox packet");
-            None
+            error!(err=%e, "failed to send message");
+            false
         }
     }
 }
 
-/// send string to VRChat chatbox
-async fn publish_chatbox(socket: &UdpSocket, addr: SocketAddr, message: String) {
-    if let Some(msg_buf) = make_msg_buffer(message) {
-        if let Err(e) = socket.send_to(&msg_buf, addr).await {
-            error!(err=%e, "failed to send message to chatbox");
+/// send messages as bundle with a timetag `delay` in the future
+///
+/// if `hold_until_due` is set, the bundle is only sent once due, for receivers
+/// ignoring timetags
+async fn publish_scheduled(
+    outbound: Outbound,
+    messages: Vec<OscMessage>,
+    delay: SignedDuration,
+    hold_until_due: bool,
+) {
+    let due = SystemTime::now() + delay.unsigned_abs();
+    let timetag = match OscTime::try_from(due) {
+        Ok(timetag) => timetag,
+        Err(e) => {
+            error!(err=?e, "failed to create timetag");
+            return;
         }
+    };
+
+    if hold_until_due {
+        tokio::time::sleep(delay.unsigned_abs()).await;
+    }
+
+    let packet = OscPacket::Bundle(OscBundle {
+        timetag,
+        content: messages.into_iter().map(OscPacket::Message).collect(),
+    });
+    match rosc::encoder::encode(&packet) {
+        Ok(buf) => {
+            send_packet(&outbound, &buf).await;
+        }
+        Err(e) => {
+            error!(err=%e, "failed to encode scheduled bundle");
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{ParameterKind, ParameterOutput, default_echo_window};
+
+    #[test]
+    fn test_boop_is_one_bundle() {
+        let options = Options {
+            bundle_outputs: true,
+            parameter_outputs: vec![ParameterOutput {
+                parameter: "BoopsToday".into(),
+                count: CountKind::Today,
+                kind: ParameterKind::Int,
+                modulo: None,
+                scale: 1.0,
+                echo_window: default_echo_window(),
+            }],
+            ..Default::default()
+        };
+
+        // as put together by `deliver_message` for a boop
+        let mut messages = output::text_messages(&options, "7 boops".into(), 7, 1234);
+        messages.extend(output::parameter_messages(
+            &options.parameter_outputs,
+            7,
+            1234,
+        ));
+
+        let packets = encode_messages(messages, options.bundle_outputs);
+        assert_eq!(packets.len(), 1);
+        let Ok((_, OscPacket::Bundle(bundle))) = rosc::decoder::decode_udp(&packets[0]) else {
+            panic!("boop not sent as bundle");
+        };
+        let addresses: Vec<_> = bundle
+            .content
+            .iter()
+            .filter_map(|packet| match packet {
+                OscPacket::Message(message) => Some(message.addr.as_str()),
+                OscPacket::Bundle(_) => None,
+            })
+            .collect();
+        assert_eq!(
+            addresses,
+            ["/chatbox/input", "/avatar/parameters/BoopsToday"]
+        );
     }
 }
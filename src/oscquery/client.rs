@@ -0,0 +1,560 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+    time::Duration,
+};
+
+use serde_json::Value;
+use tokio::{
+    net::UdpSocket,
+    sync::{
+        Notify,
+        broadcast::{self, error::RecvError},
+        watch,
+    },
+    time::Instant,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use super::{
+    mdns::{self, IPV4_MDNS, MDNS_PORT, TYPE_A, TYPE_AAAA, TYPE_PTR, TYPE_SRV},
+    tree::Node,
+};
+use crate::{
+    command::{Command, CommandSender},
+    events::BooperEvent,
+    parameters::PARAMETER_PREFIX,
+};
+
+/// Service type VRChat announces its OSCQuery server as
+const SERVICE_TYPE: &str = "_oscjson._tcp.local";
+
+/// VRChat's service instances start with this
+const VRCHAT_PREFIX: &str = "vrchat-client-";
+
+/// Wait between discovery queries while VRChat isn't found
+const QUERY_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Wait between checks whether VRChat's OSCQuery server still responds
+const HEALTH_CHECK_INTERVAL: Duration = Duration::from_secs(10);
+
+/// How long `list-parameters` looks for VRChat
+const DISCOVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// VRChat updates its tree shortly after announcing the avatar change
+const AVATAR_CHANGE_DELAY: Duration = Duration::from_secs(2);
+
+/// Parameters of the current avatar, their OSC type tag by address
+pub(crate) type Schema = BTreeMap<String, String>;
+
+/// VRChat's OSCQuery service
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct Service {
+    /// service instance name, e.g. `vrchat-client-a1b2c3._oscjson._tcp.local`
+    instance: String,
+    addr: SocketAddr,
+}
+
+/// Records of VRChat's service, collected from mDNS responses
+#[derive(Debug, Default)]
+struct Discovery {
+    /// service instance names, lowercase like all names
+    instances: Vec<String>,
+    /// host name and port by instance name
+    services: HashMap<String, (String, u16)>,
+    addresses: HashMap<String, IpAddr>,
+}
+
+impl Discovery {
+    /// Take the records of VRChat's service from a raw mDNS message
+    fn add(&mut self, data: &[u8]) {
+        let Some(records) = mdns::records(data) else {
+            return;
+        };
+
+        for record in records {
+            let name = record.name.to_lowercase();
+            let rdata = &data[record.data.clone()];
+            match record.record_type {
+                TYPE_PTR if name == SERVICE_TYPE => {
+                    let instance = mdns::read_name(data, record.data.start)
+                        .map(|(instance, _)| instance.to_lowercase())
+                        .filter(|instance| {
+                            instance.starts_with(VRCHAT_PREFIX)
+                                && !self.instances.contains(instance)
+                        });
+                    self.instances.extend(instance);
+                }
+                TYPE_SRV if rdata.len() > 6 => {
+                    let port = u16::from_be_bytes([rdata[4], rdata[5]]);
+                    if let Some((host, _)) = mdns::read_name(data, record.data.start + 6) {
+                        self.services.insert(name, (host.to_lowercase(), port));
+                    }
+                }
+                TYPE_A => {
+                    if let Ok(octets) = <[u8; 4]>::try_from(rdata) {
+                        self.addresses.insert(name, Ipv4Addr::from(octets).into());
+                    }
+                }
+                TYPE_AAAA => {
+                    if let Ok(octets) = <[u8; 16]>::try_from(rdata) {
+                        // prefer IPv4, like VRChat's own OSC
+                        self.addresses
+                            .entry(name)
+                            .or_insert(Ipv6Addr::from(octets).into());
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    /// VRChat's service, once all its records are known
+    fn resolved(&self) -> Option<Service> {
+        self.instances.iter().find_map(|instance| {
+            let (host, port) = self.services.get(instance)?;
+            let ip = self.addresses.get(host)?;
+            Some(Service {
+                instance: instance.clone(),
+                addr: (*ip, *port).into(),
+            })
+        })
+    }
+
+    /// Questions for the records still missing
+    fn questions(&self) -> Vec<(&str, u16)> {
+        let mut questions = vec![(SERVICE_TYPE, TYPE_PTR)];
+        for instance in &self.instances {
+            match self.services.get(instance) {
+                Some((host, _)) => questions.push((host.as_str(), TYPE_A)),
+                None => questions.push((instance.as_str(), TYPE_SRV)),
+            }
+        }
+        questions
+    }
+}
+
+/// Look for VRChat's OSCQuery server via mDNS until found, `None` if
+/// cancelled first
+pub(crate) async fn discover(token: &CancellationToken) -> Option<Service> {
+    let socket = match mdns::create_mdns_socket() {
+        Ok(socket) => socket,
+        Err(e) => {
+            error!(err=%e, "failed to create mDNS socket, can't look for VRChat");
+            return None;
+        }
+    };
+
+    let mut discovery = Discovery::default();
+    let mut buf = [0u8; 1500];
+    loop {
+        send_query(&socket, &discovery).await;
+
+        let deadline = Instant::now() + QUERY_INTERVAL;
+        loop {
+            let received = tokio::select! {
+                _ = token.cancelled() => return None,
+                _ = tokio::time::sleep_until(deadline) => break,
+                result = socket.recv_from(&mut buf) => result,
+            };
+            match received {
+                Ok((n, _)) => discovery.add(&buf[..n]),
+                Err(e) => error!(err=%e, "error receiving from socket"),
+            }
+
+            if let Some(service) = discovery.resolved() {
+                info!("found VRChat's OSCQuery server on {}", service.addr);
+                return Some(service);
+            }
+        }
+    }
+}
+
+async fn send_query(socket: &UdpSocket, discovery: &Discovery) {
+    let query = match mdns::query(&discovery.questions()) {
+        Ok(query) => query,
+        Err(e) => {
+            error!(err=%e, "failed to serialize mDNS query");
+            return;
+        }
+    };
+    if let Err(e) = socket.send_to(&query, (IPV4_MDNS, MDNS_PORT)).await {
+        error!(err=%e, "failed to send mDNS query");
+    }
+}
+
+/// Fetch the parameters of the current avatar from VRChat's OSCQuery server
+pub(crate) async fn fetch_schema(
+    client: &reqwest::Client,
+    addr: SocketAddr,
+) -> Result<Schema, String> {
+    let tree = client
+        .get(format!("http://{addr}/avatar"))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json::<Value>()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let mut schema = Schema::new();
+    collect_parameters(&tree, &mut schema);
+    Ok(schema)
+}
+
+/// Add the parameters of an OSCQuery node and its children to `schema`
+fn collect_parameters(node: &Value, schema: &mut Schema) {
+    let path = node.get("FULL_PATH").and_then(Value::as_str);
+    let type_tag = node.get("TYPE").and_then(Value::as_str);
+    match (path, type_tag) {
+        (Some(path), Some(type_tag)) if path.starts_with(PARAMETER_PREFIX) => {
+            schema.insert(path.into(), type_tag.into());
+        }
+        _ => {}
+    }
+
+    if let Some(Value::Object(contents)) = node.get("CONTENTS") {
+        for child in contents.values() {
+            collect_parameters(child, schema);
+        }
+    }
+}
+
+/// Follow VRChat across restarts, mirroring the parameters of its current
+/// avatar to check our contact addresses against them
+///
+/// VRChat is discovered via mDNS. Once its OSCQuery server stops responding
+/// or it says goodbye via mDNS, the main loop holds chatbox messages and
+/// VRChat is looked for again. A restarted VRChat gets announced to right
+/// away and its OSC port is followed.
+///
+/// The parameters are fetched again on every avatar change, the contacts are
+/// checked whenever `nodes` change.
+pub(crate) fn spawn(
+    token: CancellationToken,
+    mut nodes: watch::Receiver<Vec<Node>>,
+    mut events: broadcast::Receiver<BooperEvent>,
+    commands: CommandSender,
+    reannounce: Arc<Notify>,
+) {
+    let client = match http_client() {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(err=%e, "failed to create http client, not following VRChat");
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        let mut restarted = false;
+        loop {
+            let Some(vrchat) = discover(&token).await else {
+                break;
+            };
+            // VRChat only looks for services on startup
+            if restarted {
+                reannounce.notify_one();
+            }
+            restarted = true;
+
+            let follower = Follower {
+                token: &token,
+                client: &client,
+                vrchat: &vrchat,
+                commands: &commands,
+            };
+            let Some(reason) = follower.run(&mut nodes, &mut events).await else {
+                break;
+            };
+            if commands.send(Command::VrchatLost { reason }).await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Keeps track of a discovered VRChat
+struct Follower<'a> {
+    token: &'a CancellationToken,
+    client: &'a reqwest::Client,
+    vrchat: &'a Service,
+    commands: &'a CommandSender,
+}
+
+impl Follower<'_> {
+    /// Follow VRChat until it's gone, returns why, `None` once cancelled
+    async fn run(
+        &self,
+        nodes: &mut watch::Receiver<Vec<Node>>,
+        events: &mut broadcast::Receiver<BooperEvent>,
+    ) -> Option<&'static str> {
+        let socket = match mdns::create_mdns_socket() {
+            Ok(socket) => Some(socket),
+            Err(e) => {
+                warn!(err=%e, "failed to create mDNS socket, VRChat's goodbye goes unnoticed");
+                None
+            }
+        };
+        let mut buf = [0u8; 1500];
+
+        let mut schema = self.refresh().await;
+        if let Some(schema) = &schema {
+            validate(schema, &nodes.borrow_and_update());
+        }
+
+        let mut osc_port = None;
+        let mut health_check = tokio::time::interval(HEALTH_CHECK_INTERVAL);
+        loop {
+            tokio::select! {
+                _ = self.token.cancelled() => return None,
+                _ = health_check.tick() => {
+                    let Ok(port) = osc_port_of(self.client, self.vrchat.addr).await else {
+                        return Some("OSCQuery server stopped responding");
+                    };
+                    if osc_port != Some(port) {
+                        osc_port = Some(port);
+                        let found = Command::VrchatFound {
+                            addr: self.vrchat.addr.ip(),
+                            osc_port: port,
+                        };
+                        self.commands.send(found).await.ok()?;
+                    }
+                }
+                Ok((n, _)) = mdns::recv_from(socket.as_ref(), &mut buf) => {
+                    if is_goodbye(&buf[..n], &self.vrchat.instance) {
+                        return Some("VRChat said goodbye via mDNS");
+                    }
+                }
+                Ok(()) = nodes.changed() => {
+                    if let Some(schema) = &schema {
+                        validate(schema, &nodes.borrow_and_update());
+                    }
+                }
+                event = events.recv() => match event {
+                    Ok(BooperEvent::AvatarChanged { .. }) => {
+                        tokio::time::sleep(AVATAR_CHANGE_DELAY).await;
+                        schema = self.refresh().await;
+                        if let Some(schema) = &schema {
+                            validate(schema, &nodes.borrow_and_update());
+                        }
+                    }
+                    Ok(_) => {}
+                    Err(RecvError::Lagged(skipped)) => {
+                        debug!(skipped, "avatar parameter checks lagging behind");
+                    }
+                    Err(RecvError::Closed) => return None,
+                },
+            }
+        }
+    }
+
+    /// Fetch the parameters of the current avatar
+    async fn refresh(&self) -> Option<Schema> {
+        match fetch_schema(self.client, self.vrchat.addr).await {
+            Ok(schema) => {
+                debug!(parameters = schema.len(), "fetched avatar parameters");
+                Some(schema)
+            }
+            Err(e) => {
+                warn!(err=%e, "failed to fetch avatar parameters from VRChat");
+                None
+            }
+        }
+    }
+}
+
+/// Ask VRChat's OSCQuery server which port it receives OSC on
+async fn osc_port_of(client: &reqwest::Client, addr: SocketAddr) -> Result<u16, String> {
+    let host_info = client
+        .get(format!("http://{addr}/?HOST_INFO"))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json::<Value>()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    host_info
+        .get("OSC_PORT")
+        .and_then(Value::as_u64)
+        .and_then(|port| u16::try_from(port).ok())
+        .ok_or_else(|| "no OSC_PORT in host info".into())
+}
+
+/// Whether the raw mDNS message withdraws the service `instance`
+fn is_goodbye(data: &[u8], instance: &str) -> bool {
+    let Some(records) = mdns::records(data) else {
+        return false;
+    };
+    records
+        .iter()
+        .filter(|record| record.ttl == 0)
+        .any(|record| {
+            let name = match record.record_type {
+                TYPE_PTR => mdns::read_name(data, record.data.start).map(|(name, _)| name),
+                _ => Some(record.name.clone()),
+            };
+            name.is_some_and(|name| name.eq_ignore_ascii_case(instance))
+        })
+}
+
+/// Warn about contact addresses the current avatar doesn't have, suggesting
+/// similar ones, or has with another type
+fn validate(schema: &Schema, nodes: &[Node]) {
+    // no avatar loaded, or one without parameters
+    if schema.is_empty() {
+        return;
+    }
+
+    let contacts = nodes
+        .iter()
+        .filter(|node| node.path.starts_with(PARAMETER_PREFIX) && !node.type_tag.is_empty());
+    for node in contacts {
+        match schema.get(&node.path) {
+            Some(type_tag) if type_tag != node.type_tag => warn!(
+                expected = node.type_tag,
+                actual = %type_tag,
+                "avatar parameter {} has another type than configured",
+                node.path
+            ),
+            Some(_) => {}
+            None => match closest(&node.path, schema) {
+                Some(similar) => warn!(
+                    "current avatar has no parameter {}, did you mean {similar}?",
+                    node.path
+                ),
+                None => warn!("current avatar has no parameter {}", node.path),
+            },
+        }
+    }
+}
+
+/// Most similar address of `schema`, if any is close enough to be a typo
+fn closest<'a>(address: &str, schema: &'a Schema) -> Option<&'a str> {
+    let address = address.to_lowercase();
+    schema
+        .keys()
+        .map(|candidate| {
+            (
+                edit_distance(&address, &candidate.to_lowercase()),
+                candidate,
+            )
+        })
+        .filter(|(distance, _)| *distance <= 3)
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate.as_str())
+}
+
+/// Levenshtein distance between `a` and `b`
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+
+    for (i, a) in a.chars().enumerate() {
+        let mut current = vec![i + 1];
+        for (j, b) in b.iter().enumerate() {
+            let substitution = previous[j] + usize::from(a != *b);
+            current.push(substitution.min(previous[j + 1] + 1).min(current[j] + 1));
+        }
+        previous = current;
+    }
+    previous[b.len()]
+}
+
+/// Print the contact receivers of the current avatar, for the
+/// `list-parameters` command
+pub(crate) async fn list_parameters() {
+    let token = CancellationToken::new();
+    let vrchat = tokio::time::timeout(DISCOVERY_TIMEOUT, discover(&token)).await;
+    let Ok(Some(vrchat)) = vrchat else {
+        error!("VRChat not found via mDNS, is it running with OSC enabled?");
+        std::process::exit(1);
+    };
+
+    let schema = match http_client() {
+        Ok(client) => fetch_schema(&client, vrchat.addr).await,
+        Err(e) => Err(e.to_string()),
+    };
+    let schema = match schema {
+        Ok(schema) => schema,
+        Err(e) => {
+            error!(err=%e, "failed to fetch avatar parameters from VRChat");
+            std::process::exit(1);
+        }
+    };
+
+    // contact receivers are bools, or floats in proximity mode
+    for (address, type_tag) in schema {
+        let kind = match type_tag.as_str() {
+            "T" | "F" => "bool",
+            "f" => "float",
+            _ => continue,
+        };
+        let name = address.trim_start_matches(PARAMETER_PREFIX);
+        println!("{name}\t{kind}");
+    }
+}
+
+fn http_client() -> reqwest::Result<reqwest::Client> {
+    reqwest::Client::builder()
+        .user_agent(concat!("osc-booper/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(5))
+        .build()
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+
+    #[test]
+    fn test_collect_parameters() {
+        let tree = json!({
+            "FULL_PATH": "/avatar",
+            "CONTENTS": {
+                "change": { "FULL_PATH": "/avatar/change", "TYPE": "s" },
+                "parameters": {
+                    "FULL_PATH": "/avatar/parameters",
+                    "CONTENTS": {
+                        "OSCBoop": { "FULL_PATH": "/avatar/parameters/OSCBoop", "TYPE": "T" },
+                        "Nested": {
+                            "FULL_PATH": "/avatar/parameters/Nested",
+                            "CONTENTS": {
+                                "Pat": { "FULL_PATH": "/avatar/parameters/Nested/Pat", "TYPE": "f" }
+                            }
+                        }
+                    }
+                }
+            }
+        });
+
+        let mut schema = Schema::new();
+        collect_parameters(&tree, &mut schema);
+        assert_eq!(
+            schema,
+            Schema::from([
+                ("/avatar/parameters/Nested/Pat".into(), "f".into()),
+                ("/avatar/parameters/OSCBoop".into(), "T".into()),
+            ])
+        );
+
+        assert_eq!(
+            closest("/avatar/parameters/OSCBop", &schema),
+            Some("/avatar/parameters/OSCBoop")
+        );
+        assert_eq!(closest("/avatar/parameters/Headpat", &schema), None);
+    }
+
+    #[test]
+    fn test_edit_distance() {
+        assert_eq!(edit_distance("boop", "boop"), 0);
+        assert_eq!(edit_distance("boop", "bop"), 1);
+        assert_eq!(edit_distance("kitten", "sitting"), 3);
+        assert_eq!(edit_distance("", "abc"), 3);
+    }
+}
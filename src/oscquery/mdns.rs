@@ -1,101 +1,258 @@
 use std::{
     collections::HashMap,
-    net::{Ipv4Addr, SocketAddr},
+    io,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr, SocketAddrV6},
+    ops::Range,
+    sync::Arc,
+    time::Duration,
 };
 
 use mdns_proto::{
     error::{BufferType, ProtoError},
-    proto::{Label, Message, MessageType, Question, ResourceRecord, ResourceType, Serialize},
+    proto::{
+        Flags, Label, Message, MessageType, Question, ResourceRecord, ResourceType, Serialize,
+    },
     server::{Endpoint, QueryHandle, SlabEndpoint},
 };
+use rand::{
+    Rng,
+    distr::{Alphanumeric, SampleString},
+};
 use smallvec::SmallVec;
-use tokio::net::UdpSocket;
+use tokio::{net::UdpSocket, sync::Notify, time::Instant};
 use tokio_util::sync::CancellationToken;
 use tracing::{debug, error, info, trace, warn};
 
+use crate::error::BooperError;
+
 // WireShark query
 // (mdns) && (_ws.col.info matches "VRCFT" || _ws.col.info matches "osc-booper")
 
-const IPV4_MDNS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
-const MDNS_PORT: u16 = 5353;
+pub(super) const IPV4_MDNS: Ipv4Addr = Ipv4Addr::new(224, 0, 0, 251);
+const IPV6_MDNS: Ipv6Addr = Ipv6Addr::new(0xff02, 0, 0, 0, 0, 0, 0, 0xfb);
+pub(super) const MDNS_PORT: u16 = 5353;
+
+/// Record TTL, legacy unicast responses are capped to 10 seconds instead
+/// (RFC 6762, section 6.7)
+const TTL: u32 = 120;
+const LEGACY_TTL: u32 = 10;
+
+/// Unsolicited announcements on startup, a second apart (RFC 6762, section
+/// 8.3)
+const STARTUP_ANNOUNCEMENTS: u32 = 2;
+const STARTUP_ANNOUNCEMENT_DELAY: Duration = Duration::from_secs(1);
+
+/// Announce again at 80% of the TTL, before caches drop our records
+const REANNOUNCE_INTERVAL: Duration = Duration::from_secs(TTL as u64 * 4 / 5);
+
+/// Service records, answering queries for the service type
+const SERVICE_TYPE: &str = "_oscjson._tcp.local";
+
+/// Top bit of a question's class, asking for a unicast response
+const UNICAST_RESPONSE_BIT: u16 = 0x8000;
+
+/// Probe queries sent before claiming a name, 250ms apart (RFC 6762,
+/// section 8.1)
+const PROBES: u32 = 3;
+const PROBE_DELAY: Duration = Duration::from_millis(250);
+
+/// Names tried before announcing the last one regardless
+const MAX_NAME_ATTEMPTS: u32 = 10;
+
+/// Record types and class used in probes and client queries
+pub(super) const TYPE_A: u16 = 1;
+pub(super) const TYPE_PTR: u16 = 12;
+pub(super) const TYPE_SRV: u16 = 33;
+pub(super) const TYPE_AAAA: u16 = 28;
+const TYPE_ANY: u16 = 255;
+const CLASS_IN: u16 = 1;
+
+/// Resource record of a received message, its data still in the message, as
+/// it may point to names in there
+pub(super) struct Record {
+    /// owner name, without the root label
+    pub(super) name: String,
+    pub(super) record_type: u16,
+    /// zero for goodbyes
+    pub(super) ttl: u32,
+    pub(super) data: Range<usize>,
+}
+
+/// Where responses to a query are sent
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum ReplyTo {
+    /// the mDNS group of the querier's address family
+    Multicast,
+    /// the querier, which set the QU bit on its questions
+    Unicast,
+    /// the querier, a plain DNS resolver not sending from port 5353
+    Legacy,
+}
+
+impl ReplyTo {
+    /// Pick response destination for a query, per RFC 6762 sections 5.4
+    /// and 6.7
+    fn for_query(peer: SocketAddr, data: &[u8]) -> Self {
+        if peer.port() != MDNS_PORT {
+            ReplyTo::Legacy
+        } else if wants_unicast(data) {
+            ReplyTo::Unicast
+        } else {
+            ReplyTo::Multicast
+        }
+    }
+}
 
 /// create cross-platform reusable UDP socket for mDNS listening
-fn create_mdns_socket() -> UdpSocket {
+pub(super) fn create_mdns_socket() -> io::Result<UdpSocket> {
     // create reusable UDP socket (please look away)
     let socket2_socket = socket2::Socket::new(
         socket2::Domain::IPV4,
         socket2::Type::DGRAM,
         Some(socket2::Protocol::UDP),
-    )
-    .map_err(|e| error!(err=%e, "failed to create unbound socket"))
-    .unwrap();
-    socket2_socket
-        .set_reuse_address(true)
-        .map_err(|e| error!(err=%e, "failed to set SO_REUSEADDR on socket"))
-        .unwrap();
+    )?;
+    socket2_socket.set_reuse_address(true)?;
 
     // bind socket to any interface on port 5353
     let addr: SocketAddr = (Ipv4Addr::UNSPECIFIED, MDNS_PORT).into();
-    socket2_socket
-        .bind(&addr.into())
-        .map_err(|e| error!(err=%e, "failed to set bind socket to interface"))
-        .unwrap();
+    socket2_socket.bind(&addr.into())?;
 
     // join multicast
-    socket2_socket
-        .join_multicast_v4(&IPV4_MDNS, &Ipv4Addr::UNSPECIFIED)
-        .map_err(|e| error!(err=%e, "failed to join multicast group"))
-        .unwrap();
-    socket2_socket
-        .set_multicast_loop_v4(true)
-        .map_err(|e| {
-            error!(err=%e, "failed to set IP_MULTICAST_LOOP on socket");
-        })
-        .unwrap();
+    socket2_socket.join_multicast_v4(&IPV4_MDNS, &Ipv4Addr::UNSPECIFIED)?;
+    socket2_socket.set_multicast_loop_v4(true)?;
+
+    into_tokio_socket(socket2_socket)
+}
+
+/// create reusable UDP socket joined to the IPv6 mDNS group
+///
+/// Unlike IPv4, failing here isn't fatal, IPv6 may be disabled entirely
+fn create_mdns_socket_v6() -> io::Result<UdpSocket> {
+    let socket2_socket = socket2::Socket::new(
+        socket2::Domain::IPV6,
+        socket2::Type::DGRAM,
+        Some(socket2::Protocol::UDP),
+    )?;
+    socket2_socket.set_reuse_address(true)?;
+    // keep IPv4 traffic on the other socket
+    socket2_socket.set_only_v6(true)?;
+
+    let addr: SocketAddr = (Ipv6Addr::UNSPECIFIED, MDNS_PORT).into();
+    socket2_socket.bind(&addr.into())?;
+
+    // interface 0 lets the OS pick the default one
+    socket2_socket.join_multicast_v6(&IPV6_MDNS, 0)?;
+    socket2_socket.set_multicast_loop_v6(true)?;
+
+    into_tokio_socket(socket2_socket)
+}
 
+fn into_tokio_socket(socket2_socket: socket2::Socket) -> io::Result<UdpSocket> {
     // convert to std socket, for tokio
     let std_socket = std::net::UdpSocket::from(socket2_socket);
-    std_socket
-        .set_nonblocking(true)
-        .map_err(|e| error!(err=%e, "failed to mark socket as non-blocking"))
-        .unwrap();
-
+    std_socket.set_nonblocking(true)?;
     UdpSocket::from_std(std_socket)
-        .map_err(|e| error!(err=%e, "failed to convert std socket to tokio socket"))
-        .unwrap()
 }
 
-pub(crate) struct MdnsServer<'a> {
+pub struct MdnsServer<'a> {
     endpoint: SlabEndpoint,
     socket: UdpSocket,
+    /// answers queries from IPv6 peers, if IPv6 is available
+    socket_v6: Option<UdpSocket>,
     known_records: HashMap<&'a str, Vec<ResourceRecord<'a>>>,
+    /// same as `known_records`, with the TTL of legacy unicast responses
+    legacy_records: HashMap<&'a str, Vec<ResourceRecord<'a>>>,
+    /// unsolicited response with all records, sent on startup and before
+    /// they expire
+    announcement: Vec<u8>,
+    /// announcement with a TTL of zero, sent on shutdown to remove us from
+    /// caches
+    goodbye: Vec<u8>,
 }
 
 impl<'a> MdnsServer<'a> {
-    pub(crate) fn new(service_name: &str, http_port: u16) -> Self {
-        let socket = create_mdns_socket();
+    /// Create server announcing the OSCQuery service on `http_port`, with an
+    /// A or AAAA record for each of `ips`
+    ///
+    /// Fails if the IPv4 socket can't be set up, IPv6 is optional.
+    pub fn new(service_name: &str, http_port: u16, ips: &[IpAddr]) -> Result<Self, BooperError> {
+        let socket = create_mdns_socket().map_err(|source| BooperError::Bind {
+            what: "mDNS socket",
+            addr: (Ipv4Addr::UNSPECIFIED, MDNS_PORT).into(),
+            source,
+        })?;
         debug!("created mDNS socket");
 
-        let mut this = MdnsServer {
-            socket,
-            endpoint: Endpoint::new(),
-            known_records: HashMap::default(),
+        let socket_v6 = match create_mdns_socket_v6() {
+            Ok(socket) => {
+                debug!("created IPv6 mDNS socket");
+                Some(socket)
+            }
+            Err(e) => {
+                warn!(err=%e, "failed to create IPv6 mDNS socket, only answering IPv4 queries");
+                None
+            }
         };
-        this.create_records(service_name, http_port);
 
-        this
+        let known_records = create_records(service_name, http_port, ips, TTL);
+        let packet = announcement(&known_records);
+        let goodbye = announcement(&create_records(service_name, http_port, ips, 0));
+
+        Ok(MdnsServer {
+            socket,
+            socket_v6,
+            endpoint: Endpoint::new(),
+            known_records,
+            legacy_records: create_records(service_name, http_port, ips, LEGACY_TTL),
+            announcement: packet,
+            goodbye,
+        })
     }
 
-    pub(crate) async fn run(&mut self, token: CancellationToken) {
+    /// Answer queries until `token` is cancelled, saying goodbye then
+    ///
+    /// `reannounce` starts over with the startup announcements, e.g. for a
+    /// restarted VRChat.
+    pub async fn run(&mut self, token: CancellationToken, reannounce: Arc<Notify>) {
         info!("starting mDNS server");
 
         let mut buf = [0u8; 1500];
+        let mut buf_v6 = [0u8; 1500];
+        let mut next_announcement = Instant::now();
+        let mut announcements = 0;
 
         let mut listener_loop = async || {
             loop {
-                match self.socket.recv_from(&mut buf).await {
+                let received = tokio::select! {
+                    result = self.socket.recv_from(&mut buf) => result,
+                    result = recv_from(self.socket_v6.as_ref(), &mut buf_v6) => result,
+                    _ = reannounce.notified() => {
+                        debug!("announcing again");
+                        announcements = 0;
+                        next_announcement = Instant::now();
+                        continue;
+                    }
+                    _ = tokio::time::sleep_until(next_announcement) => {
+                        self.multicast(&self.announcement).await;
+                        announcements += 1;
+                        let delay = if announcements < STARTUP_ANNOUNCEMENTS {
+                            STARTUP_ANNOUNCEMENT_DELAY
+                        } else {
+                            REANNOUNCE_INTERVAL
+                        };
+                        next_announcement = Instant::now() + delay;
+                        continue;
+                    }
+                };
+
+                match received {
                     Ok((n, from)) => {
-                        let data = &buf[..n];
+                        // the IPv6 socket only receives from IPv6 peers
+                        let data = if from.is_ipv6() {
+                            &buf_v6[..n]
+                        } else {
+                            &buf[..n]
+                        };
                         self.handle_query(from, data).await;
                     }
                     Err(e) => {
@@ -113,6 +270,29 @@ impl<'a> MdnsServer<'a> {
                 warn!("mDNS server stopped unexpectedly");
             }
         }
+
+        // lets clients drop us right away, instead of after the TTL
+        debug!("sending mDNS goodbye");
+        self.multicast(&self.goodbye).await;
+    }
+
+    /// Send unsolicited message to the mDNS groups
+    async fn multicast(&self, msg: &[u8]) {
+        if msg.is_empty() {
+            return;
+        }
+
+        if let Err(e) = self.socket.send_to(msg, (IPV4_MDNS, MDNS_PORT)).await {
+            error!(err=%e, "failed to send mDNS announcement");
+        }
+        let sent_v6 = match &self.socket_v6 {
+            Some(socket) => socket.send_to(msg, (IPV6_MDNS, MDNS_PORT)).await,
+            None => Ok(0),
+        };
+        if let Err(e) = sent_v6 {
+            error!(err=%e, "failed to send IPv6 mDNS announcement");
+        }
+        trace!(size = msg.len(), "announcement sent off");
     }
 
     async fn handle_query(&mut self, peer: SocketAddr, data: &[u8]) {
@@ -175,15 +355,30 @@ impl<'a> MdnsServer<'a> {
             Ok(q) => q,
         };
 
+        let reply_to = ReplyTo::for_query(peer, data);
         for question in query.questions() {
             let msg = self
-                .lookup_answer(query.query_handle(), peer, *question)
+                .lookup_answer(query.query_handle(), peer, *question, reply_to)
                 .await;
 
             if let Some(msg) = msg {
-                match self.socket.send_to(&msg, (IPV4_MDNS, MDNS_PORT)).await {
+                // answer on the peer's address family
+                let socket = match (peer, &self.socket_v6) {
+                    (SocketAddr::V6(_), Some(socket)) => socket,
+                    _ => &self.socket,
+                };
+                let target: SocketAddr = match (reply_to, peer) {
+                    (ReplyTo::Multicast, SocketAddr::V6(peer)) => {
+                        SocketAddrV6::new(IPV6_MDNS, MDNS_PORT, 0, peer.scope_id()).into()
+                    }
+                    (ReplyTo::Multicast, SocketAddr::V4(_)) => (IPV4_MDNS, MDNS_PORT).into(),
+                    (ReplyTo::Unicast | ReplyTo::Legacy, _) => peer,
+                };
+                let result = socket.send_to(&msg, target).await;
+
+                match result {
                     Ok(bytes_written) => {
-                        trace!(addr=%peer, size=%bytes_written, "response sent off");
+                        trace!(addr=%peer, size=%bytes_written, ?reply_to, "response sent off");
                     }
                     Err(e) => {
                         error!(addr=%peer, err=%e, "failed to send response");
@@ -206,9 +401,15 @@ impl<'a> MdnsServer<'a> {
         qc: QueryHandle,
         peer: SocketAddr,
         question: Question<'_>,
+        reply_to: ReplyTo,
     ) -> Option<Vec<u8>> {
         let service_name = question.name().to_string();
-        let responses = self.known_records.get(service_name.as_str());
+        let records = match reply_to {
+            ReplyTo::Legacy => &self.legacy_records,
+            ReplyTo::Multicast | ReplyTo::Unicast => &self.known_records,
+        };
+        // records are keyed without the root label
+        let responses = records.get(service_name.trim_end_matches('.'));
         if responses.is_none() {
             trace!(addr=%peer, service_name=%service_name, "skipping response");
             return None;
@@ -232,6 +433,11 @@ impl<'a> MdnsServer<'a> {
                 // for some reason mdns_proto does not do this by itself
                 flags.set_qr(MessageType::Reply);
 
+                // legacy resolvers expect their question repeated
+                let mut questions: SmallVec<[Question; 1]> = SmallVec::new();
+                if reply_to == ReplyTo::Legacy {
+                    questions.push(question);
+                }
                 let mut answers: SmallVec<[ResourceRecord; 1]> = SmallVec::new();
                 let mut add_records: SmallVec<[ResourceRecord; 0]> = SmallVec::new();
 
@@ -245,7 +451,7 @@ impl<'a> MdnsServer<'a> {
                 let msg = Message::new(
                     out.id(),
                     flags,
-                    &mut [],
+                    &mut questions,
                     &mut answers,
                     &mut [],
                     &mut add_records,
@@ -267,53 +473,381 @@ impl<'a> MdnsServer<'a> {
             }
         }
     }
+}
 
-    /// create DNS records for OSCJSON service
-    fn create_records(&mut self, service_name: &str, http_port: u16) {
-        // we only announce an _oscjson._tcp service here as only that seems
-        // necessary the oscjson server's response contains the OSC_IP and the
-        // OSC_PORT anyway
-
-        let ttl = 120;
-
-        // I'll consider IPv6 a myth for now
-        let localhost_rdata: &'a [u8] = [127u8, 0, 0, 1].as_slice();
-
-        let oscjson_ptr_name: &'a str = format!("{service_name}.oscjson.tcp.local.").leak();
-        let oscjson_service_name: &'a str = format!("{service_name}._oscjson._tcp.local.").leak();
-
-        let ptr_rdata: &'a mut [u8] = make_dns_label(oscjson_service_name).unwrap().leak();
-        let srv_rdata = make_srv_rdata(0, 0, http_port, oscjson_ptr_name)
-            .unwrap()
-            .leak();
-
-        self.known_records.insert(
-            "_oscjson._tcp.local",
-            vec![
-                ResourceRecord::new("_oscjson._tcp.local", ResourceType::Ptr, 1, ttl, ptr_rdata),
-                ResourceRecord::new(
-                    oscjson_service_name,
-                    ResourceType::Txt,
-                    1,
-                    ttl,
-                    // I know, this is cursed, but it works.
-                    "\x09txtvers=1".as_bytes(),
-                ),
-                ResourceRecord::new(oscjson_service_name, ResourceType::Srv, 1, ttl, srv_rdata),
-                ResourceRecord::new(oscjson_ptr_name, ResourceType::A, 1, ttl, localhost_rdata),
-            ],
-        );
+/// Pick a free service name starting with `prefix`, probing for it first
+/// (RFC 6762, section 8.1)
+///
+/// Another host answering for the name, or probing for it at the same time,
+/// is a conflict, a new random suffix is tried then.
+pub async fn claim_name(
+    prefix: &str,
+    http_port: u16,
+    ips: &[IpAddr],
+) -> Result<String, BooperError> {
+    let socket = create_mdns_socket().map_err(|source| BooperError::Bind {
+        what: "mDNS probing socket",
+        addr: (Ipv4Addr::UNSPECIFIED, MDNS_PORT).into(),
+        source,
+    })?;
+
+    // keeps hosts starting at the same time from probing in lockstep
+    let delay = rand::rng().random_range(Duration::ZERO..PROBE_DELAY);
+    tokio::time::sleep(delay).await;
+
+    let mut service_name = random_name(prefix);
+    for _ in 1..MAX_NAME_ATTEMPTS {
+        if probe(&socket, &service_name, http_port, ips).await {
+            return Ok(service_name);
+        }
+
+        let taken = std::mem::replace(&mut service_name, random_name(prefix));
+        warn!("mDNS name {taken} is taken, trying {service_name}");
+    }
+
+    warn!("no free mDNS name found, announcing {service_name} regardless");
+    Ok(service_name)
+}
+
+fn random_name(prefix: &str) -> String {
+    let random_suffix = Alphanumeric.sample_string(&mut rand::rng(), 8);
+    format!("{prefix}-{random_suffix}")
+}
+
+/// Probe for the records of `service_name`, false on conflicts
+async fn probe(socket: &UdpSocket, service_name: &str, http_port: u16, ips: &[IpAddr]) -> bool {
+    let query = match probe_query(service_name, http_port, ips) {
+        Ok(query) => query,
+        Err(e) => {
+            error!(err=%e, "failed to serialize mDNS probe, skipping it");
+            return true;
+        }
+    };
+    let names = [instance_name(service_name), host_name(service_name)];
+
+    let mut buf = [0u8; 1500];
+    for _ in 0..PROBES {
+        if let Err(e) = socket.send_to(&query, (IPV4_MDNS, MDNS_PORT)).await {
+            error!(err=%e, "failed to send mDNS probe");
+        }
+
+        let deadline = Instant::now() + PROBE_DELAY;
+        loop {
+            let received = tokio::select! {
+                result = socket.recv_from(&mut buf) => result,
+                _ = tokio::time::sleep_until(deadline) => break,
+            };
+            match received {
+                // our own probe, looped back
+                Ok((n, _)) if buf[..n] == query[..] => {}
+                Ok((n, from)) if conflicts(&buf[..n], &names) => {
+                    debug!(addr=%from, "conflicting mDNS records for {service_name}");
+                    return false;
+                }
+                Ok(_) => {}
+                Err(e) => error!(err=%e, "error receiving from socket"),
+            }
+        }
+    }
+
+    trace!("no conflicts for {service_name}");
+    true
+}
+
+/// Query for any records of our names, with the records we're about to
+/// announce as authorities
+fn probe_query(service_name: &str, http_port: u16, ips: &[IpAddr]) -> Result<Vec<u8>, ProtoError> {
+    let instance = make_dns_label(&instance_name(service_name))?;
+    let host = make_dns_label(&host_name(service_name))?;
+
+    let mut authorities = vec![(
+        instance.clone(),
+        TYPE_SRV,
+        make_srv_rdata(0, 0, http_port, &host_name(service_name))?,
+    )];
+    for ip in ips {
+        let (record_type, rdata) = match ip {
+            IpAddr::V4(ip) => (TYPE_A, ip.octets().to_vec()),
+            IpAddr::V6(ip) => (TYPE_AAAA, ip.octets().to_vec()),
+        };
+        authorities.push((host.clone(), record_type, rdata));
+    }
+
+    // id, flags, questions, answers, authorities, additional records
+    let mut query = vec![0, 0, 0, 0, 0, 2, 0, 0];
+    query.extend((authorities.len() as u16).to_be_bytes());
+    query.extend([0, 0]);
+
+    for name in [&instance, &host] {
+        query.extend(name);
+        query.extend(TYPE_ANY.to_be_bytes());
+        query.extend((CLASS_IN | UNICAST_RESPONSE_BIT).to_be_bytes());
+    }
+    for (name, record_type, rdata) in authorities {
+        query.extend(name);
+        query.extend(record_type.to_be_bytes());
+        query.extend(CLASS_IN.to_be_bytes());
+        query.extend(TTL.to_be_bytes());
+        query.extend((rdata.len() as u16).to_be_bytes());
+        query.extend(rdata);
+    }
+    Ok(query)
+}
+
+/// Whether the raw message has records for any of `names`, either as
+/// response of their owner or as probe of another host
+fn conflicts(data: &[u8], names: &[String]) -> bool {
+    let Some(records) = records(data) else {
+        return false;
+    };
+    records.iter().any(|record| {
+        names
+            .iter()
+            .any(|name| record.name.eq_ignore_ascii_case(name.trim_end_matches('.')))
+    })
+}
+
+/// All records of the raw message, skipping its questions
+pub(super) fn records(data: &[u8]) -> Option<Vec<Record>> {
+    let count = |at: usize| Some(u16::from_be_bytes([*data.get(at)?, *data.get(at + 1)?]));
+    let questions = count(4)?;
+    let records = count(6)? as usize + count(8)? as usize + count(10)? as usize;
+
+    // questions follow the 12 byte header, with type and class after the name
+    let mut offset = 12;
+    for _ in 0..questions {
+        offset = read_name(data, offset)?.1 + 4;
+    }
+
+    let mut parsed = vec![];
+    for _ in 0..records {
+        let (name, end) = read_name(data, offset)?;
+
+        // type, class and TTL, then the length of the data
+        let header = data.get(end..end + 10)?;
+        let start = end + 10;
+        offset = start + u16::from_be_bytes([header[8], header[9]]) as usize;
+        if offset > data.len() {
+            return None;
+        }
+
+        parsed.push(Record {
+            name,
+            record_type: u16::from_be_bytes([header[0], header[1]]),
+            ttl: u32::from_be_bytes([header[4], header[5], header[6], header[7]]),
+            data: start..offset,
+        });
+    }
+    Some(parsed)
+}
+
+/// Query for the records of the given names and types
+pub(super) fn query(questions: &[(&str, u16)]) -> Result<Vec<u8>, ProtoError> {
+    // id, flags, questions, answers, authorities, additional records
+    let mut query = vec![0, 0, 0, 0];
+    query.extend((questions.len() as u16).to_be_bytes());
+    query.extend([0, 0, 0, 0, 0, 0]);
+
+    for &(name, record_type) in questions {
+        query.extend(make_dns_label(name)?);
+        query.extend(record_type.to_be_bytes());
+        query.extend(CLASS_IN.to_be_bytes());
+    }
+    Ok(query)
+}
+
+/// Read the name at `offset`, following compression pointers, returns it
+/// without the root label and the offset after it
+pub(super) fn read_name(data: &[u8], offset: usize) -> Option<(String, usize)> {
+    let mut labels = vec![];
+    let mut position = offset;
+    let mut end = None;
+
+    // pointers only point backwards, more jumps than bytes means a loop
+    for _ in 0..data.len() {
+        let len = *data.get(position)? as usize;
+        if len == 0 {
+            return Some((labels.join("."), end.unwrap_or(position + 1)));
+        }
 
-        self.known_records.insert(
-            oscjson_ptr_name,
-            vec![ResourceRecord::new(
-                oscjson_ptr_name,
-                ResourceType::A,
+        if len & 0xc0 == 0xc0 {
+            let pointer = ((len & 0x3f) << 8) | *data.get(position + 1)? as usize;
+            end.get_or_insert(position + 2);
+            position = pointer;
+            continue;
+        }
+
+        let label = data.get(position + 1..position + 1 + len)?;
+        labels.push(String::from_utf8_lossy(label).into_owned());
+        position += 1 + len;
+    }
+    None
+}
+
+/// Name of the service instance, owning the SRV and TXT records
+fn instance_name(service_name: &str) -> String {
+    format!("{service_name}._oscjson._tcp.local.")
+}
+
+/// Host name of the service, owning the address records
+fn host_name(service_name: &str) -> String {
+    format!("{service_name}.oscjson.tcp.local.")
+}
+
+/// Name of reverse lookups for `ip`, e.g. `1.0.0.127.in-addr.arpa`
+fn reverse_name(ip: IpAddr) -> String {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, c, d] = ip.octets();
+            format!("{d}.{c}.{b}.{a}.in-addr.arpa")
+        }
+        IpAddr::V6(ip) => {
+            let mut name = String::new();
+            for byte in ip.octets().iter().rev() {
+                name.push_str(&format!("{:x}.{:x}.", byte & 0xf, byte >> 4));
+            }
+            name + "ip6.arpa"
+        }
+    }
+}
+
+/// create DNS records for OSCJSON service, by the name they answer
+fn create_records<'a>(
+    service_name: &str,
+    http_port: u16,
+    ips: &[IpAddr],
+    ttl: u32,
+) -> HashMap<&'a str, Vec<ResourceRecord<'a>>> {
+    // we only announce an _oscjson._tcp service here as only that seems
+    // necessary the oscjson server's response contains the OSC_IP and the
+    // OSC_PORT anyway
+
+    let oscjson_ptr_name: &'a str = host_name(service_name).leak();
+    let oscjson_service_name: &'a str = instance_name(service_name).leak();
+
+    let ptr_rdata: &'a mut [u8] = make_dns_label(oscjson_service_name).unwrap().leak();
+    let srv_rdata = make_srv_rdata(0, 0, http_port, oscjson_ptr_name)
+        .unwrap()
+        .leak();
+
+    let address_records: Vec<_> = ips
+        .iter()
+        .map(|ip| {
+            let (resource_type, rdata) = match ip {
+                IpAddr::V4(ip) => (ResourceType::A, ip.octets().to_vec()),
+                IpAddr::V6(ip) => (ResourceType::AAAA, ip.octets().to_vec()),
+            };
+            ResourceRecord::new(oscjson_ptr_name, resource_type, 1, ttl, rdata.leak())
+        })
+        .collect();
+
+    let mut records = HashMap::new();
+    records.insert(
+        SERVICE_TYPE,
+        [
+            ResourceRecord::new(SERVICE_TYPE, ResourceType::Ptr, 1, ttl, ptr_rdata),
+            ResourceRecord::new(
+                oscjson_service_name,
+                ResourceType::Txt,
                 1,
                 ttl,
-                localhost_rdata,
-            )],
-        );
+                // I know, this is cursed, but it works.
+                "\x09txtvers=1".as_bytes(),
+            ),
+            ResourceRecord::new(oscjson_service_name, ResourceType::Srv, 1, ttl, srv_rdata),
+        ]
+        .into_iter()
+        .chain(address_records.iter().copied())
+        .collect(),
+    );
+    records.insert(oscjson_ptr_name.trim_end_matches('.'), address_records);
+
+    // reverse lookups of our addresses lead back to the host name
+    let host_rdata: &'a [u8] = make_dns_label(oscjson_ptr_name).unwrap().leak();
+    for &ip in ips {
+        let name: &'a str = reverse_name(ip).leak();
+        let record = ResourceRecord::new(name, ResourceType::Ptr, 1, ttl, host_rdata);
+        records.insert(name, vec![record]);
+    }
+
+    records
+}
+
+/// Unsolicited response carrying all service records, empty if it can't be
+/// serialized
+fn announcement(records: &HashMap<&str, Vec<ResourceRecord>>) -> Vec<u8> {
+    let mut flags = Flags::default();
+    flags.set_qr(MessageType::Reply);
+
+    let mut answers: SmallVec<[ResourceRecord; 4]> = records
+        .get(SERVICE_TYPE)
+        .into_iter()
+        .flatten()
+        .copied()
+        .collect();
+    let msg = Message::new(0, flags, &mut [], &mut answers, &mut [], &mut []);
+
+    let mut buf = vec![0; msg.space_needed()];
+    match msg.write(&mut buf) {
+        Ok(bytes_written) => {
+            buf.truncate(bytes_written);
+            buf
+        }
+        Err(e) => {
+            error!(err=%e, "failed to serialize mDNS announcement");
+            vec![]
+        }
+    }
+}
+
+/// Whether any question of the raw query has the QU bit set
+fn wants_unicast(data: &[u8]) -> bool {
+    let Some(count) = data.get(4..6) else {
+        return false;
+    };
+    let count = u16::from_be_bytes([count[0], count[1]]);
+
+    // questions follow the 12 byte header
+    let mut offset = 12;
+    for _ in 0..count {
+        // skip name, made of length prefixed labels and ending in either an
+        // empty label or a compression pointer
+        loop {
+            match data.get(offset) {
+                None => return false,
+                Some(0) => {
+                    offset += 1;
+                    break;
+                }
+                Some(len) if len & 0xc0 == 0xc0 => {
+                    offset += 2;
+                    break;
+                }
+                Some(len) => offset += 1 + *len as usize,
+            }
+        }
+
+        // type, then class
+        let Some(class) = data.get(offset + 2..offset + 4) else {
+            return false;
+        };
+        if u16::from_be_bytes([class[0], class[1]]) & UNICAST_RESPONSE_BIT != 0 {
+            return true;
+        }
+        offset += 4;
+    }
+
+    false
+}
+
+/// Receive from `socket`, never completing without one
+pub(super) async fn recv_from(
+    socket: Option<&UdpSocket>,
+    buf: &mut [u8],
+) -> io::Result<(usize, SocketAddr)> {
+    match socket {
+        Some(socket) => socket.recv_from(buf).await,
+        None => std::future::pending().await,
     }
 }
 
@@ -346,3 +880,79 @@ fn make_srv_rdata(
         buf
     })
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Query for `_oscjson._tcp.local` PTR records with the given class
+    fn query(class: u16) -> Vec<u8> {
+        let mut query = vec![0, 0, 0, 0, 0, 1, 0, 0, 0, 0, 0, 0];
+        query.extend(make_dns_label("_oscjson._tcp.local").unwrap());
+        query.extend(12u16.to_be_bytes());
+        query.extend(class.to_be_bytes());
+        query
+    }
+
+    #[test]
+    fn test_reply_to() {
+        let mdns_peer: SocketAddr = (Ipv4Addr::new(192, 168, 1, 2), MDNS_PORT).into();
+        let legacy_peer: SocketAddr = (Ipv4Addr::new(192, 168, 1, 2), 53211).into();
+
+        assert_eq!(ReplyTo::for_query(mdns_peer, &query(1)), ReplyTo::Multicast);
+        assert_eq!(
+            ReplyTo::for_query(mdns_peer, &query(1 | UNICAST_RESPONSE_BIT)),
+            ReplyTo::Unicast
+        );
+        assert_eq!(ReplyTo::for_query(legacy_peer, &query(1)), ReplyTo::Legacy);
+        // truncated question
+        assert!(!wants_unicast(&query(UNICAST_RESPONSE_BIT)[..20]));
+    }
+
+    #[test]
+    fn test_conflicts() {
+        let ips = [IpAddr::from(Ipv4Addr::LOCALHOST)];
+        let ours = [instance_name("booper-a"), host_name("booper-a")];
+        let theirs = [instance_name("booper-b"), host_name("booper-b")];
+
+        // simultaneous probe of another host
+        let probe = probe_query("booper-a", 9100, &ips).unwrap();
+        assert!(conflicts(&probe, &ours));
+        assert!(!conflicts(&probe, &theirs));
+
+        // response of another host, with compressed names
+        let announcement = announcement(&create_records("booper-a", 9100, &ips, TTL));
+        assert!(conflicts(&announcement, &ours));
+        assert!(!conflicts(&announcement, &theirs));
+
+        // plain queries don't claim anything
+        assert!(!conflicts(&query(1), &ours));
+    }
+
+    #[test]
+    fn test_ipv6_address() {
+        let ip = Ipv6Addr::new(0xfe80, 0, 0, 0, 0, 0, 0, 1);
+        let packet = announcement(&create_records("booper-a", 9100, &[ip.into()], TTL));
+        let records = records(&packet).unwrap();
+
+        let host = host_name("booper-a");
+        let address = records
+            .iter()
+            .find(|record| record.record_type == TYPE_AAAA)
+            .unwrap();
+        assert_eq!(address.name, host.trim_end_matches('.'));
+        assert_eq!(packet[address.data.clone()], ip.octets());
+    }
+
+    #[test]
+    fn test_reverse_name() {
+        assert_eq!(
+            reverse_name(Ipv4Addr::new(192, 168, 1, 2).into()),
+            "2.1.168.192.in-addr.arpa"
+        );
+        assert_eq!(
+            reverse_name(Ipv6Addr::LOCALHOST.into()),
+            format!("1.{}ip6.arpa", "0.".repeat(31))
+        );
+    }
+}
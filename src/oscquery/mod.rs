@@ -1,49 +1,125 @@
-use std::{collections::HashMap, net::SocketAddr};
+use std::{
+    collections::HashMap,
+    net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr},
+    sync::Arc,
+};
 
 use oscquery::{
-    node::{AccessMode, HostInfo, OSCTransport, OscNode},
+    node::{HostInfo, OSCTransport},
     server::OscQueryServer,
 };
-use rand::distr::{Alphanumeric, SampleString};
-use tokio::net::TcpListener;
+use tokio::{
+    net::TcpListener,
+    sync::{Notify, watch},
+    task::JoinHandle,
+};
 use tokio_util::sync::CancellationToken;
 use tracing::{info, warn};
 
+use crate::{config::OscTransport, error::BooperError, instance, parameters::ParameterValues};
+
+pub mod client;
 pub mod mdns;
+pub mod tree;
+
+/// Announce our OSC socket on `osc_ip` via OSCQuery and mDNS, returns the
+/// mDNS task, which says goodbye once `token` is cancelled
+///
+/// Fails if the OSCQuery port can't be picked or the mDNS socket can't be
+/// set up.
+///
+/// The OSCQuery server binds to the same address, and to `127.0.0.1` as well
+/// when listening on `::1`. The OSCQuery tree follows `nodes`, with the
+/// latest `values` of its parameters. `reannounce` makes the mDNS task
+/// announce us again right away.
+pub async fn announce(
+    token: CancellationToken,
+    reannounce: Arc<Notify>,
+    osc_ip: IpAddr,
+    osc_port: u16,
+    osc_transport: OscTransport,
+    nodes: watch::Receiver<Vec<tree::Node>>,
+    values: watch::Receiver<ParameterValues>,
+) -> Result<JoinHandle<()>, BooperError> {
+    // keep us discoverable for IPv4-only clients
+    let mut http_ips = vec![osc_ip];
+    if osc_ip == IpAddr::V6(Ipv6Addr::LOCALHOST) {
+        http_ips.insert(0, Ipv4Addr::LOCALHOST.into());
+    }
 
-pub async fn announce(token: CancellationToken, osc_port: u16) {
     // listener is dropped after this context to allow oscquery to bind again
     // this is kinda stupid, but it'll do for now
-    let http_addr = {
-        let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
-        listener.local_addr().unwrap()
+    let http_port = {
+        let addr = SocketAddr::from((http_ips[0], 0));
+        let listener = TcpListener::bind(addr)
+            .await
+            .map_err(|source| BooperError::Bind {
+                what: "oscjson listener",
+                addr,
+                source,
+            })?;
+        let addr = listener
+            .local_addr()
+            .map_err(|source| BooperError::Socket {
+                what: "oscjson listener",
+                source,
+            })?;
+        addr.port()
     };
 
-    let random_suffix: String = Alphanumeric.sample_string(&mut rand::rng(), 8);
-    let service_name = format!("osc-booper-{random_suffix}");
+    let record_ips: Vec<_> = http_ips.iter().copied().map(advertised).collect();
+    let prefix = instance::scoped("osc-booper", "-");
+    let service_name = mdns::claim_name(&prefix, http_port, &record_ips).await?;
 
     info!("announcing ourselves as {service_name}");
 
-    start_oscjson_server(token.clone(), service_name.clone(), http_addr, osc_port).await;
+    // same port on all addresses, so the SRV record fits every address record
+    for &ip in &http_ips {
+        start_oscjson_server(
+            token.clone(),
+            service_name.clone(),
+            (ip, http_port).into(),
+            (advertised(osc_ip), osc_port).into(),
+            osc_transport,
+            nodes.clone(),
+            values.clone(),
+        )
+        .await;
+    }
+
+    let mut server = mdns::MdnsServer::new(&service_name, http_port, &record_ips)?;
+    Ok(tokio::task::spawn(async move {
+        server.run(token, reannounce).await;
+    }))
+}
 
-    let mdns_token = token.clone();
-    tokio::task::spawn(async move {
-        let mut server = mdns::MdnsServer::new(&service_name, http_addr.port());
-        server.run(mdns_token).await;
-    });
+/// Address to announce for `ip`, we're only known to be reachable via
+/// loopback when bound to all interfaces
+fn advertised(ip: IpAddr) -> IpAddr {
+    match ip {
+        IpAddr::V4(ip) if ip.is_unspecified() => Ipv4Addr::LOCALHOST.into(),
+        IpAddr::V6(ip) if ip.is_unspecified() => Ipv6Addr::LOCALHOST.into(),
+        ip => ip,
+    }
 }
 
 async fn start_oscjson_server(
     token: CancellationToken,
     service_name: String,
     socket_addr: SocketAddr,
-    osc_port: u16,
+    osc_addr: SocketAddr,
+    osc_transport: OscTransport,
+    mut nodes: watch::Receiver<Vec<tree::Node>>,
+    mut values: watch::Receiver<ParameterValues>,
 ) {
     let mut server = OscQueryServer::new(HostInfo {
         name: Some(service_name),
-        osc_ip: Some("127.0.0.1".into()),
-        osc_port: Some(osc_port),
-        osc_transport: Some(OSCTransport::UDP),
+        osc_ip: Some(osc_addr.ip().to_string()),
+        osc_port: Some(osc_addr.port()),
+        osc_transport: Some(match osc_transport {
+            OscTransport::Udp => OSCTransport::UDP,
+            OscTransport::Tcp => OSCTransport::TCP,
+        }),
         extensions: Some(HashMap::from([
             ("ACCESS".into(), true),
             ("CLIPMODE".into(), false),
@@ -56,26 +132,42 @@ async fn start_oscjson_server(
     .with_address(socket_addr);
 
     // listen for all avatar events, including change and parameters
-    // boop (contact receivers) should be part of parameters
-    server
-        .add_node(
-            "/avatar",
-            OscNode::new("/avatar").with_access(AccessMode::None),
-        )
-        .await
-        .unwrap();
+    // boop (contact receivers) are part of parameters
+    let mut current = nodes.borrow_and_update().clone();
+    tree::apply(&mut server, &[], &current).await;
+    let mut sent = HashMap::new();
 
     info!("oscjson server listening on {}", socket_addr);
 
     tokio::task::spawn(async move {
-        tokio::select! {
-            _ = server.serve().await => {
-                warn!("oscjson server stopped unexpectedly");
-            },
-            _ = token.cancelled() => {
-                server.shutdown();
-                info!("stopping oscjson server");
-            },
+        let serving = server.serve().await;
+        tokio::pin!(serving);
+
+        loop {
+            tokio::select! {
+                _ = &mut serving => {
+                    warn!("oscjson server stopped unexpectedly");
+                    break;
+                },
+                _ = token.cancelled() => {
+                    server.shutdown();
+                    info!("stopping oscjson server");
+                    break;
+                },
+                Ok(()) = nodes.changed() => {
+                    // counters or the boop address were changed at runtime
+                    let new = nodes.borrow_and_update().clone();
+                    tree::apply(&mut server, &current, &new).await;
+                    current = new;
+                    sent.clear();
+                    values.mark_changed();
+                },
+                Ok(()) = values.changed() => {
+                    let changed =
+                        tree::changed_values(&current, &values.borrow_and_update(), &mut sent);
+                    tree::set_values(&mut server, changed).await;
+                },
+            }
         }
     });
 }
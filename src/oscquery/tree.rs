@@ -0,0 +1,302 @@
+use std::collections::HashMap;
+
+use oscquery::{
+    node::{AccessMode, OscNode, OscTypeTag, OscValue},
+    server::OscQueryServer,
+};
+use rosc::OscType;
+use tracing::{debug, trace, warn};
+
+use crate::{
+    config::{Options, Trigger},
+    parameters::{PARAMETER_PREFIX, ParameterValues},
+};
+
+/// Control endpoints below `/booper/`, with their OSC type tag
+const CONTROLS: &[(&str, &str, &str)] = &[
+    (
+        "pause",
+        "T",
+        "pause counting and posting, a bool sets it directly",
+    ),
+    ("resume", "T", "resume counting and posting"),
+    ("announce", "T", "post the current stats now"),
+    ("leaderboard", "T", "post today's top boopers"),
+    ("reset_today", "T", "reset today's boops"),
+    ("freeze", "T", "set or toggle freeze mode"),
+    ("undo", "T", "revert the last boop"),
+    ("adjust", "i", "add to (or subtract from) the boops"),
+];
+
+/// Node of our OSCQuery tree
+#[derive(Debug, Clone, PartialEq)]
+pub struct Node {
+    pub path: String,
+    /// OSC type tag of the accepted value, empty for containers
+    pub type_tag: &'static str,
+    pub description: String,
+}
+
+impl Node {
+    fn container(path: &str) -> Self {
+        Node {
+            path: path.into(),
+            type_tag: "",
+            description: String::new(),
+        }
+    }
+
+    fn to_osc_node(&self) -> OscNode {
+        let node = OscNode::new(&self.path);
+        if self.type_tag.is_empty() {
+            // children can only be added to nodes with contents
+            return node
+                .with_access(AccessMode::None)
+                .with_contents(HashMap::new());
+        }
+
+        // parameters expose their latest value
+        let access = if self.path.starts_with(PARAMETER_PREFIX) {
+            AccessMode::ReadWrite
+        } else {
+            AccessMode::WriteOnly
+        };
+        let node = node.with_access(access).with_description(&self.description);
+        match OscTypeTag::from_tag(self.type_tag) {
+            Some(type_tag) => node.with_type(type_tag),
+            None => node,
+        }
+    }
+}
+
+/// All nodes for the given options, parents before their children
+///
+/// `/avatar` itself makes VRChat send us all avatar events, including avatar
+/// changes and parameters we don't list.
+pub(crate) fn nodes(options: &Options) -> Vec<Node> {
+    let mut nodes = vec![
+        Node::container("/avatar"),
+        Node::container("/avatar/parameters"),
+    ];
+
+    let contacts = [(
+        &options.boop_address,
+        options.boop_trigger,
+        "boops".to_string(),
+    )]
+    .into_iter()
+    .chain(options.counters.iter().map(|counter| {
+        (
+            &counter.address,
+            counter.trigger,
+            counter.display_name().to_string(),
+        )
+    }))
+    .chain(options.boopers.iter().map(|booper| {
+        (
+            &booper.address,
+            Trigger::Bool,
+            format!("boops by {}", booper.name),
+        )
+    }));
+    for (address, trigger, counted) in contacts {
+        // prefixes and globs stand for many parameters, still received via
+        // `/avatar`
+        let Some(address) = address.path() else {
+            continue;
+        };
+        let path = parameter_path(address);
+        // counters may share an address
+        if nodes.iter().any(|node| node.path == path) {
+            continue;
+        }
+
+        nodes.push(Node {
+            path,
+            type_tag: type_tag(trigger),
+            description: format!("contact receiver counting {counted}"),
+        });
+    }
+
+    nodes.push(Node::container("/booper"));
+    nodes.extend(CONTROLS.iter().map(|&(name, type_tag, description)| Node {
+        path: format!("/booper/{name}"),
+        type_tag,
+        description: description.to_string(),
+    }));
+
+    nodes
+}
+
+/// OSC type tag of values sent to a contact receiver
+fn type_tag(trigger: Trigger) -> &'static str {
+    match trigger {
+        Trigger::Bool => "T",
+        Trigger::Int(..) => "i",
+        Trigger::Float(..) => "f",
+    }
+}
+
+/// Full parameter address of a contact address suffix
+pub(crate) fn parameter_path(address: &str) -> String {
+    if address.starts_with("/avatar/") {
+        address.into()
+    } else {
+        format!("/avatar/parameters/{}", address.trim_start_matches('/'))
+    }
+}
+
+/// Update the server's tree from `current` to `new` nodes
+pub(crate) async fn apply(server: &mut OscQueryServer, current: &[Node], new: &[Node]) {
+    // children first
+    for node in current.iter().rev().filter(|node| !new.contains(node)) {
+        debug!(path = %node.path, "removing OSCQuery node");
+        let Some((parent, name)) = node.path.rsplit_once('/') else {
+            continue;
+        };
+        let mut removed = false;
+        server
+            .map_node(parent, |parent| {
+                removed = parent
+                    .contents_mut()
+                    .and_then(|contents| contents.remove(name))
+                    .is_some();
+            })
+            .await;
+        if !removed {
+            warn!("failed to remove OSCQuery node {}", node.path);
+        }
+    }
+
+    // `add_node` can't be awaited in a spawned task, its future isn't `Send`
+    for node in new.iter().filter(|node| !current.contains(node)) {
+        debug!(path = %node.path, "adding OSCQuery node");
+        let Some((parent, name)) = node.path.rsplit_once('/') else {
+            continue;
+        };
+        let mut added = false;
+        server
+            .map_node(parent, |parent| {
+                if let Some(contents) = parent.contents_mut() {
+                    contents.insert(name.to_string(), node.to_osc_node());
+                    added = true;
+                }
+            })
+            .await;
+        if !added {
+            warn!("failed to add OSCQuery node {}", node.path);
+        }
+    }
+}
+
+/// Values of `nodes` that changed since they were `sent`, marking them sent
+pub(crate) fn changed_values(
+    nodes: &[Node],
+    values: &ParameterValues,
+    sent: &mut HashMap<String, OscType>,
+) -> Vec<(String, OscType)> {
+    let mut changed = vec![];
+    for node in nodes {
+        let Some(name) = node.path.strip_prefix(PARAMETER_PREFIX) else {
+            continue;
+        };
+        let Some(value) = values.get(name) else {
+            continue;
+        };
+        if sent.get(&node.path) == Some(value) {
+            continue;
+        }
+
+        sent.insert(node.path.clone(), value.clone());
+        changed.push((node.path.clone(), value.clone()));
+    }
+    changed
+}
+
+/// Set the VALUE attribute of our parameter nodes
+pub(crate) async fn set_values(server: &mut OscQueryServer, values: Vec<(String, OscType)>) {
+    for (path, value) in values {
+        trace!(%path, ?value, "updating OSCQuery value");
+        let value = osc_value(value);
+        let mut found = false;
+        server
+            .map_node(&path, |node| {
+                node.set_value(vec![value]);
+                found = true;
+            })
+            .await;
+        if !found {
+            warn!("failed to set OSCQuery value of {path}");
+        }
+    }
+}
+
+/// OSCQuery representation of a received OSC argument
+fn osc_value(value: OscType) -> OscValue {
+    match value {
+        OscType::Int(i) => OscValue::Int(i),
+        OscType::Float(f) => OscValue::Float(f.into()),
+        OscType::Double(d) => OscValue::Float(d),
+        OscType::String(s) => OscValue::String(s),
+        OscType::Bool(b) => OscValue::Bool(b),
+        _ => OscValue::Nil,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::CounterOptions;
+
+    #[test]
+    fn test_nodes() {
+        let mut options = Options {
+            boop_address: "/OSCBoop".into(),
+            ..Default::default()
+        };
+        let counter = r#"
+            name = "headpats"
+            address = "/avatar/parameters/OSCHeadpat"
+        "#;
+        options
+            .counters
+            .push(toml::from_str::<CounterOptions>(counter).unwrap());
+
+        let nodes = nodes(&options);
+        let paths: Vec<_> = nodes.iter().map(|node| node.path.as_str()).collect();
+        assert_eq!(
+            paths[..4],
+            [
+                "/avatar",
+                "/avatar/parameters",
+                "/avatar/parameters/OSCBoop",
+                "/avatar/parameters/OSCHeadpat",
+            ]
+        );
+        assert!(paths.contains(&"/booper/adjust"));
+
+        let values = ParameterValues::from([
+            ("OSCBoop".into(), OscType::Bool(true)),
+            ("VelocityX".into(), OscType::Float(0.2)),
+        ]);
+        let mut sent = HashMap::new();
+        assert_eq!(
+            changed_values(&nodes, &values, &mut sent),
+            [("/avatar/parameters/OSCBoop".into(), OscType::Bool(true))]
+        );
+        assert!(changed_values(&nodes, &values, &mut sent).is_empty());
+        assert!(matches!(
+            osc_value(OscType::Float(0.5)),
+            OscValue::Float(f) if f == 0.5
+        ));
+
+        for (index, node) in nodes.iter().enumerate() {
+            let (parent, _) = node.path.rsplit_once('/').unwrap();
+            assert!(
+                parent.is_empty() || nodes[..index].iter().any(|node| node.path == parent),
+                "{} listed before its parent",
+                node.path
+            );
+        }
+    }
+}
@@ -0,0 +1,249 @@
+use rosc::{OscMessage, OscType};
+
+use crate::{
+    config::{
+        ArgKind, ArgTemplate, CountKind, GenericOptions, Options, ParameterKind, ParameterOutput,
+        Platform,
+    },
+    template,
+};
+
+/// Messages displaying `text` and the counters on the configured platform
+///
+/// Platforms without any text output show `text` as desktop notification
+pub(crate) fn text_messages(
+    options: &Options,
+    text: String,
+    today: u32,
+    total: u64,
+) -> Vec<OscMessage> {
+    match options.platform {
+        Platform::VRChat => vec![chatbox_message(text)],
+        Platform::Resonite => {
+            let prefix = &options.resonite.prefix;
+            vec![
+                OscMessage {
+                    addr: format!("{prefix}/Message"),
+                    args: vec![OscType::String(text)],
+                },
+                int_message(&format!("{prefix}/Today"), today as u64),
+                int_message(&format!("{prefix}/Total"), total),
+            ]
+        }
+        Platform::ChilloutVR => {
+            let cvr = &options.cvr;
+            if cvr.notifications {
+                show_notification(text);
+            }
+            vec![
+                int_message(&parameter_address(&cvr.today_parameter), today as u64),
+                int_message(&parameter_address(&cvr.total_parameter), total),
+            ]
+        }
+        Platform::Generic => generic_message(&options.generic, &text, today, total)
+            .into_iter()
+            .collect(),
+    }
+}
+
+/// Messages setting the configured avatar parameters to the counts
+pub(crate) fn parameter_messages(
+    outputs: &[ParameterOutput],
+    today: u32,
+    total: u64,
+) -> Vec<OscMessage> {
+    outputs
+        .iter()
+        .map(|output| {
+            let address = if output.parameter.starts_with('/') {
+                output.parameter.clone()
+            } else {
+                parameter_address(&output.parameter)
+            };
+
+            let count = match output.count {
+                CountKind::Today => today as u64,
+                CountKind::Total => total,
+            };
+            let count = output.modulo.map_or(count, |modulo| count % modulo);
+
+            match output.kind {
+                ParameterKind::Int => int_message(&address, count),
+                ParameterKind::Float => OscMessage {
+                    addr: address,
+                    args: vec![OscType::Float((count as f64 / output.scale) as f32)],
+                },
+            }
+        })
+        .collect()
+}
+
+/// Messages clearing previously displayed text
+pub(crate) fn clear_messages(options: &Options) -> Vec<OscMessage> {
+    match options.platform {
+        Platform::VRChat => vec![chatbox_message("".into())],
+        Platform::Resonite => vec![OscMessage {
+            addr: format!("{}/Message", options.resonite.prefix),
+            args: vec![OscType::String("".into())],
+        }],
+        // parameters keep their values, notifications expire by themselves,
+        // generic targets keep the last counts
+        Platform::ChilloutVR | Platform::Generic => vec![],
+    }
+}
+
+/// Render the generic output message, None if an argument doesn't match its
+/// type
+fn generic_message(
+    generic: &GenericOptions,
+    text: &str,
+    today: u32,
+    total: u64,
+) -> Option<OscMessage> {
+    let lookup = |key: &str| match key {
+        "today" => Some(today.to_string()),
+        "total" => Some(total.to_string()),
+        "message" => Some(text.to_string()),
+        _ => None,
+    };
+
+    let args = generic
+        .args
+        .iter()
+        .map(|arg| render_arg(arg, lookup))
+        .collect::<Result<_, _>>()
+        .map_err(|e| {
+            tracing::warn!(err=%e, address=%generic.address, "failed to render output argument");
+        })
+        .ok()?;
+
+    Some(OscMessage {
+        addr: generic.address.clone(),
+        args,
+    })
+}
+
+/// Render argument template and convert it to its OSC type
+fn render_arg<F>(arg: &ArgTemplate, lookup: F) -> Result<OscType, String>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let value = template::render(&arg.template, lookup);
+    let invalid =
+        |e: &dyn std::fmt::Display| format!("{value:?} is not a valid {:?}: {e}", arg.kind);
+
+    Ok(match arg.kind {
+        ArgKind::Int => OscType::Int(value.trim().parse().map_err(|e| invalid(&e))?),
+        ArgKind::Long => OscType::Long(value.trim().parse().map_err(|e| invalid(&e))?),
+        ArgKind::Float => OscType::Float(value.trim().parse().map_err(|e| invalid(&e))?),
+        ArgKind::Double => OscType::Double(value.trim().parse().map_err(|e| invalid(&e))?),
+        ArgKind::Bool => OscType::Bool(value.trim().parse().map_err(|e| invalid(&e))?),
+        ArgKind::String => OscType::String(value),
+    })
+}
+
+/// Message sending `arg` to `address`, None if it doesn't match its type
+pub(crate) fn rendered_message<F>(address: &str, arg: &ArgTemplate, lookup: F) -> Option<OscMessage>
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let arg = render_arg(arg, lookup)
+        .map_err(|e| tracing::warn!(err=%e, address, "failed to render output argument"))
+        .ok()?;
+
+    Some(OscMessage {
+        addr: address.into(),
+        args: vec![arg],
+    })
+}
+
+/// Address of the avatar parameter `name`
+fn parameter_address(name: &str) -> String {
+    format!("/avatar/parameters/{name}")
+}
+
+/// Show `text` as desktop notification
+#[cfg(feature = "notify")]
+fn show_notification(text: String) {
+    // showing may block on some platforms
+    tokio::task::spawn_blocking(move || {
+        if let Err(e) = notify_rust::Notification::new()
+            .appname("osc-booper")
+            .summary("osc-booper")
+            .body(&text)
+            .show()
+        {
+            tracing::warn!(err=%e, "failed to show desktop notification");
+        }
+    });
+}
+
+/// Desktop notifications are not available in this build, warned about on
+/// startup
+#[cfg(not(feature = "notify"))]
+fn show_notification(_text: String) {}
+
+/// create OSC chatbox message
+/// https://docs.vrchat.com/docs/osc-as-input-controller
+pub(crate) fn chatbox_message(message: String) -> OscMessage {
+    OscMessage {
+        addr: "/chatbox/input".into(),
+        args: vec![
+            // message
+            OscType::String(message),
+            // send immediately, bypass keyboard input
+            OscType::Bool(true),
+            // don't trigger SFX
+            OscType::Bool(false),
+        ],
+    }
+}
+
+/// create message setting a bool parameter
+pub(crate) fn bool_message(address: &str, value: bool) -> OscMessage {
+    OscMessage {
+        addr: address.into(),
+        args: vec![OscType::Bool(value)],
+    }
+}
+
+/// create message setting an int parameter, saturating at `i32::MAX`
+pub(crate) fn int_message(address: &str, value: u64) -> OscMessage {
+    OscMessage {
+        addr: address.into(),
+        args: vec![OscType::Int(value.min(i32::MAX as u64) as i32)],
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::default_echo_window;
+
+    #[test]
+    fn test_parameter_messages() {
+        let outputs = [
+            ParameterOutput {
+                parameter: "BoopsToday".into(),
+                count: CountKind::Today,
+                kind: ParameterKind::Int,
+                modulo: None,
+                scale: 1.0,
+                echo_window: default_echo_window(),
+            },
+            ParameterOutput {
+                parameter: "/avatar/parameters/BoopRadial".into(),
+                count: CountKind::Total,
+                kind: ParameterKind::Float,
+                modulo: Some(100),
+                scale: 100.0,
+                echo_window: default_echo_window(),
+            },
+        ];
+
+        let messages = parameter_messages(&outputs, 7, 1234);
+        assert_eq!(messages[0], int_message("/avatar/parameters/BoopsToday", 7));
+        assert_eq!(messages[1].addr, "/avatar/parameters/BoopRadial");
+        assert_eq!(messages[1].args, vec![OscType::Float(0.34)]);
+    }
+}
@@ -0,0 +1,418 @@
+use std::{
+    ffi::{CString, c_void},
+    mem,
+    ptr::NonNull,
+    thread,
+    time::Duration,
+};
+
+use openvr_sys as sys;
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, warn};
+
+use crate::{
+    command::{Command, CommandSender, StatsSnapshot},
+    instance,
+};
+
+const WIDTH: usize = 512;
+const HEIGHT: usize = 256;
+
+/// How often overlay events are polled
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How often the counters are refreshed
+const REFRESH_EVERY: u32 = 20;
+
+const BACKGROUND: [u8; 4] = [24, 24, 32, 230];
+const FOREGROUND: [u8; 4] = [255, 255, 255, 255];
+const BUTTON: [u8; 4] = [255, 105, 180, 255];
+
+/// Buttons along the bottom edge of the overlay
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Button {
+    Pause,
+    Post,
+    Reset,
+}
+
+impl Button {
+    const ALL: [Button; 3] = [Button::Pause, Button::Post, Button::Reset];
+    const TOP: usize = 176;
+    const HEIGHT: usize = 64;
+    const WIDTH: usize = 150;
+
+    fn left(self) -> usize {
+        16 + self as usize * (Button::WIDTH + 15)
+    }
+
+    fn label(self, paused: bool) -> &'static str {
+        match self {
+            Button::Pause if paused => "RESUME",
+            Button::Pause => "PAUSE",
+            Button::Post => "POST",
+            Button::Reset => "RESET",
+        }
+    }
+
+    fn command(self) -> Command {
+        match self {
+            Button::Pause => Command::Pause(None),
+            Button::Post => Command::PostStats {
+                template: None,
+                manual: true,
+            },
+            Button::Reset => Command::ResetToday,
+        }
+    }
+}
+
+/// Counters shown on the overlay
+#[derive(Debug, Clone, PartialEq)]
+struct OverlayState {
+    today: u32,
+    total: u64,
+    paused: bool,
+}
+
+impl From<StatsSnapshot> for OverlayState {
+    fn from(stats: StatsSnapshot) -> Self {
+        OverlayState {
+            today: stats.today,
+            total: stats.total,
+            paused: stats.paused,
+        }
+    }
+}
+
+/// Show the counters and controls as SteamVR dashboard overlay
+///
+/// OpenVR calls block, so the overlay runs on its own thread.
+pub(crate) fn spawn(token: CancellationToken, commands: CommandSender) {
+    thread::spawn(move || {
+        let overlay = match Overlay::new() {
+            Ok(overlay) => overlay,
+            Err(e) => {
+                warn!(err=%e, "failed to create SteamVR overlay, is SteamVR running?");
+                return;
+            }
+        };
+        info!("showing SteamVR dashboard overlay");
+
+        run(&token, &commands, &overlay);
+        overlay.shutdown();
+    });
+}
+
+fn run(token: &CancellationToken, commands: &CommandSender, overlay: &Overlay) {
+    let mut state: Option<OverlayState> = None;
+    let mut ticks = 0;
+
+    while !token.is_cancelled() {
+        if ticks % REFRESH_EVERY == 0 {
+            let Some(stats) = fetch_stats(commands) else {
+                // main loop is gone
+                break;
+            };
+
+            let stats = OverlayState::from(stats);
+            if state.as_ref() != Some(&stats) {
+                if let Err(e) = overlay.set_image(&mut render(&stats)) {
+                    error!(err=%e, "failed to update SteamVR overlay");
+                }
+                state = Some(stats);
+            }
+        }
+        ticks += 1;
+
+        while let Some((x, y)) = overlay.poll_click() {
+            let Some(button) = button_at(x, y) else {
+                continue;
+            };
+
+            debug!(?button, "overlay button pressed");
+            if let Err(e) = commands.try_send(button.command()) {
+                warn!(err=%e, "failed to forward overlay action");
+            }
+            // show the new state right away
+            ticks = 0;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn fetch_stats(commands: &CommandSender) -> Option<StatsSnapshot> {
+    let (tx, rx) = oneshot::channel();
+    commands.blocking_send(Command::Stats(tx)).ok()?;
+    rx.blocking_recv().ok()
+}
+
+/// Button under the given pixel, with the origin at the top left
+fn button_at(x: f32, y: f32) -> Option<Button> {
+    if x < 0.0 || y < 0.0 {
+        return None;
+    }
+
+    let (x, y) = (x as usize, y as usize);
+    if !(Button::TOP..Button::TOP + Button::HEIGHT).contains(&y) {
+        return None;
+    }
+
+    Button::ALL
+        .into_iter()
+        .find(|button| (button.left()..button.left() + Button::WIDTH).contains(&x))
+}
+
+/// Render overlay as RGBA image
+fn render(state: &OverlayState) -> Vec<u8> {
+    let mut image = Image::new();
+    image.fill(0, 0, WIDTH, HEIGHT, BACKGROUND);
+
+    image.text(24, 24, 5, &format!("TODAY {}", state.today), FOREGROUND);
+    image.text(24, 88, 5, &format!("TOTAL {}", state.total), FOREGROUND);
+
+    for button in Button::ALL {
+        image.fill(
+            button.left(),
+            Button::TOP,
+            Button::WIDTH,
+            Button::HEIGHT,
+            BUTTON,
+        );
+
+        let label = button.label(state.paused);
+        let scale = 3;
+        let width = Image::text_width(label, scale);
+        image.text(
+            button.left() + (Button::WIDTH - width) / 2,
+            Button::TOP + (Button::HEIGHT - GLYPH_HEIGHT * scale) / 2,
+            scale,
+            label,
+            FOREGROUND,
+        );
+    }
+
+    image.pixels
+}
+
+const GLYPH_WIDTH: usize = 5;
+const GLYPH_HEIGHT: usize = 7;
+
+/// 5x7 bitmap font, covering all characters the overlay displays
+#[rustfmt::skip]
+const GLYPHS: &[(char, [u8; GLYPH_HEIGHT])] = &[
+    (' ', [0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000, 0b00000]),
+    ('0', [0b01110, 0b10001, 0b10011, 0b10101, 0b11001, 0b10001, 0b01110]),
+    ('1', [0b00100, 0b01100, 0b00100, 0b00100, 0b00100, 0b00100, 0b01110]),
+    ('2', [0b01110, 0b10001, 0b00001, 0b00010, 0b00100, 0b01000, 0b11111]),
+    ('3', [0b11110, 0b00001, 0b00001, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('4', [0b00010, 0b00110, 0b01010, 0b10010, 0b11111, 0b00010, 0b00010]),
+    ('5', [0b11111, 0b10000, 0b11110, 0b00001, 0b00001, 0b10001, 0b01110]),
+    ('6', [0b00110, 0b01000, 0b10000, 0b11110, 0b10001, 0b10001, 0b01110]),
+    ('7', [0b11111, 0b00001, 0b00010, 0b00100, 0b01000, 0b01000, 0b01000]),
+    ('8', [0b01110, 0b10001, 0b10001, 0b01110, 0b10001, 0b10001, 0b01110]),
+    ('9', [0b01110, 0b10001, 0b10001, 0b01111, 0b00001, 0b00010, 0b01100]),
+    ('A', [0b01110, 0b10001, 0b10001, 0b11111, 0b10001, 0b10001, 0b10001]),
+    ('D', [0b11110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b11110]),
+    ('E', [0b11111, 0b10000, 0b10000, 0b11110, 0b10000, 0b10000, 0b11111]),
+    ('L', [0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b10000, 0b11111]),
+    ('M', [0b10001, 0b11011, 0b10101, 0b10101, 0b10001, 0b10001, 0b10001]),
+    ('O', [0b01110, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('P', [0b11110, 0b10001, 0b10001, 0b11110, 0b10000, 0b10000, 0b10000]),
+    ('R', [0b11110, 0b10001, 0b10001, 0b11110, 0b10100, 0b10010, 0b10001]),
+    ('S', [0b01111, 0b10000, 0b10000, 0b01110, 0b00001, 0b00001, 0b11110]),
+    ('T', [0b11111, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100, 0b00100]),
+    ('U', [0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b10001, 0b01110]),
+    ('Y', [0b10001, 0b10001, 0b01010, 0b00100, 0b00100, 0b00100, 0b00100]),
+];
+
+struct Image {
+    pixels: Vec<u8>,
+}
+
+impl Image {
+    fn new() -> Self {
+        Image {
+            pixels: vec![0; WIDTH * HEIGHT * 4],
+        }
+    }
+
+    fn fill(&mut self, x: usize, y: usize, width: usize, height: usize, color: [u8; 4]) {
+        for row in y..(y + height).min(HEIGHT) {
+            for column in x..(x + width).min(WIDTH) {
+                let offset = (row * WIDTH + column) * 4;
+                self.pixels[offset..offset + 4].copy_from_slice(&color);
+            }
+        }
+    }
+
+    fn text_width(text: &str, scale: usize) -> usize {
+        let chars = text.chars().count();
+        (chars * (GLYPH_WIDTH + 1)).saturating_sub(1) * scale
+    }
+
+    /// Draw text with its top left corner at `x`, `y`, unknown characters
+    /// are skipped
+    fn text(&mut self, x: usize, y: usize, scale: usize, text: &str, color: [u8; 4]) {
+        for (index, c) in text.chars().enumerate() {
+            let Some((_, rows)) = GLYPHS.iter().find(|(glyph, _)| *glyph == c) else {
+                continue;
+            };
+
+            let left = x + index * (GLYPH_WIDTH + 1) * scale;
+            for (row, bits) in rows.iter().enumerate() {
+                for column in 0..GLYPH_WIDTH {
+                    if bits & (1 << (GLYPH_WIDTH - 1 - column)) != 0 {
+                        self.fill(left + column * scale, y + row * scale, scale, scale, color);
+                    }
+                }
+            }
+        }
+    }
+}
+
+/// Dashboard overlay, talking to the OpenVR C API
+struct Overlay {
+    api: NonNull<sys::VR_IVROverlay_FnTable>,
+    handle: sys::VROverlayHandle_t,
+}
+
+impl Overlay {
+    fn new() -> Result<Self, String> {
+        let mut init_error = sys::EVRInitError_VRInitError_None;
+        unsafe {
+            sys::VR_InitInternal(
+                &mut init_error,
+                sys::EVRApplicationType_VRApplication_Overlay,
+            )
+        };
+        if init_error != sys::EVRInitError_VRInitError_None {
+            return Err(format!("init error {init_error}"));
+        }
+
+        // the C API is only exposed as function tables
+        let version = String::from_utf8_lossy(sys::IVROverlay_Version);
+        let name = CString::new(format!("FnTable:{}", version.trim_end_matches('\0')))
+            .expect("version has no inner nul");
+        let api = unsafe { sys::VR_GetGenericInterface(name.as_ptr(), &mut init_error) };
+        let Some(api) = NonNull::new(api as *mut sys::VR_IVROverlay_FnTable) else {
+            unsafe { sys::VR_ShutdownInternal() };
+            return Err(format!("no overlay interface, error {init_error}"));
+        };
+
+        let mut overlay = Overlay { api, handle: 0 };
+        if let Err(e) = overlay.create() {
+            overlay.shutdown();
+            return Err(e);
+        }
+
+        Ok(overlay)
+    }
+
+    fn table(&self) -> &sys::VR_IVROverlay_FnTable {
+        // valid until VR_ShutdownInternal
+        unsafe { self.api.as_ref() }
+    }
+
+    fn create(&mut self) -> Result<(), String> {
+        let key = CString::new(instance::scoped("osc-booper", "-")).unwrap();
+        let name = CString::new("osc-booper").unwrap();
+        let mut thumbnail = 0;
+
+        // not tied to `self`, as the handle is written below
+        let table = unsafe { self.api.as_ref() };
+        let error = unsafe {
+            table.CreateDashboardOverlay.unwrap()(
+                key.as_ptr() as *mut _,
+                name.as_ptr() as *mut _,
+                &mut self.handle,
+                &mut thumbnail,
+            )
+        };
+        check(error)?;
+
+        let mut scale = sys::HmdVector2_t {
+            v: [WIDTH as f32, HEIGHT as f32],
+        };
+        unsafe {
+            check(table.SetOverlayWidthInMeters.unwrap()(self.handle, 1.0))?;
+            check(table.SetOverlayInputMethod.unwrap()(
+                self.handle,
+                sys::VROverlayInputMethod_VROverlayInputMethod_Mouse,
+            ))?;
+            check(table.SetOverlayMouseScale.unwrap()(self.handle, &mut scale))?;
+        }
+
+        Ok(())
+    }
+
+    /// Replace overlay contents with an RGBA image of `WIDTH` x `HEIGHT`
+    fn set_image(&self, pixels: &mut [u8]) -> Result<(), String> {
+        let error = unsafe {
+            self.table().SetOverlayRaw.unwrap()(
+                self.handle,
+                pixels.as_mut_ptr() as *mut c_void,
+                WIDTH as u32,
+                HEIGHT as u32,
+                4,
+            )
+        };
+        check(error)
+    }
+
+    /// Next click on the overlay, in pixels from the top left
+    fn poll_click(&self) -> Option<(f32, f32)> {
+        loop {
+            let mut event: sys::VREvent_t = unsafe { mem::zeroed() };
+            let available = unsafe {
+                self.table().PollNextOverlayEvent.unwrap()(
+                    self.handle,
+                    &mut event,
+                    mem::size_of::<sys::VREvent_t>() as u32,
+                )
+            };
+            if !available {
+                return None;
+            }
+
+            if event.eventType == sys::EVREventType_VREvent_MouseButtonUp {
+                let mouse = unsafe { event.data.mouse };
+                // OpenVR's origin is at the bottom left
+                return Some((mouse.x, HEIGHT as f32 - mouse.y));
+            }
+        }
+    }
+
+    fn shutdown(&self) {
+        if self.handle != 0 {
+            unsafe { self.table().DestroyOverlay.unwrap()(self.handle) };
+        }
+        unsafe { sys::VR_ShutdownInternal() };
+    }
+}
+
+fn check(error: sys::EVROverlayError) -> Result<(), String> {
+    if error == sys::EVROverlayError_VROverlayError_None {
+        Ok(())
+    } else {
+        Err(format!("overlay error {error}"))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_button_at() {
+        assert_eq!(button_at(20.0, 200.0), Some(Button::Pause));
+        assert_eq!(button_at(250.0, 180.0), Some(Button::Post));
+        assert_eq!(button_at(400.0, 239.0), Some(Button::Reset));
+        // gap between buttons
+        assert_eq!(button_at(170.0, 200.0), None);
+        // counters
+        assert_eq!(button_at(100.0, 50.0), None);
+        assert_eq!(button_at(-1.0, 200.0), None);
+    }
+}
@@ -0,0 +1,99 @@
+use std::collections::{BTreeMap, HashMap};
+
+use jiff::Timestamp;
+use rosc::OscType;
+use serde_json::{Value, json};
+
+/// Address prefix of avatar parameters, e.g. `/avatar/parameters/OSCBoop`
+pub(crate) const PARAMETER_PREFIX: &str = "/avatar/parameters/";
+
+/// Latest value per avatar parameter, keyed by name without
+/// [`PARAMETER_PREFIX`]
+pub type ParameterValues = BTreeMap<String, OscType>;
+
+/// Update the cached value of `name`, returns whether it changed
+pub(crate) fn record(values: &mut ParameterValues, name: &str, value: &OscType) -> bool {
+    if values.get(name) == Some(value) {
+        return false;
+    }
+
+    values.insert(name.into(), value.clone());
+    true
+}
+
+/// Avatar parameters we've set, until when their echo is expected
+///
+/// VRChat sends parameters set via OSC back to us, these echoes are ignored
+/// instead of being handled like changes on the avatar.
+#[derive(Debug, Default)]
+pub(crate) struct Echoes(HashMap<String, Timestamp>);
+
+impl Echoes {
+    /// Expect an echo of `address` until `until`, other addresses than avatar
+    /// parameters aren't echoed
+    pub(crate) fn expect(&mut self, address: &str, until: Timestamp) {
+        if address.starts_with(PARAMETER_PREFIX) {
+            self.0.insert(address.into(), until);
+        }
+    }
+
+    /// Whether a message on `address` received at `now` is an echo
+    pub(crate) fn is_echo(&mut self, address: &str, now: Timestamp) -> bool {
+        self.0.retain(|_, until| now < *until);
+        self.0.contains_key(address)
+    }
+}
+
+/// JSON representation of a parameter value
+///
+/// VRChat only sends bools, ints and floats, other types are included for
+/// other platforms and debugging.
+pub(crate) fn to_json(value: &OscType) -> Value {
+    match value {
+        OscType::Bool(value) => json!(value),
+        OscType::Int(value) => json!(value),
+        OscType::Long(value) => json!(value),
+        OscType::Float(value) => json!(value),
+        OscType::Double(value) => json!(value),
+        OscType::String(value) => json!(value),
+        OscType::Char(value) => json!(value),
+        OscType::Nil | OscType::Inf => Value::Null,
+        other => json!(format!("{other:?}")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record() {
+        let mut values = ParameterValues::new();
+        assert!(record(&mut values, "OSCBoop", &OscType::Bool(true)));
+        assert!(!record(&mut values, "OSCBoop", &OscType::Bool(true)));
+        assert!(record(&mut values, "OSCBoop", &OscType::Bool(false)));
+        assert_eq!(values.len(), 1);
+    }
+
+    #[test]
+    fn test_echoes() {
+        let now = Timestamp::now();
+        let window = jiff::SignedDuration::from_secs(1);
+        let mut echoes = Echoes::default();
+        echoes.expect("/avatar/parameters/BoopCountToday", now + window);
+        echoes.expect("/chatbox/input", now + window);
+
+        assert!(echoes.is_echo("/avatar/parameters/BoopCountToday", now));
+        assert!(!echoes.is_echo("/avatar/parameters/OSCBoop", now));
+        assert!(!echoes.is_echo("/chatbox/input", now));
+        assert!(!echoes.is_echo("/avatar/parameters/BoopCountToday", now + window));
+    }
+
+    #[test]
+    fn test_to_json() {
+        assert_eq!(to_json(&OscType::Bool(true)), json!(true));
+        assert_eq!(to_json(&OscType::Int(3)), json!(3));
+        assert_eq!(to_json(&OscType::Float(0.5)), json!(0.5));
+        assert_eq!(to_json(&OscType::Nil), Value::Null);
+    }
+}
@@ -0,0 +1,138 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use directories::ProjectDirs;
+use tracing::{error, info};
+
+use crate::{config, events, instance, presets, storage};
+
+/// Config file, `config.toml` in the OS config directory by default
+static CONFIG_FILE: OnceLock<PathBuf> = OnceLock::new();
+
+/// Directory of boops and the event log, the OS data directory by default
+static DATA_DIR: OnceLock<PathBuf> = OnceLock::new();
+
+/// File moved from the working directory, where earlier versions kept it
+pub(crate) struct Migration {
+    from: PathBuf,
+    to: PathBuf,
+    result: io::Result<()>,
+}
+
+impl Migration {
+    /// Log the outcome, migrations happen before logging is set up
+    pub(crate) fn log(&self) {
+        match &self.result {
+            Ok(()) => info!("moved {} to {}", self.from.display(), self.to.display()),
+            Err(e) => error!(
+                err=%e,
+                "failed to move {} to {}",
+                self.from.display(),
+                self.to.display()
+            ),
+        }
+    }
+}
+
+/// Resolve where files are kept, `config` and `data_dir` override the OS
+/// defaults
+///
+/// Files still found in the working directory are moved there, unless the
+/// destination exists already. Must happen before any files are accessed.
+pub(crate) fn init(config: Option<PathBuf>, data_dir: Option<PathBuf>) -> Vec<Migration> {
+    let dirs = ProjectDirs::from("", "", "osc-booper");
+    let os_dir = |dir: fn(&ProjectDirs) -> &Path| {
+        dirs.as_ref()
+            .map_or_else(|| PathBuf::from("."), |dirs| dir(dirs).into())
+    };
+
+    // (file in the working directory, new location)
+    let mut candidates = vec![];
+
+    let config_file = config.unwrap_or_else(|| {
+        let name = instance::file_name(config::FILE_NAME);
+        let file = os_dir(ProjectDirs::config_dir).join(&name);
+        candidates.push((PathBuf::from(name), file.clone()));
+        file
+    });
+
+    let data_dir = data_dir.unwrap_or_else(|| {
+        let dir = os_dir(ProjectDirs::data_dir);
+        let boops = instance::file_name(storage::FILE_NAME);
+        let names = [
+            format!("{boops}.{}", storage::BACKUP_EXTENSION),
+            boops,
+            instance::file_name(events::FILE_NAME),
+        ];
+        for name in names {
+            candidates.push((PathBuf::from(&name), dir.join(name)));
+        }
+        dir
+    });
+
+    let migrations = candidates
+        .into_iter()
+        .filter(|(from, to)| from.exists() && !to.exists())
+        .map(|(from, to)| {
+            let result = move_file(&from, &to);
+            Migration { from, to, result }
+        })
+        .collect();
+
+    // writers report errors themselves
+    fs::create_dir_all(&data_dir).ok();
+
+    CONFIG_FILE.set(config_file).ok();
+    DATA_DIR.set(data_dir).ok();
+    migrations
+}
+
+/// Config file of this instance
+pub(crate) fn config_file() -> PathBuf {
+    match CONFIG_FILE.get() {
+        Some(file) => file.clone(),
+        None => instance::file_name(config::FILE_NAME).into(),
+    }
+}
+
+/// Directory of boops and the event log
+pub(crate) fn data_dir() -> PathBuf {
+    match DATA_DIR.get() {
+        Some(dir) => dir.clone(),
+        None => PathBuf::from("."),
+    }
+}
+
+/// Directory of preset packs, next to the config file
+pub(crate) fn presets_dir() -> PathBuf {
+    let config_file = config_file();
+    match config_file.parent() {
+        Some(dir) => dir.join(presets::DIR_NAME),
+        None => PathBuf::from(presets::DIR_NAME),
+    }
+}
+
+/// Data file of this instance, e.g. `boops.toml`
+pub(crate) fn data_file(base: &str) -> PathBuf {
+    let name = instance::file_name(base);
+    match DATA_DIR.get() {
+        Some(dir) => dir.join(name),
+        None => name.into(),
+    }
+}
+
+/// Move `from` to `to`, which may be on another file system
+fn move_file(from: &Path, to: &Path) -> io::Result<()> {
+    if let Some(parent) = to.parent() {
+        fs::create_dir_all(parent)?;
+    }
+
+    if fs::rename(from, to).is_ok() {
+        return Ok(());
+    }
+    fs::copy(from, to)?;
+    fs::remove_file(from)
+}
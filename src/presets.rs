@@ -0,0 +1,281 @@
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+use serde::Deserialize;
+use serde_valid::Validate;
+use tracing::{error, info};
+
+use crate::{
+    config::{ConfigError, Options, PresetsCommand, TextSuffix},
+    paths,
+};
+
+/// Directory of preset packs, next to the config file
+pub(crate) const DIR_NAME: &str = "presets";
+
+const EXTENSION: &str = "toml";
+
+/// Appended to the file name of disabled packs
+const DISABLED_EXTENSION: &str = "disabled";
+
+/// Shareable pack of text suffixes and chatbox templates
+#[derive(Debug, Default, Deserialize, Validate)]
+#[serde(deny_unknown_fields)]
+pub(crate) struct PresetPack {
+    /// shown by `presets list`
+    #[serde(default)]
+    description: Option<String>,
+
+    #[serde(default)]
+    #[validate]
+    text_suffixes: Vec<TextSuffix>,
+
+    /// used in turn with the configured templates
+    #[serde(default)]
+    message_templates: Vec<String>,
+}
+
+/// Pack file in the presets directory
+struct PresetFile {
+    /// file name without extensions
+    name: String,
+    path: PathBuf,
+    enabled: bool,
+}
+
+impl PresetFile {
+    fn read(&self) -> Result<PresetPack, ConfigError> {
+        let file = self.path.display().to_string();
+        let contents = match fs::read_to_string(&self.path) {
+            Ok(contents) => contents,
+            Err(source) => return Err(ConfigError::Read { file, source }),
+        };
+
+        let pack: PresetPack = match toml::from_str(&contents) {
+            Ok(pack) => pack,
+            Err(source) => return Err(ConfigError::Parse { file, source }),
+        };
+
+        if let Err(errors) = pack.validate() {
+            return Err(ConfigError::Invalid {
+                file,
+                errors: Box::new(errors),
+            });
+        }
+
+        Ok(pack)
+    }
+}
+
+/// Enabled packs in `dir`, in the order they're applied
+pub(crate) fn load(dir: &Path) -> Result<Vec<PresetPack>, ConfigError> {
+    let files = files(dir).map_err(|source| ConfigError::Read {
+        file: dir.display().to_string(),
+        source,
+    })?;
+
+    files
+        .iter()
+        .filter(|file| file.enabled)
+        .map(PresetFile::read)
+        .collect()
+}
+
+/// Merge `packs` into `options`
+///
+/// Suffixes of later packs replace the ones for the same number of earlier
+/// packs and the config file. Templates are added to the configured ones,
+/// starting with `message_template` if there are no `message_templates`.
+pub(crate) fn apply(options: &mut Options, packs: Vec<PresetPack>) {
+    let mut templates = vec![];
+    for pack in packs {
+        for suffix in pack.text_suffixes {
+            options
+                .text_suffixes
+                .retain(|configured| configured.value() != suffix.value());
+            options.text_suffixes.push(suffix);
+        }
+        templates.extend(pack.message_templates);
+    }
+    options.text_suffixes.sort_by_key(TextSuffix::value);
+
+    if templates.is_empty() {
+        return;
+    }
+    if options.message_templates.is_empty() {
+        options
+            .message_templates
+            .push(options.message_template.clone());
+    }
+    for template in templates {
+        if !options.message_templates.contains(&template) {
+            options.message_templates.push(template);
+        }
+    }
+}
+
+/// Run a `presets` subcommand
+///
+/// Doesn't need a valid config, so broken packs can be disabled.
+pub(crate) fn run(command: &PresetsCommand) {
+    let dir = paths::presets_dir();
+    let result = match command {
+        PresetsCommand::List => list(&dir),
+        PresetsCommand::Enable { name } => set_enabled(&dir, name, true),
+        PresetsCommand::Disable { name } => set_enabled(&dir, name, false),
+    };
+
+    if let Err(e) = result {
+        error!(err=%e, "failed to manage presets in {}", dir.display());
+        std::process::exit(1);
+    }
+}
+
+fn list(dir: &Path) -> io::Result<()> {
+    let files = files(dir)?;
+    if files.is_empty() {
+        println!("no preset packs in {}", dir.display());
+        return Ok(());
+    }
+
+    for file in files {
+        let state = if file.enabled { "enabled" } else { "disabled" };
+        match file.read() {
+            Ok(pack) => println!(
+                "{} ({state}): {} suffixes, {} templates{}",
+                file.name,
+                pack.text_suffixes.len(),
+                pack.message_templates.len(),
+                pack.description
+                    .map(|description| format!(" - {description}"))
+                    .unwrap_or_default(),
+            ),
+            Err(e) => println!("{} ({state}): {e}", file.name),
+        }
+    }
+    Ok(())
+}
+
+fn set_enabled(dir: &Path, name: &str, enabled: bool) -> io::Result<()> {
+    let Some(file) = files(dir)?.into_iter().find(|file| file.name == name) else {
+        return Err(io::Error::new(
+            io::ErrorKind::NotFound,
+            format!("no preset pack {name:?}"),
+        ));
+    };
+
+    let state = if enabled { "enabled" } else { "disabled" };
+    if file.enabled == enabled {
+        info!("preset pack {name} is {state} already");
+        return Ok(());
+    }
+
+    fs::rename(&file.path, dir.join(file_name(name, enabled)))?;
+    info!("preset pack {name} {state}, restart the booper or edit its config to apply");
+    Ok(())
+}
+
+/// Packs in `dir`, sorted by name, none if it doesn't exist
+fn files(dir: &Path) -> io::Result<Vec<PresetFile>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(vec![]),
+        Err(e) => return Err(e),
+    };
+
+    let mut files = vec![];
+    for entry in entries {
+        let path = entry?.path();
+        let parsed = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(parse_file_name);
+        if let Some((name, enabled)) = parsed {
+            files.push(PresetFile {
+                name,
+                path,
+                enabled,
+            });
+        }
+    }
+
+    files.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(files)
+}
+
+/// `cats.toml` is the enabled pack `cats`, `cats.toml.disabled` the disabled
+/// one
+fn parse_file_name(file_name: &str) -> Option<(String, bool)> {
+    let (name, enabled) = match file_name.strip_suffix(DISABLED_EXTENSION) {
+        Some(name) => (name.strip_suffix('.')?, false),
+        None => (file_name, true),
+    };
+    let name = name.strip_suffix(EXTENSION)?.strip_suffix('.')?;
+    if name.is_empty() {
+        return None;
+    }
+    Some((name.into(), enabled))
+}
+
+fn file_name(name: &str, enabled: bool) -> String {
+    if enabled {
+        format!("{name}.{EXTENSION}")
+    } else {
+        format!("{name}.{EXTENSION}.{DISABLED_EXTENSION}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_names() {
+        assert_eq!(parse_file_name("cats.toml"), Some(("cats".into(), true)));
+        assert_eq!(
+            parse_file_name("cats.toml.disabled"),
+            Some(("cats".into(), false))
+        );
+        assert_eq!(parse_file_name("cats.json"), None);
+        assert_eq!(parse_file_name(".toml"), None);
+
+        for enabled in [true, false] {
+            let name = file_name("cats", enabled);
+            assert_eq!(parse_file_name(&name), Some(("cats".into(), enabled)));
+        }
+    }
+
+    #[test]
+    fn test_apply() {
+        let mut options = Options::default();
+        let packs = vec![
+            PresetPack {
+                text_suffixes: vec![TextSuffix::new(69, "nya".into())],
+                message_templates: vec!["{today} boops".into()],
+                ..Default::default()
+            },
+            PresetPack {
+                text_suffixes: vec![
+                    TextSuffix::new(3, "mew".into()),
+                    TextSuffix::new(69, "meow".into()),
+                ],
+                ..Default::default()
+            },
+        ];
+        apply(&mut options, packs);
+
+        let suffixes: Vec<_> = options
+            .text_suffixes
+            .iter()
+            .map(TextSuffix::value)
+            .collect();
+        assert_eq!(suffixes, [3, 69, 420, 621, 666, 1337]);
+        assert_eq!(options.text_suffixes[1], TextSuffix::new(69, "meow".into()));
+        assert_eq!(
+            options.message_templates,
+            [options.message_template.clone(), "{today} boops".into()]
+        );
+    }
+}
@@ -0,0 +1,70 @@
+use std::collections::VecDeque;
+
+use jiff::{SignedDuration, Timestamp};
+
+/// Window recent boops are counted in
+const WINDOW: SignedDuration = SignedDuration::from_secs(60);
+
+/// Sliding window of recent boops, for boops per minute and storm detection
+#[derive(Debug, Default)]
+pub(crate) struct BoopRate {
+    boops: VecDeque<Timestamp>,
+
+    /// whether the last check was above the storm threshold
+    storming: bool,
+}
+
+impl BoopRate {
+    /// Record boop, dropping the ones that left the window
+    pub(crate) fn record(&mut self, now: Timestamp) {
+        self.boops.push_back(now);
+        while self.boops.front().is_some_and(|&time| time <= now - WINDOW) {
+            self.boops.pop_front();
+        }
+    }
+
+    /// Boops within the last minute
+    pub(crate) fn per_minute(&self, now: Timestamp) -> u32 {
+        self.boops
+            .iter()
+            .filter(|&&time| time > now - WINDOW)
+            .count() as u32
+    }
+
+    /// Whether the rate just reached `threshold`, a storm only starts again
+    /// after the rate dropped below it
+    pub(crate) fn storm_started(&mut self, now: Timestamp, threshold: u32) -> bool {
+        let storming = self.per_minute(now) >= threshold;
+        let started = storming && !self.storming;
+        self.storming = storming;
+
+        started
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rate_and_storm() {
+        let start: Timestamp = "2025-01-01T12:00:00Z".parse().unwrap();
+        let mut rate = BoopRate::default();
+
+        for second in 0..3 {
+            rate.record(start + SignedDuration::from_secs(second));
+        }
+        let now = start + SignedDuration::from_secs(2);
+        assert_eq!(rate.per_minute(now), 3);
+        assert!(rate.storm_started(now, 3));
+        assert!(!rate.storm_started(now, 3), "storm already running");
+
+        // first boop left the window
+        let later = start + SignedDuration::from_secs(60);
+        assert_eq!(rate.per_minute(later), 2);
+        assert!(!rate.storm_started(later, 3));
+
+        rate.record(later);
+        assert!(rate.storm_started(later, 3));
+    }
+}
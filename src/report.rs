@@ -0,0 +1,191 @@
+use std::{fmt::Write, fs};
+
+use tracing::{error, info};
+
+use crate::{
+    config::{ReportFormat, ReportOptions},
+    instance,
+    storage::PeriodSummary,
+};
+
+/// Width of the longest bar in the hourly chart
+const BAR_WIDTH: u32 = 40;
+
+/// Write report of a finished period into the reports directory
+pub(crate) fn write(options: &ReportOptions, summary: &PeriodSummary) {
+    let (extension, contents) = match options.format {
+        ReportFormat::Markdown => ("md", markdown(summary)),
+        ReportFormat::Html => ("html", html(summary)),
+    };
+
+    if let Err(e) = fs::create_dir_all(&options.dir) {
+        error!(err=%e, "failed to create reports directory {}", options.dir.display());
+        return;
+    }
+
+    let file_name = instance::file_name(&format!("{}.{extension}", summary.start.date()));
+    let path = options.dir.join(file_name);
+    match fs::write(&path, contents) {
+        Ok(()) => info!("wrote report {}", path.display()),
+        Err(e) => error!(err=%e, "failed to write report {}", path.display()),
+    }
+}
+
+fn markdown(summary: &PeriodSummary) -> String {
+    let mut out = String::new();
+    writeln!(out, "# Boops {}\n", summary.start.date()).ok();
+    writeln!(out, "| | |\n|---|---|").ok();
+    writeln!(out, "| Boops | {} |", summary.boops).ok();
+    writeln!(
+        out,
+        "| Daily record | {}{} |",
+        summary.record,
+        record_note(summary)
+    )
+    .ok();
+    writeln!(out, "| Lifetime boops | {} |", summary.total).ok();
+    for (name, counter) in &summary.counters {
+        writeln!(
+            out,
+            "| {name} | {} ({} total) |",
+            counter.today, counter.total
+        )
+        .ok();
+    }
+    writeln!(out, "| Period | {} |", period(summary)).ok();
+
+    writeln!(out, "\n## Per hour\n\n```").ok();
+    for (hour, boops) in summary.hourly.iter().enumerate() {
+        let bar = "█".repeat(bar_length(*boops, summary) as usize);
+        writeln!(out, "{hour:02}:00 {bar} {boops}").ok();
+    }
+    writeln!(out, "```").ok();
+
+    out
+}
+
+fn html(summary: &PeriodSummary) -> String {
+    let mut counters = String::new();
+    for (name, counter) in &summary.counters {
+        writeln!(
+            counters,
+            "<tr><th>{name}</th><td>{} ({} total)</td></tr>",
+            counter.today, counter.total
+        )
+        .ok();
+    }
+
+    let mut rows = String::new();
+    for (hour, boops) in summary.hourly.iter().enumerate() {
+        writeln!(
+            rows,
+            r#"<tr><td>{hour:02}:00</td><td><div class="bar" style="width: {}ch"></div></td><td>{boops}</td></tr>"#,
+            bar_length(*boops, summary)
+        )
+        .ok();
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>Boops {date}</title>
+<style>
+body {{ font-family: sans-serif; }}
+td {{ padding: 0 .5em; }}
+.bar {{ background: #ff69b4; height: 1em; }}
+</style>
+</head>
+<body>
+<h1>Boops {date}</h1>
+<table>
+<tr><th>Boops</th><td>{boops}</td></tr>
+<tr><th>Daily record</th><td>{record}{record_note}</td></tr>
+<tr><th>Lifetime boops</th><td>{total}</td></tr>
+{counters}<tr><th>Period</th><td>{period}</td></tr>
+</table>
+<h2>Per hour</h2>
+<table>
+{rows}</table>
+</body>
+</html>
+"#,
+        date = summary.start.date(),
+        boops = summary.boops,
+        record = summary.record,
+        record_note = record_note(summary),
+        total = summary.total,
+        period = period(summary),
+    )
+}
+
+fn period(summary: &PeriodSummary) -> String {
+    format!(
+        "{} – {}",
+        summary.start.strftime("%F %R"),
+        summary.end.strftime("%F %R")
+    )
+}
+
+fn record_note(summary: &PeriodSummary) -> &'static str {
+    if summary.boops > 0 && summary.boops == summary.record {
+        " (new record!)"
+    } else {
+        ""
+    }
+}
+
+/// Bar length relative to the busiest hour
+fn bar_length(boops: u32, summary: &PeriodSummary) -> u32 {
+    let max = summary.hourly.iter().copied().max().unwrap_or(0);
+    if max == 0 {
+        return 0;
+    }
+
+    (boops as u64 * BAR_WIDTH as u64).div_ceil(max as u64) as u32
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use jiff::Zoned;
+
+    use super::*;
+    use crate::storage::CounterBoops;
+
+    #[test]
+    fn test_markdown_report() {
+        let mut hourly = vec![0; 24];
+        hourly[13] = 2;
+        hourly[20] = 8;
+
+        let summary = PeriodSummary {
+            start: Zoned::from_str("2025-03-29T00:00:00+00:00[UTC]").unwrap(),
+            end: Zoned::from_str("2025-03-30T00:00:00+00:00[UTC]").unwrap(),
+            boops: 10,
+            hourly,
+            record: 10,
+            total: 1234,
+            counters: [(
+                "headpats".into(),
+                CounterBoops {
+                    today: 3,
+                    total: 42,
+                    record: 5,
+                    last_reset: None,
+                },
+            )]
+            .into(),
+        };
+
+        let report = markdown(&summary);
+        assert!(report.starts_with("# Boops 2025-03-29\n"));
+        assert!(report.contains("| Daily record | 10 (new record!) |"));
+        assert!(report.contains("| headpats | 3 (42 total) |"));
+        assert!(report.contains(&format!("20:00 {} 8\n", "█".repeat(40))));
+        assert!(report.contains(&format!("13:00 {} 2\n", "█".repeat(10))));
+        assert!(report.contains("00:00  0\n"));
+    }
+}
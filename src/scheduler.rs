@@ -0,0 +1,61 @@
+use jiff::{SignedDuration, Zoned};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    command::{Command, CommandSender},
+    config::Schedule,
+};
+
+/// Spawn one task per configured schedule, posting via the command channel
+pub(crate) fn spawn(token: CancellationToken, schedules: &[Schedule], commands: CommandSender) {
+    for schedule in schedules {
+        if schedule.every.is_zero() || schedule.every.is_negative() {
+            warn!(every=%schedule.every, "ignoring schedule with non-positive interval");
+            continue;
+        }
+
+        let token = token.clone();
+        let commands = commands.clone();
+        let schedule = schedule.clone();
+
+        info!(every=%schedule.every, align=%schedule.align, "starting schedule");
+
+        tokio::spawn(async move {
+            loop {
+                let wait = time_until_next(&schedule, &Zoned::now());
+                debug!(every=%schedule.every, wait=%wait, "waiting for next scheduled post");
+
+                tokio::select! {
+                    _ = token.cancelled() => break,
+                    _ = tokio::time::sleep(wait.unsigned_abs()) => {}
+                }
+
+                let command = Command::PostStats {
+                    template: schedule.template.clone(),
+                    manual: false,
+                };
+                if commands.send(command).await.is_err() {
+                    warn!("command channel closed, stopping schedule");
+                    break;
+                }
+            }
+        });
+    }
+}
+
+/// Calculate time until schedule fires next
+///
+/// Aligned schedules fire on multiples of `every` since local midnight,
+/// e.g. `every = "30m"` fires at :00 and :30, similar to cron.
+fn time_until_next(schedule: &Schedule, now: &Zoned) -> SignedDuration {
+    if !schedule.align {
+        return schedule.every;
+    }
+
+    let every = schedule.every.as_secs().max(1);
+    let since_midnight = now.time().duration_since(jiff::civil::Time::midnight());
+    let elapsed = since_midnight.as_secs() % every;
+
+    SignedDuration::from_secs(every - elapsed)
+}
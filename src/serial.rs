@@ -0,0 +1,80 @@
+use std::{io::Write, sync::mpsc, time::Duration};
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{config::SerialOptions, events::BooperEvent, template};
+
+/// Write the counts to a serial port whenever they change
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: SerialOptions,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    // serial I/O is blocking, keep it on a dedicated thread
+    let (tx, rx) = mpsc::channel::<String>();
+    let writer_options = options.clone();
+    std::thread::spawn(move || write_loop(writer_options, rx));
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            let (today, total) = match event {
+                Ok(event) => match event.counts() {
+                    Some(counts) => counts,
+                    None => continue,
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "serial output lagging behind");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            let line = template::render(&options.template, |key| match key {
+                "today" => Some(today.to_string()),
+                "total" => Some(total.to_string()),
+                _ => None,
+            });
+            if tx.send(line).is_err() {
+                break;
+            }
+        }
+    });
+}
+
+/// Write lines to the port, reopening it after errors, e.g. when the device
+/// got replugged
+fn write_loop(options: SerialOptions, lines: mpsc::Receiver<String>) {
+    let mut port = None;
+
+    while let Ok(line) = lines.recv() {
+        if port.is_none() {
+            port = match serialport::new(&options.port, options.baud)
+                .timeout(Duration::from_secs(1))
+                .open()
+            {
+                Ok(port) => {
+                    info!("opened serial port {}", options.port);
+                    Some(port)
+                }
+                Err(e) => {
+                    warn!(err=%e, "failed to open serial port {}", options.port);
+                    continue;
+                }
+            };
+        }
+
+        if let Some(open) = &mut port
+            && let Err(e) = open.write_all(line.as_bytes()).and_then(|_| open.flush())
+        {
+            warn!(err=%e, "failed to write to serial port {}", options.port);
+            port = None;
+        }
+    }
+}
@@ -0,0 +1,196 @@
+//! Integration with service managers: systemd's `sd_notify` protocol on Linux
+//! and the service control manager on Windows
+
+use tokio_util::sync::CancellationToken;
+
+/// Prepare running as service, before the config is loaded
+///
+/// Windows services start in `System32`, so files are looked up next to our
+/// executable instead.
+pub(crate) fn init() {
+    #[cfg(windows)]
+    if let Some(dir) = std::env::current_exe()
+        .ok()
+        .as_deref()
+        .and_then(std::path::Path::parent)
+    {
+        std::env::set_current_dir(dir).ok();
+    }
+}
+
+/// Report that we're up and running, stopping once `token` is cancelled,
+/// which the service manager may do as well
+pub(crate) fn ready(token: CancellationToken) {
+    imp::ready(token.clone());
+
+    tokio::spawn(async move {
+        token.cancelled().await;
+        imp::stopping();
+    });
+}
+
+/// Report that we're done, right before exiting
+pub(crate) fn stopped() {
+    imp::stopped();
+}
+
+#[cfg(target_os = "linux")]
+mod imp {
+    use std::{
+        ffi::OsString,
+        os::{
+            linux::net::SocketAddrExt,
+            unix::{
+                ffi::OsStrExt,
+                net::{SocketAddr, UnixDatagram},
+            },
+        },
+    };
+
+    use tokio_util::sync::CancellationToken;
+    use tracing::{debug, warn};
+
+    const NOTIFY_SOCKET_ENV: &str = "NOTIFY_SOCKET";
+
+    pub(super) fn ready(_token: CancellationToken) {
+        // systemd stops us via SIGTERM, handled like everywhere else
+        notify("READY=1");
+    }
+
+    pub(super) fn stopping() {
+        notify("STOPPING=1");
+    }
+
+    pub(super) fn stopped() {}
+
+    /// Send state to systemd, if it's listening
+    fn notify(state: &str) {
+        let Some(path) = std::env::var_os(NOTIFY_SOCKET_ENV) else {
+            debug!("{NOTIFY_SOCKET_ENV} not set, not notifying systemd");
+            return;
+        };
+
+        if let Err(e) = send(&path, state) {
+            warn!(err=%e, "failed to notify systemd");
+        }
+    }
+
+    fn send(path: &OsString, state: &str) -> std::io::Result<()> {
+        // abstract socket names start with `@`
+        let address = match path.as_bytes().strip_prefix(b"@") {
+            Some(name) => SocketAddr::from_abstract_name(name)?,
+            None => SocketAddr::from_pathname(path)?,
+        };
+
+        let socket = UnixDatagram::unbound()?;
+        socket.send_to_addr(state.as_bytes(), &address)?;
+        Ok(())
+    }
+}
+
+#[cfg(all(windows, feature = "service"))]
+mod imp {
+    use std::{ffi::OsString, sync::OnceLock, thread, time::Duration};
+
+    use tokio_util::sync::CancellationToken;
+    use tracing::{error, info};
+    use windows_service::{
+        define_windows_service,
+        service::{
+            ServiceControl, ServiceControlAccept, ServiceExitCode, ServiceState, ServiceStatus,
+            ServiceType,
+        },
+        service_control_handler::{self, ServiceControlHandlerResult, ServiceStatusHandle},
+        service_dispatcher,
+    };
+
+    use crate::instance;
+
+    /// Cancelled when the service manager asks us to stop
+    static TOKEN: OnceLock<CancellationToken> = OnceLock::new();
+
+    static STATUS: OnceLock<ServiceStatusHandle> = OnceLock::new();
+
+    define_windows_service!(ffi_service_main, service_main);
+
+    pub(super) fn ready(token: CancellationToken) {
+        TOKEN.set(token).ok();
+
+        // blocks until the service is stopped
+        thread::spawn(|| {
+            let name = instance::scoped("osc-booper", "-");
+            if let Err(e) = service_dispatcher::start(&name, ffi_service_main) {
+                error!(err=%e, "failed to connect to the service control manager");
+            }
+        });
+    }
+
+    pub(super) fn stopping() {
+        set_state(ServiceState::StopPending);
+    }
+
+    pub(super) fn stopped() {
+        set_state(ServiceState::Stopped);
+    }
+
+    fn service_main(_arguments: Vec<OsString>) {
+        let handler = |control| match control {
+            ServiceControl::Stop | ServiceControl::Shutdown => {
+                info!("stop requested by the service control manager");
+                if let Some(token) = TOKEN.get() {
+                    token.cancel();
+                }
+                ServiceControlHandlerResult::NoError
+            }
+            ServiceControl::Interrogate => ServiceControlHandlerResult::NoError,
+            _ => ServiceControlHandlerResult::NotImplemented,
+        };
+
+        let name = instance::scoped("osc-booper", "-");
+        match service_control_handler::register(&name, handler) {
+            Ok(status) => {
+                STATUS.set(status).ok();
+                set_state(ServiceState::Running);
+            }
+            Err(e) => error!(err=%e, "failed to register service control handler"),
+        }
+    }
+
+    fn set_state(state: ServiceState) {
+        let Some(status) = STATUS.get() else {
+            return;
+        };
+
+        let controls_accepted = match state {
+            ServiceState::Running => ServiceControlAccept::STOP | ServiceControlAccept::SHUTDOWN,
+            _ => ServiceControlAccept::empty(),
+        };
+        let result = status.set_service_status(ServiceStatus {
+            service_type: ServiceType::OWN_PROCESS,
+            current_state: state,
+            controls_accepted,
+            exit_code: ServiceExitCode::Win32(0),
+            checkpoint: 0,
+            wait_hint: Duration::from_secs(5),
+            process_id: None,
+        });
+
+        if let Err(e) = result {
+            error!(err=%e, ?state, "failed to report service state");
+        }
+    }
+}
+
+#[cfg(not(any(target_os = "linux", all(windows, feature = "service"))))]
+mod imp {
+    use tokio_util::sync::CancellationToken;
+    use tracing::warn;
+
+    pub(super) fn ready(_token: CancellationToken) {
+        warn!("service mode is not available in this build");
+    }
+
+    pub(super) fn stopping() {}
+
+    pub(super) fn stopped() {}
+}
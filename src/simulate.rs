@@ -0,0 +1,318 @@
+use std::{net::SocketAddr, time::Duration};
+
+use rosc::{OscMessage, OscPacket, OscType, encoder};
+use tokio::{
+    net::UdpSocket,
+    time::{Instant, Interval},
+};
+use tracing::{error, info};
+
+use crate::{
+    config::{Comparison, Options, SimulateArgs, Trigger},
+    oscquery::tree,
+};
+
+/// Parameter sent as noise, like the many parameters of a moving avatar
+const NOISE_PARAMETER: &str = "/avatar/parameters/SimulatedNoise";
+
+/// Time a simulated contact stays triggered before it's released
+const CONTACT_DURATION: Duration = Duration::from_millis(100);
+
+/// Packets VRChat would send to the booper
+#[derive(Debug)]
+pub(crate) struct Simulator {
+    /// full address of the boop contact
+    boop_address: String,
+    /// values sent when the contact is triggered and released
+    pressed: OscType,
+    released: OscType,
+    avatars: u32,
+}
+
+impl Simulator {
+    pub(crate) fn new(options: &Options) -> Self {
+        let (pressed, released) = contact_values(options.boop_trigger, options.boop_hysteresis);
+        Simulator {
+            boop_address: tree::parameter_path(&options.boop_address.example()),
+            pressed,
+            released,
+            avatars: 0,
+        }
+    }
+
+    /// Contact triggering a boop, followed by its release
+    pub(crate) fn boop(&self) -> [OscPacket; 2] {
+        [
+            message(&self.boop_address, self.pressed.clone()),
+            message(&self.boop_address, self.released.clone()),
+        ]
+    }
+
+    /// Switch to a new avatar
+    pub(crate) fn avatar_change(&mut self) -> OscPacket {
+        self.avatars += 1;
+        message(
+            "/avatar/change",
+            OscType::String(format!("avtr_simulated-{}", self.avatars)),
+        )
+    }
+
+    /// Random value of a parameter we don't care about
+    pub(crate) fn noise(&self) -> OscPacket {
+        message(NOISE_PARAMETER, OscType::Float(rand::random()))
+    }
+}
+
+/// Send simulated packets to a running booper until stopped
+pub(crate) async fn run(args: &SimulateArgs, options: &Options) {
+    let target = SocketAddr::new(
+        options.osc_listen_addr(),
+        args.port.or(options.listen_port).unwrap_or(9001),
+    );
+    let socket = match UdpSocket::bind((options.loopback(), 0)).await {
+        Ok(socket) => socket,
+        Err(e) => {
+            error!(err=%e, "failed to bind osc socket");
+            std::process::exit(1);
+        }
+    };
+
+    let mut simulator = Simulator::new(options);
+    let mut boops = rate(args.boops, 60);
+    let mut avatar_changes = rate(args.avatar_changes, 60);
+    let mut noise = rate(args.parameters, 1);
+    // runs until stopped without a duration
+    let stop = tokio::time::sleep(args.duration.map_or(Duration::MAX, |d| d.unsigned_abs()));
+    tokio::pin!(stop);
+
+    info!("simulating VRChat, sending to {target}");
+    let mut sent_boops = 0;
+    loop {
+        let packets = tokio::select! {
+            _ = tokio::signal::ctrl_c() => break,
+            _ = &mut stop => break,
+            _ = tick(&mut boops) => {
+                sent_boops += 1;
+                let [pressed, released] = simulator.boop();
+                send(&socket, target, pressed).await;
+                tokio::time::sleep(CONTACT_DURATION).await;
+                vec![released]
+            },
+            _ = tick(&mut avatar_changes) => vec![simulator.avatar_change()],
+            _ = tick(&mut noise) => vec![simulator.noise()],
+        };
+
+        for packet in packets {
+            send(&socket, target, packet).await;
+        }
+    }
+
+    info!(boops = sent_boops, "simulation stopped");
+}
+
+/// Interval of `amount` events per `seconds`, none for zero
+fn rate(amount: u32, seconds: u64) -> Option<Interval> {
+    if amount == 0 {
+        return None;
+    }
+
+    let period = Duration::from_secs(seconds) / amount;
+    Some(tokio::time::interval_at(Instant::now() + period, period))
+}
+
+/// Next tick of `interval`, never for disabled ones
+async fn tick(interval: &mut Option<Interval>) {
+    match interval {
+        Some(interval) => {
+            interval.tick().await;
+        }
+        None => std::future::pending().await,
+    }
+}
+
+async fn send(socket: &UdpSocket, target: SocketAddr, packet: OscPacket) {
+    let buf = encoder::encode(&packet).expect("simulated packets are valid");
+    if let Err(e) = socket.send_to(&buf, target).await {
+        error!(err=%e, "failed to send simulated packet");
+    }
+}
+
+fn message(address: &str, value: OscType) -> OscPacket {
+    OscPacket::Message(OscMessage {
+        addr: address.into(),
+        args: vec![value],
+    })
+}
+
+/// Values triggering and releasing a contact with `trigger`
+fn contact_values(trigger: Trigger, hysteresis: f32) -> (OscType, OscType) {
+    match trigger {
+        Trigger::Bool => (OscType::Bool(true), OscType::Bool(false)),
+        Trigger::Int(comparison, threshold) => {
+            let pressed = match comparison {
+                Comparison::Greater => threshold.saturating_add(1),
+                Comparison::GreaterOrEqual | Comparison::Equal => threshold,
+            };
+            let released = threshold.saturating_sub(hysteresis.ceil() as i32 + 1);
+            (OscType::Int(pressed), OscType::Int(released))
+        }
+        Trigger::Float(comparison, threshold) => {
+            let pressed = match comparison {
+                Comparison::Greater => threshold + 0.1,
+                Comparison::GreaterOrEqual | Comparison::Equal => threshold,
+            };
+            let released = threshold - hysteresis - 0.1;
+            (OscType::Float(pressed), OscType::Float(released))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{net::Ipv4Addr, sync::Once};
+
+    use tokio::sync::broadcast;
+    use tokio_util::sync::CancellationToken;
+
+    use super::*;
+    use crate::{events::BooperEvent, osc::OscBooper};
+
+    /// Run the booper against a fake VRChat on loopback, its files are
+    /// written to a temporary directory
+    struct Harness {
+        booper: OscBooper,
+        vrchat: UdpSocket,
+        events: broadcast::Receiver<BooperEvent>,
+    }
+
+    impl Harness {
+        async fn new(options: Options) -> Self {
+            static TEMP_DIR: Once = Once::new();
+            TEMP_DIR.call_once(|| {
+                let dir = std::env::temp_dir().join(format!("osc-booper-{}", std::process::id()));
+                std::fs::create_dir_all(&dir).unwrap();
+                std::env::set_current_dir(dir).unwrap();
+            });
+
+            let vrchat = UdpSocket::bind((Ipv4Addr::LOCALHOST, 0)).await.unwrap();
+            let booper = OscBooper::new(Options {
+                osc_send_port: vrchat.local_addr().unwrap().port(),
+                announce: false,
+                ..options
+            })
+            .await
+            .unwrap();
+            let events = booper.subscribe();
+
+            Harness {
+                booper,
+                vrchat,
+                events,
+            }
+        }
+
+        /// Send `packets` to the running booper and collect all events until
+        /// it's idle
+        async fn run(self, packets: Vec<OscPacket>) -> Vec<BooperEvent> {
+            let Harness {
+                mut booper,
+                vrchat,
+                mut events,
+            } = self;
+            let target = SocketAddr::from((Ipv4Addr::LOCALHOST, booper.osc_port));
+            let token = CancellationToken::new();
+
+            let simulate = async {
+                for packet in packets {
+                    send(&vrchat, target, packet).await;
+                }
+
+                let mut received = vec![];
+                while let Ok(Ok(event)) =
+                    tokio::time::timeout(Duration::from_millis(500), events.recv()).await
+                {
+                    received.push(event);
+                }
+
+                token.cancel();
+                received
+            };
+
+            let (result, received) = tokio::join!(booper.run(token.clone()), simulate);
+            result.unwrap();
+            received
+        }
+    }
+
+    fn options(trigger: &str) -> Options {
+        Options {
+            boop_address: "/OSCBoop".into(),
+            boop_trigger: Trigger::try_from(trigger.to_string()).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    fn counted(events: &[BooperEvent]) -> Vec<u32> {
+        events
+            .iter()
+            .filter_map(|event| match event {
+                BooperEvent::BoopCounted { today, .. } => Some(*today),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_boops_are_counted() {
+        let options = options("bool");
+        let simulator = Simulator::new(&options);
+        let harness = Harness::new(options).await;
+
+        let mut packets: Vec<_> = simulator.boop().into();
+        packets.push(simulator.noise());
+        packets.extend(simulator.boop());
+
+        let events = harness.run(packets).await;
+        assert_eq!(counted(&events).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_proximity_counts_rising_edges() {
+        let options = Options {
+            boop_hysteresis: 0.2,
+            ..options("float > 0.8")
+        };
+        let harness = Harness::new(options).await;
+
+        let packets = [0.9, 0.95, 0.7, 0.9, 0.1, 0.85]
+            .into_iter()
+            .map(|value| message("/avatar/parameters/OSCBoop", OscType::Float(value)))
+            .collect();
+
+        let events = harness.run(packets).await;
+        assert_eq!(counted(&events).len(), 2);
+    }
+
+    #[tokio::test]
+    async fn test_avatar_change() {
+        let options = options("bool");
+        let mut simulator = Simulator::new(&options);
+        let harness = Harness::new(options).await;
+
+        let events = harness.run(vec![simulator.avatar_change()]).await;
+        assert!(events.iter().any(|event| matches!(
+            event,
+            BooperEvent::AvatarChanged { avatar_id } if avatar_id == "avtr_simulated-1"
+        )));
+    }
+
+    #[test]
+    fn test_contact_values() {
+        for trigger in ["bool", "int", "int >= 2", "float > 0.8", "float == 0.5"] {
+            let trigger = Trigger::try_from(trigger.to_string()).unwrap();
+            let (pressed, released) = contact_values(trigger, 0.3);
+            assert!(trigger.matches(&pressed), "{trigger:?} not pressed");
+            assert!(trigger.releases(&released, 0.3), "{trigger:?} not released");
+        }
+    }
+}
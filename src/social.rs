@@ -0,0 +1,160 @@
+use std::time::Duration;
+
+use jiff::Timestamp;
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{config::SocialOptions, events::BooperEvent, template};
+
+/// Access token of the Mastodon account
+const MASTODON_TOKEN_ENV: &str = "OSC_BOOPER_MASTODON_TOKEN";
+
+/// Handle and app password of the Bluesky account
+const BLUESKY_HANDLE_ENV: &str = "OSC_BOOPER_BLUESKY_HANDLE";
+const BLUESKY_PASSWORD_ENV: &str = "OSC_BOOPER_BLUESKY_APP_PASSWORD";
+
+const BLUESKY_SERVICE: &str = "https://bsky.social";
+
+/// Post a summary of every finished period to the configured accounts
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: SocialOptions,
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    let client = match reqwest::Client::builder()
+        .user_agent(concat!("osc-booper/", env!("CARGO_PKG_VERSION")))
+        .timeout(Duration::from_secs(30))
+        .build()
+    {
+        Ok(client) => client,
+        Err(e) => {
+            warn!(err=%e, "failed to create http client, social posting disabled");
+            return;
+        }
+    };
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            match event {
+                Ok(BooperEvent::Reset {
+                    previous,
+                    date,
+                    record,
+                    total,
+                }) => {
+                    if previous == 0 && options.skip_empty {
+                        debug!("no boops in finished period, not posting summary");
+                        continue;
+                    }
+
+                    let text = template::render(&options.template, |key| match key {
+                        "boops" => Some(previous.to_string()),
+                        "date" => Some(date.to_string()),
+                        "record" => Some(record.to_string()),
+                        "total" => Some(total.to_string()),
+                        _ => None,
+                    });
+                    post(&client, &options, &text).await;
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "social poster lagging behind");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+/// Post text to every configured account
+async fn post(client: &reqwest::Client, options: &SocialOptions, text: &str) {
+    if let Some(instance) = &options.mastodon_instance {
+        match post_mastodon(client, instance, text).await {
+            Ok(()) => info!("posted summary to mastodon"),
+            Err(e) => warn!(err=%e, "failed to post summary to mastodon"),
+        }
+    }
+
+    if options.bluesky {
+        match post_bluesky(client, text).await {
+            Ok(()) => info!("posted summary to bluesky"),
+            Err(e) => warn!(err=%e, "failed to post summary to bluesky"),
+        }
+    }
+}
+
+async fn post_mastodon(client: &reqwest::Client, instance: &str, text: &str) -> Result<(), String> {
+    let access_token = env(MASTODON_TOKEN_ENV)?;
+
+    client
+        .post(format!(
+            "{}/api/v1/statuses",
+            instance.trim_end_matches('/')
+        ))
+        .bearer_auth(access_token)
+        .json(&json!({ "status": text }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+async fn post_bluesky(client: &reqwest::Client, text: &str) -> Result<(), String> {
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    struct Session {
+        access_jwt: String,
+        did: String,
+    }
+
+    let session: Session = client
+        .post(format!(
+            "{BLUESKY_SERVICE}/xrpc/com.atproto.server.createSession"
+        ))
+        .json(&json!({
+            "identifier": env(BLUESKY_HANDLE_ENV)?,
+            "password": env(BLUESKY_PASSWORD_ENV)?,
+        }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?
+        .json()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    client
+        .post(format!(
+            "{BLUESKY_SERVICE}/xrpc/com.atproto.repo.createRecord"
+        ))
+        .bearer_auth(session.access_jwt)
+        .json(&json!({
+            "repo": session.did,
+            "collection": "app.bsky.feed.post",
+            "record": {
+                "$type": "app.bsky.feed.post",
+                "text": text,
+                "createdAt": Timestamp::now().to_string(),
+            },
+        }))
+        .send()
+        .await
+        .and_then(|r| r.error_for_status())
+        .map_err(|e| e.to_string())?;
+
+    Ok(())
+}
+
+fn env(name: &str) -> Result<String, String> {
+    std::env::var(name).map_err(|_| format!("{name} is not set"))
+}
@@ -0,0 +1,51 @@
+use std::net::IpAddr;
+
+use crate::config::Options;
+
+/// Whether OSC packets from `addr` are accepted
+///
+/// Without configured `osc_allowed_sources`, only loopback, `osc_send_addr`
+/// and the VRChat found via OSCQuery are trusted.
+pub(crate) fn is_allowed(options: &Options, vrchat: Option<IpAddr>, addr: IpAddr) -> bool {
+    // IPv4 peers show up mapped on dual-stack sockets
+    let addr = addr.to_canonical();
+
+    match &options.osc_allowed_sources {
+        Some(allowed) => allowed.iter().any(|allowed| allowed.to_canonical() == addr),
+        None => {
+            addr.is_loopback()
+                || addr == options.osc_send_addr().to_canonical()
+                || vrchat.is_some_and(|vrchat| vrchat.to_canonical() == addr)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{Ipv4Addr, Ipv6Addr};
+
+    use super::*;
+
+    #[test]
+    fn test_is_allowed() {
+        let lan = IpAddr::from([192, 168, 1, 20]);
+        let vrchat = IpAddr::from([192, 168, 1, 30]);
+
+        let mut options = Options::default();
+        assert!(is_allowed(&options, None, Ipv4Addr::LOCALHOST.into()));
+        assert!(is_allowed(&options, None, Ipv6Addr::LOCALHOST.into()));
+        assert!(!is_allowed(&options, None, lan));
+        assert!(is_allowed(&options, Some(vrchat), vrchat));
+        let mapped = IpAddr::from(Ipv4Addr::new(192, 168, 1, 30).to_ipv6_mapped());
+        assert!(is_allowed(&options, Some(vrchat), mapped));
+
+        options.osc_send_addr = Some(lan);
+        assert!(is_allowed(&options, None, lan));
+
+        // configured sources replace the defaults
+        options.osc_allowed_sources = Some(vec![vrchat]);
+        assert!(is_allowed(&options, None, vrchat));
+        assert!(!is_allowed(&options, None, lan));
+        assert!(!is_allowed(&options, None, Ipv4Addr::LOCALHOST.into()));
+    }
+}
@@ -1,19 +1,52 @@
-use std::{fs, ops::Sub, path::Path};
+use std::{
+    cmp::Reverse,
+    collections::{BTreeMap, VecDeque},
+    ffi::OsString,
+    fmt::Write,
+    fs,
+    io::{self, Write as _},
+    ops::Sub,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
 
-use jiff::{SignedDuration, Timestamp, Zoned, civil::Time};
+use jiff::{
+    SignedDuration, Span, Timestamp, Zoned,
+    civil::{Date, Time, Weekday},
+    tz::TimeZone,
+};
 use serde::{Deserialize, Serialize};
-use tracing::{debug, error};
+use tokio::{sync::watch, task::JoinHandle};
+use tracing::{debug, error, info, warn};
 
-const FILE_NAME: &str = "boops.toml";
+use crate::{
+    config::{CounterOptions, ResetPolicy},
+    paths,
+};
 
+pub(crate) const FILE_NAME: &str = "boops.toml";
+
+/// Extension of the previous save, e.g. `boops.toml.bak`
+pub(crate) const BACKUP_EXTENSION: &str = "bak";
+
+/// Amount of boops kept in memory for undo
+const UNDO_HISTORY: usize = 20;
+
+/// Amount of finished periods kept in the history
+const HISTORY_DAYS: usize = 400;
+
+/// Columns of CSV exports
+const CSV_HEADER: &str = "kind,key,today,total,record";
+
+/// Boop stats, persisted in `boops.toml`
 #[derive(Debug, Serialize, Deserialize)]
-pub(crate) struct BoopStorage {
+pub struct BoopStorage {
     /// Lifetime boops
     total_boops: u64,
 
     /// Today's boops
     ///
-    /// Resets on midnight, local TZ
+    /// Resets on midnight, local TZ, or as configured via [`ResetPolicy`]
     today_boops: u32,
 
     /// Highest daily boops achieved
@@ -22,13 +55,178 @@ pub(crate) struct BoopStorage {
     /// Yesterday's boops
     yesterday_boops: u32,
 
+    /// Boops of finished periods, by their start date
+    #[serde(default)]
+    history: BTreeMap<Date, u32>,
+
     /// Last reset
     #[serde(default = "today_midnight")]
     last_reset: Zoned,
 
+    /// Boops of the current period per local hour of day
+    #[serde(default = "empty_hours")]
+    hourly_boops: Vec<u32>,
+
+    /// Currently worn avatar, if known
+    #[serde(default)]
+    current_avatar: Option<String>,
+
+    /// Boops per avatar ID
+    #[serde(default)]
+    avatars: BTreeMap<String, AvatarBoops>,
+
+    /// Additional counters by name, e.g. headpats
+    #[serde(default)]
+    counters: BTreeMap<String, CounterBoops>,
+
+    /// Boops per booper, as told by `boopers` contacts
+    #[serde(default)]
+    boopers: BTreeMap<String, CounterBoops>,
+
+    /// Current world ID, if world tracking is enabled
+    #[serde(default)]
+    current_world: Option<String>,
+
+    /// Boops per world ID
+    #[serde(default)]
+    worlds: BTreeMap<String, WorldBoops>,
+
+    /// Chatbox messages were turned off via the avatar menu
+    #[serde(default)]
+    chatbox_disabled: bool,
+
     /// Last time our boop storage got saved
     #[serde(skip)]
     last_save: Timestamp,
+
+    /// Changed in a way that should be saved right away
+    #[serde(skip)]
+    unsaved: bool,
+
+    /// Boops since the booper started or VRChat reconnected
+    #[serde(skip)]
+    session_boops: u32,
+
+    /// Start of the current session
+    #[serde(skip, default = "Timestamp::now")]
+    session_start: Timestamp,
+
+    /// When today's boops should be reset
+    #[serde(skip)]
+    reset_policy: ResetPolicy,
+
+    /// Time of day periods start at
+    #[serde(skip)]
+    reset_time: Time,
+
+    /// Time zone of `reset_time`
+    #[serde(skip, default = "TimeZone::system")]
+    reset_timezone: TimeZone,
+
+    /// Counters resetting on their own schedule, by name
+    #[serde(skip)]
+    counter_resets: BTreeMap<String, CounterReset>,
+
+    /// Most recent boops, newest last
+    #[serde(skip)]
+    recent_boops: VecDeque<RecentBoop>,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct AvatarBoops {
+    /// Lifetime boops while wearing this avatar
+    total_boops: u64,
+
+    /// Boops of the current period while wearing this avatar
+    #[serde(default)]
+    today_boops: u32,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub(crate) struct WorldBoops {
+    /// Name of the world when it was last entered
+    name: String,
+
+    /// Lifetime boops in this world
+    total_boops: u64,
+
+    /// Boops of the current period in this world
+    #[serde(default)]
+    today_boops: u32,
+}
+
+/// Stats of an additional counter
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct CounterBoops {
+    pub today: u32,
+    pub total: u64,
+
+    /// highest daily count achieved
+    pub record: u32,
+
+    /// last reset of a counter with its own reset policy, the boops' before
+    /// it was first counted
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub last_reset: Option<Zoned>,
+}
+
+/// Reset schedule of a counter, the boops' where unset
+#[derive(Debug, Clone)]
+struct CounterReset {
+    policy: Option<ResetPolicy>,
+    time: Option<Time>,
+    timezone: Option<TimeZone>,
+}
+
+/// Combined stats of a counter group
+#[derive(Debug, Default, Clone, PartialEq, Serialize, Deserialize)]
+pub(crate) struct GroupBoops {
+    pub today: u32,
+    pub total: u64,
+}
+
+/// Stats of a finished period, produced on reset
+#[derive(Debug, Clone)]
+pub(crate) struct PeriodSummary {
+    /// when the period started
+    pub start: Zoned,
+
+    /// when the period got reset
+    pub end: Zoned,
+
+    /// boops counted during the period
+    pub boops: u32,
+
+    /// boops per local hour of day
+    pub hourly: Vec<u32>,
+
+    /// highest daily boops, including this period
+    pub record: u32,
+
+    /// lifetime boops at the end of the period
+    pub total: u64,
+
+    /// additional counters at the end of the period
+    pub counters: BTreeMap<String, CounterBoops>,
+}
+
+/// Counted boop, kept around to allow undoing it
+#[derive(Debug, Clone)]
+struct RecentBoop {
+    /// when the boop was counted
+    time: Timestamp,
+
+    /// avatar worn while the boop was counted
+    avatar: Option<String>,
+
+    /// world the boop was counted in
+    world: Option<String>,
+
+    /// additional counter incremented instead of boops
+    counter: Option<String>,
+
+    /// whether the boop raised the daily record
+    raised_record: bool,
 }
 
 impl Default for BoopStorage {
@@ -38,60 +236,132 @@ impl Default for BoopStorage {
             today_boops: 0,
             today_boops_record: 0,
             yesterday_boops: 0,
+            history: BTreeMap::new(),
             last_reset: today_midnight(),
+            hourly_boops: empty_hours(),
+            current_avatar: None,
+            avatars: BTreeMap::new(),
+            counters: BTreeMap::new(),
+            boopers: BTreeMap::new(),
+            current_world: None,
+            worlds: BTreeMap::new(),
             last_save: Timestamp::now(),
+            unsaved: false,
+            session_boops: 0,
+            session_start: Timestamp::now(),
+            reset_policy: ResetPolicy::default(),
+            reset_time: Time::midnight(),
+            reset_timezone: TimeZone::system(),
+            counter_resets: BTreeMap::new(),
+            recent_boops: VecDeque::new(),
+            chatbox_disabled: false,
         }
     }
 }
 
 impl BoopStorage {
     /// Load or create boop stats
-    pub(crate) fn load() -> Self {
-        let file = Path::new(FILE_NAME);
-
-        if file.exists() {
-            // try to read existing config
-            let contents = match fs::read_to_string(file) {
-                Ok(contents) => contents,
-                Err(e) => {
-                    error!(err=%e, "failed to read {FILE_NAME}");
-                    return BoopStorage::default();
-                }
-            };
+    ///
+    /// Falls back to the backup of the previous save when the file can't be
+    /// read, instead of starting from zero.
+    pub fn load() -> Self {
+        let file = paths::data_file(FILE_NAME);
+        let backup = with_extension(&file, BACKUP_EXTENSION);
 
-            // parse contents or return to defaults
-            return toml::from_str::<BoopStorage>(&contents).unwrap_or_else(|e| {
-                error!(err=%e, "failed to parse {FILE_NAME}, reverting to defaults");
+        match read(&file) {
+            Ok(Some(storage)) => return storage,
+            Ok(None) => {}
+            Err(e) => error!(
+                err=%e,
+                "failed to load {}, trying {}",
+                file.display(),
+                backup.display()
+            ),
+        }
+
+        match read(&backup) {
+            Ok(Some(storage)) => {
+                warn!("restored boop stats from {}", backup.display());
+                storage
+            }
+            Ok(None) => BoopStorage::default(),
+            Err(e) => {
+                error!(err=%e, "failed to load {}, reverting to defaults", backup.display());
                 BoopStorage::default()
-            });
+            }
         }
+    }
+
+    /// Set policy for resetting today's boops
+    pub fn with_reset_policy(mut self, reset_policy: ResetPolicy) -> Self {
+        self.reset_policy = reset_policy;
+        self
+    }
 
-        BoopStorage::default()
+    /// Start periods at `time` in `timezone` instead of local midnight
+    pub fn with_reset_time(mut self, time: Time, timezone: Option<TimeZone>) -> Self {
+        self.reset_time = time;
+        self.reset_timezone = timezone.unwrap_or_else(TimeZone::system);
+        self
+    }
+
+    /// Reset counters overriding the reset policy, time or time zone on their
+    /// own schedule
+    pub fn with_counter_resets(mut self, counters: &[CounterOptions]) -> Self {
+        self.counter_resets = counters
+            .iter()
+            .filter(|counter| {
+                counter.reset_policy.is_some()
+                    || counter.reset_time.is_some()
+                    || counter.reset_timezone.is_some()
+            })
+            .map(|counter| {
+                let reset = CounterReset {
+                    policy: counter.reset_policy,
+                    time: counter.reset_time,
+                    timezone: counter.reset_timezone.clone(),
+                };
+                (counter.name.clone(), reset)
+            })
+            .collect();
+        self
     }
 
     /// Save boop stats
-    pub(crate) fn save(&mut self) {
-        let toml = match toml::to_string(&self) {
+    pub fn save(&mut self) {
+        if !write(&self.to_toml()) {
+            return;
+        }
+
+        self.last_save = Timestamp::now();
+        self.unsaved = false;
+        debug!("saved boop stats: {:?}", self);
+    }
+
+    /// Save boop stats in the background, only serializing them right away
+    pub(crate) fn save_with(&mut self, saver: &Saver) {
+        saver.contents.send_replace(Some(self.to_toml()));
+        self.last_save = Timestamp::now();
+        self.unsaved = false;
+    }
+
+    fn to_toml(&self) -> String {
+        match toml::to_string(self) {
             Ok(toml) => toml,
             Err(e) => {
                 error!(err=%e, "failed to serialize boop storage to string");
                 panic!();
             }
-        };
-
-        if let Err(e) = fs::write(FILE_NAME, toml) {
-            error!(err=%e, "failed to write boop storage to {FILE_NAME}");
-            return;
         }
-
-        self.last_save = Timestamp::now();
-        debug!("saved boop stats: {:?}", self);
     }
 
     /// Check if storage should be saved again
-    pub(crate) fn time_to_save(&self) -> bool {
+    ///
+    /// Changes are never saved on their own, the main loop hands them to
+    /// its [`Saver`] instead of blocking on file IO.
+    pub(crate) fn needs_save(&self) -> bool {
         let now = Timestamp::now();
-        self.last_save < now.sub(SignedDuration::from_mins(5))
+        self.unsaved || self.last_save < now.sub(SignedDuration::from_mins(5))
     }
 
     pub(crate) fn inc_boops(&mut self) {
@@ -99,31 +369,585 @@ impl BoopStorage {
 
         self.today_boops += 1;
         self.total_boops += 1;
+        self.session_boops += 1;
+        if let Some(hour) = self.hourly_boops.get_mut(Zoned::now().hour() as usize) {
+            *hour += 1;
+        }
+        if let Some(avatar) = self.current_avatar_boops() {
+            avatar.total_boops += 1;
+            avatar.today_boops += 1;
+        }
+        if let Some(world) = self.current_world_boops() {
+            world.total_boops += 1;
+            world.today_boops += 1;
+        }
 
-        if self.today_boops > self.today_boops_record {
+        let raised_record = self.today_boops > self.today_boops_record;
+        if raised_record {
             self.today_boops_record = self.today_boops;
         }
 
-        if self.time_to_save() {
-            self.save();
+        self.push_recent(RecentBoop {
+            time: Timestamp::now(),
+            avatar: self.current_avatar.clone(),
+            world: self.current_world.clone(),
+            counter: None,
+            raised_record,
+        });
+    }
+
+    /// Increment additional counter, returns its new stats
+    pub(crate) fn inc_counter(&mut self, name: &str) -> CounterBoops {
+        self.check_reset();
+
+        // starts its own period right away
+        let last_reset = self.counter_resets.contains_key(name).then(Zoned::now);
+        let counter = self
+            .counters
+            .entry(name.into())
+            .or_insert_with(|| CounterBoops {
+                last_reset,
+                ..Default::default()
+            });
+        counter.today += 1;
+        counter.total += 1;
+
+        let raised_record = counter.today > counter.record;
+        if raised_record {
+            counter.record = counter.today;
+        }
+        let counter = counter.clone();
+
+        self.push_recent(RecentBoop {
+            time: Timestamp::now(),
+            avatar: None,
+            world: None,
+            counter: Some(name.into()),
+            raised_record,
+        });
+
+        counter
+    }
+
+    /// Count a boop by `name`
+    pub(crate) fn inc_booper(&mut self, name: &str) {
+        let booper = self.boopers.entry(name.into()).or_default();
+        booper.today += 1;
+        booper.total += 1;
+        booper.record = booper.record.max(booper.today);
+    }
+
+    /// Boops of every booper seen, by name
+    pub(crate) fn boopers(&self) -> impl Iterator<Item = (&str, &CounterBoops)> {
+        self.boopers
+            .iter()
+            .map(|(name, booper)| (name.as_str(), booper))
+    }
+
+    /// Today's top boopers, most boops first
+    pub(crate) fn leaderboard(&self, size: usize) -> Vec<(&str, &CounterBoops)> {
+        let mut boopers: Vec<_> = self
+            .boopers
+            .iter()
+            .filter(|(_, booper)| booper.today > 0)
+            .map(|(name, booper)| (name.as_str(), booper))
+            .collect();
+        // stable, so ties stay sorted by name
+        boopers.sort_by_key(|(_, booper)| Reverse(booper.today));
+        boopers.truncate(size);
+
+        boopers
+    }
+
+    fn push_recent(&mut self, boop: RecentBoop) {
+        if self.recent_boops.len() == UNDO_HISTORY {
+            self.recent_boops.pop_front();
+        }
+        self.recent_boops.push_back(boop);
+    }
+
+    /// Revert the most recent boop, returns false if there is nothing to undo
+    pub(crate) fn undo_boop(&mut self) -> bool {
+        let Some(boop) = self.recent_boops.pop_back() else {
+            return false;
+        };
+
+        if let Some(name) = boop.counter {
+            // the counter is gone, there is nothing left to revert
+            let Some(counter) = self.counters.get_mut(&name) else {
+                return false;
+            };
+
+            counter.total = counter.total.saturating_sub(1);
+            let last_reset = counter.last_reset.as_ref().unwrap_or(&self.last_reset);
+            if boop.time >= last_reset.timestamp() {
+                counter.today = counter.today.saturating_sub(1);
+                if boop.raised_record {
+                    counter.record = counter.record.saturating_sub(1);
+                }
+            }
+
+            self.unsaved = true;
+            return true;
+        }
+
+        // boops from before the last reset are no longer part of today
+        let is_today = boop.time >= self.last_reset.timestamp();
+
+        self.total_boops = self.total_boops.saturating_sub(1);
+        if boop.time >= self.session_start {
+            self.session_boops = self.session_boops.saturating_sub(1);
+        }
+        if let Some(avatar) = boop.avatar.and_then(|id| self.avatars.get_mut(&id)) {
+            avatar.total_boops = avatar.total_boops.saturating_sub(1);
+            if is_today {
+                avatar.today_boops = avatar.today_boops.saturating_sub(1);
+            }
+        }
+        if let Some(world) = boop.world.and_then(|id| self.worlds.get_mut(&id)) {
+            world.total_boops = world.total_boops.saturating_sub(1);
+            if is_today {
+                world.today_boops = world.today_boops.saturating_sub(1);
+            }
+        }
+
+        if is_today {
+            self.today_boops = self.today_boops.saturating_sub(1);
+            let hour = boop
+                .time
+                .to_zoned(self.last_reset.time_zone().clone())
+                .hour();
+            if let Some(hour) = self.hourly_boops.get_mut(hour as usize) {
+                *hour = hour.saturating_sub(1);
+            }
+            if boop.raised_record {
+                self.today_boops_record = self.today_boops_record.saturating_sub(1);
+            }
+        }
+
+        self.unsaved = true;
+        true
+    }
+
+    /// Add `by` to today's and total boops, never going below zero
+    ///
+    /// The current avatar, world and hour take the adjustment as well, so
+    /// their sums keep matching.
+    pub fn adjust(&mut self, by: i64) {
+        self.today_boops = adjusted_u32(self.today_boops, by);
+        self.total_boops = adjusted_u64(self.total_boops, by);
+        self.session_boops = adjusted_u32(self.session_boops, by);
+        if let Some(hour) = self.hourly_boops.get_mut(Zoned::now().hour() as usize) {
+            *hour = adjusted_u32(*hour, by);
+        }
+        if let Some(avatar) = self.current_avatar_boops() {
+            avatar.total_boops = adjusted_u64(avatar.total_boops, by);
+            avatar.today_boops = adjusted_u32(avatar.today_boops, by);
+        }
+        if let Some(world) = self.current_world_boops() {
+            world.total_boops = adjusted_u64(world.total_boops, by);
+            world.today_boops = adjusted_u32(world.today_boops, by);
+        }
+
+        if self.today_boops > self.today_boops_record {
+            self.today_boops_record = self.today_boops;
         }
+
+        self.unsaved = true;
     }
 
-    pub(crate) fn boop_numbers(&self) -> (u32, u64) {
+    /// Today's and total boops
+    pub fn boop_numbers(&self) -> (u32, u64) {
         (self.today_boops, self.total_boops)
     }
 
+    /// Boops since the booper started or VRChat reconnected
+    pub(crate) fn session_boops(&self) -> u32 {
+        self.session_boops
+    }
+
+    /// Start a new session, e.g. when VRChat is back
+    pub(crate) fn reset_session(&mut self) {
+        info!(session = self.session_boops, "starting a new session");
+        self.session_boops = 0;
+        self.session_start = Timestamp::now();
+    }
+
+    pub(crate) fn yesterday_boops(&self) -> u32 {
+        self.yesterday_boops
+    }
+
+    /// Most boops on a single day
+    pub fn today_record(&self) -> u32 {
+        self.today_boops_record
+    }
+
+    /// Boops of the current week, starting on Monday
+    pub(crate) fn week_boops(&self) -> u64 {
+        let today = Zoned::now().date();
+        let monday = today
+            .checked_sub(Span::new().days(today.weekday().to_monday_zero_offset()))
+            .unwrap_or(today);
+        self.boops_since(monday)
+    }
+
+    /// Boops of the current month
+    pub(crate) fn month_boops(&self) -> u64 {
+        self.boops_since(Zoned::now().date().first_of_month())
+    }
+
+    /// Consecutive days with at least one boop, up to the current period
+    pub(crate) fn streak(&self) -> u32 {
+        let period = self.period_date(&self.last_reset);
+        streak(&self.history, period, self.today_boops)
+    }
+
+    /// Boops of the current period and all finished periods since `date`
+    fn boops_since(&self, date: Date) -> u64 {
+        let finished: u64 = self
+            .history
+            .range(date..)
+            .map(|(_, boops)| *boops as u64)
+            .sum();
+        finished + self.today_boops as u64
+    }
+
+    /// Stats of an additional counter, zero if it was never incremented
+    pub(crate) fn counter(&self, name: &str) -> CounterBoops {
+        self.counters.get(name).cloned().unwrap_or_default()
+    }
+
+    pub(crate) fn counters(&self) -> &BTreeMap<String, CounterBoops> {
+        &self.counters
+    }
+
+    /// Sum of the given counters, unknown ones count as zero
+    pub(crate) fn group(&self, counters: &[String]) -> GroupBoops {
+        counters
+            .iter()
+            .filter_map(|name| self.counters.get(name))
+            .fold(GroupBoops::default(), |group, counter| GroupBoops {
+                today: group.today + counter.today,
+                total: group.total + counter.total,
+            })
+    }
+
+    pub(crate) fn current_avatar(&self) -> Option<&str> {
+        self.current_avatar.as_deref()
+    }
+
+    /// Lifetime boops of the current avatar, if known
+    pub(crate) fn avatar_total(&self) -> Option<u64> {
+        let avatar = self.current_avatar.as_ref()?;
+        self.avatars.get(avatar).map(|a| a.total_boops)
+    }
+
+    /// Today's boops of the current avatar, if known
+    pub(crate) fn avatar_today(&self) -> Option<u32> {
+        let avatar = self.current_avatar.as_ref()?;
+        self.avatars.get(avatar).map(|a| a.today_boops)
+    }
+
+    /// Switch current avatar, registering it if it's new
+    ///
+    /// New avatars start at zero, or at the current lifetime total
+    pub(crate) fn set_avatar(&mut self, avatar_id: String, start_at_zero: bool) {
+        let total_boops = if start_at_zero { 0 } else { self.total_boops };
+        self.avatars
+            .entry(avatar_id.clone())
+            .or_insert_with(|| AvatarBoops {
+                total_boops,
+                today_boops: 0,
+            });
+        self.current_avatar = Some(avatar_id);
+    }
+
+    fn current_avatar_boops(&mut self) -> Option<&mut AvatarBoops> {
+        let avatar = self.current_avatar.as_ref()?;
+        self.avatars.get_mut(avatar)
+    }
+
+    /// Whether chatbox messages are sent, unless turned off via the avatar
+    /// menu
+    pub(crate) fn chatbox_enabled(&self) -> bool {
+        !self.chatbox_disabled
+    }
+
+    pub(crate) fn set_chatbox_enabled(&mut self, enabled: bool) {
+        self.chatbox_disabled = !enabled;
+    }
+
+    pub(crate) fn current_world(&self) -> Option<&str> {
+        self.current_world.as_deref()
+    }
+
+    /// Name of the current world, if known
+    pub(crate) fn world_name(&self) -> Option<&str> {
+        let world = self.current_world.as_ref()?;
+        self.worlds.get(world).map(|w| w.name.as_str())
+    }
+
+    /// Lifetime boops in the current world, if known
+    pub(crate) fn world_total(&self) -> Option<u64> {
+        let world = self.current_world.as_ref()?;
+        self.worlds.get(world).map(|w| w.total_boops)
+    }
+
+    /// Today's boops in the current world, if known
+    pub(crate) fn world_today(&self) -> Option<u32> {
+        let world = self.current_world.as_ref()?;
+        self.worlds.get(world).map(|w| w.today_boops)
+    }
+
+    /// Switch current world, registering it if it's new
+    pub(crate) fn set_world(&mut self, world_id: String, name: String) {
+        self.worlds.entry(world_id.clone()).or_default().name = name;
+        self.current_world = Some(world_id);
+    }
+
+    fn current_world_boops(&mut self) -> Option<&mut WorldBoops> {
+        let world = self.current_world.as_ref()?;
+        self.worlds.get_mut(world)
+    }
+
     /// Check if today's boops should be reset
-    fn check_reset(&mut self) {
-        // reset today's boops, copy to yesterday if past midnight
+    ///
+    /// Returns the finished period, if it got reset
+    pub(crate) fn check_reset(&mut self) -> Option<PeriodSummary> {
+        let now = Zoned::now();
+        self.check_counter_resets(&now);
+
+        // reset today's boops, copy to yesterday if policy says so
+        let (last_reset, clock) = (self.period_clock(&self.last_reset), self.period_clock(&now));
+        if reset_is_due(self.reset_policy, &last_reset, &clock) {
+            return Some(self.end_period(now, false));
+        }
+
+        None
+    }
+
+    /// Reset counters on their own schedule whose period is over at `now`
+    fn check_counter_resets(&mut self, now: &Zoned) {
+        for (name, reset) in &self.counter_resets {
+            let Some(counter) = self.counters.get_mut(name) else {
+                continue;
+            };
+
+            let policy = reset.policy.unwrap_or(self.reset_policy);
+            let time = reset.time.unwrap_or(self.reset_time);
+            let timezone = reset.timezone.as_ref().unwrap_or(&self.reset_timezone);
+            let last_reset = counter.last_reset.as_ref().unwrap_or(&self.last_reset);
+            let last_reset = period_clock(last_reset, time, timezone);
+            if reset_is_due(policy, &last_reset, &period_clock(now, time, timezone)) {
+                info!(counter = %name, today = counter.today, "resetting counter");
+                counter.today = 0;
+                counter.last_reset = Some(now.clone());
+                self.unsaved = true;
+            }
+        }
+    }
+
+    /// When today's boops or a counter on its own schedule are reset next, if
+    /// ever
+    pub(crate) fn next_reset(&self) -> Option<Zoned> {
         let now = Zoned::now();
-        if time_is_past_midnight(&self.last_reset, &now) {
-            self.yesterday_boops = self.today_boops;
-            self.today_boops = 0;
-            self.last_reset = now;
-            self.save();
+        let counters = self.counter_resets.values().filter_map(|reset| {
+            next_reset(
+                reset.policy.unwrap_or(self.reset_policy),
+                &now,
+                reset.time.unwrap_or(self.reset_time),
+                reset.timezone.as_ref().unwrap_or(&self.reset_timezone),
+            )
+        });
+        next_reset(
+            self.reset_policy,
+            &now,
+            self.reset_time,
+            &self.reset_timezone,
+        )
+        .into_iter()
+        .chain(counters)
+        .min()
+    }
+
+    /// Clock on which periods start at midnight, see [`period_clock`]
+    fn period_clock(&self, time: &Zoned) -> Zoned {
+        period_clock(time, self.reset_time, &self.reset_timezone)
+    }
+
+    /// Day the period containing `time` started on
+    fn period_date(&self, time: &Zoned) -> Date {
+        self.period_clock(time).date()
+    }
+
+    /// Reset today's boops and all counters, copying the boops to yesterday
+    ///
+    /// Returns the finished period
+    pub(crate) fn reset_today(&mut self, now: Zoned) -> PeriodSummary {
+        self.end_period(now, true)
+    }
+
+    /// Finish the current period, counters on their own schedule only reset
+    /// if `manual`
+    fn end_period(&mut self, now: Zoned, manual: bool) -> PeriodSummary {
+        info!(today = self.today_boops, "resetting today's boops");
+
+        let summary = PeriodSummary {
+            start: self.last_reset.clone(),
+            end: now.clone(),
+            boops: self.today_boops,
+            hourly: std::mem::replace(&mut self.hourly_boops, empty_hours()),
+            record: self.today_boops_record,
+            total: self.total_boops,
+            counters: self.counters.clone(),
+        };
+
+        // manual resets may finish multiple periods a day
+        let period = self.period_date(&self.last_reset);
+        *self.history.entry(period).or_default() += self.today_boops;
+        while self.history.len() > HISTORY_DAYS {
+            self.history.pop_first();
+        }
+
+        self.yesterday_boops = self.today_boops;
+        self.today_boops = 0;
+        for (name, counter) in &mut self.counters {
+            // manual resets start a new period for every counter
+            let own_schedule = self.counter_resets.contains_key(name);
+            if own_schedule && !manual {
+                continue;
+            }
+            counter.today = 0;
+            counter.last_reset = own_schedule.then(|| now.clone());
+        }
+        for avatar in self.avatars.values_mut() {
+            avatar.today_boops = 0;
+        }
+        for world in self.worlds.values_mut() {
+            world.today_boops = 0;
+        }
+        for booper in self.boopers.values_mut() {
+            booper.today = 0;
+        }
+        self.last_reset = now;
+        self.unsaved = true;
+
+        summary
+    }
+
+    /// Complete storage as JSON
+    pub(crate) fn to_json(&self) -> serde_json::Result<String> {
+        serde_json::to_string_pretty(self)
+    }
+
+    pub(crate) fn from_json(json: &str) -> serde_json::Result<Self> {
+        serde_json::from_str(json)
+    }
+
+    /// Counts, daily history, avatars and counters as CSV, one row each
+    pub(crate) fn to_csv(&self) -> String {
+        let mut csv = format!("{CSV_HEADER}\n");
+
+        // writing to a String never fails
+        writeln!(
+            csv,
+            "boops,,{},{},{}",
+            self.today_boops, self.total_boops, self.today_boops_record
+        )
+        .ok();
+        writeln!(csv, "yesterday,,{},,", self.yesterday_boops).ok();
+        for (date, boops) in &self.history {
+            writeln!(csv, "day,{date},{boops},,").ok();
+        }
+        for (id, avatar) in &self.avatars {
+            writeln!(
+                csv,
+                "avatar,{id},{},{},",
+                avatar.today_boops, avatar.total_boops
+            )
+            .ok();
+        }
+        for (name, counter) in &self.counters {
+            writeln!(
+                csv,
+                "counter,{name},{},{},{}",
+                counter.today, counter.total, counter.record
+            )
+            .ok();
+        }
+
+        csv
+    }
+
+    /// Replace the counts contained in a [`BoopStorage::to_csv`] export,
+    /// keeping everything else
+    pub(crate) fn apply_csv(&mut self, csv: &str) -> Result<(), String> {
+        let mut lines = csv.lines().enumerate();
+        if lines.next().map(|(_, header)| header.trim()) != Some(CSV_HEADER) {
+            return Err(format!("expected header {CSV_HEADER:?}"));
+        }
+
+        for (index, line) in lines {
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            let fields: Vec<_> = line.trim().split(',').collect();
+            let [kind, key, today, total, record] = fields[..] else {
+                return Err(format!("line {}: expected 5 columns", index + 1));
+            };
+            let error = |e: String| format!("line {}: {e}", index + 1);
+
+            match kind {
+                "boops" => {
+                    self.today_boops = parse_field(today).map_err(error)?;
+                    self.total_boops = parse_field(total).map_err(error)?;
+                    self.today_boops_record = parse_field(record).map_err(error)?;
+                }
+                "yesterday" => self.yesterday_boops = parse_field(today).map_err(error)?,
+                "day" => {
+                    let date = key
+                        .parse()
+                        .map_err(|e| error(format!("invalid date: {e}")))?;
+                    self.history
+                        .insert(date, parse_field(today).map_err(error)?);
+                }
+                "avatar" => {
+                    let avatar = AvatarBoops {
+                        total_boops: parse_field(total).map_err(error)?,
+                        today_boops: parse_field(today).map_err(error)?,
+                    };
+                    self.avatars.insert(key.into(), avatar);
+                }
+                "counter" => {
+                    let counter = CounterBoops {
+                        today: parse_field(today).map_err(error)?,
+                        total: parse_field(total).map_err(error)?,
+                        record: parse_field(record).map_err(error)?,
+                        last_reset: None,
+                    };
+                    self.counters.insert(key.into(), counter);
+                }
+                other => return Err(error(format!("unknown kind {other:?}"))),
+            }
         }
+
+        Ok(())
+    }
+}
+
+/// Parse CSV field, empty ones are zero
+fn parse_field<T: FromStr + Default>(value: &str) -> Result<T, String>
+where
+    T::Err: std::fmt::Display,
+{
+    if value.is_empty() {
+        return Ok(T::default());
     }
+
+    value
+        .parse()
+        .map_err(|e| format!("invalid number {value:?}: {e}"))
 }
 
 /// Get midnight of today
@@ -135,6 +959,94 @@ fn today_midnight() -> Zoned {
         .expect("failed to create midnight")
 }
 
+fn empty_hours() -> Vec<u32> {
+    vec![0; 24]
+}
+
+/// `count` plus `by`, clamped to the range of the count
+fn adjusted_u32(count: u32, by: i64) -> u32 {
+    (count as i64 + by).clamp(0, u32::MAX as i64) as u32
+}
+
+fn adjusted_u64(count: u64, by: i64) -> u64 {
+    (count as i128 + by as i128).clamp(0, u64::MAX as i128) as u64
+}
+
+/// Count consecutive days with boops, ending on `current` if it has boops
+/// already, else on the day before
+fn streak(history: &BTreeMap<Date, u32>, current: Date, current_boops: u32) -> u32 {
+    let mut streak = (current_boops > 0) as u32;
+
+    let mut day = current;
+    while let Ok(previous) = day.yesterday() {
+        if history.get(&previous).is_none_or(|boops| *boops == 0) {
+            break;
+        }
+        streak += 1;
+        day = previous;
+    }
+
+    streak
+}
+
+/// Check if the reset policy requires a reset since `last_reset`
+fn reset_is_due(policy: ResetPolicy, last_reset: &Zoned, now: &Zoned) -> bool {
+    match policy {
+        ResetPolicy::Daily => time_is_past_midnight(last_reset, now),
+        ResetPolicy::Weekly(weekday) => {
+            let weekday: Weekday = weekday.into();
+            // most recent occurrence of weekday, including today
+            let days_back = (now.weekday().to_monday_zero_offset()
+                - weekday.to_monday_zero_offset())
+            .rem_euclid(7);
+            let boundary = now
+                .date()
+                .checked_sub(Span::new().days(days_back))
+                .unwrap_or(now.date());
+
+            last_reset.date() < boundary
+        }
+        ResetPolicy::Monthly => {
+            (last_reset.year(), last_reset.month()) != (now.year(), now.month())
+        }
+        ResetPolicy::Never | ResetPolicy::Manual => false,
+    }
+}
+
+/// Start of the period following the one `now` is in, according to `policy`,
+/// at `reset_time` in `timezone`
+fn next_reset(
+    policy: ResetPolicy,
+    now: &Zoned,
+    reset_time: Time,
+    timezone: &TimeZone,
+) -> Option<Zoned> {
+    let date = period_clock(now, reset_time, timezone).date();
+    let next = match policy {
+        ResetPolicy::Daily => date.tomorrow().ok()?,
+        ResetPolicy::Weekly(weekday) => date.nth_weekday(1, weekday.into()).ok()?,
+        ResetPolicy::Monthly => date.last_of_month().tomorrow().ok()?,
+        ResetPolicy::Never | ResetPolicy::Manual => return None,
+    };
+
+    next.to_datetime(reset_time).to_zoned(timezone.clone()).ok()
+}
+
+/// Wall clock time of `time` in `timezone`, moved back by `reset_time`
+///
+/// Periods start at midnight of this clock, so 03:00 still counts as the day
+/// before with a `reset_time` of 04:00. Only its civil date and time are
+/// meaningful.
+fn period_clock(time: &Zoned, reset_time: Time, timezone: &TimeZone) -> Zoned {
+    let local = time.with_time_zone(timezone.clone()).datetime();
+    let shifted = local
+        .checked_sub(reset_time.duration_since(Time::midnight()))
+        .unwrap_or(local);
+    shifted
+        .to_zoned(TimeZone::UTC)
+        .unwrap_or_else(|_| time.clone())
+}
+
 /// Check if we're past our last reset `date`, assume we're past today's
 /// midnight
 fn time_is_past_midnight(last_reset: &Zoned, time: &Zoned) -> bool {
@@ -142,11 +1054,175 @@ fn time_is_past_midnight(last_reset: &Zoned, time: &Zoned) -> bool {
     time.date() != last_reset.date()
 }
 
+/// Parsed storage file, `None` if it doesn't exist
+fn read(file: &Path) -> Result<Option<BoopStorage>, String> {
+    let contents = match fs::read_to_string(file) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.to_string()),
+    };
+
+    toml::from_str(&contents)
+        .map(Some)
+        .map_err(|e| e.to_string())
+}
+
+/// Writes boop stats saved via [`BoopStorage::save_with`], keeping file IO
+/// off the receive loop
+///
+/// Only the latest contents are written if saves pile up.
+#[derive(Debug)]
+pub(crate) struct Saver {
+    contents: watch::Sender<Option<String>>,
+    task: JoinHandle<()>,
+}
+
+impl Saver {
+    /// Wait until the latest contents are written
+    pub(crate) async fn finish(self) {
+        drop(self.contents);
+        self.task.await.ok();
+    }
+}
+
+pub(crate) fn spawn_saver() -> Saver {
+    let (contents, mut pending) = watch::channel(None::<String>);
+    let task = tokio::spawn(async move {
+        while pending.changed().await.is_ok() {
+            let Some(toml) = pending.borrow_and_update().clone() else {
+                continue;
+            };
+            if let Err(e) = tokio::task::spawn_blocking(move || write(&toml)).await {
+                error!(err=%e, "failed to save boop storage");
+            }
+        }
+    });
+
+    Saver { contents, task }
+}
+
+/// Write serialized boop stats, `false` if that failed
+fn write(toml: &str) -> bool {
+    let file = paths::data_file(FILE_NAME);
+    if let Err(e) = write_atomic(&file, toml) {
+        error!(err=%e, "failed to write boop storage to {}", file.display());
+        return false;
+    }
+    true
+}
+
+/// Replace `file` without ever leaving it half written, keeping its previous
+/// contents as backup
+///
+/// The contents are written to a temporary file first, which is then renamed
+/// over the original.
+fn write_atomic(file: &Path, contents: &str) -> io::Result<()> {
+    let temp = with_extension(file, "tmp");
+    let mut temp_file = fs::File::create(&temp)?;
+    temp_file.write_all(contents.as_bytes())?;
+    temp_file.sync_all()?;
+    drop(temp_file);
+
+    if file.exists() {
+        fs::copy(file, with_extension(file, BACKUP_EXTENSION))?;
+    }
+    fs::rename(&temp, file)
+}
+
+/// `file` with another extension appended, e.g. `boops.toml.tmp`
+fn with_extension(file: &Path, extension: &str) -> PathBuf {
+    let mut name = OsString::from(file);
+    name.push(".");
+    name.push(extension);
+    name.into()
+}
+
 #[cfg(test)]
 mod tests {
-    use std::str::FromStr;
-
     use super::*;
+
+    #[test]
+    fn test_next_reset() {
+        // a Wednesday
+        let now = Zoned::from_str("2025-03-26T15:00:00+01:00[Europe/Berlin]").unwrap();
+        let next = |policy| {
+            next_reset(policy, &now, Time::midnight(), now.time_zone()).map(|next| next.to_string())
+        };
+
+        assert_eq!(
+            next(ResetPolicy::Daily).as_deref(),
+            Some("2025-03-27T00:00:00+01:00[Europe/Berlin]")
+        );
+        // after the switch to summer time
+        assert_eq!(
+            next(ResetPolicy::Weekly(crate::config::ResetWeekday::Monday)).as_deref(),
+            Some("2025-03-31T00:00:00+02:00[Europe/Berlin]")
+        );
+        assert_eq!(
+            next(ResetPolicy::Monthly).as_deref(),
+            Some("2025-04-01T00:00:00+02:00[Europe/Berlin]")
+        );
+        assert_eq!(next(ResetPolicy::Manual), None);
+    }
+
+    #[test]
+    fn test_reset_time() {
+        let berlin = TimeZone::get("Europe/Berlin").unwrap();
+        let reset_time = Time::constant(4, 0, 0, 0);
+        let clock = |time: &str| {
+            let time = Zoned::from_str(time).unwrap();
+            period_clock(&time, reset_time, &berlin)
+        };
+
+        // still Wednesday's period at 03:00 on Thursday, in Berlin
+        let last_reset = clock("2025-03-26T04:00:00+01:00[Europe/Berlin]");
+        let night = clock("2025-03-27T02:00:00+00:00[UTC]");
+        let morning = clock("2025-03-27T04:30:00+01:00[Europe/Berlin]");
+        assert!(!reset_is_due(ResetPolicy::Daily, &last_reset, &night));
+        assert!(reset_is_due(ResetPolicy::Daily, &last_reset, &morning));
+
+        let now = Zoned::from_str("2025-03-27T02:00:00+00:00[UTC]").unwrap();
+        let next = next_reset(ResetPolicy::Daily, &now, reset_time, &berlin);
+        assert_eq!(
+            next.map(|next| next.to_string()).as_deref(),
+            Some("2025-03-27T04:00:00+01:00[Europe/Berlin]")
+        );
+    }
+
+    #[test]
+    fn test_write_atomic() {
+        let dir = std::env::temp_dir().join(format!("osc-booper-storage-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let file = dir.join(FILE_NAME);
+
+        write_atomic(&file, "total_boops = 1").unwrap();
+        write_atomic(&file, "total_boops = 2").unwrap();
+        assert_eq!(fs::read_to_string(&file).unwrap(), "total_boops = 2");
+        assert_eq!(
+            fs::read_to_string(with_extension(&file, BACKUP_EXTENSION)).unwrap(),
+            "total_boops = 1"
+        );
+        assert!(!with_extension(&file, "tmp").exists());
+
+        assert!(read(&dir.join("missing.toml")).unwrap().is_none());
+        fs::write(&file, "total_boops = ").unwrap();
+        assert!(read(&file).is_err());
+
+        fs::remove_dir_all(dir).ok();
+    }
+
+    #[test]
+    fn test_group() {
+        let mut storage = BoopStorage::default();
+        storage.inc_counter("left_ear");
+        storage.inc_counter("left_ear");
+        storage.inc_counter("nose");
+        storage.inc_counter("tail");
+
+        let head = ["left_ear", "right_ear", "nose"].map(String::from);
+        assert_eq!(storage.group(&head), GroupBoops { today: 3, total: 3 });
+    }
+
     #[test]
     fn test_time_is_past_midnight() {
         let last_reset = Zoned::from_str("2025-03-30T00:00:00Z[Europe/Berlin]").unwrap();
@@ -157,4 +1233,175 @@ mod tests {
         let now2 = Zoned::from_str("2025-03-31T00:00:00Z[Europe/Berlin]").unwrap();
         assert!(time_is_past_midnight(&last_reset, &now2));
     }
+
+    #[test]
+    fn test_leaderboard() {
+        let mut storage = BoopStorage::default();
+        for name in ["carol", "alice", "bob", "alice", "carol", "dave"] {
+            storage.inc_booper(name);
+        }
+
+        let top: Vec<_> = storage
+            .leaderboard(3)
+            .into_iter()
+            .map(|(name, booper)| (name, booper.today))
+            .collect();
+        assert_eq!(top, [("alice", 2), ("carol", 2), ("bob", 1)]);
+
+        storage.boopers.get_mut("alice").unwrap().today = 0;
+        assert_eq!(storage.leaderboard(1)[0].0, "carol");
+    }
+
+    #[test]
+    fn test_undo_removed_counter() {
+        let mut storage = BoopStorage::default();
+        storage.inc_boops();
+        storage.inc_counter("headpats");
+        storage.counters.remove("headpats");
+
+        assert!(!storage.undo_boop());
+        assert_eq!(storage.boop_numbers(), (1, 1));
+        assert!(storage.undo_boop());
+        assert_eq!(storage.boop_numbers(), (0, 0));
+    }
+
+    #[test]
+    fn test_changes_are_saved_later() {
+        let mut storage = BoopStorage::default();
+        let last_save = storage.last_save;
+        assert!(!storage.needs_save());
+
+        storage.inc_boops();
+        storage.inc_counter("headpats");
+        storage.undo_boop();
+        storage.adjust(3);
+        storage.reset_today(Zoned::now());
+
+        // nothing got written on the receive path, the main loop hands it to
+        // the saver
+        assert_eq!(storage.last_save, last_save);
+        assert!(storage.needs_save());
+    }
+
+    #[test]
+    fn test_adjust() {
+        let mut storage = BoopStorage::default();
+        storage.set_world("wrld_1".into(), "Home".into());
+        storage.inc_boops();
+        storage.adjust(2);
+        assert_eq!(storage.boop_numbers(), (3, 3));
+        assert_eq!(storage.world_today(), Some(3));
+        assert_eq!(storage.hourly_boops.iter().sum::<u32>(), 3);
+
+        storage.adjust(-5);
+        assert_eq!(storage.boop_numbers(), (0, 0));
+        assert_eq!(storage.world_total(), Some(0));
+        assert_eq!(storage.hourly_boops.iter().sum::<u32>(), 0);
+    }
+
+    #[test]
+    fn test_streak() {
+        let date = |s: &str| Date::from_str(s).unwrap();
+        let history: BTreeMap<Date, u32> = [
+            (date("2025-03-25"), 4),
+            (date("2025-03-27"), 1),
+            (date("2025-03-28"), 0),
+            (date("2025-03-29"), 2),
+            (date("2025-03-30"), 5),
+        ]
+        .into();
+
+        assert_eq!(streak(&history, date("2025-03-31"), 3), 3);
+        assert_eq!(streak(&history, date("2025-03-31"), 0), 2);
+        assert_eq!(streak(&history, date("2025-04-02"), 1), 1);
+    }
+
+    #[test]
+    fn test_weekly_reset_is_due() {
+        use crate::config::ResetWeekday;
+
+        let policy = ResetPolicy::Weekly(ResetWeekday::Monday);
+        let saturday = Zoned::from_str("2025-03-29T12:00:00+00:00[UTC]").unwrap();
+        let sunday = Zoned::from_str("2025-03-30T23:59:00+00:00[UTC]").unwrap();
+        let monday = Zoned::from_str("2025-03-31T00:00:00+00:00[UTC]").unwrap();
+        let tuesday = Zoned::from_str("2025-04-01T08:00:00+00:00[UTC]").unwrap();
+
+        assert!(!reset_is_due(policy, &saturday, &sunday));
+        assert!(reset_is_due(policy, &sunday, &monday));
+        assert!(!reset_is_due(policy, &monday, &tuesday));
+    }
+
+    #[test]
+    fn test_counter_reset_policy() {
+        let counters: Vec<CounterOptions> = ["headpats", "hugs"]
+            .into_iter()
+            .map(|name| {
+                let reset = if name == "hugs" {
+                    "reset_policy = { weekly = \"monday\" }"
+                } else {
+                    ""
+                };
+                toml::from_str(&format!(
+                    "name = \"{name}\"\naddress = \"/{name}\"\n{reset}"
+                ))
+                .unwrap()
+            })
+            .collect();
+        let mut storage = BoopStorage::default()
+            .with_reset_time(Time::midnight(), Some(TimeZone::UTC))
+            .with_counter_resets(&counters);
+        storage.last_reset = Zoned::from_str("2025-03-29T00:00:00+00:00[UTC]").unwrap();
+        for name in ["headpats", "hugs"] {
+            let counter = CounterBoops {
+                today: 1,
+                total: 1,
+                ..Default::default()
+            };
+            storage.counters.insert(name.into(), counter);
+        }
+
+        // daily boundary, only the counter following the boops resets
+        let sunday = Zoned::from_str("2025-03-30T00:00:00+00:00[UTC]").unwrap();
+        storage.check_counter_resets(&sunday);
+        storage.end_period(sunday, false);
+        assert_eq!(storage.counter("headpats").today, 0);
+        assert_eq!(storage.counter("hugs").today, 1);
+
+        let monday = Zoned::from_str("2025-03-31T00:00:00+00:00[UTC]").unwrap();
+        storage.check_counter_resets(&monday);
+        assert_eq!(storage.counter("hugs").today, 0);
+        assert_eq!(storage.counter("hugs").total, 1);
+        assert_eq!(storage.counter("hugs").last_reset, Some(monday));
+    }
+
+    #[test]
+    fn test_csv_round_trip() {
+        let mut storage = BoopStorage {
+            total_boops: 1234,
+            today_boops: 12,
+            today_boops_record: 56,
+            yesterday_boops: 7,
+            ..Default::default()
+        };
+        storage
+            .history
+            .insert(Date::from_str("2025-03-30").unwrap(), 7);
+        storage.avatars.insert(
+            "avtr_1".into(),
+            AvatarBoops {
+                total_boops: 100,
+                today_boops: 2,
+            },
+        );
+        storage.inc_counter("headpats");
+
+        let csv = storage.to_csv();
+        let mut imported = BoopStorage::default();
+        imported.apply_csv(&csv).unwrap();
+        assert_eq!(imported.to_csv(), csv);
+
+        assert!(imported.apply_csv("today,total\n1,2").is_err());
+        let invalid = format!("{CSV_HEADER}\nboops,,x,,");
+        assert!(imported.apply_csv(&invalid).is_err());
+    }
 }
@@ -0,0 +1,331 @@
+use std::net::SocketAddr;
+
+use rosc::OscPacket;
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+    sync::mpsc,
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info, trace, warn};
+
+use crate::{config::TcpFraming, decode, error::BooperError};
+
+// SLIP framing as per OSC 1.1, https://datatracker.ietf.org/doc/html/rfc1055
+const SLIP_END: u8 = 0xC0;
+const SLIP_ESC: u8 = 0xDB;
+const SLIP_ESC_END: u8 = 0xDC;
+const SLIP_ESC_ESC: u8 = 0xDD;
+
+/// Upper bound for a single frame, anything bigger is garbage
+const MAX_FRAME_SIZE: usize = 64 * 1024;
+
+/// Size prefix of length framing
+const LENGTH_PREFIX_SIZE: usize = 4;
+
+/// Accept OSC-over-TCP connections and forward decoded packets
+pub(crate) async fn spawn_listener(
+    token: CancellationToken,
+    addr: SocketAddr,
+    framing: TcpFraming,
+    packets: mpsc::Sender<(SocketAddr, OscPacket)>,
+) -> Result<(), BooperError> {
+    let listener = TcpListener::bind(addr)
+        .await
+        .map_err(|source| BooperError::Bind {
+            what: "osc tcp listener",
+            addr,
+            source,
+        })?;
+
+    info!("receiving osc packets via tcp on {}", addr);
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, peer) = tokio::select! {
+                _ = token.cancelled() => break,
+                accepted = listener.accept() => match accepted {
+                    Ok(accepted) => accepted,
+                    Err(e) => {
+                        error!(err=%e, "failed to accept tcp connection");
+                        continue;
+                    }
+                },
+            };
+
+            debug!(peer=%peer, "accepted osc tcp connection");
+            tokio::spawn(handle_connection(
+                token.clone(),
+                stream,
+                peer,
+                framing,
+                packets.clone(),
+            ));
+        }
+
+        info!("stopping osc tcp listener");
+    });
+    Ok(())
+}
+
+async fn handle_connection(
+    token: CancellationToken,
+    mut stream: TcpStream,
+    peer: SocketAddr,
+    framing: TcpFraming,
+    packets: mpsc::Sender<(SocketAddr, OscPacket)>,
+) {
+    let mut decoder = FrameDecoder::new(framing);
+    let mut buf = [0u8; 4096];
+
+    loop {
+        let read = tokio::select! {
+            _ = token.cancelled() => break,
+            read = stream.read(&mut buf) => read,
+        };
+
+        let size = match read {
+            Ok(0) => break,
+            Ok(size) => size,
+            Err(e) => {
+                warn!(peer=%peer, err=%e, "error reading from tcp connection");
+                break;
+            }
+        };
+
+        for frame in decoder.feed(&buf[..size]) {
+            match decode::decode_packet(&frame) {
+                Ok(packet) => {
+                    if packets.send((peer, packet)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(e) => {
+                    error!(err=%e, peer=%peer, "failed to parse packet");
+                }
+            }
+        }
+    }
+
+    debug!(peer=%peer, "osc tcp connection closed");
+}
+
+/// Connection sending OSC packets to a TCP receiver
+///
+/// Connects on the first packet and reconnects after errors, packets sent
+/// while the receiver is unreachable are dropped like UDP datagrams would be.
+#[derive(Debug, Clone)]
+pub(crate) struct TcpSender {
+    packets: mpsc::Sender<Vec<u8>>,
+}
+
+impl TcpSender {
+    /// Spawn the connection task, which stops once all senders are dropped
+    pub(crate) fn spawn(addr: SocketAddr, framing: TcpFraming) -> Self {
+        let (packets, mut rx) = mpsc::channel::<Vec<u8>>(128);
+
+        tokio::spawn(async move {
+            let mut connection: Option<TcpStream> = None;
+
+            while let Some(packet) = rx.recv().await {
+                let stream = match &mut connection {
+                    Some(stream) => stream,
+                    None => match TcpStream::connect(addr).await {
+                        Ok(stream) => {
+                            debug!(%addr, "connected to osc tcp receiver");
+                            connection.insert(stream)
+                        }
+                        Err(e) => {
+                            warn!(err=%e, %addr, "failed to connect to osc tcp receiver");
+                            continue;
+                        }
+                    },
+                };
+
+                if let Err(e) = stream.write_all(&encode_frame(framing, &packet)).await {
+                    warn!(err=%e, %addr, "failed to send packet, reconnecting");
+                    connection = None;
+                }
+            }
+        });
+
+        TcpSender { packets }
+    }
+
+    /// Queue encoded packet for sending
+    pub(crate) fn send(&self, packet: &[u8]) {
+        if self.packets.try_send(packet.to_vec()).is_err() {
+            warn!("osc tcp receiver isn't keeping up, dropping packet");
+        }
+    }
+}
+
+/// Frame encoded packet for a TCP stream
+pub(crate) fn encode_frame(framing: TcpFraming, packet: &[u8]) -> Vec<u8> {
+    match framing {
+        TcpFraming::Slip => {
+            // leading END flushes line noise, see RFC 1055
+            let mut frame = vec![SLIP_END];
+            for &byte in packet {
+                match byte {
+                    SLIP_END => frame.extend([SLIP_ESC, SLIP_ESC_END]),
+                    SLIP_ESC => frame.extend([SLIP_ESC, SLIP_ESC_ESC]),
+                    byte => frame.push(byte),
+                }
+            }
+            frame.push(SLIP_END);
+            frame
+        }
+        TcpFraming::Length => {
+            let size = packet.len() as u32;
+            let mut frame = size.to_be_bytes().to_vec();
+            frame.extend_from_slice(packet);
+            frame
+        }
+    }
+}
+
+/// Incremental decoder of the configured framing
+#[derive(Debug)]
+pub(crate) enum FrameDecoder {
+    Slip(SlipDecoder),
+    Length(LengthDecoder),
+}
+
+impl FrameDecoder {
+    pub(crate) fn new(framing: TcpFraming) -> Self {
+        match framing {
+            TcpFraming::Slip => FrameDecoder::Slip(SlipDecoder::default()),
+            TcpFraming::Length => FrameDecoder::Length(LengthDecoder::default()),
+        }
+    }
+
+    /// Feed received bytes, returning all frames completed by them
+    pub(crate) fn feed(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        match self {
+            FrameDecoder::Slip(decoder) => decoder.feed(data),
+            FrameDecoder::Length(decoder) => decoder.feed(data),
+        }
+    }
+}
+
+/// Incremental decoder of size prefixed frames
+#[derive(Debug, Default)]
+pub(crate) struct LengthDecoder {
+    buf: Vec<u8>,
+}
+
+impl LengthDecoder {
+    /// Feed received bytes, returning all frames completed by them
+    pub(crate) fn feed(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        self.buf.extend_from_slice(data);
+        let mut frames = vec![];
+
+        while let Some(prefix) = self.buf.first_chunk::<LENGTH_PREFIX_SIZE>() {
+            let size = u32::from_be_bytes(*prefix) as usize;
+            if size > MAX_FRAME_SIZE {
+                // there's no way to find the next frame, start over
+                trace!(size, "dropping oversized frame");
+                self.buf.clear();
+                break;
+            }
+
+            let end = LENGTH_PREFIX_SIZE + size;
+            if self.buf.len() < end {
+                break;
+            }
+            frames.push(self.buf[LENGTH_PREFIX_SIZE..end].to_vec());
+            self.buf.drain(..end);
+        }
+
+        frames
+    }
+}
+
+/// Incremental SLIP frame decoder
+#[derive(Debug, Default)]
+pub(crate) struct SlipDecoder {
+    frame: Vec<u8>,
+    escaped: bool,
+}
+
+impl SlipDecoder {
+    /// Feed received bytes, returning all frames completed by them
+    pub(crate) fn feed(&mut self, data: &[u8]) -> Vec<Vec<u8>> {
+        let mut frames = vec![];
+
+        for &byte in data {
+            match (self.escaped, byte) {
+                (false, SLIP_END) => {
+                    // leading END bytes are allowed to flush line noise
+                    if !self.frame.is_empty() {
+                        frames.push(std::mem::take(&mut self.frame));
+                    }
+                }
+                (false, SLIP_ESC) => self.escaped = true,
+                (false, byte) => self.frame.push(byte),
+                (true, SLIP_ESC_END) => {
+                    self.frame.push(SLIP_END);
+                    self.escaped = false;
+                }
+                (true, SLIP_ESC_ESC) => {
+                    self.frame.push(SLIP_ESC);
+                    self.escaped = false;
+                }
+                (true, byte) => {
+                    // protocol violation, keep the byte as-is
+                    self.frame.push(byte);
+                    self.escaped = false;
+                }
+            }
+
+            if self.frame.len() > MAX_FRAME_SIZE {
+                trace!("dropping oversized slip frame");
+                self.frame.clear();
+            }
+        }
+
+        frames
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_slip_decoder() {
+        let mut decoder = SlipDecoder::default();
+
+        assert!(
+            decoder
+                .feed(&[SLIP_END, 1, SLIP_ESC, SLIP_ESC_END])
+                .is_empty()
+        );
+        assert_eq!(
+            decoder.feed(&[SLIP_ESC, SLIP_ESC_ESC, 2, SLIP_END, 3, SLIP_END]),
+            vec![vec![1, SLIP_END, SLIP_ESC, 2], vec![3]]
+        );
+    }
+
+    #[test]
+    fn test_length_decoder() {
+        let mut decoder = LengthDecoder::default();
+
+        assert!(decoder.feed(&[0, 0, 0]).is_empty());
+        assert!(decoder.feed(&[2, 1]).is_empty());
+        assert_eq!(decoder.feed(&[2, 0, 0, 0, 1, 3]), vec![vec![1, 2], vec![3]]);
+        assert!(decoder.buf.is_empty());
+    }
+
+    #[test]
+    fn test_frame_round_trip() {
+        let packet = [1, SLIP_END, 2, SLIP_ESC, 3];
+
+        for framing in [TcpFraming::Slip, TcpFraming::Length] {
+            let frame = encode_frame(framing, &packet);
+            let mut decoder = FrameDecoder::new(framing);
+            assert_eq!(decoder.feed(&frame), vec![packet.to_vec()]);
+        }
+    }
+}
@@ -0,0 +1,309 @@
+/// VRChat's chatbox character limit
+pub(crate) const CHATBOX_MAX_CHARS: usize = 144;
+
+/// Width of progress bars without an explicit one
+const DEFAULT_BAR_WIDTH: usize = 10;
+
+/// Widest progress bar, leaving most of the 144 chatbox characters for text
+const MAX_BAR_WIDTH: usize = 40;
+
+/// Variables of every chatbox template, as rendered by the booper
+pub(crate) const VARIABLES: &[&str] = &[
+    "today",
+    "total",
+    "session",
+    "today_suffix",
+    "total_suffix",
+    "session_suffix",
+    "week",
+    "month",
+    "streak",
+    "avatar_today",
+    "avatar_total",
+    "world",
+    "world_id",
+    "world_today",
+    "world_total",
+    "rate",
+    "leaderboard",
+    "top_booper",
+];
+
+/// Keys of all `{key}` placeholders in `template`
+pub(crate) fn placeholders(template: &str) -> Vec<String> {
+    let keys = std::cell::RefCell::new(vec![]);
+    render(template, |key| {
+        keys.borrow_mut().push(key.to_string());
+        None
+    });
+    keys.into_inner()
+}
+
+/// Render `template`, replacing every `{key}` with the result of `lookup`
+///
+/// Unknown keys are left untouched so typos stay visible in the chatbox,
+/// `{{` and `}}` produce literal braces.
+pub(crate) fn render<F>(template: &str, lookup: F) -> String
+where
+    F: Fn(&str) -> Option<String>,
+{
+    let mut out = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(pos) = rest.find(['{', '}']) {
+        out.push_str(&rest[..pos]);
+        let tail = &rest[pos..];
+
+        if tail.starts_with("{{") || tail.starts_with("}}") {
+            out.push_str(&tail[..1]);
+            rest = &tail[2..];
+            continue;
+        }
+
+        // placeholder, unless the brace is never closed
+        if let Some(end) = tail.find('}').filter(|_| tail.starts_with('{')) {
+            let key = &tail[1..end];
+            match lookup(key) {
+                Some(value) => out.push_str(&value),
+                None => out.push_str(&tail[..=end]),
+            }
+            rest = &tail[end + 1..];
+            continue;
+        }
+
+        // lone brace, keep as-is
+        out.push_str(&tail[..1]);
+        rest = &tail[1..];
+    }
+    out.push_str(rest);
+
+    out
+}
+
+/// Split text into chunks of at most `limit` chars, preferring line breaks
+pub(crate) fn split_chunks(text: &str, limit: usize) -> Vec<String> {
+    let mut chunks = vec![];
+    let mut current = String::new();
+
+    for line in text.lines() {
+        let current_len = current.chars().count();
+        let line_len = line.chars().count();
+
+        // line fits into current chunk, including separator
+        if current.is_empty() && line_len <= limit {
+            current.push_str(line);
+            continue;
+        }
+        if !current.is_empty() && current_len + 1 + line_len <= limit {
+            current.push('\n');
+            current.push_str(line);
+            continue;
+        }
+
+        if !current.is_empty() {
+            chunks.push(std::mem::take(&mut current));
+        }
+
+        // hard split lines exceeding the limit by themselves
+        let chars: Vec<char> = line.chars().collect();
+        let mut pieces = chars.chunks(limit.max(1)).map(String::from_iter).peekable();
+        while let Some(piece) = pieces.next() {
+            if pieces.peek().is_some() {
+                chunks.push(piece);
+            } else {
+                current = piece;
+            }
+        }
+    }
+
+    if !current.is_empty() {
+        chunks.push(current);
+    }
+
+    chunks
+}
+
+/// Number in the given `format`: `sep` for thousands separators, `short` for
+/// abbreviations like `1.2k`
+pub(crate) fn format_number(format: &str, number: u64, separator: &str) -> Option<String> {
+    match format {
+        "sep" => Some(with_separators(number, separator)),
+        "short" => Some(abbreviate(number)),
+        _ => None,
+    }
+}
+
+/// `1234567` as `1,234,567`
+fn with_separators(number: u64, separator: &str) -> String {
+    let digits = number.to_string();
+    let mut out = String::with_capacity(digits.len() * 2);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            out.push_str(separator);
+        }
+        out.push(digit);
+    }
+    out
+}
+
+/// `1234` as `1.2k`, `12345678` as `12.3M`, rounded down so a goal isn't
+/// shown as reached early
+fn abbreviate(number: u64) -> String {
+    let Some((unit, suffix)) = [(1_000_000_000, "B"), (1_000_000, "M"), (1_000, "k")]
+        .into_iter()
+        .find(|(unit, _)| number >= *unit)
+    else {
+        return number.to_string();
+    };
+
+    let (whole, tenth) = (number / unit, number % unit * 10 / unit);
+    if whole >= 100 || tenth == 0 {
+        format!("{whole}{suffix}")
+    } else {
+        format!("{whole}.{tenth}{suffix}")
+    }
+}
+
+/// Progress bar for `{progress:<variable>/<goal>}`, optionally followed by
+/// `:<width>`
+///
+/// The goal may be a number or another variable, values are looked up via
+/// `value`.
+pub(crate) fn progress(spec: &str, value: impl Fn(&str) -> Option<u64>) -> Option<String> {
+    let (spec, width) = match spec.split_once(':') {
+        Some((spec, width)) => (spec, width.parse().ok()?),
+        None => (spec, DEFAULT_BAR_WIDTH),
+    };
+    let (variable, goal) = spec.split_once('/')?;
+    let goal = goal.parse().ok().or_else(|| value(goal))?;
+
+    Some(progress_bar(value(variable)?, goal, width))
+}
+
+/// `width` segments, filled in proportion to `current` of `goal`
+fn progress_bar(current: u64, goal: u64, width: usize) -> String {
+    let width = width.clamp(1, MAX_BAR_WIDTH);
+    let filled = match goal {
+        0 => width,
+        goal => (current.min(goal) as u128 * width as u128 / goal as u128) as usize,
+    };
+    "▰".repeat(filled) + &"▱".repeat(width - filled)
+}
+
+/// Collapse runs of whitespace into a single space, or a line break if the
+/// run contains one
+pub(crate) fn compress_whitespace(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut pending: Option<char> = None;
+
+    for c in text.trim().chars() {
+        if c.is_whitespace() {
+            if pending != Some('\n') {
+                pending = Some(if c == '\n' { '\n' } else { ' ' });
+            }
+            continue;
+        }
+        if let Some(separator) = pending.take() {
+            out.push(separator);
+        }
+        out.push(c);
+    }
+
+    out
+}
+
+/// Cut `text` to at most `limit` chars, ending with `…`
+///
+/// Cuts at the last whitespace that fits, so numbers and words are never
+/// split, unless there's none.
+pub(crate) fn truncate_words(text: &str, limit: usize) -> String {
+    if text.chars().count() <= limit {
+        return text.into();
+    }
+
+    let kept: String = text.chars().take(limit.saturating_sub(1)).collect();
+    let kept = match kept.rfind(char::is_whitespace) {
+        Some(end) if end > 0 => kept[..end].trim_end(),
+        _ => &kept,
+    };
+    format!("{kept}…")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn lookup(key: &str) -> Option<String> {
+        match key {
+            "today" => Some("12".into()),
+            "data:song" => Some("Never Gonna Give You Up".into()),
+            _ => None,
+        }
+    }
+
+    #[test]
+    fn test_render_variables() {
+        assert_eq!(
+            render("Today: {today} | {data:song}", lookup),
+            "Today: 12 | Never Gonna Give You Up"
+        );
+    }
+
+    #[test]
+    fn test_render_unknown_and_escaped() {
+        assert_eq!(render("{nope} {{today}} {", lookup), "{nope} {today} {");
+    }
+
+    #[test]
+    fn test_format_number() {
+        assert_eq!(format_number("sep", 1234567, ","), Some("1,234,567".into()));
+        assert_eq!(format_number("sep", 123, ","), Some("123".into()));
+        assert_eq!(format_number("short", 999, ","), Some("999".into()));
+        assert_eq!(format_number("short", 1234, ","), Some("1.2k".into()));
+        assert_eq!(format_number("short", 1000, ","), Some("1k".into()));
+        assert_eq!(format_number("short", 123_456, ","), Some("123k".into()));
+        assert_eq!(
+            format_number("short", 12_345_678, ","),
+            Some("12.3M".into())
+        );
+        assert_eq!(format_number("boop", 1, ","), None);
+    }
+
+    #[test]
+    fn test_progress() {
+        let value = |key: &str| match key {
+            "today" => Some(300),
+            "record" => Some(600),
+            _ => None,
+        };
+        assert_eq!(progress("today/500", value).as_deref(), Some("▰▰▰▰▰▰▱▱▱▱"));
+        assert_eq!(progress("today/500:5", value).as_deref(), Some("▰▰▰▱▱"));
+        assert_eq!(progress("today/record:4", value).as_deref(), Some("▰▰▱▱"));
+        assert_eq!(progress("today/100:3", value).as_deref(), Some("▰▰▰"));
+        assert_eq!(
+            progress("today/500:1000", value).map(|bar| bar.chars().count()),
+            Some(40)
+        );
+        assert_eq!(progress("nope/500", value), None);
+    }
+
+    #[test]
+    fn test_shortening() {
+        assert_eq!(
+            compress_whitespace("  Today:   12 \n\n Total:\t34 "),
+            "Today: 12\nTotal: 34"
+        );
+        assert_eq!(truncate_words("Today: 12345", 20), "Today: 12345");
+        assert_eq!(truncate_words("Today: 12345", 10), "Today:…");
+        assert_eq!(truncate_words("12345678", 5), "1234…");
+    }
+
+    #[test]
+    fn test_split_chunks() {
+        assert_eq!(
+            split_chunks("aaa\nbb\ncccccccc", 6),
+            vec!["aaa\nbb", "cccccc", "cc"]
+        );
+        assert_eq!(split_chunks("short", 144), vec!["short"]);
+    }
+}
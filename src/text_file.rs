@@ -0,0 +1,70 @@
+use std::fs;
+
+use tokio::sync::broadcast::{self, error::RecvError};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, error, info};
+
+use crate::{config::TextFileOptions, events::BooperEvent, template};
+
+/// Write the counts to a text file whenever they change, e.g. for OBS "Text
+/// from file" sources
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: TextFileOptions,
+    (today, total): (u32, u64),
+    mut events: broadcast::Receiver<BooperEvent>,
+) {
+    info!("writing counts to {}", options.path.display());
+    write(&options, today, total);
+
+    tokio::spawn(async move {
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = events.recv() => event,
+            };
+
+            let mut counts = match event {
+                Ok(event) => match event.counts() {
+                    Some(counts) => counts,
+                    None => continue,
+                },
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "text file output lagging behind");
+                    continue;
+                }
+                Err(RecvError::Closed) => break,
+            };
+
+            // only write the latest counts of a boop storm
+            let settle = tokio::time::sleep(options.debounce.unsigned_abs());
+            tokio::pin!(settle);
+            loop {
+                tokio::select! {
+                    _ = &mut settle => break,
+                    event = events.recv() => match event {
+                        Ok(event) => counts = event.counts().unwrap_or(counts),
+                        Err(RecvError::Lagged(_)) => {}
+                        Err(RecvError::Closed) => break,
+                    },
+                }
+            }
+
+            write(&options, counts.0, counts.1);
+        }
+    });
+}
+
+fn write(options: &TextFileOptions, today: u32, total: u64) {
+    let text = template::render(&options.template, |key| match key {
+        "today" => Some(today.to_string()),
+        "total" => Some(total.to_string()),
+        _ => None,
+    });
+
+    // OBS may read while we're writing, replace the file in one go
+    let tmp = options.path.with_extension("tmp");
+    if let Err(e) = fs::write(&tmp, text).and_then(|_| fs::rename(&tmp, &options.path)) {
+        error!(err=%e, "failed to write {}", options.path.display());
+    }
+}
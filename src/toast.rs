@@ -0,0 +1,93 @@
+use jiff::SignedDuration;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{debug, warn};
+use windows::{
+    Data::Xml::Dom::XmlDocument,
+    Foundation::TypedEventHandler,
+    UI::Notifications::{ToastActivatedEventArgs, ToastNotification, ToastNotificationManager},
+    core::{HSTRING, IInspectable, Interface},
+};
+
+use crate::{
+    command::{Command, CommandSender},
+    events::BooperEvent,
+};
+
+/// Unpackaged apps can't register their own AppUserModelID without an
+/// installer, borrow PowerShell's like most CLI tools do
+const APP_ID: &str =
+    r"{1AC14E77-02E7-4E5D-B744-2EB1AE5198B7}\WindowsPowerShell\v1.0\powershell.exe";
+
+/// Show actionable toasts on milestones
+pub(crate) fn spawn(commands: CommandSender, mut events: broadcast::Receiver<BooperEvent>) {
+    tokio::spawn(async move {
+        loop {
+            match events.recv().await {
+                Ok(BooperEvent::Milestone { message, .. }) => {
+                    if let Err(e) = show_milestone_toast(&message, commands.clone()) {
+                        warn!(err=%e, "failed to show toast");
+                    }
+                }
+                Ok(_) => {}
+                Err(RecvError::Lagged(skipped)) => {
+                    debug!(skipped, "toasts lagging behind");
+                }
+                Err(RecvError::Closed) => break,
+            }
+        }
+    });
+}
+
+fn show_milestone_toast(message: &str, commands: CommandSender) -> windows::core::Result<()> {
+    let xml = XmlDocument::new()?;
+    xml.LoadXml(&HSTRING::from(format!(
+        r#"<toast>
+            <visual>
+                <binding template="ToastGeneric">
+                    <text>Boop milestone!</text>
+                    <text>{}</text>
+                </binding>
+            </visual>
+            <actions>
+                <action content="Post again" arguments="post" activationType="foreground"/>
+                <action content="Pause 1h" arguments="pause" activationType="foreground"/>
+            </actions>
+        </toast>"#,
+        escape_xml(message)
+    )))?;
+
+    let toast = ToastNotification::CreateToastNotification(&xml)?;
+    toast.Activated(&TypedEventHandler::new(
+        move |_: &Option<ToastNotification>, args: &Option<IInspectable>| {
+            let Some(args) = args else {
+                return Ok(());
+            };
+
+            let arguments = args.cast::<ToastActivatedEventArgs>()?.Arguments()?;
+            let command = match arguments.to_string().as_str() {
+                "post" => Command::PostStats {
+                    template: None,
+                    manual: true,
+                },
+                "pause" => Command::PauseFor(SignedDuration::from_hours(1)),
+                // toast body clicked
+                _ => return Ok(()),
+            };
+
+            // called from a WinRT thread, never block it
+            if let Err(e) = commands.try_send(command) {
+                warn!(err=%e, "failed to forward toast action");
+            }
+            Ok(())
+        },
+    ))?;
+
+    ToastNotificationManager::CreateToastNotifierWithId(&HSTRING::from(APP_ID))?.Show(&toast)
+}
+
+fn escape_xml(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
@@ -0,0 +1,209 @@
+use std::{process, thread, time::Duration};
+
+use tokio::sync::oneshot;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+use tray_icon::{
+    Icon, TrayIcon, TrayIconBuilder,
+    menu::{Menu, MenuEvent, MenuItem, PredefinedMenuItem},
+};
+
+use crate::command::{Command, CommandSender, StatsSnapshot};
+
+/// How often menu and platform events are polled
+const POLL_INTERVAL: Duration = Duration::from_millis(50);
+/// How often the counters are refreshed
+const REFRESH_EVERY: u32 = 20;
+
+const ICON_SIZE: u32 = 32;
+const ICON_COLOR: [u8; 4] = [255, 105, 180, 255];
+
+/// Entries of the tray menu
+struct TrayMenu {
+    pause: MenuItem,
+    post: MenuItem,
+    /// only with the HTTP server running
+    dashboard: Option<MenuItem>,
+    quit: MenuItem,
+}
+
+/// Show live counts in a tray icon's tooltip, with controls in its menu
+///
+/// The tray icon needs an event loop on the thread it was created on, so it
+/// runs on its own thread, like the SteamVR overlay.
+pub(crate) fn spawn(token: CancellationToken, commands: CommandSender, dashboard: Option<String>) {
+    thread::spawn(move || {
+        #[cfg(target_os = "linux")]
+        if let Err(e) = gtk::init() {
+            warn!(err=%e, "failed to initialize GTK for the tray icon");
+            return;
+        }
+
+        let (tray, menu) = match create(dashboard.is_some()) {
+            Ok(created) => created,
+            Err(e) => {
+                warn!(err=%e, "failed to create tray icon");
+                return;
+            }
+        };
+        info!("showing tray icon");
+
+        run(&token, &commands, &tray, &menu, dashboard.as_deref());
+    });
+}
+
+fn create(dashboard: bool) -> Result<(TrayIcon, TrayMenu), Box<dyn std::error::Error>> {
+    let menu = TrayMenu {
+        pause: MenuItem::new("Pause", true, None),
+        post: MenuItem::new("Post now", true, None),
+        dashboard: dashboard.then(|| MenuItem::new("Open dashboard", true, None)),
+        quit: MenuItem::new("Quit", true, None),
+    };
+
+    let items = Menu::new();
+    items.append_items(&[&menu.pause, &menu.post])?;
+    if let Some(dashboard) = &menu.dashboard {
+        items.append(dashboard)?;
+    }
+    items.append_items(&[&PredefinedMenuItem::separator(), &menu.quit])?;
+
+    let tray = TrayIconBuilder::new()
+        .with_menu(Box::new(items))
+        .with_tooltip("osc-booper")
+        .with_icon(icon()?)
+        .build()?;
+    Ok((tray, menu))
+}
+
+fn run(
+    token: &CancellationToken,
+    commands: &CommandSender,
+    tray: &TrayIcon,
+    menu: &TrayMenu,
+    dashboard: Option<&str>,
+) {
+    let mut state: Option<(u32, u64, bool)> = None;
+    let mut ticks = 0;
+
+    while !token.is_cancelled() {
+        pump_events();
+
+        if ticks % REFRESH_EVERY == 0 {
+            let Some(stats) = fetch_stats(commands) else {
+                // main loop is gone
+                break;
+            };
+
+            let stats_state = (stats.today, stats.total, stats.paused);
+            if state != Some(stats_state) {
+                if let Err(e) = tray.set_tooltip(Some(tooltip(&stats))) {
+                    warn!(err=%e, "failed to update tray tooltip");
+                }
+                menu.pause
+                    .set_text(if stats.paused { "Resume" } else { "Pause" });
+                state = Some(stats_state);
+            }
+        }
+        ticks += 1;
+
+        while let Ok(event) = MenuEvent::receiver().try_recv() {
+            let command = if event.id() == menu.pause.id() {
+                Command::Pause(None)
+            } else if event.id() == menu.post.id() {
+                Command::PostStats {
+                    template: None,
+                    manual: true,
+                }
+            } else if event.id() == menu.quit.id() {
+                info!("quitting via tray icon");
+                token.cancel();
+                return;
+            } else {
+                if let Some(url) = dashboard {
+                    open(url);
+                }
+                continue;
+            };
+
+            debug!(?command, "tray menu item clicked");
+            if let Err(e) = commands.try_send(command) {
+                warn!(err=%e, "failed to forward tray action");
+            }
+            // show the new state right away
+            ticks = 0;
+        }
+
+        thread::sleep(POLL_INTERVAL);
+    }
+}
+
+fn fetch_stats(commands: &CommandSender) -> Option<StatsSnapshot> {
+    let (tx, rx) = oneshot::channel();
+    commands.blocking_send(Command::Stats(tx)).ok()?;
+    rx.blocking_recv().ok()
+}
+
+fn tooltip(stats: &StatsSnapshot) -> String {
+    let paused = if stats.paused { " (paused)" } else { "" };
+    format!("Today: {}\nTotal: {}{paused}", stats.today, stats.total)
+}
+
+/// Filled circle on a transparent background
+fn icon() -> Result<Icon, tray_icon::BadIcon> {
+    let center = ICON_SIZE as f32 / 2.0 - 0.5;
+    let radius = ICON_SIZE as f32 / 2.0 - 1.0;
+
+    let mut rgba = Vec::with_capacity((ICON_SIZE * ICON_SIZE * 4) as usize);
+    for y in 0..ICON_SIZE {
+        for x in 0..ICON_SIZE {
+            let distance = (x as f32 - center).hypot(y as f32 - center);
+            let pixel = if distance <= radius {
+                ICON_COLOR
+            } else {
+                [0; 4]
+            };
+            rgba.extend_from_slice(&pixel);
+        }
+    }
+
+    Icon::from_rgba(rgba, ICON_SIZE, ICON_SIZE)
+}
+
+/// Open `url` in the default browser
+fn open(url: &str) {
+    #[cfg(windows)]
+    let opened = process::Command::new("explorer").arg(url).spawn();
+    #[cfg(not(windows))]
+    let opened = process::Command::new("xdg-open").arg(url).spawn();
+
+    if let Err(e) = opened {
+        warn!(err=%e, "failed to open {url}");
+    }
+}
+
+/// Handle pending GTK events, which drive the tray icon on Linux
+#[cfg(target_os = "linux")]
+fn pump_events() {
+    while gtk::events_pending() {
+        gtk::main_iteration_do(false);
+    }
+}
+
+/// Handle pending window messages, which drive the tray icon on Windows
+#[cfg(windows)]
+fn pump_events() {
+    use windows::Win32::{
+        Foundation::HWND,
+        UI::WindowsAndMessaging::{
+            DispatchMessageW, MSG, PM_REMOVE, PeekMessageW, TranslateMessage,
+        },
+    };
+
+    let mut message = MSG::default();
+    unsafe {
+        while PeekMessageW(&mut message, HWND::default(), 0, 0, PM_REMOVE).as_bool() {
+            let _ = TranslateMessage(&message);
+            DispatchMessageW(&message);
+        }
+    }
+}
@@ -0,0 +1,88 @@
+use std::{path::Path, time::Duration};
+
+use fs_notify::{Event, RecursiveMode, Watcher};
+use tokio::sync::mpsc;
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    command::{Command, CommandSender},
+    config::{Cli, Options},
+    paths,
+};
+
+/// Time to let editors finish writing before reloading
+const SETTLE_DELAY: Duration = Duration::from_millis(250);
+
+/// Watch the config file and hand reloaded options to the main loop
+pub(crate) fn spawn(token: CancellationToken, args: Cli, commands: CommandSender) {
+    let (tx, mut rx) = mpsc::unbounded_channel::<Event>();
+    let mut watcher = match fs_notify::recommended_watcher(move |event| match event {
+        Ok(event) => {
+            tx.send(event).ok();
+        }
+        Err(e) => warn!(err=%e, "config watcher error"),
+    }) {
+        Ok(watcher) => watcher,
+        Err(e) => {
+            warn!(err=%e, "failed to create config watcher, hot reload disabled");
+            return;
+        }
+    };
+
+    // editors often replace the file instead of writing to it, which would
+    // end a watch on the file itself
+    let config_file = paths::config_file();
+    let dir = match config_file.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => Path::new("."),
+    };
+    if let Err(e) = watcher.watch(dir, RecursiveMode::NonRecursive) {
+        warn!(err=%e, "failed to watch config directory, hot reload disabled");
+        return;
+    }
+
+    let Some(file_name) = config_file.file_name().map(ToOwned::to_owned) else {
+        return;
+    };
+    info!("watching {} for changes", config_file.display());
+
+    tokio::spawn(async move {
+        // dropping the watcher stops it
+        let _watcher = watcher;
+
+        loop {
+            let event = tokio::select! {
+                _ = token.cancelled() => break,
+                event = rx.recv() => event,
+            };
+            let Some(event) = event else {
+                break;
+            };
+
+            let is_config = event
+                .paths
+                .iter()
+                .any(|path| path.file_name() == Some(file_name.as_os_str()));
+            if !is_config || !(event.kind.is_create() || event.kind.is_modify()) {
+                continue;
+            }
+
+            // a single save usually causes a burst of events
+            tokio::time::sleep(SETTLE_DELAY).await;
+            while rx.try_recv().is_ok() {}
+
+            debug!("{} changed, reloading", config_file.display());
+            let Some(options) = Options::reload(&args) else {
+                continue;
+            };
+            if commands
+                .send(Command::Reload(Box::new(options)))
+                .await
+                .is_err()
+            {
+                break;
+            }
+        }
+    });
+}
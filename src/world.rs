@@ -0,0 +1,216 @@
+use std::{
+    io::{self, SeekFrom},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+
+use tokio::{
+    fs,
+    io::{AsyncReadExt, AsyncSeekExt},
+};
+use tokio_util::sync::CancellationToken;
+use tracing::{debug, info, warn};
+
+use crate::{
+    command::{Command, CommandSender},
+    config::WorldTrackingOptions,
+};
+
+/// World the user is currently in
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) struct World {
+    /// e.g. `wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd`
+    pub id: String,
+    pub name: String,
+}
+
+/// Log lines announcing a world change, VRChat logs both on every join
+#[derive(Debug, PartialEq)]
+enum LogLine<'a> {
+    /// `[Behaviour] Joining wrld_…:<instance>`
+    Joining(&'a str),
+    /// `[Behaviour] Entering Room: <name>`
+    Entering(&'a str),
+}
+
+/// Follow VRChat's newest output log and report world changes to the main
+/// loop
+pub(crate) fn spawn(
+    token: CancellationToken,
+    options: WorldTrackingOptions,
+    commands: CommandSender,
+) {
+    info!("tracking worlds via logs in {}", options.log_dir.display());
+
+    tokio::spawn(async move {
+        let mut tail = LogTail::new(options.log_dir);
+        let mut interval = tokio::time::interval(options.poll_interval.unsigned_abs());
+        let mut current: Option<World> = None;
+        let (mut id, mut name) = (None, None);
+        let mut failing = false;
+
+        loop {
+            tokio::select! {
+                _ = token.cancelled() => break,
+                _ = interval.tick() => {},
+            }
+
+            let lines = match tail.read_lines().await {
+                Ok(lines) => lines,
+                Err(e) => {
+                    // VRChat may not have been started yet, don't warn every poll
+                    if !failing {
+                        warn!(err=%e, "failed to read VRChat log");
+                    }
+                    failing = true;
+                    continue;
+                }
+            };
+            failing = false;
+
+            // only the latest world matters when catching up
+            let mut latest = None;
+            for line in &lines {
+                match parse_line(line) {
+                    Some(LogLine::Joining(world_id)) => id = Some(world_id.to_string()),
+                    Some(LogLine::Entering(world_name)) => name = Some(world_name.to_string()),
+                    None => continue,
+                }
+
+                if id.is_some() && name.is_some() {
+                    latest = id
+                        .take()
+                        .zip(name.take())
+                        .map(|(id, name)| World { id, name });
+                }
+            }
+
+            let Some(world) = latest.filter(|world| current.as_ref() != Some(world)) else {
+                continue;
+            };
+            current = Some(world.clone());
+            if commands.send(Command::WorldChanged(world)).await.is_err() {
+                break;
+            }
+        }
+    });
+}
+
+fn parse_line(line: &str) -> Option<LogLine<'_>> {
+    let (_, message) = line.split_once("[Behaviour] ")?;
+    if let Some(name) = message.strip_prefix("Entering Room: ") {
+        return Some(LogLine::Entering(name.trim()));
+    }
+
+    // skips "Joining or Creating Room" and friends, which carry no world ID
+    let location = message.strip_prefix("Joining ")?;
+    if !location.starts_with("wrld_") {
+        return None;
+    }
+    location.split([':', ' ']).next().map(LogLine::Joining)
+}
+
+/// Lines appended to the newest log file, VRChat starts a new one per launch
+struct LogTail {
+    dir: PathBuf,
+    path: Option<PathBuf>,
+    offset: u64,
+    /// incomplete last line, completed by a later read
+    partial: Vec<u8>,
+}
+
+impl LogTail {
+    fn new(dir: PathBuf) -> Self {
+        LogTail {
+            dir,
+            path: None,
+            offset: 0,
+            partial: vec![],
+        }
+    }
+
+    /// Complete lines written since the last call, newer log files are read
+    /// from the start
+    async fn read_lines(&mut self) -> io::Result<Vec<String>> {
+        let Some(newest) = newest_log(&self.dir).await? else {
+            return Ok(vec![]);
+        };
+        if self.path.as_ref() != Some(&newest) {
+            debug!("following {}", newest.display());
+            self.path = Some(newest.clone());
+            self.offset = 0;
+            self.partial.clear();
+        }
+
+        let mut file = fs::File::open(&newest).await?;
+        file.seek(SeekFrom::Start(self.offset)).await?;
+        let read = file.read_to_end(&mut self.partial).await?;
+        self.offset += read as u64;
+
+        let Some(end) = self.partial.iter().rposition(|&b| b == b'\n') else {
+            return Ok(vec![]);
+        };
+        let complete: Vec<u8> = self.partial.drain(..=end).collect();
+
+        Ok(String::from_utf8_lossy(&complete)
+            .lines()
+            .map(Into::into)
+            .collect())
+    }
+}
+
+/// Most recently modified `output_log_*.txt` in `dir`
+async fn newest_log(dir: &Path) -> io::Result<Option<PathBuf>> {
+    let mut entries = fs::read_dir(dir).await?;
+    let mut newest: Option<(SystemTime, PathBuf)> = None;
+
+    while let Some(entry) = entries.next_entry().await? {
+        let file_name = entry.file_name();
+        let file_name = file_name.to_string_lossy();
+        if !file_name.starts_with("output_log_") || !file_name.ends_with(".txt") {
+            continue;
+        }
+
+        let modified = entry.metadata().await?.modified()?;
+        if newest.as_ref().is_none_or(|(time, _)| modified > *time) {
+            newest = Some((modified, entry.path()));
+        }
+    }
+
+    Ok(newest.map(|(_, path)| path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_line() {
+        assert_eq!(
+            parse_line(
+                "2025.01.01 20:00:00 Log        -  [Behaviour] Joining \
+                 wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd:12345~region(eu)"
+            ),
+            Some(LogLine::Joining(
+                "wrld_4432ea9b-729c-46e3-8eaf-846aa0a37fdd"
+            ))
+        );
+        assert_eq!(
+            parse_line(
+                "2025.01.01 20:00:01 Log        -  [Behaviour] Entering Room: The Black Cat"
+            ),
+            Some(LogLine::Entering("The Black Cat"))
+        );
+        assert_eq!(
+            parse_line(
+                "2025.01.01 20:00:01 Log        -  [Behaviour] Joining or Creating Room: Cat"
+            ),
+            None
+        );
+        assert_eq!(
+            parse_line("2025.01.01 20:00:02 Log        -  [Behaviour] Joining friend"),
+            None
+        );
+        assert_eq!(parse_line("Entering Room: not from VRChat"), None);
+    }
+}